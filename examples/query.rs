@@ -1,38 +1,28 @@
-use std::collections::HashMap;
-
-use dafont::{get_font_name, FcFontCache, FcPattern, PatternMatch};
+use dafont::FcFontCache;
 
 fn main() {
     let cache = FcFontCache::build();
-    let fonts = cache.query_all(&FcPattern {
-        monospace: PatternMatch::True,
-        ..Default::default()
-    });
-
-    println!("total fonts: {}", fonts.len());
-
-    let mut font_by_family = HashMap::new();
-    for font in fonts {
-        let Some((family, name)) = get_font_name(font) else {
-            eprintln!("failed to get font name for {}", font.path);
-            continue;
-        };
-
-        font_by_family
-            .entry(family)
-            .or_insert_with(Vec::new)
-            .push(name);
-    }
-
-    let mut families: Vec<_> = font_by_family.keys().collect();
-    families.sort();
+    let families: Vec<_> = cache
+        .families()
+        .into_iter()
+        .filter(|family| family.faces.iter().any(|face| face.monospace))
+        .collect();
+
+    let total = families
+        .iter()
+        .flat_map(|family| &family.faces)
+        .filter(|face| face.monospace)
+        .count();
+    println!("total fonts: {total}");
 
     for family in families {
-        println!("{family}");
+        println!("{}", family.name);
 
-        let names = &font_by_family[family];
-        for name in names {
-            println!("  {name}");
+        for face in family.faces.iter().filter(|face| face.monospace) {
+            match &face.name {
+                Some(name) => println!("  {name} ({})", face.style()),
+                None => println!("  {}", face.style()),
+            }
         }
 
         println!();
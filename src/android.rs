@@ -0,0 +1,317 @@
+//! Android font discovery
+//!
+//! Android has no fontconfig: fonts live in a few fixed directories and
+//! `/system/etc/fonts.xml` declares the generic families ("sans-serif",
+//! "serif", ...) together with the weight / style of every file.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{
+    FcFallbackFamily, FcFontPath, FcPattern, FcRetainLocations, FcScanDirectoriesInner,
+    FcScanOptions, FcTermuxFontDirectories, FcWeight, PatternMatch,
+};
+
+const ANDROID_FONT_DIRS: &[&str] = &["/system/fonts", "/product/fonts", "/data/fonts"];
+const ANDROID_FONTS_XML: &str = "/system/etc/fonts.xml";
+
+/// A `<family>` element of `fonts.xml`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct AndroidFamily {
+    // `name` attribute, only set for the named (generic) families
+    pub name: Option<String>,
+    // `lang` attribute of fallback families, i.e. "und-Arab" or "ja"
+    pub lang: Option<String>,
+    // `variant` attribute ("compact" / "elegant")
+    pub variant: Option<String>,
+    pub fonts: Vec<AndroidFont>,
+}
+
+/// A `<font>` element of `fonts.xml`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct AndroidFont {
+    // file name, relative to the font directories
+    pub file: String,
+    pub weight: FcWeight,
+    pub italic: bool,
+    pub font_index: usize,
+}
+
+/// An `<alias>` element of `fonts.xml`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct AndroidAlias {
+    pub name: String,
+    pub to: String,
+    pub weight: Option<FcWeight>,
+}
+
+/// Parsed contents of `fonts.xml`, families are kept in declaration order
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct AndroidFontsXml {
+    pub families: Vec<AndroidFamily>,
+    pub aliases: Vec<AndroidAlias>,
+}
+
+/// Scans the Android font directories and adds the families declared in `fonts.xml`,
+/// returns the fonts and the fallback order of `fonts.xml`
+pub(crate) fn FcScanAndroidFonts(
+    options: &FcScanOptions,
+) -> (Vec<(FcPattern, FcFontPath)>, Vec<FcFallbackFamily>) {
+    // fonts installed through Termux packages come first
+    let mut font_dirs = FcTermuxFontDirectories();
+    font_dirs.extend(
+        ANDROID_FONT_DIRS
+            .iter()
+            .map(|dir| (None, (*dir).to_owned())),
+    );
+    FcRetainLocations(&mut font_dirs, options);
+
+    let mut fonts = FcScanDirectoriesInner(&font_dirs, options);
+    let mut fallback = Vec::new();
+
+    if let Some(fonts_xml) = FcReadAndroidFontsXml() {
+        fallback = FcAndroidFallbackFamilies(&fonts_xml, &fonts);
+        let declared = FcAndroidFamilyPatterns(&fonts_xml, &fonts);
+        fonts.extend(declared);
+    }
+
+    (fonts, fallback)
+}
+
+/// Reads and parses `/system/etc/fonts.xml`
+pub(crate) fn FcReadAndroidFontsXml() -> Option<AndroidFontsXml> {
+    let xml_utf8 = std::fs::read_to_string(ANDROID_FONTS_XML).ok()?;
+    FcParseAndroidFontsXml(&xml_utf8)
+}
+
+// Creates one pattern per `<font>` of every named family (and alias), pointing to the scanned file
+fn FcAndroidFamilyPatterns(
+    fonts_xml: &AndroidFontsXml,
+    scanned: &[(FcPattern, FcFontPath)],
+) -> Vec<(FcPattern, FcFontPath)> {
+    let mut declared = Vec::new();
+
+    for family in fonts_xml.families.iter() {
+        let family_name = match family.name.as_ref() {
+            Some(s) => s,
+            None => continue,
+        };
+
+        for font in family.fonts.iter() {
+            if let Some((pattern, path)) = FcAndroidFontPattern(font, family_name, scanned) {
+                declared.push((pattern, path));
+            }
+        }
+    }
+
+    let mut aliased = Vec::new();
+    for alias in fonts_xml.aliases.iter() {
+        aliased.extend(
+            declared
+                .iter()
+                .filter(|(pattern, _)| pattern.family.as_deref() == Some(alias.to.as_str()))
+                .filter(|(pattern, _)| {
+                    alias.weight.is_none() || alias.weight == Some(pattern.weight)
+                })
+                .map(|(pattern, path)| {
+                    let mut pattern = pattern.clone();
+                    pattern.family = Some(alias.name.clone());
+                    (pattern, path.clone())
+                }),
+        );
+    }
+
+    declared.extend(aliased);
+    declared
+}
+
+// Every `<family>` (named or not) is a step in the fallback order, in declaration order
+fn FcAndroidFallbackFamilies(
+    fonts_xml: &AndroidFontsXml,
+    scanned: &[(FcPattern, FcFontPath)],
+) -> Vec<FcFallbackFamily> {
+    fonts_xml
+        .families
+        .iter()
+        .filter_map(|family| {
+            let fonts = family
+                .fonts
+                .iter()
+                .filter_map(|font| FcAndroidFontPath(font, scanned))
+                .collect::<Vec<_>>();
+
+            if fonts.is_empty() {
+                return None;
+            }
+
+            // `lang` may contain multiple space-separated tags, i.e. "zh-Hans zh-Bopo"
+            let langs = family
+                .lang
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(ToOwned::to_owned)
+                .collect();
+
+            Some(FcFallbackFamily { langs, fonts })
+        })
+        .collect()
+}
+
+// Resolves the file name of a `<font>` against the scanned fonts
+fn FcAndroidFontPath(
+    font: &AndroidFont,
+    scanned: &[(FcPattern, FcFontPath)],
+) -> Option<FcFontPath> {
+    FcAndroidScannedFont(font, scanned).map(|(_, path)| path.clone())
+}
+
+fn FcAndroidScannedFont<'a>(
+    font: &AndroidFont,
+    scanned: &'a [(FcPattern, FcFontPath)],
+) -> Option<&'a (FcPattern, FcFontPath)> {
+    // fonts.xml only contains file names, prefer the first directory that has the file
+    ANDROID_FONT_DIRS.iter().find_map(|dir| {
+        let full_path = std::path::Path::new(dir).join(&font.file);
+        let full_path = full_path.to_string_lossy();
+        scanned.iter().find(|(_, path)| {
+            path.path() == Some(&*full_path) && path.font_index == font.font_index
+        })
+    })
+}
+
+fn FcAndroidFontPattern(
+    font: &AndroidFont,
+    family_name: &str,
+    scanned: &[(FcPattern, FcFontPath)],
+) -> Option<(FcPattern, FcFontPath)> {
+    let (pattern, path) = FcAndroidScannedFont(font, scanned)?;
+
+    let mut pattern = pattern.clone();
+    pattern.family = Some(family_name.to_owned());
+    pattern.weight = font.weight;
+    pattern.bold = PatternMatch::from(font.weight >= FcWeight::BOLD);
+    pattern.italic = PatternMatch::from(font.italic);
+
+    Some((pattern, path.clone()))
+}
+
+// Parses the `<familyset>` of an Android fonts.xml file
+pub(crate) fn FcParseAndroidFontsXml(input: &str) -> Option<AndroidFontsXml> {
+    use xmlparser::Token::*;
+    use xmlparser::Tokenizer;
+
+    const TAG_FAMILY: &str = "family";
+    const TAG_FONT: &str = "font";
+    const TAG_ALIAS: &str = "alias";
+
+    let mut fonts_xml = AndroidFontsXml::default();
+
+    let mut current_tag: Option<&str> = None;
+    let mut current_family: Option<AndroidFamily> = None;
+    let mut current_font: Option<AndroidFont> = None;
+    let mut current_alias: Option<AndroidAlias> = None;
+
+    for token in Tokenizer::from(input) {
+        let token = token.ok()?;
+        match token {
+            ElementStart { local, .. } => {
+                let tag = local.as_str();
+                match tag {
+                    TAG_FAMILY => current_family = Some(AndroidFamily::default()),
+                    TAG_FONT if current_family.is_some() => {
+                        current_font = Some(AndroidFont {
+                            weight: FcWeight::REGULAR,
+                            ..Default::default()
+                        })
+                    }
+                    TAG_ALIAS => current_alias = Some(AndroidAlias::default()),
+                    _ => {}
+                }
+                current_tag = Some(tag);
+            }
+            Attribute { local, value, .. } => {
+                let value = value.as_str();
+                match (current_tag, local.as_str()) {
+                    (Some(TAG_FAMILY), attr) => {
+                        if let Some(family) = current_family.as_mut() {
+                            match attr {
+                                "name" => family.name = Some(value.to_owned()),
+                                "lang" => family.lang = Some(value.to_owned()),
+                                "variant" => family.variant = Some(value.to_owned()),
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Some(TAG_FONT), attr) => {
+                        if let Some(font) = current_font.as_mut() {
+                            match attr {
+                                "weight" => {
+                                    font.weight = value.parse().unwrap_or(FcWeight::REGULAR)
+                                }
+                                "style" => font.italic = value == "italic",
+                                "index" => font.font_index = value.parse().unwrap_or(0),
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Some(TAG_ALIAS), attr) => {
+                        if let Some(alias) = current_alias.as_mut() {
+                            match attr {
+                                "name" => alias.name = value.to_owned(),
+                                "to" => alias.to = value.to_owned(),
+                                "weight" => alias.weight = value.parse().ok(),
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Text { text } => {
+                let text = text.as_str().trim();
+                if let Some(font) = current_font.as_mut() {
+                    if font.file.is_empty() && !text.is_empty() {
+                        font.file = text.to_owned();
+                    }
+                }
+            }
+            ElementEnd { end, .. } => {
+                let closed_tag = match end {
+                    xmlparser::ElementEnd::Open => continue,
+                    xmlparser::ElementEnd::Empty => current_tag,
+                    xmlparser::ElementEnd::Close(_, local) => Some(local.as_str()),
+                };
+
+                match closed_tag {
+                    Some(TAG_FONT) => {
+                        if let (Some(family), Some(font)) =
+                            (current_family.as_mut(), current_font.take())
+                        {
+                            if !font.file.is_empty() {
+                                family.fonts.push(font);
+                            }
+                        }
+                    }
+                    Some(TAG_FAMILY) => {
+                        if let Some(family) = current_family.take() {
+                            fonts_xml.families.push(family);
+                        }
+                    }
+                    Some(TAG_ALIAS) => {
+                        if let Some(alias) = current_alias.take() {
+                            if !alias.name.is_empty() && !alias.to.is_empty() {
+                                fonts_xml.aliases.push(alias);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(fonts_xml)
+}
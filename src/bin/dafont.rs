@@ -0,0 +1,232 @@
+//! Command line tool (`cli` feature) to list and match fonts on platforms without
+//! fontconfig, similar to `fc-list` / `fc-match`
+//!
+//! ```text
+//! dafont list [--fc-list] [pattern]           fonts matching the pattern, all fonts without one
+//! dafont match [-s] [--lang <lang>] <pattern> best match, `-s` for all fonts in fallback order
+//! dafont info <file>...                       patterns, metadata and issues of the fonts of a file
+//! ```
+//!
+//! Patterns are font names (`DejaVu Sans:style=Bold:monospace=true`, see the `name`
+//! module), fonts are printed the same way followed by their file. `--fc-list` prints
+//! the fonts in the output format of `fc-list` instead (`/path/to/file: Family:style=Style`,
+//! see `FcPattern::to_fc_list`), for scripts that parse fc-list output.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use dafont::{FcFontCache, FcFontPath, FcFontSource, FcPattern, GenericFamily, PatternMatch};
+
+const USAGE: &str = "\
+usage: dafont list [--fc-list] [pattern]
+       dafont match [-s] [--lang <lang>] <pattern>
+       dafont info <file>...";
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.first().map(String::as_str) {
+        Some("list") => list(&args[1..]),
+        Some("match") => match_pattern(&args[1..]),
+        Some("info") => info(&args[1..]),
+        Some("-h") | Some("--help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        _ => Err(String::from(USAGE)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn list(args: &[String]) -> Result<(), String> {
+    let (fc_list, args) = match args {
+        [flag, args @ ..] if flag == "--fc-list" => (true, args),
+        args => (false, args),
+    };
+    let cache = FcFontCache::build_cached();
+    let fonts = match args {
+        [] => cache.list().iter().collect(),
+        [pattern] => cache.query_all_entries(&parse_pattern(pattern)?),
+        _ => return Err(String::from(USAGE)),
+    };
+    // stops at the first failed write, i.e. when piped into `head`
+    let mut out = io::stdout().lock();
+    for (pattern, path) in fonts {
+        let written = match fc_list {
+            true => writeln!(out, "{}", pattern.to_fc_list(path)),
+            false => writeln!(out, "{pattern}:{path}"),
+        };
+        if written.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn match_pattern(args: &[String]) -> Result<(), String> {
+    let mut sort = false;
+    let mut lang = None;
+    let mut pattern = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" | "--sort" => sort = true,
+            "--lang" => lang = Some(args.next().ok_or(USAGE)?.as_str()),
+            _ if pattern.is_none() => pattern = Some(parse_pattern(arg)?),
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+    let pattern = pattern.ok_or(USAGE)?;
+
+    let cache = FcFontCache::build_cached();
+    if !sort {
+        if let Some(font) = cache.query_owned(&pattern) {
+            println!("{font}");
+            return Ok(());
+        }
+    }
+
+    // same as fc-match, a pattern that no font matches gets the first fallback font (or the
+    // default sans-serif font on platforms without a fallback order)
+    let patterns = cache
+        .list()
+        .iter()
+        .map(|(pattern, path)| (path, pattern))
+        .collect::<BTreeMap<&FcFontPath, &FcPattern>>();
+    let mut sorted = cache.query_sorted(&pattern, lang);
+    if sorted.is_empty() {
+        sorted.extend(cache.default_font(GenericFamily::SansSerif));
+    }
+    if sorted.is_empty() {
+        return Err(String::from("dafont: no fonts found"));
+    }
+    let count = if sort { sorted.len() } else { 1 };
+    let mut out = io::stdout().lock();
+    for path in sorted.into_iter().take(count) {
+        let written = match patterns.get(path) {
+            Some(pattern) => writeln!(out, "{pattern}:{path}"),
+            None => writeln!(out, "{path}"),
+        };
+        if written.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn info(files: &[String]) -> Result<(), String> {
+    if files.is_empty() {
+        return Err(String::from(USAGE));
+    }
+
+    let mut found_all = true;
+    for file in files {
+        let mut cache = FcFontCache::default();
+        cache.add_font_file(file);
+        if cache.list().is_empty() {
+            eprintln!("dafont: {file}: no fonts found");
+            let path = FcFontPath {
+                source: FcFontSource::Disk(file.as_str().into()),
+                font_index: 0,
+            };
+            for issue in cache.validate(&path).errors() {
+                eprintln!("  error: {issue}");
+            }
+            found_all = false;
+            continue;
+        }
+
+        for (pattern, path) in cache.list() {
+            println!("{pattern}:{path}");
+            print_metadata(pattern);
+            for issue in cache.validate(path).issues {
+                let kind = if issue.is_error() { "error" } else { "warning" };
+                println!("  {kind}: {issue}");
+            }
+        }
+    }
+
+    match found_all {
+        true => Ok(()),
+        false => Err(String::from("dafont: some files have no fonts")),
+    }
+}
+
+fn print_metadata(pattern: &FcPattern) {
+    let metadata = &pattern.metadata;
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+
+    println!("  format:       {}", metadata.format.as_str());
+    println!(
+        "  weight:       {} ({})",
+        pattern.weight,
+        pattern.weight.name()
+    );
+    if let Some(vendor_id) = &metadata.vendor_id {
+        println!("  vendor:       {vendor_id}");
+    }
+    if metadata.italic_angle != 0 {
+        println!("  italic angle: {}", metadata.italic_angle());
+    }
+    println!(
+        "  scalable:     {}",
+        yes_no(pattern.scalable != PatternMatch::False)
+    );
+    println!("  hinted:       {}", yes_no(metadata.hinting.is_hinted()));
+    if !metadata.strikes.is_empty() {
+        let strikes = metadata.strikes.iter().map(u16::to_string);
+        println!("  strikes:      {}", strikes.collect::<Vec<_>>().join(" "));
+    }
+    println!("  characters:   {}", metadata.charset.len());
+    if !metadata.shaping.is_empty() {
+        let scripts = metadata.shaping.iter().map(|script| script.code());
+        println!("  shaping:      {}", scripts.collect::<Vec<_>>().join(" "));
+    }
+    if let Some(locale) = metadata.cjk.default_locale {
+        println!("  cjk:          {locale}");
+    }
+    println!(
+        "  kerning:      kern table {}, GPOS {}",
+        yes_no(metadata.kerning.has_kern_table),
+        yes_no(metadata.kerning.has_gpos_kerning)
+    );
+
+    let features = &metadata.features;
+    let tags = [
+        ("smcp", features.small_caps),
+        ("c2sc", features.caps_to_small_caps),
+        ("tnum", features.tabular_figures),
+        ("onum", features.old_style_figures),
+        ("lnum", features.lining_figures),
+    ];
+    let tags = tags
+        .iter()
+        .filter(|(_, has)| *has)
+        .map(|(tag, _)| *tag)
+        .collect::<Vec<_>>();
+    match tags.is_empty() {
+        true => println!("  features:     none"),
+        false => println!("  features:     {}", tags.join(" ")),
+    }
+    let figures = features
+        .default_figures
+        .map_or("unknown", |style| style.as_str());
+    let spacing = match features.monospaced_digits {
+        true => "tabular",
+        false => "proportional",
+    };
+    println!("  figures:      {figures}, {spacing}");
+}
+
+fn parse_pattern(pattern: &str) -> Result<FcPattern, String> {
+    pattern
+        .parse()
+        .map_err(|err| format!("dafont: invalid pattern {pattern:?}: {err}"))
+}
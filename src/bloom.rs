@@ -0,0 +1,66 @@
+//! Bloom filters over the characters of the fonts, see `FcFontCache::use_coverage_filters`
+
+use alloc::vec::Vec;
+
+use crate::FcCharset;
+
+// bits per element, about 1% false positives with `HASHES` hashes
+const BITS_PER_PAGE: usize = 10;
+const HASHES: u32 = 3;
+
+// Characters of every font as the pages of 256 code points the font has characters in,
+// `might_contain` is false for all characters of pages the font has no character in and
+// true for the characters of the other pages (and of about 1% of the pages by chance)
+//
+// The filters of all fonts are stored one after the other, so a font costs about 10 bits
+// per page instead of the 4 bytes per page of the index by pages.
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub(crate) struct FcCoverageFilters {
+    bits: Vec<u64>,
+    // end of the filter of every font in `bits`, in words
+    ends: Vec<u32>,
+}
+
+impl FcCoverageFilters {
+    pub(crate) fn clear(&mut self) {
+        self.bits.clear();
+        self.ends.clear();
+    }
+
+    // Adds the filter of the next font
+    pub(crate) fn push(&mut self, charset: &FcCharset) {
+        let pages = charset.pages().collect::<Vec<_>>();
+        let start = self.bits.len();
+        let words = (pages.len() * BITS_PER_PAGE).div_ceil(64);
+        self.bits.resize(start + words, 0);
+
+        let filter = &mut self.bits[start..];
+        for page in pages {
+            for bit in FcBitIndices(page, words) {
+                filter[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        self.ends.push(self.bits.len() as u32);
+    }
+
+    // Whether font `i` might have the character, also true for fonts without characters
+    // (i.e. fonts whose characters are only read from the font file, see `charset`)
+    pub(crate) fn might_contain(&self, i: usize, c: char) -> bool {
+        let start = match i {
+            0 => 0,
+            _ => self.ends[i - 1] as usize,
+        };
+        let filter = &self.bits[start..self.ends[i] as usize];
+        filter.is_empty()
+            || FcBitIndices(FcCharset::page(c), filter.len())
+                .all(|bit| filter[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+// Bits of a page in a filter of `words` words, double hashing of two multiplicative hashes
+fn FcBitIndices(page: u32, words: usize) -> impl Iterator<Item = usize> {
+    let len = (words * 64) as u64;
+    let h1 = u64::from(page.wrapping_mul(0x9E37_79B1));
+    let h2 = u64::from(page.wrapping_mul(0x85EB_CA77) | 1);
+    (0..u64::from(HASHES)).map(move |i| ((h1 + i * h2) % len) as usize)
+}
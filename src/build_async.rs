@@ -0,0 +1,125 @@
+//! Runtime-agnostic asynchronous builds
+//!
+//! The scan runs on its own thread (like `spawn_blocking`), the returned future
+//! only waits for the result, so it works with any executor.
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use core::any::Any;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+
+use crate::{FcCancellationToken, FcFontCache, FcFontCacheBuilder};
+
+type FcBuildResult = Result<FcFontCache, Box<dyn Any + Send>>;
+
+#[derive(Default)]
+struct FcBuildState {
+    result: Option<FcBuildResult>,
+    waker: Option<Waker>,
+}
+
+/// Future of `FcFontCache::build_async` / `FcFontCacheBuilder::build_async`
+///
+/// Dropping the future before it completes cancels the build.
+pub struct FcBuildFuture {
+    state: Arc<Mutex<FcBuildState>>,
+    // token created for this build, `None` if the builder has its own token
+    cancellation: Option<FcCancellationToken>,
+}
+
+impl core::fmt::Debug for FcBuildFuture {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FcBuildFuture").finish_non_exhaustive()
+    }
+}
+
+impl Future for FcBuildFuture {
+    type Output = FcFontCache;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<FcFontCache> {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        match state.result.take() {
+            Some(Ok(cache)) => Poll::Ready(cache),
+            // same as awaiting a `JoinHandle`: a panic of the build is raised in the awaiting task
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for FcBuildFuture {
+    fn drop(&mut self) {
+        if let Some(token) = self.cancellation.as_ref() {
+            token.cancel();
+        }
+    }
+}
+
+impl FcFontCacheBuilder {
+    /// Builds the font cache on a background thread, without blocking the executor
+    ///
+    /// Falls back to building on the current thread if no thread can be spawned
+    /// (i.e. on `wasm32-unknown-unknown`).
+    pub fn build_async(&self) -> FcBuildFuture {
+        let mut builder = self.clone();
+        let cancellation = match builder.options.cancellation {
+            Some(_) => None,
+            None => {
+                let token = FcCancellationToken::new();
+                builder.cancellation(token.clone());
+                Some(token)
+            }
+        };
+
+        let state = Arc::new(Mutex::new(FcBuildState::default()));
+        let thread_state = state.clone();
+        let thread_builder = builder.clone();
+
+        let spawned = std::thread::Builder::new()
+            .name("dafont-build".to_owned())
+            .spawn(move || {
+                let result = std::panic::catch_unwind(AssertUnwindSafe(|| thread_builder.build()));
+                FcFinishBuild(&thread_state, result);
+            });
+
+        if spawned.is_err() {
+            FcFinishBuild(&state, Ok(builder.build()));
+        }
+
+        FcBuildFuture {
+            state,
+            cancellation,
+        }
+    }
+}
+
+impl FcFontCache {
+    /// Same as `build`, but scans on a background thread and returns a future that
+    /// resolves to the font cache, works with any async runtime
+    pub fn build_async() -> FcBuildFuture {
+        FcFontCacheBuilder::new().build_async()
+    }
+}
+
+// Stores the result and wakes the task waiting for it
+fn FcFinishBuild(state: &Mutex<FcBuildState>, result: FcBuildResult) {
+    let mut state = match state.lock() {
+        Ok(state) => state,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    state.result = Some(result);
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
@@ -0,0 +1,84 @@
+//! Access to the data of the cached fonts, see `FcFontCache::get_font_bytes`
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Deref;
+use std::path::Path;
+
+use crate::{FcFontCache, FcFontPath, FcFontSource};
+
+/// Data of a font file (all faces of a font collection), derefs to `[u8]`
+#[derive(Debug)]
+pub enum FcFontBytes {
+    /// Memory-mapped font file
+    #[cfg(not(target_family = "wasm"))]
+    Mapped(mmapio::Mmap),
+    /// Font file that was read into memory, where it can't be mapped
+    Read(Vec<u8>),
+    /// Data of an in-memory font, shared with the cache
+    Memory(Arc<[u8]>),
+}
+
+impl FcFontBytes {
+    /// Maps (or reads) the font file of `source`, in-memory fonts are shared
+    pub(crate) fn load(source: &FcFontSource) -> Option<Self> {
+        match source {
+            FcFontSource::Disk(file) => FcFontBytes::open(Path::new(&**file)),
+            FcFontSource::Memory(data) => Some(FcFontBytes::Memory(data.clone())),
+        }
+    }
+
+    /// Maps (or reads) the font file at `path`
+    pub(crate) fn open(path: &Path) -> Option<Self> {
+        // legacy font suitcases only have a resource fork, the data fork is empty
+        #[cfg(target_os = "macos")]
+        let resource_fork_path;
+        #[cfg(target_os = "macos")]
+        let path = if std::fs::metadata(path).ok()?.len() == 0 {
+            resource_fork_path = path.join("..namedfork/rsrc");
+            &resource_fork_path
+        } else {
+            path
+        };
+
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let file = std::fs::File::open(path).ok()?;
+            // empty files can't be mapped
+            if let Ok(mmap) = unsafe { mmapio::MmapOptions::new().map(&file) } {
+                return Some(FcFontBytes::Mapped(mmap));
+            }
+        }
+
+        std::fs::read(path).ok().map(FcFontBytes::Read)
+    }
+}
+
+impl Deref for FcFontBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(not(target_family = "wasm"))]
+            FcFontBytes::Mapped(mmap) => mmap,
+            FcFontBytes::Read(bytes) => bytes,
+            FcFontBytes::Memory(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for FcFontBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl FcFontCache {
+    /// Returns the data of the font file of `path` (use `path.font_index` to select the
+    /// face of a collection), fonts on disk are memory-mapped
+    ///
+    /// Returns `None` if the file can't be read anymore.
+    pub fn get_font_bytes(&self, path: &FcFontPath) -> Option<FcFontBytes> {
+        FcFontBytes::load(&path.source)
+    }
+}
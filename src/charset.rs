@@ -0,0 +1,268 @@
+//! Sets of Unicode characters, see `FcCharset`
+
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+use core::ops::RangeInclusive;
+
+// characters per page, one bit per character
+const PAGE_BITS: u32 = 256;
+
+/// Set of Unicode characters, i.e. the characters a font supports (see
+/// `FcFontMetadata::charset`), same as the `FcCharSet` of fontconfig
+///
+/// Stored as a sparse bitset of 256-character pages, so that both small sets and the
+/// full repertoire of a pan-Unicode font stay compact and lookups only need a binary
+/// search over the pages.
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FcCharset {
+    // (page, bits) sorted by page, page `p` holds the characters `p * 256..p * 256 + 256`,
+    // no page is empty
+    pages: Vec<(u32, [u32; 8])>,
+}
+
+impl FcCharset {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a set of all characters of the given ranges
+    pub fn from_ranges<I: IntoIterator<Item = RangeInclusive<char>>>(ranges: I) -> Self {
+        let mut charset = Self::new();
+        for range in ranges {
+            charset.insert_range(u32::from(*range.start()), u32::from(*range.end()));
+        }
+        charset
+    }
+
+    /// Adds a character, returns whether it wasn't in the set yet
+    pub fn insert(&mut self, c: char) -> bool {
+        let c = u32::from(c);
+        let (word, bit) = FcBitOf(c);
+        let bits = self.page_mut(c / PAGE_BITS);
+        let inserted = bits[word] & bit == 0;
+        bits[word] |= bit;
+        inserted
+    }
+
+    /// Returns whether the set contains a character
+    pub fn contains(&self, c: char) -> bool {
+        let c = u32::from(c);
+        let (word, bit) = FcBitOf(c);
+        match self
+            .pages
+            .binary_search_by_key(&(c / PAGE_BITS), |(page, _)| *page)
+        {
+            Ok(index) => self.pages[index].1[word] & bit != 0,
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the number of characters in the set
+    pub fn len(&self) -> usize {
+        self.pages
+            .iter()
+            .flat_map(|(_, bits)| bits.iter())
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns whether the set has no characters
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Returns the characters that are in either set
+    pub fn union(&self, other: &FcCharset) -> FcCharset {
+        self.merge(other, true, true, |a, b| a | b)
+    }
+
+    /// Returns the characters that are in both sets
+    pub fn intersection(&self, other: &FcCharset) -> FcCharset {
+        self.merge(other, false, false, |a, b| a & b)
+    }
+
+    /// Returns the characters of this set that aren't in `other`
+    pub fn difference(&self, other: &FcCharset) -> FcCharset {
+        self.merge(other, true, false, |a, b| a & !b)
+    }
+
+    /// Returns whether all characters of this set are in `other`, i.e. whether a font with
+    /// the characters of `other` supports a text with the characters of this set
+    pub fn is_subset(&self, other: &FcCharset) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    /// Returns the characters of the set in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        self.pages.iter().flat_map(|(page, bits)| {
+            bits.iter().enumerate().flat_map(move |(i, word)| {
+                let base = page * PAGE_BITS + i as u32 * 32;
+                (0..32)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .filter_map(move |bit| char::from_u32(base + bit))
+            })
+        })
+    }
+
+    /// Returns the characters of the set as ascending, non-adjacent ranges
+    pub fn ranges(&self) -> impl Iterator<Item = RangeInclusive<char>> + '_ {
+        let mut chars = self.iter().peekable();
+        core::iter::from_fn(move || {
+            let start = chars.next()?;
+            let mut end = start;
+            while let Some(next) = chars.peek() {
+                // the surrogates are never part of a set, the ranges are split around them
+                if u32::from(*next) != u32::from(end) + 1 {
+                    break;
+                }
+                end = *next;
+                chars.next();
+            }
+            Some(start..=end)
+        })
+    }
+
+    // Adds the characters `start..=end` (code points, invalid ones are skipped)
+    pub(crate) fn insert_range(&mut self, start: u32, end: u32) {
+        const SURROGATES: RangeInclusive<u32> = 0xD800..=0xDFFF;
+
+        let end = end.min(0x10FFFF);
+        let mut c = start;
+        while c <= end {
+            if SURROGATES.contains(&c) {
+                c = *SURROGATES.end() + 1;
+                continue;
+            }
+            let (word, bit) = FcBitOf(c);
+            // whole words at once when possible
+            if bit == 1 && end - c >= 31 {
+                self.page_mut(c / PAGE_BITS)[word] = u32::MAX;
+                c += 32;
+            } else {
+                self.page_mut(c / PAGE_BITS)[word] |= bit;
+                c += 1;
+            }
+        }
+    }
+
+    // Page of a character, see `pages`
+    pub(crate) fn page(c: char) -> u32 {
+        u32::from(c) / PAGE_BITS
+    }
+
+    // Pages that have at least one character, ascending
+    pub(crate) fn pages(&self) -> impl Iterator<Item = u32> + '_ {
+        self.pages.iter().map(|(page, _)| *page)
+    }
+
+    // Adds the characters of a page (bit `i` of word `w` is the character `w * 32 + i`),
+    // pages of surrogates or beyond the last code point are skipped
+    #[cfg(all(
+        any(
+            target_os = "linux",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly"
+        ),
+        feature = "std",
+        feature = "scan"
+    ))]
+    pub(crate) fn insert_page(&mut self, page: u32, bits: [u32; 8]) {
+        let invalid = (0xD8..=0xDF).contains(&page) || page > 0x10FFFF / PAGE_BITS;
+        if invalid || bits.iter().all(|word| *word == 0) {
+            return;
+        }
+        let page = self.page_mut(page);
+        for (word, new) in page.iter_mut().zip(bits.iter()) {
+            *word |= new;
+        }
+    }
+
+    fn page_mut(&mut self, page: u32) -> &mut [u32; 8] {
+        let index = match self.pages.binary_search_by_key(&page, |(page, _)| *page) {
+            Ok(index) => index,
+            Err(index) => {
+                self.pages.insert(index, (page, [0; 8]));
+                index
+            }
+        };
+        &mut self.pages[index].1
+    }
+
+    // Combines the pages of both sets, `keep_self` / `keep_other` tell whether the pages
+    // that only one of the sets has are kept
+    fn merge<F: Fn(u32, u32) -> u32>(
+        &self,
+        other: &FcCharset,
+        keep_self: bool,
+        keep_other: bool,
+        op: F,
+    ) -> FcCharset {
+        let mut pages = Vec::new();
+        let (mut a, mut b) = (self.pages.iter().peekable(), other.pages.iter().peekable());
+        loop {
+            let (page, bits) = match (a.peek().copied(), b.peek().copied()) {
+                (Some((pa, bits_a)), Some((pb, bits_b))) if pa == pb => {
+                    let mut bits = [0; 8];
+                    for i in 0..8 {
+                        bits[i] = op(bits_a[i], bits_b[i]);
+                    }
+                    a.next();
+                    b.next();
+                    (*pa, bits)
+                }
+                (Some((pa, bits)), Some((pb, _))) if pa < pb => {
+                    a.next();
+                    match keep_self {
+                        true => (*pa, *bits),
+                        false => continue,
+                    }
+                }
+                (Some(_), Some((pb, bits))) => {
+                    b.next();
+                    match keep_other {
+                        true => (*pb, *bits),
+                        false => continue,
+                    }
+                }
+                (Some((pa, bits)), None) if keep_self => {
+                    a.next();
+                    (*pa, *bits)
+                }
+                (None, Some((pb, bits))) if keep_other => {
+                    b.next();
+                    (*pb, *bits)
+                }
+                _ => break,
+            };
+            if bits.iter().any(|word| *word != 0) {
+                pages.push((page, bits));
+            }
+        }
+        FcCharset { pages }
+    }
+}
+
+impl FromIterator<char> for FcCharset {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        let mut charset = Self::new();
+        charset.extend(iter);
+        charset
+    }
+}
+
+impl Extend<char> for FcCharset {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        for c in iter {
+            self.insert(c);
+        }
+    }
+}
+
+// Word and bit of a character in its page
+fn FcBitOf(c: u32) -> (usize, u32) {
+    (((c % PAGE_BITS) / 32) as usize, 1 << (c % 32))
+}
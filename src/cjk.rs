@@ -0,0 +1,328 @@
+//! Regional glyph conventions of CJK fonts (Han unification), see `FcCjkInfo`
+
+#[cfg(all(feature = "std", feature = "scan"))]
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+#[cfg(all(feature = "std", feature = "scan"))]
+use crate::utils::{read_u16, read_u32};
+use crate::FcError;
+
+/// Regional conventions for the glyphs of the unified Han characters, the same code point
+/// is drawn differently in Chinese, Japanese and Korean text
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcCjkLocale {
+    /// Simplified Chinese (mainland China, Singapore)
+    SimplifiedChinese,
+    /// Traditional Chinese (Taiwan)
+    TraditionalChinese,
+    /// Traditional Chinese (Hong Kong, Macau)
+    HongKong,
+    Japanese,
+    Korean,
+}
+
+impl FcCjkLocale {
+    /// Returns the locale of a language tag (`"ja-JP"`, `"zh-Hant"`, `"zh-HK"`, ...),
+    /// `None` for languages that aren't written with Han characters
+    ///
+    /// Chinese without a region or script is Simplified Chinese.
+    pub fn from_lang(lang: &str) -> Option<Self> {
+        let mut subtags = lang.split(['-', '_']);
+        let language = subtags.next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "ja" => return Some(FcCjkLocale::Japanese),
+            "ko" => return Some(FcCjkLocale::Korean),
+            "zh" | "cmn" | "yue" => {}
+            _ => return None,
+        }
+
+        let mut locale = match language.as_str() {
+            "yue" => FcCjkLocale::HongKong,
+            _ => FcCjkLocale::SimplifiedChinese,
+        };
+        for subtag in subtags {
+            match subtag.to_ascii_lowercase().as_str() {
+                "hans" | "cn" | "sg" => locale = FcCjkLocale::SimplifiedChinese,
+                "hant" | "tw" => locale = FcCjkLocale::TraditionalChinese,
+                "hk" | "mo" => return Some(FcCjkLocale::HongKong),
+                _ => {}
+            }
+        }
+        Some(locale)
+    }
+
+    /// Returns the language tag of the locale (`"zh-CN"`, `"zh-TW"`, `"zh-HK"`, `"ja"`, `"ko"`)
+    pub fn lang(&self) -> &'static str {
+        match self {
+            FcCjkLocale::SimplifiedChinese => "zh-CN",
+            FcCjkLocale::TraditionalChinese => "zh-TW",
+            FcCjkLocale::HongKong => "zh-HK",
+            FcCjkLocale::Japanese => "ja",
+            FcCjkLocale::Korean => "ko",
+        }
+    }
+}
+
+/// Writes the language tag of the locale, see `lang`
+impl fmt::Display for FcCjkLocale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.lang())
+    }
+}
+
+/// Parses a language tag, see `from_lang`
+impl FromStr for FcCjkLocale {
+    type Err = FcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FcCjkLocale::from_lang(s).ok_or_else(|| FcError::InvalidValue { value: s.into() })
+    }
+}
+
+/// CJK locales a font is designed for, see `FcFontMetadata::cjk`
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[repr(C)]
+pub struct FcCjkInfo {
+    // conventions of the default glyphs, `None` for fonts without CJK characters and fonts
+    // that don't declare their locale (names, code pages)
+    pub default_locale: Option<FcCjkLocale>,
+    // locales with own glyphs through the `locl` feature of the `GSUB` table (pan-CJK fonts),
+    // sorted
+    pub locl: Vec<FcCjkLocale>,
+}
+
+impl FcCjkInfo {
+    /// Returns whether the font draws the Han characters with the conventions of `locale`,
+    /// by default or through `locl` (the text has to be shaped with the language)
+    pub fn supports(&self, locale: FcCjkLocale) -> bool {
+        self.default_locale == Some(locale) || self.locl.contains(&locale)
+    }
+}
+
+// Detects the CJK locales of a font from its OS/2, name and GSUB tables
+#[cfg(all(feature = "std", feature = "scan"))]
+pub(crate) fn FcParseCjkInfo(
+    os2_data: Option<&[u8]>,
+    name_data: Option<&[u8]>,
+    gsub_data: Option<&[u8]>,
+) -> FcCjkInfo {
+    // OS/2 ulUnicodeRange2 bits of the CJK blocks
+    const RANGE2_HIRAGANA: u32 = 1 << (49 - 32);
+    const RANGE2_KATAKANA: u32 = 1 << (50 - 32);
+    const RANGE2_HANGUL: u32 = 1 << (56 - 32);
+    const RANGE2_CJK_IDEOGRAPHS: u32 = 1 << (59 - 32);
+
+    // ulUnicodeRange2, see https://learn.microsoft.com/en-us/typography/opentype/spec/os2#ulunicoderange1-bits-031ulunicoderange2-bits-3263ulunicoderange3-bits-6495ulunicoderange4-bits-96127
+    let unicode_range2 = os2_data.and_then(|os2| read_u32(os2, 46)).unwrap_or(0);
+    let has_han = unicode_range2 & RANGE2_CJK_IDEOGRAPHS != 0;
+    let has_kana = unicode_range2 & (RANGE2_HIRAGANA | RANGE2_KATAKANA) != 0;
+    let has_hangul = unicode_range2 & RANGE2_HANGUL != 0;
+    if !has_han && !has_kana && !has_hangul {
+        return FcCjkInfo::default();
+    }
+
+    let default_locale = name_data
+        .and_then(FcFamilyLocale)
+        .or_else(|| name_data.and_then(FcNameLocale))
+        .or_else(|| os2_data.and_then(FcCodePageLocale))
+        .or(match (has_han, has_kana, has_hangul) {
+            (false, true, false) => Some(FcCjkLocale::Japanese),
+            (false, false, true) => Some(FcCjkLocale::Korean),
+            _ => None,
+        });
+
+    let mut locl = gsub_data.map(FcLoclLocales).unwrap_or_default();
+    locl.sort();
+    locl.dedup();
+
+    FcCjkInfo {
+        default_locale,
+        locl,
+    }
+}
+
+// Returns the locale if exactly one locale was found
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcSingleLocale<I: Iterator<Item = FcCjkLocale>>(locales: I) -> Option<FcCjkLocale> {
+    let mut locales = locales.collect::<Vec<_>>();
+    locales.sort();
+    locales.dedup();
+    match locales.as_slice() {
+        [locale] => Some(*locale),
+        _ => None,
+    }
+}
+
+// Region suffix of the English family name ("Noto Sans CJK JP", "Source Han Sans TC")
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcFamilyLocale(name_data: &[u8]) -> Option<FcCjkLocale> {
+    let family = FcEnglishFamily(name_data)?;
+    FcSingleLocale(family.split_whitespace().filter_map(|word| match word {
+        "SC" | "CN" | "Hans" => Some(FcCjkLocale::SimplifiedChinese),
+        "TC" | "TW" | "Hant" => Some(FcCjkLocale::TraditionalChinese),
+        "HK" => Some(FcCjkLocale::HongKong),
+        "JP" => Some(FcCjkLocale::Japanese),
+        "KR" => Some(FcCjkLocale::Korean),
+        _ => None,
+    }))
+}
+
+// Languages of the localized names (Windows platform), see
+// https://learn.microsoft.com/en-us/typography/opentype/spec/name
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcNameLocale(name_data: &[u8]) -> Option<FcCjkLocale> {
+    // Windows language IDs of localized names
+    const NAME_LANGUAGES: &[(u16, FcCjkLocale)] = &[
+        (0x0404, FcCjkLocale::TraditionalChinese),
+        (0x0411, FcCjkLocale::Japanese),
+        (0x0412, FcCjkLocale::Korean),
+        (0x0804, FcCjkLocale::SimplifiedChinese),
+        (0x0C04, FcCjkLocale::HongKong),
+        (0x1004, FcCjkLocale::SimplifiedChinese),
+        (0x1404, FcCjkLocale::HongKong),
+    ];
+
+    FcSingleLocale(
+        FcNameRecords(name_data).filter_map(|(platform_id, language_id, _, _)| {
+            if platform_id != 3 {
+                return None;
+            }
+            NAME_LANGUAGES
+                .iter()
+                .find(|(id, _)| *id == language_id)
+                .map(|(_, locale)| *locale)
+        }),
+    )
+}
+
+// Supported code pages (`ulCodePageRange1`, OS/2 version 1 and later)
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcCodePageLocale(os2_data: &[u8]) -> Option<FcCjkLocale> {
+    // OS/2 ulCodePageRange1 bits
+    const CODE_PAGES: &[(u32, FcCjkLocale)] = &[
+        (1 << 17, FcCjkLocale::Japanese),
+        (1 << 18, FcCjkLocale::SimplifiedChinese),
+        (1 << 19, FcCjkLocale::Korean),
+        (1 << 20, FcCjkLocale::TraditionalChinese),
+        (1 << 21, FcCjkLocale::Korean),
+    ];
+
+    if read_u16(os2_data, 0)? < 1 {
+        return None;
+    }
+    let code_pages = read_u32(os2_data, 78)?;
+    FcSingleLocale(
+        CODE_PAGES
+            .iter()
+            .filter(|(bit, _)| code_pages & bit != 0)
+            .map(|(_, locale)| *locale),
+    )
+}
+
+// (platform ID, language ID, name ID, UTF-16BE / bytes) of the records of a name table
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcNameRecords(name_data: &[u8]) -> impl Iterator<Item = (u16, u16, u16, &[u8])> + '_ {
+    let count = read_u16(name_data, 2).unwrap_or(0);
+    let storage = usize::from(read_u16(name_data, 4).unwrap_or(0));
+    (0..usize::from(count)).filter_map(move |i| {
+        let record = 6 + i * 12;
+        let platform_id = read_u16(name_data, record)?;
+        let language_id = read_u16(name_data, record + 4)?;
+        let name_id = read_u16(name_data, record + 6)?;
+        let length = usize::from(read_u16(name_data, record + 8)?);
+        let offset = storage + usize::from(read_u16(name_data, record + 10)?);
+        let bytes = name_data.get(offset..offset.checked_add(length)?)?;
+        Some((platform_id, language_id, name_id, bytes))
+    })
+}
+
+// Family name (name ID 1) in US English, Windows platform
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcEnglishFamily(name_data: &[u8]) -> Option<String> {
+    let (_, _, _, bytes) =
+        FcNameRecords(name_data).find(|(platform_id, language_id, name_id, _)| {
+            *platform_id == 3 && *language_id == 0x0409 && *name_id == 1
+        })?;
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+    Some(
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    )
+}
+
+// Language systems of the Han, kana and Hangul scripts with a `locl` feature, see
+// https://learn.microsoft.com/en-us/typography/opentype/spec/chapter2#slTbl_sRec
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcLoclLocales(gsub_data: &[u8]) -> Vec<FcCjkLocale> {
+    // OpenType language system tags of `GSUB`
+    const LANG_SYSTEMS: &[(&[u8; 4], FcCjkLocale)] = &[
+        (b"ZHS ", FcCjkLocale::SimplifiedChinese),
+        (b"ZHT ", FcCjkLocale::TraditionalChinese),
+        (b"ZHH ", FcCjkLocale::HongKong),
+        (b"JAN ", FcCjkLocale::Japanese),
+        (b"KOR ", FcCjkLocale::Korean),
+    ];
+
+    let mut locales = Vec::new();
+    let script_list = match read_u16(gsub_data, 4) {
+        Some(offset) => usize::from(offset),
+        None => return locales,
+    };
+    let feature_list = match read_u16(gsub_data, 6) {
+        Some(offset) => usize::from(offset),
+        None => return locales,
+    };
+    let is_locl = |feature_index: u16| {
+        let record = feature_list + 2 + usize::from(feature_index) * 6;
+        gsub_data.get(record..record + 4) == Some(b"locl")
+    };
+
+    let script_count = read_u16(gsub_data, script_list).unwrap_or(0);
+    for i in 0..usize::from(script_count) {
+        let record = script_list + 2 + i * 6;
+        let tag = match gsub_data.get(record..record + 4) {
+            Some(tag) => tag,
+            None => break,
+        };
+        if !matches!(tag, b"hani" | b"kana" | b"hang") {
+            continue;
+        }
+        let script = match read_u16(gsub_data, record + 4) {
+            Some(offset) => script_list + usize::from(offset),
+            None => break,
+        };
+
+        let lang_sys_count = read_u16(gsub_data, script + 2).unwrap_or(0);
+        for j in 0..usize::from(lang_sys_count) {
+            let lang_record = script + 4 + j * 6;
+            let locale = match gsub_data.get(lang_record..lang_record + 4) {
+                Some(lang_tag) => LANG_SYSTEMS
+                    .iter()
+                    .find(|(tag, _)| &tag[..] == lang_tag)
+                    .map(|(_, locale)| *locale),
+                None => break,
+            };
+            let (locale, lang_sys) = match (locale, read_u16(gsub_data, lang_record + 4)) {
+                (Some(locale), Some(offset)) => (locale, script + usize::from(offset)),
+                _ => continue,
+            };
+            let feature_count = read_u16(gsub_data, lang_sys + 4).unwrap_or(0);
+            let has_locl = (0..usize::from(feature_count))
+                .any(|k| read_u16(gsub_data, lang_sys + 6 + k * 2).is_some_and(is_locl));
+            if has_locl {
+                locales.push(locale);
+            }
+        }
+    }
+    locales
+}
@@ -0,0 +1,147 @@
+//! Font caches generated at compile time, see `FcCacheCodegen`
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::path::{Path, PathBuf};
+
+use crate::store::FcFontStore;
+use crate::{FcError, FcFontCache, FcFontCacheBuilder, FcFontPath, FcFontSource};
+
+/// Generates the cache of the fonts an application ships with from a build script, so that
+/// the application doesn't scan or parse any font at runtime
+///
+/// The cache is written in the format of `FcFontCache::to_serialized` and can be embedded
+/// with `include_bytes!`. Only the fonts of the added directories are included, never the
+/// fonts of the machine that builds the application.
+///
+/// ```rust,no_run
+/// // build.rs
+/// let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+/// dafont::FcCacheCodegen::new()
+///     .add_dir("assets/fonts")
+///     .embed_fonts(true)
+///     .write(out_dir.join("fonts.cache"))
+///     .unwrap();
+/// ```
+///
+/// ```rust,ignore
+/// // main.rs
+/// static FONTS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/fonts.cache"));
+///
+/// let cache = dafont::FcFontCache::from_serialized(FONTS).unwrap();
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FcCacheCodegen {
+    dirs: Vec<PathBuf>,
+    // store the font data instead of the paths, see `embed_fonts`
+    embed_fonts: bool,
+    // paths are stored relative to this directory, see `relative_to`
+    base_dir: Option<PathBuf>,
+}
+
+impl FcCacheCodegen {
+    /// Creates a generator without any font directory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the fonts of the given directory (and its subdirectories)
+    pub fn add_dir<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    /// Stores the data of the font files in the cache instead of their paths (default: false)
+    ///
+    /// The fonts of the generated cache are in-memory fonts, so the application doesn't
+    /// need to ship or find the font files (i.e. on `no_std` or wasm targets), at the cost
+    /// of a cache as large as all fonts together.
+    pub fn embed_fonts(&mut self, embed_fonts: bool) -> &mut Self {
+        self.embed_fonts = embed_fonts;
+        self
+    }
+
+    /// Stores the paths of the font files relative to `dir` (i.e. the directory of the
+    /// assets that is shipped with the application), files outside of `dir` keep their
+    /// path
+    ///
+    /// The application has to resolve the paths of the query results against the location
+    /// of its assets. Ignored if the fonts are embedded.
+    pub fn relative_to<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.base_dir = Some(dir.into());
+        self
+    }
+
+    /// Scans and parses the fonts of the directories
+    ///
+    /// Fails if one of the directories or font files can't be read.
+    pub fn build(&self) -> Result<FcFontCache, FcError> {
+        let mut builder = FcFontCacheBuilder::new();
+        builder.system_fonts(false).user_fonts(false);
+        for dir in self.dirs.iter() {
+            builder.add_dir(dir);
+        }
+        let cache = builder.try_build()?;
+
+        // every face / style of a file shares the data of the file
+        let mut files = BTreeMap::<Arc<str>, FcFontSource>::new();
+        let mut fonts = Vec::new();
+        for (pattern, path) in cache.list() {
+            let source = match &path.source {
+                FcFontSource::Disk(file) => match files.get(file) {
+                    Some(source) => source.clone(),
+                    None => {
+                        let source = self.source(file)?;
+                        files.insert(file.clone(), source.clone());
+                        source
+                    }
+                },
+                FcFontSource::Memory(data) => FcFontSource::Memory(data.clone()),
+            };
+            let path = FcFontPath {
+                source,
+                font_index: path.font_index,
+            };
+            fonts.push((pattern.clone(), path));
+        }
+
+        Ok(FcFontCache {
+            map: FcFontStore::from_entries(fonts),
+            ..Default::default()
+        })
+    }
+
+    /// Builds the cache and writes it to `file` (usually inside `OUT_DIR`)
+    ///
+    /// Also prints `cargo:rerun-if-changed` for every directory, so that the build script
+    /// runs again when fonts are added or removed.
+    pub fn write<P: AsRef<Path>>(&self, file: P) -> Result<(), FcError> {
+        for dir in self.dirs.iter() {
+            println!("cargo:rerun-if-changed={}", dir.display());
+        }
+
+        let file = file.as_ref();
+        let cache = self.build()?;
+        std::fs::write(file, cache.to_serialized()).map_err(|error| FcError::io(file, error))
+    }
+
+    // Source of a font file in the generated cache
+    fn source(&self, file: &str) -> Result<FcFontSource, FcError> {
+        if self.embed_fonts {
+            let data = std::fs::read(file).map_err(|error| FcError::io(Path::new(file), error))?;
+            return Ok(FcFontSource::Memory(data.into()));
+        }
+
+        let relative = self
+            .base_dir
+            .as_ref()
+            .and_then(|base_dir| Path::new(file).strip_prefix(base_dir).ok())
+            .map(|relative| String::from(relative.to_string_lossy()));
+        Ok(match relative {
+            Some(relative) => FcFontSource::Disk(relative.into()),
+            None => FcFontSource::Disk(file.into()),
+        })
+    }
+}
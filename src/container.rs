@@ -0,0 +1,134 @@
+//! File formats fonts are stored in, see `FcFontContainer` and `FcFontFormat`
+
+use alloc::string::String;
+use core::str::FromStr;
+
+use crate::FcError;
+
+const TAG_WOFF: u32 = u32::from_be_bytes(*b"wOFF");
+const TAG_WOFF2: u32 = u32::from_be_bytes(*b"wOF2");
+const TAG_PCF: u32 = u32::from_be_bytes(*b"\x01fcp");
+
+/// File format a font is stored in, see `FcFontMetadata::container`
+#[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcFontContainer {
+    /// Plain OpenType / TrueType font or collection
+    #[default]
+    Sfnt,
+    /// WOFF (zlib-compressed tables)
+    Woff,
+    /// WOFF2 (Brotli-compressed, transformed `glyf` / `loca` / `hmtx` tables)
+    Woff2,
+    /// PostScript Type 1 font, binary (`.pfb`) or ASCII (`.pfa`)
+    Type1,
+    /// X11 bitmap font (`.pcf`, gzip-compressed `.pcf.gz` files with the `gzip` feature)
+    Pcf,
+}
+
+impl FcFontContainer {
+    /// Returns the container of font data from its signature, `Sfnt` for all data that
+    /// isn't a WOFF / WOFF2 / Type 1 / PCF file
+    pub fn of(font_bytes: &[u8]) -> Self {
+        match font_bytes.get(..4) {
+            Some(tag) if tag == TAG_WOFF.to_be_bytes() => FcFontContainer::Woff,
+            Some(tag) if tag == TAG_WOFF2.to_be_bytes() => FcFontContainer::Woff2,
+            Some(tag) if tag == TAG_PCF.to_be_bytes() => FcFontContainer::Pcf,
+            _ if FcIsType1(font_bytes) => FcFontContainer::Type1,
+            _ => FcFontContainer::Sfnt,
+        }
+    }
+}
+
+// Whether the data starts with the header comment of a Type 1 font, after the segment
+// header of a PFB file (0x80, segment type 1 = ASCII, u32 little-endian length)
+fn FcIsType1(font_bytes: &[u8]) -> bool {
+    const PFB_ASCII_SEGMENT: [u8; 2] = [0x80, 0x01];
+    const SIGNATURES: [&[u8]; 2] = [b"%!PS-AdobeFont", b"%!FontType1"];
+
+    let header = match font_bytes.get(..2) {
+        Some(segment) if segment == PFB_ASCII_SEGMENT => font_bytes.get(6..).unwrap_or(&[]),
+        _ => font_bytes,
+    };
+    SIGNATURES
+        .iter()
+        .any(|signature| header.starts_with(signature))
+}
+
+/// Format of a font, see `FcFontMetadata::format`
+///
+/// Unlike `FcFontContainer`, this tells apart the outline formats of OpenType fonts and
+/// collections, i.e. what a PDF writer or rasterizer has to be able to read. Callers that
+/// only read some formats can drop the others with `FcFontCache::filtered`.
+#[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcFontFormat {
+    /// OpenType font with TrueType (`glyf`) outlines (`.ttf`)
+    #[default]
+    TrueType,
+    /// OpenType font with CFF / CFF2 outlines (`.otf`)
+    Cff,
+    /// OpenType / TrueType collection (`.ttc`, `.otc`)
+    Collection,
+    /// WOFF web font
+    Woff,
+    /// WOFF2 web font
+    Woff2,
+    /// PostScript Type 1 font
+    Type1,
+    /// Font without outlines: X11 bitmap fonts and OpenType fonts that only have bitmap
+    /// strikes (i.e. color emoji fonts)
+    Bitmap,
+}
+
+impl FcFontFormat {
+    /// Returns whether the font is a plain OpenType / TrueType font or collection, that
+    /// can be read without decompressing it first
+    pub fn is_sfnt(&self) -> bool {
+        matches!(
+            self,
+            FcFontFormat::TrueType | FcFontFormat::Cff | FcFontFormat::Collection
+        )
+    }
+
+    /// Returns the name of the format ("truetype", "cff", "collection", ...), used by the
+    /// JSON cache and font names
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FcFontFormat::TrueType => "truetype",
+            FcFontFormat::Cff => "cff",
+            FcFontFormat::Collection => "collection",
+            FcFontFormat::Woff => "woff",
+            FcFontFormat::Woff2 => "woff2",
+            FcFontFormat::Type1 => "type1",
+            FcFontFormat::Bitmap => "bitmap",
+        }
+    }
+}
+
+/// Parses the name of a format (see `FcFontFormat::as_str`, case-insensitive)
+impl FromStr for FcFontFormat {
+    type Err = FcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const FORMATS: [FcFontFormat; 7] = [
+            FcFontFormat::TrueType,
+            FcFontFormat::Cff,
+            FcFontFormat::Collection,
+            FcFontFormat::Woff,
+            FcFontFormat::Woff2,
+            FcFontFormat::Type1,
+            FcFontFormat::Bitmap,
+        ];
+
+        FORMATS
+            .iter()
+            .find(|format| format.as_str().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| FcError::InvalidValue {
+                value: String::from(s),
+            })
+    }
+}
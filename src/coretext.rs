@@ -0,0 +1,81 @@
+//! Font enumeration through CoreText (`coretext` feature)
+//!
+//! `CTFontManagerCopyAvailableFontURLs` also returns fonts that were activated
+//! through Font Book or installed by configuration profiles, which never show
+//! up in the standard font directories.
+
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use crate::{FcFontPath, FcParseFontFiles, FcPattern, FcScanOptions};
+
+type CFIndex = isize;
+type CFArrayRef = *const c_void;
+type CFURLRef = *const c_void;
+
+// PATH_MAX on macOS
+const MAX_PATH_LEN: usize = 1024;
+
+#[link(name = "CoreText", kind = "framework")]
+extern "C" {
+    fn CTFontManagerCopyAvailableFontURLs() -> CFArrayRef;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFArrayGetCount(array: CFArrayRef) -> CFIndex;
+    fn CFArrayGetValueAtIndex(array: CFArrayRef, index: CFIndex) -> *const c_void;
+    fn CFURLGetFileSystemRepresentation(
+        url: CFURLRef,
+        resolve_against_base: u8,
+        buffer: *mut u8,
+        max_buf_len: CFIndex,
+    ) -> u8;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Parses all fonts known to the CoreText font manager,
+/// returns `None` if CoreText doesn't return a font list
+pub(crate) fn FcScanCoreTextFonts(options: &FcScanOptions) -> Option<Vec<(FcPattern, FcFontPath)>> {
+    let files = unsafe { FcCoreTextFontFiles()? };
+    Some(FcParseFontFiles(&files, options))
+}
+
+// Returns the (deduplicated) files of all available fonts
+unsafe fn FcCoreTextFontFiles() -> Option<Vec<PathBuf>> {
+    let urls = CTFontManagerCopyAvailableFontURLs();
+    if urls.is_null() {
+        return None;
+    }
+
+    let mut files = Vec::new();
+    let mut buffer = [0_u8; MAX_PATH_LEN];
+
+    for i in 0..CFArrayGetCount(urls) {
+        let url = CFArrayGetValueAtIndex(urls, i);
+        if url.is_null()
+            || CFURLGetFileSystemRepresentation(
+                url,
+                1,
+                buffer.as_mut_ptr(),
+                buffer.len() as CFIndex,
+            ) == 0
+        {
+            continue;
+        }
+
+        // the buffer contains a null-terminated C string
+        let len = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+        let path = PathBuf::from(OsStr::from_bytes(&buffer[..len]));
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+
+    CFRelease(urls);
+
+    Some(files)
+}
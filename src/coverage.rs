@@ -0,0 +1,243 @@
+//! Characters supported by the fonts of the cache, see `FcFontCache::charset`
+
+use alloc::borrow::Cow;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::sync::Mutex;
+
+use crate::bytes::FcFontBytes;
+use crate::utils::{read_u16, read_u32};
+use crate::{FcCharset, FcFontCache, FcFontPath};
+
+const TAG_CMAP: u32 = u32::from_be_bytes(*b"cmap");
+
+// Coverage of the fonts without a charset in their pattern that were queried so far,
+// shared with the clones of the cache
+#[derive(Default)]
+pub(crate) struct FcCoverageCache(Mutex<BTreeMap<FcFontPath, FcCharset>>);
+
+// The coverage doesn't take part in comparisons, same as the loaded fonts
+impl core::fmt::Debug for FcCoverageCache {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FcCoverageCache").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for FcCoverageCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for FcCoverageCache {}
+
+impl PartialOrd for FcCoverageCache {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FcCoverageCache {
+    fn cmp(&self, _: &Self) -> core::cmp::Ordering {
+        core::cmp::Ordering::Equal
+    }
+}
+
+impl FcFontCache {
+    /// Returns the characters a font of the cache supports
+    ///
+    /// Borrows the `charset` of the pattern of the font, except for fonts without one (i.e.
+    /// fonts added with a `FcFontProvider` pattern), whose characters are read from the font
+    /// file on first use. Empty if the font can't be read.
+    pub fn charset(&self, path: &FcFontPath) -> Cow<'_, FcCharset> {
+        if let Some((pattern, _)) = self
+            .map
+            .with_path(path)
+            .find(|(pattern, _)| !pattern.metadata.charset.is_empty())
+        {
+            return Cow::Borrowed(&pattern.metadata.charset);
+        }
+
+        if let Some(charset) = self
+            .coverage
+            .0
+            .lock()
+            .ok()
+            .and_then(|c| c.get(path).cloned())
+        {
+            return Cow::Owned(charset);
+        }
+
+        // read without holding the lock, a font read twice by two threads is harmless
+        let charset = FcFontBytes::load(&path.source)
+            .and_then(|bytes| crate::FcFontTableData(&bytes, path.font_index, TAG_CMAP))
+            .map(|cmap| FcParseCharset(&cmap))
+            .unwrap_or_default();
+        if let Ok(mut cache) = self.coverage.0.lock() {
+            cache.insert(path.clone(), charset.clone());
+        }
+        Cow::Owned(charset)
+    }
+}
+
+// Characters of a `cmap` table, empty if the table can't be parsed
+pub(crate) fn FcParseCharset(cmap: &[u8]) -> FcCharset {
+    let mut charset = FcCharset::new();
+    for (start, end) in FcCmapRanges(cmap).unwrap_or_default() {
+        charset.insert_range(start, end);
+    }
+    charset
+}
+
+// Glyph id of a character in the best Unicode subtable of a cmap table, `None` for
+// characters the font doesn't have
+pub(crate) fn FcCmapGlyph(cmap: &[u8], c: char) -> Option<u16> {
+    let subtable = FcUnicodeSubtable(cmap)?;
+    let c = u32::from(c);
+    let glyph = match read_u16(subtable, 0)? {
+        4 => {
+            let c = u16::try_from(c).ok()?;
+            let seg_count = usize::from(read_u16(subtable, 6)? / 2);
+            let end_codes = 14;
+            let start_codes = end_codes + seg_count * 2 + 2;
+            let id_deltas = start_codes + seg_count * 2;
+            let id_range_offsets = id_deltas + seg_count * 2;
+
+            // segments are sorted by their end code
+            let i = (0..seg_count).find(|i| read_u16(subtable, end_codes + i * 2) >= Some(c))?;
+            let start = read_u16(subtable, start_codes + i * 2)?;
+            let id_delta = read_u16(subtable, id_deltas + i * 2)?;
+            let id_range_offset = read_u16(subtable, id_range_offsets + i * 2)?;
+            if c < start {
+                return None;
+            }
+            match id_range_offset {
+                0 => c.wrapping_add(id_delta),
+                _ => {
+                    // offset from the idRangeOffset entry into the glyph id array
+                    let address = id_range_offsets
+                        + i * 2
+                        + usize::from(id_range_offset)
+                        + usize::from(c - start) * 2;
+                    match read_u16(subtable, address)? {
+                        0 => 0,
+                        glyph => glyph.wrapping_add(id_delta),
+                    }
+                }
+            }
+        }
+        format @ (12 | 13) => {
+            let num_groups = read_u32(subtable, 12)? as usize;
+            let group = (0..num_groups).map(|i| 16 + i * 12).find(|group| {
+                read_u32(subtable, *group).is_some_and(|start| start <= c)
+                    && read_u32(subtable, group + 4).is_some_and(|end| c <= end)
+            })?;
+            let start_glyph = read_u32(subtable, group + 8)?;
+            let glyph = match format {
+                12 => start_glyph.checked_add(c - read_u32(subtable, group)?)?,
+                _ => start_glyph,
+            };
+            u16::try_from(glyph).ok()?
+        }
+        _ => return None,
+    };
+    Some(glyph).filter(|glyph| *glyph != 0)
+}
+
+// Characters of the best Unicode subtable of a cmap table, see
+// https://learn.microsoft.com/en-us/typography/opentype/spec/cmap
+fn FcCmapRanges(cmap: &[u8]) -> Option<Vec<(u32, u32)>> {
+    let subtable = FcUnicodeSubtable(cmap)?;
+    match read_u16(subtable, 0)? {
+        4 => FcCmapFormat4(subtable),
+        12 | 13 => FcCmapFormat12(subtable),
+        _ => None,
+    }
+}
+
+// Best Unicode subtable of a cmap table (and the data after it)
+fn FcUnicodeSubtable(cmap: &[u8]) -> Option<&[u8]> {
+    let num_tables = usize::from(read_u16(cmap, 2)?);
+
+    // (priority, offset): full repertoire subtables before BMP-only ones
+    let mut best: Option<(u8, usize)> = None;
+    for i in 0..num_tables {
+        let record = 4 + i * 8;
+        let platform_id = read_u16(cmap, record)?;
+        let encoding_id = read_u16(cmap, record + 2)?;
+        let offset = read_u32(cmap, record + 4)? as usize;
+        let priority = match (platform_id, encoding_id) {
+            (3, 10) | (0, 4) | (0, 6) => 2,
+            (3, 1) | (0, 0..=3) => 1,
+            _ => continue,
+        };
+        if best.is_none_or(|(best, _)| priority > best) {
+            best = Some((priority, offset));
+        }
+    }
+
+    cmap.get(best?.1..)
+}
+
+// Segment mapping to delta values (BMP)
+fn FcCmapFormat4(subtable: &[u8]) -> Option<Vec<(u32, u32)>> {
+    let seg_count = usize::from(read_u16(subtable, 6)? / 2);
+    let end_codes = 14;
+    let start_codes = end_codes + seg_count * 2 + 2;
+    let id_deltas = start_codes + seg_count * 2;
+    let id_range_offsets = id_deltas + seg_count * 2;
+
+    let mut ranges: Vec<(u32, u32)> = Vec::with_capacity(seg_count);
+    for i in 0..seg_count {
+        let end = read_u16(subtable, end_codes + i * 2)?;
+        let start = read_u16(subtable, start_codes + i * 2)?;
+        let id_delta = read_u16(subtable, id_deltas + i * 2)?;
+        let id_range_offset = read_u16(subtable, id_range_offsets + i * 2)?;
+        if start > end || start == 0xFFFF {
+            continue;
+        }
+        // only the characters that map to a glyph, same as `FcCmapGlyph`
+        for c in start..=end {
+            let glyph = match id_range_offset {
+                0 => c.wrapping_add(id_delta),
+                _ => {
+                    // offset from the idRangeOffset entry into the glyph id array
+                    let address = id_range_offsets
+                        + i * 2
+                        + usize::from(id_range_offset)
+                        + usize::from(c - start) * 2;
+                    match read_u16(subtable, address) {
+                        None | Some(0) => 0,
+                        Some(glyph) => glyph.wrapping_add(id_delta),
+                    }
+                }
+            };
+            if glyph == 0 {
+                continue;
+            }
+            let c = u32::from(c);
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == c => *end = c,
+                _ => ranges.push((c, c)),
+            }
+        }
+    }
+    Some(ranges)
+}
+
+// Segmented coverage (12) and many-to-one range mappings (13), same layout
+fn FcCmapFormat12(subtable: &[u8]) -> Option<Vec<(u32, u32)>> {
+    let num_groups = read_u32(subtable, 12)? as usize;
+    // no `with_capacity`, the count isn't trusted
+    let mut ranges = Vec::new();
+    for i in 0..num_groups {
+        let group = 16 + i * 12;
+        let start = read_u32(subtable, group)?;
+        let end = read_u32(subtable, group + 4)?;
+        if start <= end && start <= 0x10FFFF {
+            ranges.push((start, end.min(0x10FFFF)));
+        }
+    }
+    Some(ranges)
+}
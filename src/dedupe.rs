@@ -0,0 +1,127 @@
+//! Deduplication of font files that are reachable through several paths
+//!
+//! Symlinked and bind-mounted font directories make the same font file show up
+//! under different paths. Files are identified by their canonical path (and
+//! optionally by their size and a hash of their contents). The fonts of every
+//! file are only kept under its first path in sorted order, the other paths
+//! are recorded as alternates (see `FcFontCache::alternate_paths`).
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::path::PathBuf;
+
+use crate::{FcFontCache, FcFontPath, FcFontSource};
+
+/// Keeps the fonts of every physical file under a single path, `files` are all paths
+/// the fonts were found under (including the ones whose fonts aren't in the cache anymore)
+pub(crate) fn FcDeduplicateFiles(
+    cache: &mut FcFontCache,
+    files: BTreeSet<String>,
+    by_contents: bool,
+) {
+    // canonical path -> paths of the file, in order
+    let mut groups = BTreeMap::<PathBuf, Vec<String>>::new();
+    for file in files {
+        if let Ok(canonical) = std::fs::canonicalize(&file) {
+            groups.entry(canonical).or_default().push(file);
+        }
+    }
+
+    if by_contents {
+        for duplicates in FcDuplicateContents(groups.keys()) {
+            let mut duplicates = duplicates.into_iter();
+            let first = match duplicates.next() {
+                Some(first) => first,
+                None => continue,
+            };
+            let mut merged = Vec::new();
+            for duplicate in duplicates {
+                merged.extend(groups.remove(&duplicate).unwrap_or_default());
+            }
+            if let Some(group) = groups.get_mut(&first) {
+                group.extend(merged);
+                group.sort();
+            }
+        }
+    }
+
+    // duplicate path -> path that is kept
+    let mut replacements = BTreeMap::<String, Arc<str>>::new();
+    for group in groups.into_values().filter(|group| group.len() > 1) {
+        for file in group.iter() {
+            cache.alternate_paths.remove(file);
+        }
+        let kept: Arc<str> = group[0].as_str().into();
+        for duplicate in group[1..].iter() {
+            replacements.insert(duplicate.clone(), kept.clone());
+        }
+        cache
+            .alternate_paths
+            .insert(group[0].clone(), group[1..].to_vec());
+    }
+
+    if replacements.is_empty() {
+        return;
+    }
+
+    cache
+        .scanned_files
+        .retain(|file, _| !replacements.contains_key(file));
+
+    if cache
+        .map
+        .paths()
+        .filter_map(|path| path.path())
+        .any(|path| replacements.contains_key(path))
+    {
+        let entries = cache
+            .map
+            .iter()
+            .map(|(pattern, path)| {
+                let path = match path.path().and_then(|path| replacements.get(path)) {
+                    Some(kept) => FcFontPath {
+                        source: FcFontSource::Disk(kept.clone()),
+                        font_index: path.font_index,
+                    },
+                    None => path.clone(),
+                };
+                (pattern.clone(), path)
+            })
+            .collect();
+        cache.map.set_entries(entries);
+    }
+}
+
+// Groups of files with the same contents, only files of the same size are read
+fn FcDuplicateContents<'a, I: Iterator<Item = &'a PathBuf>>(files: I) -> Vec<Vec<PathBuf>> {
+    let mut by_size = BTreeMap::<u64, Vec<&PathBuf>>::new();
+    for file in files {
+        if let Ok(metadata) = std::fs::metadata(file) {
+            by_size.entry(metadata.len()).or_default().push(file);
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for candidates in by_size.into_values().filter(|files| files.len() > 1) {
+        let mut by_hash = BTreeMap::<u64, Vec<PathBuf>>::new();
+        for file in candidates {
+            if let Some(hash) = FcContentHash(file) {
+                by_hash.entry(hash).or_default().push(file.clone());
+            }
+        }
+        duplicates.extend(by_hash.into_values().filter(|files| files.len() > 1));
+    }
+    duplicates
+}
+
+fn FcContentHash(file: &PathBuf) -> Option<u64> {
+    use core::hash::Hasher;
+
+    let bytes = std::fs::read(file).ok()?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    hasher.write(&bytes);
+    Some(hasher.finish())
+}
@@ -0,0 +1,68 @@
+//! Mac OS resource fork font containers (`.dfont` files and legacy font suitcases)
+//!
+//! Both store every face as an `sfnt` resource, a `.dfont` keeps the resource
+//! fork in the data fork, suitcases keep it in the actual resource fork of the
+//! file. See "Inside Macintosh: More Macintosh Toolbox", chapter 1-121.
+
+use alloc::vec::Vec;
+
+use crate::utils::{read_u16, read_u32};
+
+const RESOURCE_TYPE_SFNT: &[u8; 4] = b"sfnt";
+
+/// Returns the `sfnt` resources (one per face, in resource order) if `data`
+/// is a resource fork, `None` otherwise
+pub(crate) fn FcResourceForkFaces(data: &[u8]) -> Option<Vec<&[u8]>> {
+    // resource header: offset + length of the resource data and of the resource map
+    let data_offset = read_u32(data, 0)? as usize;
+    let map_offset = read_u32(data, 4)? as usize;
+    let data_length = read_u32(data, 8)? as usize;
+    let map_length = read_u32(data, 12)? as usize;
+
+    if data_offset.checked_add(data_length)? > data.len()
+        || map_offset.checked_add(map_length)? > data.len()
+        || map_length < 28
+    {
+        return None;
+    }
+
+    // the resource map starts with a copy of the header, which is used to validate the file
+    if data.get(map_offset..map_offset + 16)? != data.get(0..16)? {
+        return None;
+    }
+
+    let map = &data[map_offset..map_offset + map_length];
+    let type_list_offset = read_u16(map, 24)? as usize;
+    let type_list = map.get(type_list_offset..)?;
+
+    // counts are stored as "number of entries - 1"
+    let num_types = read_u16(type_list, 0)?.wrapping_add(1) as usize;
+
+    let mut faces = Vec::new();
+    for i in 0..num_types {
+        let type_entry = 2 + i * 8;
+        if type_list.get(type_entry..type_entry + 4)? != RESOURCE_TYPE_SFNT {
+            continue;
+        }
+
+        let num_resources = read_u16(type_list, type_entry + 4)?.wrapping_add(1) as usize;
+        let ref_list_offset = read_u16(type_list, type_entry + 6)? as usize;
+
+        for j in 0..num_resources {
+            let ref_entry = ref_list_offset + j * 12;
+            // 1 byte attributes + 3 bytes offset from the start of the resource data
+            let resource_offset = (read_u32(type_list, ref_entry + 4)? & 0x00FF_FFFF) as usize;
+            let resource_start = data_offset.checked_add(resource_offset)?;
+            let resource_length = read_u32(data, resource_start)? as usize;
+            let face =
+                data.get(resource_start + 4..(resource_start + 4).checked_add(resource_length)?)?;
+            faces.push(face);
+        }
+    }
+
+    if faces.is_empty() {
+        None
+    } else {
+        Some(faces)
+    }
+}
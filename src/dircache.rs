@@ -0,0 +1,641 @@
+//! On-disk directory cache for `FcFontCache::build_cached` and `FcFontCache::refresh`
+//!
+//! Same model as `fc-cache`: for every scanned directory, the modification time,
+//! the subdirectories and the fonts directly inside of it are recorded. Adding,
+//! removing or renaming a file changes the mtime of its directory, so directories
+//! with an unchanged mtime can take their fonts from the record instead of
+//! parsing the font files again.
+//!
+//! Files that couldn't be parsed (corrupt or unsupported fonts, but also README files
+//! and the like) are recorded with their mtime and size, and aren't parsed again
+//! until they change.
+//!
+//! Layout (see `persist.rs` for the encoding of the individual values):
+//!
+//! ```text
+//! magic           b"DAFONTD\0"
+//! format version  u32
+//! crate version   string
+//! extensions      0 | 1, strings (scan options the records depend on)
+//! follow symlinks bool
+//! max file size   0 | 1, u64
+//! max faces       0 | 1, u64
+//! directories     count, (path, mtime secs u64, mtime nanos u32, subdirs, (pattern, path)*)*
+//! failed files    count, (path, mtime secs u64, mtime nanos u32, size u64)*
+//! ```
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::persist::{Reader, Writer, CRATE_VERSION, FORMAT_VERSION};
+use crate::{
+    FcFontCache, FcFontPath, FcParseFontFiles, FcPattern, FcScanDirectoriesInner, FcScanOptions,
+    FcSkippedFiles,
+};
+
+const MAGIC: &[u8; 8] = b"DAFONTD\0";
+
+/// Contents of a single font directory at the time it was scanned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FcDirRecord {
+    pub mtime: (u64, u32),
+    pub subdirs: Vec<PathBuf>,
+    pub fonts: Vec<(FcPattern, FcFontPath)>,
+}
+
+/// Modification time (seconds, nanoseconds) and size of a file
+pub(crate) type FcFileStamp = (u64, u32, u64);
+
+#[derive(Debug, Default)]
+struct FcDirCacheContents {
+    dirs: BTreeMap<PathBuf, FcDirRecord>,
+    // files that didn't contain any font that could be parsed
+    failed: BTreeMap<PathBuf, FcFileStamp>,
+}
+
+/// Directory records of the previous build, and the records collected by the current one
+#[derive(Debug, Default)]
+pub(crate) struct FcDirCache {
+    previous: FcDirCacheContents,
+    current: Mutex<FcDirCacheContents>,
+}
+
+// only compared as part of `FcScanOptions`, every build has its own cache
+impl PartialEq for FcDirCache {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+impl Eq for FcDirCache {}
+
+impl FcDirCache {
+    /// Reads the records written by a previous build, starts with an empty cache if the
+    /// file is missing, corrupted or was written with different scan options
+    pub(crate) fn load(file: &Path, options: &FcScanOptions) -> Self {
+        let previous = std::fs::read(file)
+            .ok()
+            .and_then(|bytes| FcParseDirCache(&bytes, options))
+            .unwrap_or_default();
+
+        FcDirCache {
+            previous,
+            current: Mutex::new(FcDirCacheContents::default()),
+        }
+    }
+
+    /// Returns the record of the previous build if the directory hasn't changed since
+    pub(crate) fn fresh_record(&self, dir: &Path) -> Option<&FcDirRecord> {
+        let record = self.previous.dirs.get(dir)?;
+        if FcModifiedTime(dir)? == record.mtime {
+            Some(record)
+        } else {
+            None
+        }
+    }
+
+    /// Records the contents of a directory scanned by the current build
+    pub(crate) fn insert(&self, dir: PathBuf, record: FcDirRecord) {
+        if let Ok(mut current) = self.current.lock() {
+            current.dirs.insert(dir, record);
+        }
+    }
+
+    /// Returns whether the file failed to parse in the previous build and hasn't changed
+    /// since, the failure is carried over to the current build
+    pub(crate) fn is_known_failure(&self, file: &Path) -> bool {
+        let stamp = match self.previous.failed.get(file) {
+            Some(stamp) if FcFileStamp(file).as_ref() == Some(stamp) => *stamp,
+            _ => return false,
+        };
+        if let Ok(mut current) = self.current.lock() {
+            current.failed.insert(file.to_path_buf(), stamp);
+        }
+        true
+    }
+
+    /// Carries the failures of an unchanged directory over to the current build, returns
+    /// the failed files that were modified since and have to be parsed again
+    pub(crate) fn modified_failures(&self, dir: &Path) -> Vec<PathBuf> {
+        self.previous
+            .failed
+            .keys()
+            .filter(|file| file.parent() == Some(dir))
+            .filter(|file| !self.is_known_failure(file) && file.is_file())
+            .cloned()
+            .collect()
+    }
+
+    /// Records a file that couldn't be parsed by the current build
+    pub(crate) fn insert_failed(&self, file: &Path) {
+        if let (Some(stamp), Ok(mut current)) = (FcFileStamp(file), self.current.lock()) {
+            current.failed.insert(file.to_path_buf(), stamp);
+        }
+    }
+
+    /// Returns the modification times of all directories scanned by the current build
+    pub(crate) fn scanned_dirs(&self) -> BTreeMap<String, (u64, u32)> {
+        match self.current.lock() {
+            Ok(current) => current
+                .dirs
+                .iter()
+                .map(|(dir, record)| (dir.to_string_lossy().to_string(), record.mtime))
+                .collect(),
+            Err(_) => BTreeMap::new(),
+        }
+    }
+
+    /// Returns the font files of all directories recorded by the current build
+    pub(crate) fn font_files(&self) -> BTreeSet<String> {
+        match self.current.lock() {
+            Ok(current) => current
+                .dirs
+                .values()
+                .flat_map(|record| record.fonts.iter())
+                .filter_map(|(_, path)| path.path())
+                .map(String::from)
+                .collect(),
+            Err(_) => BTreeSet::new(),
+        }
+    }
+
+    /// Returns the files that couldn't be parsed by the current build
+    pub(crate) fn failed_files(&self) -> BTreeMap<String, FcFileStamp> {
+        match self.current.lock() {
+            Ok(current) => current
+                .failed
+                .iter()
+                .map(|(file, stamp)| (file.to_string_lossy().to_string(), *stamp))
+                .collect(),
+            Err(_) => BTreeMap::new(),
+        }
+    }
+
+    /// Writes the records of the current build, directories that weren't scanned
+    /// (i.e. because they were removed) are dropped
+    pub(crate) fn save(&self, file: &Path, options: &FcScanOptions) -> std::io::Result<()> {
+        let current = match self.current.lock() {
+            Ok(current) => current,
+            Err(_) => return Ok(()),
+        };
+
+        let mut w = Writer::default();
+        w.bytes(MAGIC);
+        w.u32(FORMAT_VERSION);
+        w.str(CRATE_VERSION);
+        FcWriteScanOptions(&mut w, options);
+
+        w.len(current.dirs.len());
+        for (dir, record) in current.dirs.iter() {
+            w.str(&dir.to_string_lossy());
+            w.u64(record.mtime.0);
+            w.u32(record.mtime.1);
+            w.len(record.subdirs.len());
+            for subdir in record.subdirs.iter() {
+                w.str(&subdir.to_string_lossy());
+            }
+            w.len(record.fonts.len());
+            for (pattern, path) in record.fonts.iter() {
+                w.pattern(pattern);
+                w.path(path);
+            }
+        }
+
+        w.len(current.failed.len());
+        for (file, (secs, nanos, size)) in current.failed.iter() {
+            w.str(&file.to_string_lossy());
+            w.u64(*secs);
+            w.u32(*nanos);
+            w.u64(*size);
+        }
+
+        if let Some(parent) = file.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut tmp_file = file.as_os_str().to_owned();
+        tmp_file.push(".tmp");
+        std::fs::write(&tmp_file, &w.0)?;
+        std::fs::rename(&tmp_file, file)
+    }
+}
+
+/// Modification time of the file or directory, as seconds + nanoseconds since the UNIX epoch
+pub(crate) fn FcModifiedTime(path: &Path) -> Option<(u64, u32)> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+}
+
+/// Modification time and size of the file
+pub(crate) fn FcFileStamp(file: &Path) -> Option<FcFileStamp> {
+    let metadata = std::fs::metadata(file).ok()?;
+    let since_epoch = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos(),
+        metadata.len(),
+    ))
+}
+
+/// Default location of the cache file of `FcFontCache::build_cached`
+pub(crate) fn FcDefaultDiskCacheFile() -> Option<PathBuf> {
+    use std::env::var_os;
+
+    #[cfg(target_os = "windows")]
+    let cache_dir = var_os("LOCALAPPDATA").map(PathBuf::from);
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    let cache_dir = var_os("HOME").map(|home| PathBuf::from(home).join("Library/Caches"));
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "ios")))]
+    let cache_dir = var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+
+    Some(cache_dir?.join("dafont").join("fonts.cache"))
+}
+
+/// Returns the modification times of the given font files, in-memory fonts are skipped
+pub(crate) fn FcScannedFiles<'a, I: Iterator<Item = &'a FcFontPath>>(
+    paths: I,
+) -> BTreeMap<String, (u64, u32)> {
+    let mut files = BTreeMap::new();
+    for path in paths.filter_map(|path| path.path()) {
+        if files.contains_key(path) {
+            continue;
+        }
+        if let Some(mtime) = FcModifiedTime(Path::new(path)) {
+            files.insert(path.to_string(), mtime);
+        }
+    }
+    files
+}
+
+impl FcFontCache {
+    /// Updates the cache after fonts were installed, updated or removed, without
+    /// requiring a full `build`
+    ///
+    /// The directories and font files of the last build are checked again: fonts whose
+    /// files vanished are removed, modified files are parsed again and new files and
+    /// subdirectories of changed directories are added. Files that couldn't be parsed are
+    /// only tried again once they are modified. New files and subdirectories go through the
+    /// same filters and limits as during the build (extensions, excluded directories,
+    /// `max_depth`, ...), new paths of known files are recorded in `alternate_paths` if they
+    /// resolve to the same file.
+    pub fn refresh(&mut self) -> &mut Self {
+        let options = FcIncrementalOptions(&self.scan_options.0);
+        let mut files_to_parse = Vec::new();
+        let mut removed_files = BTreeSet::new();
+
+        // files that failed to parse are only tried again once they change
+        let mut removed_failures = Vec::new();
+        for (file, stamp) in self.failed_files.iter() {
+            match FcFileStamp(Path::new(file)) {
+                Some(modified) if modified == *stamp => {}
+                Some(_) => {
+                    removed_failures.push(file.clone());
+                    files_to_parse.push(PathBuf::from(file));
+                }
+                None => removed_failures.push(file.clone()),
+            }
+        }
+        for file in removed_failures {
+            self.failed_files.remove(&file);
+        }
+
+        for alternates in self.alternate_paths.values_mut() {
+            alternates.retain(|file| Path::new(file).exists());
+        }
+        self.alternate_paths
+            .retain(|_, alternates| !alternates.is_empty());
+
+        let mut vanished_files = Vec::new();
+        for (file, mtime) in self.scanned_files.iter() {
+            match FcModifiedTime(Path::new(file)) {
+                Some(modified) if modified == *mtime => {}
+                Some(_) => {
+                    removed_files.insert(file.clone());
+                    files_to_parse.push(PathBuf::from(file));
+                }
+                None => {
+                    removed_files.insert(file.clone());
+                    vanished_files.push(file.clone());
+                }
+            }
+        }
+
+        // the fonts of a file stay available as long as one of its paths exists
+        for file in vanished_files {
+            if let Some(alternates) = self.alternate_paths.remove(&file) {
+                files_to_parse.extend(alternates.into_iter().map(PathBuf::from));
+            }
+        }
+        let alternates = self
+            .alternate_paths
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let skipped = self
+            .skipped_files
+            .iter()
+            .map(|file| file.path.clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut removed_dirs = Vec::new();
+        let mut changed_dirs = Vec::new();
+        let mut new_dirs = Vec::new();
+
+        for (dir, mtime) in self.scanned_dirs.iter() {
+            let modified = match FcModifiedTime(Path::new(dir)) {
+                Some(modified) => modified,
+                None => {
+                    // the fonts inside were removed as well, see above
+                    removed_dirs.push(dir.clone());
+                    continue;
+                }
+            };
+            if modified == *mtime {
+                continue;
+            }
+            changed_dirs.push((dir.clone(), modified));
+
+            let entries = match std::fs::read_dir(dir) {
+                Ok(o) => o,
+                Err(_) => continue,
+            };
+            // new subdirectories are one level below the changed directory
+            let depth = FcRecordedDepth(&self.scanned_dirs, Path::new(dir)) + 1;
+            let max_depth = match options.max_depth {
+                Some(max) if depth > max => None,
+                Some(max) => Some(Some(max - depth)),
+                None => Some(None),
+            };
+
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                if !options.follow_symlinks && entry.file_type().is_ok_and(|t| t.is_symlink()) {
+                    continue;
+                }
+                let path = entry.path();
+                let name = path.to_string_lossy().to_string();
+                if path.is_dir() {
+                    if let Some(max_depth) = max_depth {
+                        if !self.scanned_dirs.contains_key(&name) && options.includes_dir(&path) {
+                            new_dirs.push((max_depth, name));
+                        }
+                    }
+                } else if !self.scanned_files.contains_key(&name)
+                    && !self.failed_files.contains_key(&name)
+                    && !alternates.contains(&name)
+                    && !skipped.contains(&name)
+                    && options.accepts_file(&name)
+                {
+                    files_to_parse.push(path);
+                }
+            }
+        }
+
+        self.map
+            .retain(|_, path| path.path().is_none_or(|path| !removed_files.contains(path)));
+        self.scanned_files
+            .retain(|file, _| !removed_files.contains(file));
+        for dir in removed_dirs {
+            self.scanned_dirs.remove(&dir);
+        }
+        self.scanned_dirs.extend(changed_dirs);
+
+        let mut fonts = FcParseFontFiles(&files_to_parse, &options);
+        for (max_depth, dir) in new_dirs {
+            let options = FcScanOptions {
+                max_depth,
+                ..options.clone()
+            };
+            fonts.extend(FcScanDirectoriesInner(&[(None, dir)], &options));
+        }
+
+        self.insert_scanned(fonts, &options)
+    }
+
+    /// Adds the fonts of a single font file (i.e. installed by the user at runtime),
+    /// without rebuilding the cache
+    ///
+    /// Fonts that were added from the same path before are replaced. The file goes through
+    /// the filters and limits of the build (extensions, excluded directories, `max_file_size`,
+    /// ...) and the configured parser. Files that can't be parsed are ignored, and the file
+    /// is kept up to date by `refresh`.
+    pub fn add_font_file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        let path = path.as_ref();
+        let name = path.to_string_lossy().to_string();
+        self.map.retain(|_, font| font.path() != Some(&*name));
+        self.scanned_files.remove(&name);
+        self.failed_files.remove(&name);
+        self.skipped_files.retain(|file| file.path != name);
+
+        let options = FcIncrementalOptions(&self.scan_options.0);
+        if !options.accepts_file(&name) {
+            return self;
+        }
+        let fonts = FcParseFontFiles(&[path.to_path_buf()], &options);
+        self.insert_scanned(fonts, &options)
+    }
+
+    /// Adds the fonts of a directory and its subdirectories, without rebuilding the cache
+    ///
+    /// The directory is scanned with the options of the build (`max_depth`, extensions,
+    /// limits, parser, ...) and kept up to date by `refresh`.
+    pub fn add_font_dir<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        let dir = path.as_ref().to_string_lossy().to_string();
+        let options = FcIncrementalOptions(&self.scan_options.0);
+        let fonts = FcScanDirectoriesInner(&[(None, dir)], &options);
+        self.insert_scanned(fonts, &options)
+    }
+
+    /// Removes the fonts of a font file, or of all font files inside a directory
+    /// (i.e. uninstalled by the user at runtime), without rebuilding the cache
+    pub fn remove_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        let path = path.as_ref();
+        let removed = |file: &str| Path::new(file).starts_with(path);
+
+        self.map.retain(|_, font| !font.path().is_some_and(removed));
+        self.scanned_dirs.retain(|dir, _| !removed(dir));
+        self.scanned_files.retain(|file, _| !removed(file));
+        self.failed_files.retain(|file, _| !removed(file));
+        self.pending_files.retain(|file| !removed(file));
+        self.skipped_files.retain(|file| !removed(&file.path));
+
+        self.alternate_paths.retain(|file, _| !removed(file));
+        for alternates in self.alternate_paths.values_mut() {
+            alternates.retain(|file| !removed(file));
+        }
+        self.alternate_paths
+            .retain(|_, alternates| !alternates.is_empty());
+        self
+    }
+
+    // Adds fonts parsed after the build, and the directories and files they were found in
+    fn insert_scanned(
+        &mut self,
+        fonts: Vec<(FcPattern, FcFontPath)>,
+        options: &FcScanOptions,
+    ) -> &mut Self {
+        if let Some(dir_cache) = options.dir_cache.as_ref() {
+            self.scanned_dirs.extend(dir_cache.scanned_dirs());
+            self.failed_files.extend(dir_cache.failed_files());
+        }
+        if let Some(skipped_files) = options.skipped_files.as_ref() {
+            self.skipped_files.extend(skipped_files.take());
+            self.skipped_files.sort();
+            self.skipped_files.dedup();
+        }
+        self.scanned_files
+            .extend(FcScannedFiles(fonts.iter().map(|(_, path)| path)));
+
+        if !fonts.is_empty() {
+            // new files might be other paths of known files
+            let mut files = self.scanned_files.keys().cloned().collect::<BTreeSet<_>>();
+            files.extend(self.alternate_paths.values().flatten().cloned());
+            self.map.extend(fonts);
+            crate::dedupe::FcDeduplicateFiles(self, files, false);
+        }
+        self
+    }
+}
+
+// Options of the build for scans after the build, with a directory cache to collect the
+// scanned directories and files that failed to parse
+fn FcIncrementalOptions(options: &FcScanOptions) -> FcScanOptions {
+    FcScanOptions {
+        dir_cache: Some(Arc::new(FcDirCache::default())),
+        skipped_files: Some(Arc::new(FcSkippedFiles::default())),
+        ..options.clone()
+    }
+}
+
+// Number of recorded directories above `dir`, i.e. its depth below the scanned directory
+fn FcRecordedDepth(scanned_dirs: &BTreeMap<String, (u64, u32)>, dir: &Path) -> usize {
+    dir.ancestors()
+        .skip(1)
+        .take_while(|parent| scanned_dirs.contains_key(&*parent.to_string_lossy()))
+        .count()
+}
+
+// Features that change which files can be parsed and what their patterns contain
+const PARSING_FEATURES: u32 = cfg!(feature = "parsing") as u32
+    | (cfg!(feature = "ttf-parser") as u32) << 1
+    | (cfg!(feature = "woff") as u32) << 2
+    | (cfg!(feature = "gzip") as u32) << 3;
+
+// The records only contain files accepted by the extension whitelist and the limits, and
+// depend on whether symlinks were followed, on the parser (the type name of a custom parser,
+// and the enabled features) and on whether fontconfig caches were read, so these options
+// have to match. The failed files are only parsed again by a cache with other options.
+fn FcWriteScanOptions(w: &mut Writer, options: &FcScanOptions) {
+    match options.extensions.as_ref() {
+        Some(extensions) => {
+            w.u8(1);
+            w.strs(extensions);
+        }
+        None => w.u8(0),
+    }
+    w.bool(options.follow_symlinks);
+    for limit in [
+        options.max_file_size,
+        options.max_faces.map(|max| max as u64),
+    ]
+    .iter()
+    {
+        match limit {
+            Some(limit) => {
+                w.u8(1);
+                w.u64(*limit);
+            }
+            None => w.u8(0),
+        }
+    }
+    match options.parser.as_ref() {
+        Some(parser) => {
+            w.u8(1);
+            w.str(parser.1);
+        }
+        None => w.u8(0),
+    }
+    w.u32(PARSING_FEATURES);
+    w.bool(options.fontconfig_caches);
+}
+
+fn FcParseDirCache(bytes: &[u8], options: &FcScanOptions) -> Option<FcDirCacheContents> {
+    let mut r = Reader::new(bytes);
+    if r.bytes(MAGIC.len())? != MAGIC || r.u32()? != FORMAT_VERSION || r.str()? != CRATE_VERSION {
+        return None;
+    }
+
+    let extensions: Option<Vec<String>> = match r.bool()? {
+        true => Some(r.strs()?),
+        false => None,
+    };
+    if extensions != options.extensions || r.bool()? != options.follow_symlinks {
+        return None;
+    }
+    for limit in [
+        options.max_file_size,
+        options.max_faces.map(|max| max as u64),
+    ]
+    .iter()
+    {
+        let read = match r.bool()? {
+            true => Some(r.u64()?),
+            false => None,
+        };
+        if read != *limit {
+            return None;
+        }
+    }
+    let parser = match r.bool()? {
+        true => Some(r.str()?),
+        false => None,
+    };
+    if parser.as_deref() != options.parser.as_ref().map(|parser| parser.1)
+        || r.u32()? != PARSING_FEATURES
+        || r.bool()? != options.fontconfig_caches
+    {
+        return None;
+    }
+
+    let mut records = BTreeMap::new();
+    for _ in 0..r.len()? {
+        let dir = PathBuf::from(r.str()?);
+        let mtime = (r.u64()?, r.u32()?);
+        let subdirs = r.strs()?.into_iter().map(PathBuf::from).collect();
+        let mut fonts = Vec::new();
+        for _ in 0..r.len()? {
+            let pattern = r.pattern()?;
+            let path = r.path()?;
+            fonts.push((pattern, path));
+        }
+        records.insert(
+            dir,
+            FcDirRecord {
+                mtime,
+                subdirs,
+                fonts,
+            },
+        );
+    }
+
+    let mut failed = BTreeMap::new();
+    for _ in 0..r.len()? {
+        let file = PathBuf::from(r.str()?);
+        failed.insert(file, (r.u64()?, r.u32()?, r.u64()?));
+    }
+
+    Some(FcDirCacheContents {
+        dirs: records,
+        failed,
+    })
+}
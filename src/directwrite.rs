@@ -0,0 +1,297 @@
+//! Font enumeration through DirectWrite (`directwrite` feature)
+//!
+//! Walks the system font collection (`IDWriteFontCollection`) and returns the
+//! local file of every font face, so the cache contains exactly the fonts that
+//! Windows applications see. Fonts that are not backed by a local file (custom
+//! loaders, remote fonts) are skipped. The few COM interfaces that are needed
+//! are declared by hand, only up to the last vtable slot that is called.
+
+#![allow(clippy::upper_case_acronyms)]
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::ptr;
+use std::path::PathBuf;
+
+use crate::{FcFontPath, FcParseFontFiles, FcPattern, FcScanOptions};
+
+type HRESULT = i32;
+
+#[repr(C)]
+struct GUID {
+    data1: u32,
+    data2: u16,
+    data3: u16,
+    data4: [u8; 8],
+}
+
+const DWRITE_FACTORY_TYPE_SHARED: u32 = 0;
+
+// {b859ee5a-d838-4b5b-a2e8-1adc7d93db48}
+const IID_IDWRITE_FACTORY: GUID = GUID {
+    data1: 0xb859_ee5a,
+    data2: 0xd838,
+    data3: 0x4b5b,
+    data4: [0xa2, 0xe8, 0x1a, 0xdc, 0x7d, 0x93, 0xdb, 0x48],
+};
+
+// {b2d9f3ec-c9fe-4a11-a2ec-d86208f7c0a2}
+const IID_IDWRITE_LOCAL_FONT_FILE_LOADER: GUID = GUID {
+    data1: 0xb2d9_f3ec,
+    data2: 0xc9fe,
+    data3: 0x4a11,
+    data4: [0xa2, 0xec, 0xd8, 0x62, 0x08, 0xf7, 0xc0, 0xa2],
+};
+
+#[link(name = "dwrite")]
+extern "system" {
+    fn DWriteCreateFactory(
+        factory_type: u32,
+        iid: *const GUID,
+        factory: *mut *mut c_void,
+    ) -> HRESULT;
+}
+
+#[repr(C)]
+struct IUnknownVtbl {
+    query_interface:
+        unsafe extern "system" fn(*mut c_void, *const GUID, *mut *mut c_void) -> HRESULT,
+    _add_ref: unsafe extern "system" fn(*mut c_void) -> u32,
+    release: unsafe extern "system" fn(*mut c_void) -> u32,
+}
+
+#[repr(C)]
+struct IDWriteFactoryVtbl {
+    base: IUnknownVtbl,
+    get_system_font_collection:
+        unsafe extern "system" fn(*mut c_void, *mut *mut c_void, i32) -> HRESULT,
+}
+
+#[repr(C)]
+struct IDWriteFontCollectionVtbl {
+    base: IUnknownVtbl,
+    get_font_family_count: unsafe extern "system" fn(*mut c_void) -> u32,
+    get_font_family: unsafe extern "system" fn(*mut c_void, u32, *mut *mut c_void) -> HRESULT,
+}
+
+// IDWriteFontFamily inherits from IDWriteFontList
+#[repr(C)]
+struct IDWriteFontListVtbl {
+    base: IUnknownVtbl,
+    _get_font_collection: unsafe extern "system" fn(*mut c_void, *mut *mut c_void) -> HRESULT,
+    get_font_count: unsafe extern "system" fn(*mut c_void) -> u32,
+    get_font: unsafe extern "system" fn(*mut c_void, u32, *mut *mut c_void) -> HRESULT,
+}
+
+#[repr(C)]
+struct IDWriteFontVtbl {
+    base: IUnknownVtbl,
+    // GetFontFamily, GetWeight, GetStretch, GetStyle, IsSymbolFont, GetFaceNames,
+    // GetInformationalStrings, GetSimulations, GetMetrics, HasCharacter
+    _unused: [usize; 10],
+    create_font_face: unsafe extern "system" fn(*mut c_void, *mut *mut c_void) -> HRESULT,
+}
+
+#[repr(C)]
+struct IDWriteFontFaceVtbl {
+    base: IUnknownVtbl,
+    _get_type: unsafe extern "system" fn(*mut c_void) -> u32,
+    get_files: unsafe extern "system" fn(*mut c_void, *mut u32, *mut *mut c_void) -> HRESULT,
+    get_index: unsafe extern "system" fn(*mut c_void) -> u32,
+}
+
+#[repr(C)]
+struct IDWriteFontFileVtbl {
+    base: IUnknownVtbl,
+    get_reference_key:
+        unsafe extern "system" fn(*mut c_void, *mut *const c_void, *mut u32) -> HRESULT,
+    get_loader: unsafe extern "system" fn(*mut c_void, *mut *mut c_void) -> HRESULT,
+}
+
+#[repr(C)]
+struct IDWriteLocalFontFileLoaderVtbl {
+    base: IUnknownVtbl,
+    _create_stream_from_key: usize,
+    get_file_path_length_from_key:
+        unsafe extern "system" fn(*mut c_void, *const c_void, u32, *mut u32) -> HRESULT,
+    get_file_path_from_key:
+        unsafe extern "system" fn(*mut c_void, *const c_void, u32, *mut u16, u32) -> HRESULT,
+}
+
+/// Owned COM interface pointer, released on drop
+struct ComPtr(*mut c_void);
+
+impl ComPtr {
+    fn new(ptr: *mut c_void) -> Option<Self> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(ComPtr(ptr))
+        }
+    }
+
+    // SAFETY: `T` has to be the vtable layout (or a prefix of it) of the interface
+    unsafe fn vtbl<T>(&self) -> &T {
+        &**(self.0 as *mut *const T)
+    }
+}
+
+impl Drop for ComPtr {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtbl::<IUnknownVtbl>().release)(self.0);
+        }
+    }
+}
+
+/// Enumerates the DirectWrite system font collection and parses the font files,
+/// returns `None` if DirectWrite is unavailable
+pub(crate) fn FcScanDirectWriteFonts(
+    options: &FcScanOptions,
+) -> Option<Vec<(FcPattern, FcFontPath)>> {
+    let files = unsafe { FcDirectWriteFontFiles()? };
+    Some(FcParseFontFiles(&files, options))
+}
+
+// Returns the (deduplicated) local files of all fonts in the system font collection
+unsafe fn FcDirectWriteFontFiles() -> Option<Vec<PathBuf>> {
+    let mut factory = ptr::null_mut();
+    if DWriteCreateFactory(
+        DWRITE_FACTORY_TYPE_SHARED,
+        &IID_IDWRITE_FACTORY,
+        &mut factory,
+    ) < 0
+    {
+        return None;
+    }
+    let factory = ComPtr::new(factory)?;
+
+    let mut collection = ptr::null_mut();
+    let get_system_font_collection = factory
+        .vtbl::<IDWriteFactoryVtbl>()
+        .get_system_font_collection;
+    if get_system_font_collection(factory.0, &mut collection, 0) < 0 {
+        return None;
+    }
+    let collection = ComPtr::new(collection)?;
+    let collection_vtbl = collection.vtbl::<IDWriteFontCollectionVtbl>();
+
+    let mut files = Vec::new();
+    for family_index in 0..(collection_vtbl.get_font_family_count)(collection.0) {
+        let mut family = ptr::null_mut();
+        if (collection_vtbl.get_font_family)(collection.0, family_index, &mut family) < 0 {
+            continue;
+        }
+        let family = match ComPtr::new(family) {
+            Some(s) => s,
+            None => continue,
+        };
+        let family_vtbl = family.vtbl::<IDWriteFontListVtbl>();
+
+        for font_index in 0..(family_vtbl.get_font_count)(family.0) {
+            let mut font = ptr::null_mut();
+            if (family_vtbl.get_font)(family.0, font_index, &mut font) < 0 {
+                continue;
+            }
+            let font = match ComPtr::new(font) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            for path in FcDirectWriteFontPaths(&font) {
+                if !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+        }
+    }
+
+    Some(files)
+}
+
+// Local file paths of a single IDWriteFont
+unsafe fn FcDirectWriteFontPaths(font: &ComPtr) -> Vec<PathBuf> {
+    let mut face = ptr::null_mut();
+    if (font.vtbl::<IDWriteFontVtbl>().create_font_face)(font.0, &mut face) < 0 {
+        return Vec::new();
+    }
+    let face = match ComPtr::new(face) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+    let face_vtbl = face.vtbl::<IDWriteFontFaceVtbl>();
+
+    let mut num_files = 0;
+    if (face_vtbl.get_files)(face.0, &mut num_files, ptr::null_mut()) < 0 || num_files == 0 {
+        return Vec::new();
+    }
+
+    let mut file_ptrs = vec![ptr::null_mut(); num_files as usize];
+    if (face_vtbl.get_files)(face.0, &mut num_files, file_ptrs.as_mut_ptr()) < 0 {
+        return Vec::new();
+    }
+
+    file_ptrs
+        .into_iter()
+        .filter_map(ComPtr::new)
+        .filter_map(|file| FcDirectWriteFilePath(&file))
+        .collect()
+}
+
+// Resolves an IDWriteFontFile to a path, if it is loaded by the local file loader
+unsafe fn FcDirectWriteFilePath(file: &ComPtr) -> Option<PathBuf> {
+    let file_vtbl = file.vtbl::<IDWriteFontFileVtbl>();
+
+    let mut key = ptr::null();
+    let mut key_size = 0;
+    if (file_vtbl.get_reference_key)(file.0, &mut key, &mut key_size) < 0 {
+        return None;
+    }
+
+    let mut loader = ptr::null_mut();
+    if (file_vtbl.get_loader)(file.0, &mut loader) < 0 {
+        return None;
+    }
+    let loader = ComPtr::new(loader)?;
+
+    let mut local_loader = ptr::null_mut();
+    let query_interface = loader.vtbl::<IUnknownVtbl>().query_interface;
+    if query_interface(
+        loader.0,
+        &IID_IDWRITE_LOCAL_FONT_FILE_LOADER,
+        &mut local_loader,
+    ) < 0
+    {
+        return None; // not a local file
+    }
+    let local_loader = ComPtr::new(local_loader)?;
+    let local_loader_vtbl = local_loader.vtbl::<IDWriteLocalFontFileLoaderVtbl>();
+
+    let mut path_len = 0;
+    if (local_loader_vtbl.get_file_path_length_from_key)(
+        local_loader.0,
+        key,
+        key_size,
+        &mut path_len,
+    ) < 0
+    {
+        return None;
+    }
+
+    // + 1 for the null terminator
+    let mut path = vec![0_u16; path_len as usize + 1];
+    if (local_loader_vtbl.get_file_path_from_key)(
+        local_loader.0,
+        key,
+        key_size,
+        path.as_mut_ptr(),
+        path.len() as u32,
+    ) < 0
+    {
+        return None;
+    }
+
+    path.truncate(path_len as usize);
+    Some(PathBuf::from(String::from_utf16_lossy(&path)))
+}
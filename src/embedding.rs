@@ -0,0 +1,216 @@
+//! Whether and how fonts can be embedded in documents (i.e. PDF), see
+//! `FcFontCache::embedding_info`
+
+#[cfg(all(feature = "std", feature = "scan"))]
+use crate::utils::read_u16;
+
+/// Embedding permission of a font, from the usage permission bits of `fsType` in the OS/2
+/// table
+///
+/// See: https://learn.microsoft.com/en-us/typography/opentype/spec/os2#fstype
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcEmbeddingPermission {
+    /// The font can be embedded and permanently installed on the remote system
+    Installable,
+    /// The font must not be embedded without the permission of the legal owner
+    Restricted,
+    /// The font can be embedded to view and print the document, not to edit it
+    PreviewAndPrint,
+    /// The font can be embedded to view, print and edit the document
+    Editable,
+}
+
+impl FcEmbeddingPermission {
+    /// Returns the permission of an `fsType` value, the least restrictive one if a font sets
+    /// several of them (same as the spec asks applications to do for old fonts)
+    pub fn from_fs_type(fs_type: u16) -> Self {
+        if fs_type & 0x0008 != 0 {
+            FcEmbeddingPermission::Editable
+        } else if fs_type & 0x0004 != 0 {
+            FcEmbeddingPermission::PreviewAndPrint
+        } else if fs_type & 0x0002 != 0 {
+            FcEmbeddingPermission::Restricted
+        } else {
+            FcEmbeddingPermission::Installable
+        }
+    }
+}
+
+/// Format of the glyph outlines of a font
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcOutlineFormat {
+    /// Quadratic outlines (`glyf` table), embedded as `FontFile2` in PDF
+    TrueType,
+    /// Cubic outlines (`CFF ` table), embedded as `FontFile3` / `OpenType` in PDF
+    Cff,
+    /// Variable cubic outlines (`CFF2` table), usually instantiated to CFF before embedding
+    Cff2,
+    /// No outlines, the font only has bitmap strikes
+    Bitmap,
+}
+
+/// Everything a PDF writer needs to choose a font to embed, see
+/// `FcFontCache::embedding_info`
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcEmbeddingInfo {
+    // raw `fsType` of the OS/2 table, 0 (installable) for fonts without one
+    pub fs_type: u16,
+    // usage permission of `fs_type`
+    pub permission: FcEmbeddingPermission,
+    // format of the glyph outlines
+    pub outline_format: FcOutlineFormat,
+    // the font may be subset before embedding (`fsType` bit 8 "no subsetting" is clear)
+    pub subsetting_allowed: bool,
+    // only the bitmaps of the font may be embedded (`fsType` bit 9)
+    pub bitmap_embedding_only: bool,
+    // the CFF outlines are CID-keyed (the top DICT has a `ROS` operator), embedded as a
+    // `CIDFontType0` font in PDF instead of `Type1`
+    pub cid_keyed: bool,
+}
+
+impl FcEmbeddingInfo {
+    /// Returns the information of a font from its `fsType`, outline format and CID-keyed
+    /// status
+    pub fn new(fs_type: u16, outline_format: FcOutlineFormat, cid_keyed: bool) -> Self {
+        FcEmbeddingInfo {
+            fs_type,
+            permission: FcEmbeddingPermission::from_fs_type(fs_type),
+            outline_format,
+            subsetting_allowed: fs_type & 0x0100 == 0,
+            bitmap_embedding_only: fs_type & 0x0200 != 0,
+            cid_keyed,
+        }
+    }
+
+    /// Returns whether the outlines of the font may be embedded in a document (i.e. the
+    /// font isn't restricted, bitmap-only or limited to embedding its bitmaps)
+    pub fn can_embed_outlines(&self) -> bool {
+        self.permission != FcEmbeddingPermission::Restricted
+            && self.outline_format != FcOutlineFormat::Bitmap
+            && !self.bitmap_embedding_only
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl crate::FcFontCache {
+    /// Returns the embedding permissions and outline format of a font, `None` if the font
+    /// can't be read
+    ///
+    /// Fonts without an OS/2 table (i.e. old Mac fonts) are installable, same as most PDF
+    /// writers assume.
+    pub fn embedding_info(&self, path: &crate::FcFontPath) -> Option<FcEmbeddingInfo> {
+        const TAG_OS2: u32 = u32::from_be_bytes(*b"OS/2");
+        const TAG_GLYF: u32 = u32::from_be_bytes(*b"glyf");
+        const TAG_CFF: u32 = u32::from_be_bytes(*b"CFF ");
+        const TAG_CFF2: u32 = u32::from_be_bytes(*b"CFF2");
+        const TAG_HEAD: u32 = u32::from_be_bytes(*b"head");
+
+        let bytes = crate::FcFontBytes::load(&path.source)?;
+        let sfnt = crate::FcSfntData(&bytes);
+        let table = |tag| crate::FcSfntTableData(&sfnt, path.font_index, tag);
+
+        // the head table is required, fonts without one aren't fonts (or can't be read)
+        table(TAG_HEAD)?;
+
+        let fs_type = table(TAG_OS2)
+            .and_then(|os2| read_u16(&os2, 8))
+            .unwrap_or(0);
+
+        let (outline_format, cid_keyed) = if table(TAG_GLYF).is_some() {
+            (FcOutlineFormat::TrueType, false)
+        } else if let Some(cff) = table(TAG_CFF) {
+            (FcOutlineFormat::Cff, FcIsCidKeyed(&cff).unwrap_or(false))
+        } else if table(TAG_CFF2).is_some() {
+            (FcOutlineFormat::Cff2, false)
+        } else {
+            (FcOutlineFormat::Bitmap, false)
+        };
+
+        Some(FcEmbeddingInfo::new(fs_type, outline_format, cid_keyed))
+    }
+}
+
+// Whether the first top DICT of a CFF table has a `ROS` (12 30) operator, see
+// https://adobe-type-tools.github.io/font-tech-notes/pdfs/5176.CFF.pdf
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcIsCidKeyed(cff: &[u8]) -> Option<bool> {
+    const OP_ESCAPE: u8 = 12;
+    const OP_ROS: u8 = 30;
+
+    // header, then the Name INDEX and the Top DICT INDEX
+    let header_size = usize::from(*cff.get(2)?);
+    let top_dict_index = FcCffIndexEnd(cff, header_size)?;
+    let top_dict = FcCffIndexItem(cff, top_dict_index, 0)?;
+
+    let mut pos = 0;
+    while let Some(&b0) = top_dict.get(pos) {
+        pos += match b0 {
+            OP_ESCAPE if top_dict.get(pos + 1) == Some(&OP_ROS) => return Some(true),
+            OP_ESCAPE => 2,
+            // integers
+            28 => 3,
+            29 => 5,
+            32..=246 => 1,
+            247..=254 => 2,
+            // real number, nibbles up to the end nibble 0xf
+            30 => {
+                let len = top_dict[pos + 1..]
+                    .iter()
+                    .position(|b| b & 0x0f == 0x0f || b & 0xf0 == 0xf0)?;
+                len + 2
+            }
+            // other operators
+            _ => 1,
+        };
+    }
+    Some(false)
+}
+
+// Offset of the end of the CFF INDEX at `offset`
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcCffIndexEnd(cff: &[u8], offset: usize) -> Option<usize> {
+    let count = usize::from(read_u16(cff, offset)?);
+    if count == 0 {
+        return Some(offset + 2);
+    }
+    let off_size = usize::from(*cff.get(offset + 2)?);
+    let data_start = offset + 3 + (count + 1) * off_size - 1;
+    Some(data_start + FcCffOffset(cff, offset, count)?)
+}
+
+// Data of an item of the CFF INDEX at `offset`
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcCffIndexItem(cff: &[u8], offset: usize, item: usize) -> Option<&[u8]> {
+    let count = usize::from(read_u16(cff, offset)?);
+    if item >= count {
+        return None;
+    }
+    let off_size = usize::from(*cff.get(offset + 2)?);
+    // offsets are 1-based, relative to the byte before the data
+    let data_start = offset + 3 + (count + 1) * off_size - 1;
+    let start = data_start + FcCffOffset(cff, offset, item)?;
+    let end = data_start + FcCffOffset(cff, offset, item + 1)?;
+    cff.get(start..end)
+}
+
+// Offset `i` of the CFF INDEX at `offset` (1 to 4 bytes each)
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcCffOffset(cff: &[u8], offset: usize, i: usize) -> Option<usize> {
+    let off_size = usize::from(*cff.get(offset + 2)?);
+    if !(1..=4).contains(&off_size) {
+        return None;
+    }
+    let start = offset + 3 + i * off_size;
+    let bytes = cff.get(start..start + off_size)?;
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| (value << 8) | usize::from(*byte)),
+    )
+}
@@ -0,0 +1,62 @@
+//! Errors of the `try_` variants of the APIs, see `FcFontCacheBuilder::try_build`
+
+use alloc::string::String;
+use core::fmt;
+
+/// Why a font cache couldn't be built, a font couldn't be found or a value couldn't be parsed
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub enum FcError {
+    /// None of the fontconfig configuration files (`fonts.conf`) exist, see `FONTCONFIG_FILE`
+    /// and `FONTCONFIG_PATH`
+    NoFontConfig,
+    /// A file or directory couldn't be read, i.e. because of missing permissions
+    #[cfg(feature = "std")]
+    Io {
+        path: String,
+        kind: std::io::ErrorKind,
+    },
+    /// A configuration file was read, but isn't valid (or doesn't declare any font directories)
+    Parse { path: String },
+    /// The build was stopped through its `FcCancellationToken`
+    Cancelled,
+    /// No font matches the pattern (also after substitution)
+    NoMatch,
+    /// A string couldn't be parsed, i.e. an unknown weight name
+    InvalidValue { value: String },
+}
+
+impl fmt::Display for FcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FcError::NoFontConfig => write!(f, "no fontconfig configuration file found"),
+            #[cfg(feature = "std")]
+            FcError::Io { path, kind } => {
+                write!(
+                    f,
+                    "failed to read {}: {}",
+                    path,
+                    std::io::Error::from(*kind)
+                )
+            }
+            FcError::Parse { path } => write!(f, "invalid configuration file {}", path),
+            FcError::Cancelled => write!(f, "the build was cancelled"),
+            FcError::NoMatch => write!(f, "no font matches the pattern"),
+            FcError::InvalidValue { value } => write!(f, "invalid value {:?}", value),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FcError {}
+
+#[cfg(feature = "std")]
+impl FcError {
+    // Error of reading `path`
+    pub(crate) fn io(path: &std::path::Path, error: std::io::Error) -> Self {
+        FcError::Io {
+            path: path.to_string_lossy().to_string(),
+            kind: error.kind(),
+        }
+    }
+}
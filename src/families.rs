@@ -0,0 +1,132 @@
+//! Fonts of the cache grouped by family, see `FcFontCache::families`
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{FcFontCache, FcFontPath, FcPattern, FcWeight};
+
+/// A font family of the cache with all its faces
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcFamily {
+    // family name ("DejaVu Sans")
+    pub name: String,
+    // faces ordered by width, slant and weight
+    pub faces: Vec<FcFaceInfo>,
+}
+
+/// A face (style) of a font family
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcFaceInfo {
+    // full name of the face ("DejaVu Sans Bold Oblique")
+    pub name: Option<String>,
+    // `FcWeight(0)` if the font doesn't declare its weight, see `bold`
+    pub weight: FcWeight,
+    pub bold: bool,
+    pub italic: bool,
+    pub oblique: bool,
+    // stretch, only condensed or normal
+    pub condensed: bool,
+    pub monospace: bool,
+    pub path: FcFontPath,
+}
+
+impl FcFaceInfo {
+    /// Returns a style name for the face ("Regular", "Semibold Italic", "Condensed Bold", ...)
+    pub fn style(&self) -> String {
+        let mut words = Vec::new();
+        if self.condensed {
+            words.push("Condensed");
+        }
+        let weight = match self.weight {
+            FcWeight(0) if self.bold => Some("Bold"),
+            FcWeight(0) => None,
+            weight if weight.name() == "regular" => None,
+            weight => Some(FcWeightStyleName(weight)),
+        };
+        words.extend(weight);
+        if self.italic {
+            words.push("Italic");
+        } else if self.oblique {
+            words.push("Oblique");
+        }
+        if words.is_empty() {
+            words.push("Regular");
+        }
+        words.join(" ")
+    }
+
+    pub(crate) fn new(pattern: &FcPattern, path: &FcFontPath) -> Self {
+        FcFaceInfo {
+            name: pattern.name.clone(),
+            weight: pattern.weight,
+            bold: pattern.bold.is_true(),
+            italic: pattern.italic.is_true(),
+            oblique: pattern.oblique.is_true(),
+            condensed: pattern.condensed.is_true(),
+            monospace: pattern.monospace.is_true(),
+            path: path.clone(),
+        }
+    }
+}
+
+// Capitalized name of the weight ("Semibold")
+fn FcWeightStyleName(weight: FcWeight) -> &'static str {
+    match weight.name() {
+        "thin" => "Thin",
+        "extralight" => "Extralight",
+        "light" => "Light",
+        "semilight" => "Semilight",
+        "book" => "Book",
+        "medium" => "Medium",
+        "semibold" => "Semibold",
+        "bold" => "Bold",
+        "extrabold" => "Extrabold",
+        "black" => "Black",
+        "extrablack" => "Extrablack",
+        _ => "Regular",
+    }
+}
+
+impl FcFontCache {
+    /// Returns the fonts of the cache grouped by family (i.e. for a font selection dialog),
+    /// families are ordered by name
+    ///
+    /// Fonts without a family are grouped by their full name, every font file / index is
+    /// only listed once per family.
+    pub fn families(&self) -> Vec<FcFamily> {
+        let mut families = BTreeMap::<&str, Vec<FcFaceInfo>>::new();
+        for (pattern, path) in self.list() {
+            let name = match pattern.family.as_deref().or(pattern.name.as_deref()) {
+                Some(name) => name,
+                None => continue,
+            };
+            let faces = families.entry(name).or_default();
+            if faces.iter().all(|face| face.path != *path) {
+                faces.push(FcFaceInfo::new(pattern, path));
+            }
+        }
+
+        families
+            .into_iter()
+            .map(|(name, mut faces)| {
+                faces.sort_by_key(|face| {
+                    let weight = match face.weight {
+                        FcWeight(0) if face.bold => FcWeight::BOLD,
+                        FcWeight(0) => FcWeight::REGULAR,
+                        weight => weight,
+                    };
+                    (face.condensed, face.italic || face.oblique, weight)
+                });
+                FcFamily {
+                    name: String::from(name),
+                    faces,
+                }
+            })
+            .collect()
+    }
+}
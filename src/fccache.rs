@@ -0,0 +1,567 @@
+//! Reading fontconfig's binary caches (`/var/cache/fontconfig/*.cache-*`)
+//!
+//! `fc-cache` stores the parsed patterns of every font directory in a cache
+//! file that is meant to be `mmap`ed, so reading it is much cheaper than
+//! opening every font file. Only the 64-bit layout of cache versions 7 - 9 is
+//! supported; directories without a cache, with a stale cache (the directory
+//! has been modified since `fc-cache` ran) or with an unreadable cache are
+//! scanned as usual.
+//!
+//! Fonts read from a cache only carry the `charset`, `container`, `format`, `strikes` and
+//! `style` of `FcPattern::metadata`, since fontconfig doesn't store the hinting tables, the
+//! vendor ID or the `GSUB` features, so the caches are only used if enabled with
+//! `FcFontCacheBuilder::fontconfig_caches`.
+
+use alloc::borrow::ToOwned;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::{TryFrom, TryInto};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::dircache::{FcDirRecord, FcModifiedTime};
+use crate::{
+    process_path, FcCharset, FcFontContainer, FcFontFormat, FcFontMetadata, FcFontPath,
+    FcFontSource, FcPattern, FcScanDirectoriesInner, FcScanOptions, FcWeight, PatternMatch,
+};
+
+// `fc-cache` default system cache directory, the per-user one is `$XDG_CACHE_HOME/fontconfig`
+const FONTCONFIG_CACHE_DIR: &str = "/var/cache/fontconfig";
+
+const FC_CACHE_MAGIC_MMAP: u32 = 0xFC02_FC04;
+const FC_CACHE_MIN_VERSION: i32 = 7;
+const FC_CACHE_MAX_VERSION: i32 = 9;
+
+// `FcObject` ids, see fontconfig's `fcobjs.h`
+const FC_FAMILY_OBJECT: i32 = 1;
+const FC_STYLE_OBJECT: i32 = 3;
+const FC_FULLNAME_OBJECT: i32 = 5;
+const FC_SLANT_OBJECT: i32 = 7;
+const FC_WEIGHT_OBJECT: i32 = 8;
+const FC_PIXEL_SIZE_OBJECT: i32 = 12;
+const FC_SPACING_OBJECT: i32 = 13;
+const FC_FILE_OBJECT: i32 = 21;
+const FC_INDEX_OBJECT: i32 = 22;
+const FC_SCALABLE_OBJECT: i32 = 25;
+const FC_CHARSET_OBJECT: i32 = 33;
+const FC_FONTFORMAT_OBJECT: i32 = 37;
+
+// `FcType`
+const FC_TYPE_INTEGER: i32 = 1;
+const FC_TYPE_DOUBLE: i32 = 2;
+const FC_TYPE_STRING: i32 = 3;
+const FC_TYPE_BOOL: i32 = 4;
+const FC_TYPE_CHARSET: i32 = 6;
+const FC_TYPE_RANGE: i32 = 9;
+
+const FC_SLANT_ROMAN: i64 = 0;
+const FC_WEIGHT_REGULAR: i64 = 80;
+const FC_WEIGHT_BOLD: i64 = 200;
+const FC_SPACING_MONO: i64 = 100;
+const FC_SPACING_CHARCELL: i64 = 110;
+
+/// Contents of a single cache file (one font directory, not including subdirectories)
+#[derive(Debug, Clone)]
+struct FcCacheFile {
+    // subdirectories, each one has its own cache file
+    subdirs: Vec<PathBuf>,
+    // `st_mtime` / `st_mtim.tv_nsec` of the directory when the cache was written
+    checksum: i32,
+    checksum_nano: i64,
+    fonts: Vec<(FcPattern, FcFontPath)>,
+}
+
+impl FcCacheFile {
+    // same check as fontconfig's `FcDirCacheValidateHelper`
+    fn is_fresh(&self, dir: &Path) -> bool {
+        std::fs::metadata(dir).is_ok_and(|metadata| {
+            metadata.mtime() as i32 == self.checksum && metadata.mtime_nsec() == self.checksum_nano
+        })
+    }
+}
+
+/// Same as `FcScanDirectoriesInner`, but takes the fonts from the fontconfig caches if they
+/// are up to date. Font directories with missing or stale caches are scanned instead.
+pub(crate) fn FcScanDirectoriesCached(
+    paths: &[(Option<String>, String)],
+    options: &FcScanOptions,
+) -> Vec<(FcPattern, FcFontPath)> {
+    // the caches always describe the whole directory tree, a custom parser has to see
+    // every font file
+    if !options.fontconfig_caches || options.parser.is_some() || !options.is_unbounded() {
+        return FcScanDirectoriesInner(paths, options);
+    }
+
+    let caches = FcReadFontconfigCaches();
+    if caches.is_empty() {
+        return FcScanDirectoriesInner(paths, options);
+    }
+
+    let mut fonts = Vec::new();
+    let mut uncached_dirs = Vec::new();
+
+    for (prefix, path) in paths {
+        let dir = match process_path(prefix, PathBuf::from(path), false) {
+            Some(dir) if dir.is_dir() => dir,
+            _ => continue,
+        };
+
+        let cached = match FcCachedDirsRecursive(&caches, &dir) {
+            Some(cached) => cached,
+            None => {
+                uncached_dirs.push((None, dir.to_string_lossy().to_string()));
+                continue;
+            }
+        };
+
+        for (dir, cache) in cached {
+            if let Some(progress) = options.progress.as_ref() {
+                progress.found_dir(&dir, 0);
+            }
+
+            fonts.extend(
+                cache
+                    .fonts
+                    .iter()
+                    .filter(|(_, path)| path.path().is_none_or(|path| options.accepts_file(path)))
+                    .cloned(),
+            );
+
+            // keep track of the directory for `FcFontCache::refresh`
+            if let (Some(dir_cache), Some(mtime)) =
+                (options.dir_cache.as_ref(), FcModifiedTime(&dir))
+            {
+                dir_cache.insert(
+                    dir,
+                    FcDirRecord {
+                        mtime,
+                        subdirs: cache.subdirs.clone(),
+                        fonts: cache.fonts.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    fonts.extend(FcScanDirectoriesInner(&uncached_dirs, options));
+    fonts
+}
+
+// Collects the caches of `dir` and all of its subdirectories,
+// returns `None` if any of the directories doesn't have an up-to-date cache
+fn FcCachedDirsRecursive<'a>(
+    caches: &'a BTreeMap<PathBuf, Vec<FcCacheFile>>,
+    dir: &Path,
+) -> Option<Vec<(PathBuf, &'a FcCacheFile)>> {
+    let mut cached = Vec::new();
+    let mut dirs_to_visit = vec![dir.to_path_buf()];
+    let mut visited = BTreeSet::new();
+
+    while let Some(dir) = dirs_to_visit.pop() {
+        // caches of a symlink loop would list each other as subdirectories
+        if !visited.insert(dir.clone()) {
+            continue;
+        }
+
+        // the system and the per-user cache directory can both have a cache for `dir`
+        let cache = caches
+            .get(&dir)?
+            .iter()
+            .find(|cache| cache.is_fresh(&dir))?;
+
+        dirs_to_visit.extend(cache.subdirs.iter().cloned());
+        cached.push((dir, cache));
+    }
+
+    Some(cached)
+}
+
+// Reads all cache files of the system and per-user cache directories, keyed by font directory
+fn FcReadFontconfigCaches() -> BTreeMap<PathBuf, Vec<FcCacheFile>> {
+    let mut cache_dirs = vec![PathBuf::from(FONTCONFIG_CACHE_DIR)];
+    if let Some(user_cache_dir) = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+    {
+        cache_dirs.push(user_cache_dir.join("fontconfig"));
+    }
+
+    let mut caches = BTreeMap::<PathBuf, Vec<FcCacheFile>>::new();
+    for cache_dir in cache_dirs {
+        let entries = match std::fs::read_dir(&cache_dir) {
+            Ok(o) => o,
+            Err(_) => continue,
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            // i.e. "3830d5c3ddfd5cd38a049b759396e72e-le64.cache-9"
+            if !entry.file_name().to_string_lossy().contains(".cache-") {
+                continue;
+            }
+
+            let data = match std::fs::read(entry.path()) {
+                Ok(o) => o,
+                Err(_) => continue,
+            };
+
+            if let Some((dir, cache)) = FcParseCacheFile(&data) {
+                caches.entry(dir).or_default().push(cache);
+            }
+        }
+    }
+
+    caches
+}
+
+// Parses a cache file, see `struct FcCache` in fontconfig's `fcint.h`:
+//
+// ```c
+// struct _FcCache {
+//     unsigned int magic;
+//     int          version;
+//     intptr_t     size;
+//     intptr_t     dir;            /* offset to dir name */
+//     intptr_t     dirs;           /* offset to subdirs */
+//     int          dirs_count;
+//     intptr_t     set;            /* offset to font set */
+//     int          checksum;
+//     int64_t      checksum_nano;
+// };
+// ```
+fn FcParseCacheFile(data: &[u8]) -> Option<(PathBuf, FcCacheFile)> {
+    // offsets are stored as `intptr_t`, only the 64-bit layout is supported
+    if core::mem::size_of::<usize>() != 8 {
+        return None;
+    }
+
+    if read_u32(data, 0)? != FC_CACHE_MAGIC_MMAP {
+        return None;
+    }
+
+    let version = read_i32(data, 4)?;
+    if !(FC_CACHE_MIN_VERSION..=FC_CACHE_MAX_VERSION).contains(&version) {
+        return None;
+    }
+
+    if read_offset(data, 8)? != data.len() {
+        return None;
+    }
+
+    let dir = PathBuf::from(read_string(data, read_offset(data, 16)?)?);
+
+    let dirs = read_offset(data, 24)?;
+    let dirs_count = usize::try_from(read_i32(data, 32)?).ok()?;
+    let subdirs = (0..dirs_count)
+        .map(|i| {
+            // offsets of the subdirectory names are relative to the array
+            let subdir = dirs.checked_add(read_offset(data, dirs + i * 8)?)?;
+            read_string(data, subdir).map(PathBuf::from)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let set = read_offset(data, 40)?;
+    let checksum = read_i32(data, 48)?;
+    let checksum_nano = read_i64(data, 56)?;
+
+    let fonts = FcParseCacheFontSet(data, set, &dir)?;
+
+    let cache = FcCacheFile {
+        subdirs,
+        checksum,
+        checksum_nano,
+        fonts,
+    };
+
+    Some((dir, cache))
+}
+
+// Parses a `FcFontSet { int nfont; int sfont; FcPattern **fonts; }`
+fn FcParseCacheFontSet(
+    data: &[u8],
+    set: usize,
+    dir: &Path,
+) -> Option<Vec<(FcPattern, FcFontPath)>> {
+    let nfont = usize::try_from(read_i32(data, set)?).ok()?;
+    let fonts = read_encoded_offset(data, set, set + 8)?;
+
+    let mut patterns: Vec<(FcPattern, FcFontPath)> = Vec::with_capacity(nfont);
+    for i in 0..nfont {
+        // offsets of the patterns are relative to the font set
+        let pattern = read_encoded_offset(data, set, fonts + i * 8)?;
+        if let Some(mut font) = FcParseCachePattern(data, pattern, dir) {
+            // the faces of a file are stored next to each other, they share the path
+            if let Some((_, previous)) = patterns.last() {
+                if previous.source == font.1.source {
+                    font.1.source = previous.source.clone();
+                }
+            }
+            patterns.push(font);
+        }
+    }
+
+    Some(patterns)
+}
+
+// Converts a cached `FcPattern` to a pattern / path, the same way `FcParseFontFace` would
+//
+// ```c
+// struct _FcPattern { int num; int size; intptr_t elts_offset; FcRef ref; };
+// struct _FcPatternElt { FcObject object; FcValueList *values; };
+// struct _FcValueList { struct _FcValueList *next; FcValue value; FcValueBinding binding; };
+// struct _FcValue { FcType type; union { const FcChar8 *s; int i; FcBool b; double d; ... } u; };
+// ```
+fn FcParseCachePattern(data: &[u8], pattern: usize, dir: &Path) -> Option<(FcPattern, FcFontPath)> {
+    let num = usize::try_from(read_i32(data, pattern)?).ok()?;
+    let elts = pattern.checked_add(read_offset(data, pattern + 8)?)?;
+
+    let mut family = None;
+    let mut style = None;
+    let mut name = None;
+    let mut file = None;
+    let mut index = 0;
+    let mut slant = FC_SLANT_ROMAN;
+    let mut weight = 0;
+    let mut spacing = 0;
+    let mut scalable = true;
+    let mut charset = FcCharset::new();
+    let mut font_format = None;
+    let mut strikes = Vec::new();
+
+    for i in 0..num {
+        let elt = elts + i * 16;
+        let object = read_i32(data, elt)?;
+
+        // only the first value of every object is used (i.e. the english family name), values
+        // of other types than expected are skipped
+        let values = read_encoded_offset(data, elt, elt + 8)?;
+        let value = values + 8;
+
+        match object {
+            FC_FAMILY_OBJECT => family = FcCacheValueString(data, value),
+            FC_STYLE_OBJECT => style = FcCacheValueString(data, value),
+            FC_FULLNAME_OBJECT => name = FcCacheValueString(data, value),
+            FC_FILE_OBJECT => file = FcCacheValueString(data, value),
+            FC_INDEX_OBJECT => index = FcCacheValueInteger(data, value).unwrap_or(index),
+            FC_SLANT_OBJECT => {
+                slant = FcCacheValueStyle(data, value, FC_SLANT_ROMAN).unwrap_or(slant)
+            }
+            FC_WEIGHT_OBJECT => {
+                weight = FcCacheValueStyle(data, value, FC_WEIGHT_REGULAR).unwrap_or(weight)
+            }
+            FC_SPACING_OBJECT => spacing = FcCacheValueInteger(data, value).unwrap_or(spacing),
+            FC_SCALABLE_OBJECT => {
+                scalable = FcCacheValueInteger(data, value).map_or(scalable, |s| s != 0)
+            }
+            FC_CHARSET_OBJECT => charset = FcCacheValueCharset(data, value).unwrap_or_default(),
+            FC_FONTFORMAT_OBJECT => font_format = FcCacheValueString(data, value),
+            FC_PIXEL_SIZE_OBJECT => strikes = FcCacheStrikes(data, values),
+            _ => {}
+        }
+    }
+
+    // the upper 16 bits of the index are the named instance of a variable font
+    if index >> 16 != 0 {
+        return None;
+    }
+
+    // `FcParseFontFace` skips fonts without a full name as well
+    let name = name.filter(|name| !name.is_empty())?;
+    let file = Path::new(&file?).to_path_buf();
+    let file = if file.is_absolute() {
+        file
+    } else {
+        dir.join(file)
+    };
+    let (container, format) = FcCacheFontFormat(font_format.as_deref(), &file, scalable);
+
+    let pattern = FcPattern {
+        name: Some(name),
+        family,
+        bold: PatternMatch::from(weight >= FC_WEIGHT_BOLD),
+        weight: FcWeight::from_fontconfig(weight),
+        italic: PatternMatch::from(slant != FC_SLANT_ROMAN),
+        monospace: PatternMatch::from(spacing == FC_SPACING_MONO || spacing == FC_SPACING_CHARCELL),
+        scalable: PatternMatch::from(scalable),
+        metadata: FcFontMetadata {
+            charset,
+            container,
+            format,
+            strikes,
+            style: style.filter(|style| !style.is_empty()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let path = FcFontPath {
+        source: FcFontSource::Disk(file.to_string_lossy().into()),
+        font_index: usize::try_from(index).ok()?,
+    };
+
+    Some((pattern, path))
+}
+
+// Container and format of a font from its `fontformat` (the FreeType driver: "TrueType",
+// "CFF", "Type 1", "PCF", ...) and extension, fontconfig reports the outline format of
+// WOFF / WOFF2 files and the format of every face of collections
+fn FcCacheFontFormat(
+    font_format: Option<&str>,
+    file: &Path,
+    scalable: bool,
+) -> (FcFontContainer, FcFontFormat) {
+    let extension = file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match (font_format, extension.as_deref()) {
+        (_, Some("woff")) => (FcFontContainer::Woff, FcFontFormat::Woff),
+        (_, Some("woff2")) => (FcFontContainer::Woff2, FcFontFormat::Woff2),
+        (Some("Type 1") | Some("CID Type 1"), _) => (FcFontContainer::Type1, FcFontFormat::Type1),
+        (Some("PCF"), _) => (FcFontContainer::Pcf, FcFontFormat::Bitmap),
+        (_, Some("ttc") | Some("otc")) => (FcFontContainer::Sfnt, FcFontFormat::Collection),
+        _ if !scalable => (FcFontContainer::Sfnt, FcFontFormat::Bitmap),
+        (Some("CFF"), _) => (FcFontContainer::Sfnt, FcFontFormat::Cff),
+        _ => (FcFontContainer::Sfnt, FcFontFormat::TrueType),
+    }
+}
+
+// Reads all values of the `pixelsize` of a font, fontconfig stores one per bitmap strike of
+// fonts without outlines
+fn FcCacheStrikes(data: &[u8], values: usize) -> Vec<u16> {
+    // a font can't have more strikes than ppem values, more values are a corrupt cache
+    const MAX_STRIKES: usize = 256;
+
+    let mut strikes =
+        core::iter::successors(Some(values), |list| read_encoded_offset(data, *list, *list))
+            .take(MAX_STRIKES)
+            .filter_map(|list| FcCacheValueInteger(data, list + 8))
+            .filter_map(|size| u16::try_from(size).ok())
+            .filter(|size| *size != 0)
+            .collect::<Vec<_>>();
+    strikes.sort_unstable();
+    strikes.dedup();
+    strikes
+}
+
+// Reads a `FcTypeString` value, the string offset is relative to the `FcValue`
+fn FcCacheValueString(data: &[u8], value: usize) -> Option<String> {
+    if read_i32(data, value)? != FC_TYPE_STRING {
+        return None;
+    }
+    let s = read_encoded_offset(data, value, value + 8)?;
+    read_string(data, s).map(ToOwned::to_owned)
+}
+
+// Reads a `FcTypeInteger`, `FcTypeDouble` or `FcTypeBool` value as an integer
+fn FcCacheValueInteger(data: &[u8], value: usize) -> Option<i64> {
+    match read_i32(data, value)? {
+        FC_TYPE_INTEGER | FC_TYPE_BOOL => read_i32(data, value + 8).map(i64::from),
+        FC_TYPE_DOUBLE => read_f64(data, value + 8).map(|d| d as i64),
+        _ => None,
+    }
+}
+
+// Reads a weight / slant as an integer, variable fonts have a `FcTypeRange` value instead
+// (`struct _FcRange { double begin; double end; }`) and get `default` if the range includes
+// it, the closest end of the range otherwise
+fn FcCacheValueStyle(data: &[u8], value: usize, default: i64) -> Option<i64> {
+    if read_i32(data, value)? != FC_TYPE_RANGE {
+        return FcCacheValueInteger(data, value);
+    }
+    let range = read_encoded_offset(data, value, value + 8)?;
+    let (begin, end) = (read_f64(data, range)?, read_f64(data, range + 8)?);
+    // NaN ends are a corrupt cache
+    match begin.partial_cmp(&end) {
+        Some(Ordering::Less) | Some(Ordering::Equal) => {
+            Some((default as f64).clamp(begin, end) as i64)
+        }
+        _ => None,
+    }
+}
+
+// Reads a `FcTypeCharSet` value, the charset offset is relative to the `FcValue`
+//
+// ```c
+// struct _FcCharSet { int ref; int num; intptr_t leaves_offset; intptr_t numbers_offset; };
+// struct _FcCharLeaf { FcChar32 map[256 / 32]; };
+// ```
+//
+// `leaves_offset` points to `num` offsets of the leaves (relative to the offsets),
+// `numbers_offset` to the `num` page numbers (`FcChar16`) of the leaves.
+fn FcCacheValueCharset(data: &[u8], value: usize) -> Option<FcCharset> {
+    if read_i32(data, value)? != FC_TYPE_CHARSET {
+        return None;
+    }
+    let charset = read_encoded_offset(data, value, value + 8)?;
+    let num = usize::try_from(read_i32(data, charset + 4)?).ok()?;
+    let leaves = read_relative_offset(data, charset, charset + 8)?;
+    let numbers = read_relative_offset(data, charset, charset + 16)?;
+
+    let mut result = FcCharset::new();
+    for i in 0..num {
+        // leaves are shared between the charsets, they can come before the offsets
+        let leaf = read_relative_offset(data, leaves, leaves + i * 8)?;
+        let page = read_u16(data, numbers + i * 2)?;
+        let mut bits = [0; 8];
+        for (j, word) in bits.iter_mut().enumerate() {
+            *word = read_u32(data, leaf + j * 4)?;
+        }
+        result.insert_page(u32::from(page), bits);
+    }
+    Some(result)
+}
+
+// Cache files are written in native byte order
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    read_bytes(data, offset).map(u16::from_ne_bytes)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    read_bytes(data, offset).map(u32::from_ne_bytes)
+}
+
+fn read_i32(data: &[u8], offset: usize) -> Option<i32> {
+    read_bytes(data, offset).map(i32::from_ne_bytes)
+}
+
+fn read_i64(data: &[u8], offset: usize) -> Option<i64> {
+    read_bytes(data, offset).map(i64::from_ne_bytes)
+}
+
+fn read_f64(data: &[u8], offset: usize) -> Option<f64> {
+    read_bytes(data, offset).map(f64::from_ne_bytes)
+}
+
+// Plain (`intptr_t`) offset
+fn read_offset(data: &[u8], offset: usize) -> Option<usize> {
+    usize::try_from(read_i64(data, offset)?).ok()
+}
+
+// Plain offset relative to `base`, can be negative
+fn read_relative_offset(data: &[u8], base: usize, offset: usize) -> Option<usize> {
+    base.checked_add_signed(isize::try_from(read_i64(data, offset)?).ok()?)
+}
+
+// Pointers stored in the cache are offsets relative to `base` with the lowest bit set
+fn read_encoded_offset(data: &[u8], base: usize, offset: usize) -> Option<usize> {
+    let encoded = read_i64(data, offset)?;
+    if encoded & 1 == 0 {
+        return None;
+    }
+    base.checked_add_signed(isize::try_from(encoded & !1).ok()?)
+}
+
+// Null-terminated UTF-8 string
+fn read_string(data: &[u8], offset: usize) -> Option<&str> {
+    let bytes = data.get(offset..)?;
+    let len = bytes.iter().position(|b| *b == 0)?;
+    core::str::from_utf8(&bytes[..len]).ok()
+}
@@ -0,0 +1,182 @@
+//! Typographic features of fonts (small caps, figure styles, ...), see
+//! `FcFontMetadata::features`
+
+#[cfg(all(feature = "std", feature = "scan"))]
+use crate::coverage::FcCmapGlyph;
+#[cfg(all(feature = "std", feature = "scan"))]
+use crate::utils::{read_u16, read_u32};
+
+/// Typographic features a font has `GSUB` lookups for, see `FcFontMetadata::features`
+///
+/// Text has to be shaped with the feature enabled to use them, fonts without a feature
+/// can only imitate it (i.e. small caps from scaled capitals).
+#[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[repr(C)]
+pub struct FcFeatureInfo {
+    // lowercase letters to small capitals (`smcp`)
+    pub small_caps: bool,
+    // capital letters to small capitals (`c2sc`)
+    pub caps_to_small_caps: bool,
+    // figures of equal width (`tnum`)
+    pub tabular_figures: bool,
+    // the default digits 0 - 9 all have the same advance width, without any feature
+    pub monospaced_digits: bool,
+    // old-style figures (`onum`)
+    pub old_style_figures: bool,
+    // lining figures (`lnum`)
+    pub lining_figures: bool,
+    // style of the default digits, `None` if it isn't known (fonts without `glyf` outlines)
+    pub default_figures: Option<FcFigureStyle>,
+}
+
+/// Style of the digits of a font, see `FcFeatureInfo::default_figures`
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcFigureStyle {
+    /// Digits of capital height
+    Lining,
+    /// Digits of lowercase height with ascenders (6, 8) and descenders (3, 4, 5, 7, 9)
+    OldStyle,
+}
+
+impl FcFigureStyle {
+    /// Returns the name of the style ("lining" or "old_style"), used by the JSON cache
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FcFigureStyle::Lining => "lining",
+            FcFigureStyle::OldStyle => "old_style",
+        }
+    }
+}
+
+impl FcFeatureInfo {
+    /// Returns whether the font can set digits that line up in columns, by default or
+    /// through `tnum`
+    pub fn has_tabular_figures(&self) -> bool {
+        self.tabular_figures || self.monospaced_digits
+    }
+
+    /// Returns whether the font can set digits in the given style, by default or through
+    /// `onum` / `lnum`
+    pub fn has_figures(&self, style: FcFigureStyle) -> bool {
+        self.default_figures == Some(style)
+            || match style {
+                FcFigureStyle::Lining => self.lining_figures,
+                FcFigureStyle::OldStyle => self.old_style_figures,
+            }
+    }
+}
+
+// Detects the features of a font from the feature list of its GSUB table (features without
+// lookups don't count) and the advance widths of its digits
+#[cfg(all(feature = "std", feature = "scan"))]
+pub(crate) fn FcParseFeatureInfo(
+    gsub_data: Option<&[u8]>,
+    cmap_data: Option<&[u8]>,
+    hhea_data: Option<&[u8]>,
+    hmtx_data: Option<&[u8]>,
+) -> FcFeatureInfo {
+    let monospaced_digits = match (cmap_data, hhea_data, hmtx_data) {
+        (Some(cmap), Some(hhea), Some(hmtx)) => FcHasMonospacedDigits(cmap, hhea, hmtx),
+        _ => false,
+    };
+    let gsub_data = match gsub_data {
+        Some(gsub_data) => gsub_data,
+        None => {
+            return FcFeatureInfo {
+                monospaced_digits,
+                ..Default::default()
+            }
+        }
+    };
+    let feature_list = usize::from(read_u16(gsub_data, 6).unwrap_or(0));
+    let feature_count = match feature_list {
+        0 => 0,
+        _ => read_u16(gsub_data, feature_list).unwrap_or(0),
+    };
+
+    // see https://learn.microsoft.com/en-us/typography/opentype/spec/chapter2#flTbl
+    let has_feature = |feature: &[u8; 4]| {
+        (0..usize::from(feature_count)).any(|i| {
+            let record = feature_list + 2 + i * 6;
+            gsub_data.get(record..record + 4) == Some(&feature[..])
+                && read_u16(gsub_data, record + 4)
+                    .and_then(|offset| read_u16(gsub_data, feature_list + usize::from(offset) + 2))
+                    .is_some_and(|lookup_count| lookup_count > 0)
+        })
+    };
+
+    FcFeatureInfo {
+        small_caps: has_feature(b"smcp"),
+        caps_to_small_caps: has_feature(b"c2sc"),
+        tabular_figures: has_feature(b"tnum"),
+        monospaced_digits,
+        old_style_figures: has_feature(b"onum"),
+        lining_figures: has_feature(b"lnum"),
+        default_figures: None,
+    }
+}
+
+// Detects the style of the default digits from the heights of their `glyf` outlines:
+// old-style zeros only reach the x-height, while the eight has an ascender
+#[cfg(all(feature = "std", feature = "scan"))]
+pub(crate) fn FcParseFigureStyle(
+    cmap_data: Option<&[u8]>,
+    head_data: Option<&[u8]>,
+    loca_data: Option<&[u8]>,
+    glyf_data: Option<&[u8]>,
+) -> Option<FcFigureStyle> {
+    let (cmap_data, loca_data, glyf_data) = (cmap_data?, loca_data?, glyf_data?);
+    // indexToLocFormat, 0 for u16 offsets / 2, 1 for u32 offsets
+    let long_offsets = read_u16(head_data?, 50)? == 1;
+
+    // yMax of the bounding box in the glyph header
+    let y_max = |c: char| {
+        let glyph = usize::from(FcCmapGlyph(cmap_data, c)?);
+        let (start, end) = match long_offsets {
+            true => (
+                read_u32(loca_data, glyph * 4)? as usize,
+                read_u32(loca_data, glyph * 4 + 4)? as usize,
+            ),
+            false => (
+                usize::from(read_u16(loca_data, glyph * 2)?) * 2,
+                usize::from(read_u16(loca_data, glyph * 2 + 2)?) * 2,
+            ),
+        };
+        match end > start {
+            true => read_u16(glyf_data, start + 8).map(|y_max| i32::from(y_max as i16)),
+            false => None,
+        }
+    };
+
+    let (zero, eight) = (y_max('0')?, y_max('8')?);
+    if eight <= 0 {
+        return None;
+    }
+    // x-height is usually 65 - 75% of the cap height
+    match zero * 100 < eight * 85 {
+        true => Some(FcFigureStyle::OldStyle),
+        false => Some(FcFigureStyle::Lining),
+    }
+}
+
+// Whether the glyphs of the digits 0 - 9 all have the same advance width in the hmtx table
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcHasMonospacedDigits(cmap_data: &[u8], hhea_data: &[u8], hmtx_data: &[u8]) -> bool {
+    // numberOfHMetrics, glyphs after the last metric have its advance width
+    let num_h_metrics = match read_u16(hhea_data, 34) {
+        Some(count) if count > 0 => usize::from(count),
+        _ => return false,
+    };
+    let advance = |c: char| {
+        let glyph = usize::from(FcCmapGlyph(cmap_data, c)?);
+        read_u16(hmtx_data, glyph.min(num_h_metrics - 1) * 4)
+    };
+
+    let mut advances = ('0'..='9').map(advance);
+    let first = advances.next().flatten();
+    first.is_some() && advances.all(|advance| advance == first)
+}
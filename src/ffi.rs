@@ -0,0 +1,285 @@
+//! C API (`ffi` feature), so that C / C++ and other languages can use dafont instead of
+//! fontconfig
+//!
+//! Build the crate as a C library with `cargo rustc --release --features ffi --crate-type cdylib`
+//! (or `staticlib`), the header can be generated with cbindgen. The cache is an opaque
+//! `FcFontCache *`, patterns are passed in the format of `FcPattern`'s `FromStr`
+//! (`"DejaVu Sans:style=Bold"`, `":monospace=true"`).
+//!
+//! ```c
+//! FcFontCache *cache = dafont_cache_build();
+//! DafontResult *result = dafont_cache_query(cache, "DejaVu Sans:style=Bold");
+//! if (result != NULL && result->path != NULL) {
+//!     printf("%s (%zu)\n", result->path, result->font_index);
+//! }
+//! dafont_result_free(result);
+//! dafont_cache_free(cache);
+//! ```
+//!
+//! All strings are UTF-8 and owned by the results, they stay valid until the result is freed.
+//! Panics don't unwind into the caller, the functions return NULL / false instead.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ptr;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{FcFontCache, FcFontPath, FcPattern};
+
+/// A font found by `dafont_cache_query` / `dafont_cache_query_all`
+#[derive(Debug)]
+#[repr(C)]
+pub struct DafontResult {
+    /// Path of the font file, NULL for in-memory fonts
+    pub path: *mut c_char,
+    /// Index of the font in the file (font collections)
+    pub font_index: usize,
+    /// Family name ("DejaVu Sans"), NULL if unknown
+    pub family: *mut c_char,
+    /// Full name ("DejaVu Sans Bold"), NULL if unknown
+    pub name: *mut c_char,
+    /// CSS weight (100 - 1000), 0 if the font doesn't declare its weight
+    pub weight: u16,
+    pub bold: bool,
+    pub italic: bool,
+    pub monospace: bool,
+    /// The pattern asked for bold but the font isn't, the glyphs have to be emboldened
+    pub needs_synthetic_bold: bool,
+    /// The pattern asked for italic / oblique but the font is upright, the glyphs have to
+    /// be sheared
+    pub needs_synthetic_oblique: bool,
+    /// Clockwise angle in degrees to shear the glyphs by when `needs_synthetic_oblique`
+    pub oblique_angle: f32,
+}
+
+impl DafontResult {
+    fn new(pattern: &FcPattern, path: &FcFontPath) -> Self {
+        DafontResult {
+            path: FcCString(path.path()),
+            font_index: path.font_index,
+            family: FcCString(pattern.family.as_deref()),
+            name: FcCString(pattern.name.as_deref()),
+            weight: pattern.weight.0,
+            bold: pattern.bold.is_true(),
+            italic: pattern.italic.is_true(),
+            monospace: pattern.monospace.is_true(),
+            needs_synthetic_bold: false,
+            needs_synthetic_oblique: false,
+            oblique_angle: 0.0,
+        }
+    }
+
+    // Frees the strings of the result
+    unsafe fn free_strings(&mut self) {
+        for s in [self.path, self.family, self.name].iter().copied() {
+            if !s.is_null() {
+                drop(CString::from_raw(s));
+            }
+        }
+    }
+}
+
+// Copies a string for C, strings with interior NUL bytes are returned as NULL
+fn FcCString(s: Option<&str>) -> *mut c_char {
+    s.and_then(|s| CString::new(s).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+// Runs the body of an exported function, a panic returns `error` instead of unwinding
+// into the C caller (which is undefined behavior)
+fn FcCatchUnwind<T, F: FnOnce() -> T>(error: T, f: F) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(error)
+}
+
+// Parses a C string as a pattern, NULL is the empty pattern (matches all fonts)
+unsafe fn FcParsePattern(pattern: *const c_char) -> Option<FcPattern> {
+    if pattern.is_null() {
+        return Some(FcPattern::default());
+    }
+    CStr::from_ptr(pattern).to_str().ok()?.parse().ok()
+}
+
+/// Builds a cache of the system fonts, see `FcFontCache::build`
+///
+/// The cache has to be freed with `dafont_cache_free`.
+#[no_mangle]
+pub extern "C" fn dafont_cache_build() -> *mut FcFontCache {
+    FcCatchUnwind(ptr::null_mut(), || {
+        Box::into_raw(Box::new(FcFontCache::build()))
+    })
+}
+
+/// Reads a cache written by `dafont_cache_save`, see `FcFontCache::load`
+///
+/// Returns NULL if the file doesn't exist, is corrupted or was written by a different
+/// version of dafont. The cache has to be freed with `dafont_cache_free`.
+///
+/// # Safety
+///
+/// `path` has to be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dafont_cache_load(path: *const c_char) -> *mut FcFontCache {
+    FcCatchUnwind(ptr::null_mut(), || {
+        if path.is_null() {
+            return ptr::null_mut();
+        }
+        let path = match CStr::from_ptr(path).to_str() {
+            Ok(path) => path,
+            Err(_) => return ptr::null_mut(),
+        };
+        FcFontCache::load(path).map_or(ptr::null_mut(), |cache| Box::into_raw(Box::new(cache)))
+    })
+}
+
+/// Writes the cache to a file, see `FcFontCache::save`, returns false on errors
+///
+/// # Safety
+///
+/// `cache` has to be NULL or a cache of `dafont_cache_build` / `dafont_cache_load` that
+/// wasn't freed, `path` NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dafont_cache_save(cache: *const FcFontCache, path: *const c_char) -> bool {
+    FcCatchUnwind(false, || {
+        let cache = match cache.as_ref() {
+            Some(cache) => cache,
+            None => return false,
+        };
+        if path.is_null() {
+            return false;
+        }
+        match CStr::from_ptr(path).to_str() {
+            Ok(path) => cache.save(path).is_ok(),
+            Err(_) => false,
+        }
+    })
+}
+
+/// Frees a cache, NULL is ignored
+///
+/// # Safety
+///
+/// `cache` has to be NULL or a cache of `dafont_cache_build` / `dafont_cache_load` that
+/// wasn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn dafont_cache_free(cache: *mut FcFontCache) {
+    FcCatchUnwind((), || {
+        if !cache.is_null() {
+            drop(Box::from_raw(cache));
+        }
+    })
+}
+
+/// Returns the first font matching the pattern, see `FcFontCache::query`
+///
+/// Returns NULL if no font matches or the pattern can't be parsed, a NULL pattern matches
+/// all fonts. The result has to be freed with `dafont_result_free`, it stays valid after
+/// the cache is freed.
+///
+/// # Safety
+///
+/// `cache` has to be NULL or a cache of `dafont_cache_build` / `dafont_cache_load` that
+/// wasn't freed, `pattern` NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dafont_cache_query(
+    cache: *const FcFontCache,
+    pattern: *const c_char,
+) -> *mut DafontResult {
+    FcCatchUnwind(ptr::null_mut(), || {
+        let cache = match cache.as_ref() {
+            Some(cache) => cache,
+            None => return ptr::null_mut(),
+        };
+        let result = FcParsePattern(pattern)
+            .and_then(|pattern| cache.query_owned(&pattern))
+            .map(|font| {
+                let oblique_angle = font.oblique_angle();
+                let path = FcFontPath {
+                    source: font.source,
+                    font_index: font.index,
+                };
+                DafontResult {
+                    needs_synthetic_bold: font.needs_synthetic_bold,
+                    needs_synthetic_oblique: font.needs_synthetic_oblique,
+                    oblique_angle,
+                    ..DafontResult::new(&font.pattern, &path)
+                }
+            });
+        result.map_or(ptr::null_mut(), |result| Box::into_raw(Box::new(result)))
+    })
+}
+
+/// Returns all fonts matching the pattern, see `FcFontCache::query_all`
+///
+/// The number of fonts is written to `count` (0 and NULL if no font matches or the pattern
+/// can't be parsed), a NULL pattern matches all fonts. The results have to be freed with
+/// `dafont_results_free`, they stay valid after the cache is freed.
+///
+/// # Safety
+///
+/// `cache` has to be NULL or a cache of `dafont_cache_build` / `dafont_cache_load` that
+/// wasn't freed, `pattern` NULL or a valid NUL-terminated string and `count` NULL or a
+/// valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn dafont_cache_query_all(
+    cache: *const FcFontCache,
+    pattern: *const c_char,
+    count: *mut usize,
+) -> *mut DafontResult {
+    FcCatchUnwind(ptr::null_mut(), || {
+        // 0 if the query panics
+        if let Some(count) = count.as_mut() {
+            *count = 0;
+        }
+        let results = match (cache.as_ref(), FcParsePattern(pattern)) {
+            (Some(cache), Some(pattern)) => cache
+                .query_all_entries(&pattern)
+                .into_iter()
+                .map(|(pattern, path)| DafontResult::new(pattern, path))
+                .collect::<Vec<_>>(),
+            _ => Vec::new(),
+        };
+
+        if let Some(count) = count.as_mut() {
+            *count = results.len();
+        }
+        if results.is_empty() {
+            return ptr::null_mut();
+        }
+        Box::into_raw(results.into_boxed_slice()) as *mut DafontResult
+    })
+}
+
+/// Frees a result of `dafont_cache_query`, NULL is ignored
+///
+/// # Safety
+///
+/// `result` has to be NULL or a result of `dafont_cache_query` that wasn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn dafont_result_free(result: *mut DafontResult) {
+    FcCatchUnwind((), || {
+        if !result.is_null() {
+            Box::from_raw(result).free_strings();
+        }
+    })
+}
+
+/// Frees the results of `dafont_cache_query_all`, NULL is ignored
+///
+/// # Safety
+///
+/// `results` has to be NULL or the results of `dafont_cache_query_all` that weren't freed
+/// yet and `count` the number of results written by it.
+#[no_mangle]
+pub unsafe extern "C" fn dafont_results_free(results: *mut DafontResult, count: usize) {
+    FcCatchUnwind((), || {
+        if results.is_null() {
+            return;
+        }
+        let mut results = Box::from_raw(ptr::slice_from_raw_parts_mut(results, count));
+        for result in results.iter_mut() {
+            result.free_strings();
+        }
+    })
+}
@@ -0,0 +1,127 @@
+//! Conversions to `fontdb` (`fontdb` feature), for projects that use cosmic-text / fontdb
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::path::PathBuf;
+
+use ::fontdb::{Database, FaceInfo, Family, Language, Query, Source, Stretch, Style, Weight, ID};
+
+use crate::{FcFontCache, FcFontPath, FcFontSource, FcPattern, FcWeight, GenericFamily};
+
+/// Adds every font of the cache to a new `fontdb::Database`, without parsing the fonts again
+///
+/// Every font file / index is added once, `post_script_name` is set to the full name of the
+/// pattern.
+impl From<&FcFontCache> for Database {
+    fn from(cache: &FcFontCache) -> Self {
+        let mut db = Database::new();
+        let mut added = alloc::collections::btree_set::BTreeSet::new();
+        for (pattern, path) in cache.list() {
+            if added.insert(path) {
+                db.push_face_info(FcFontdbFace(pattern, path));
+            }
+        }
+        db
+    }
+}
+
+impl FcPattern {
+    /// Runs the pattern as `fontdb::Query` on `db`
+    ///
+    /// The family (or the generic family, "sans-serif" if the pattern doesn't have a family),
+    /// weight, italic / oblique and condensed are used, the full name is ignored.
+    pub fn query_fontdb(&self, db: &Database) -> Option<ID> {
+        let family = match self.family.as_deref() {
+            Some(family) => match FcGenericFamily(family) {
+                Some(generic) => FcFontdbGeneric(generic),
+                None => Family::Name(family),
+            },
+            None if self.monospace.is_true() => Family::Monospace,
+            None => Family::SansSerif,
+        };
+
+        db.query(&Query {
+            families: &[family],
+            weight: Weight(FcPatternWeight(self).0),
+            stretch: FcFontdbStretch(self),
+            style: FcFontdbStyle(self),
+        })
+    }
+}
+
+fn FcFontdbFace(pattern: &FcPattern, path: &FcFontPath) -> FaceInfo {
+    let source = match &path.source {
+        FcFontSource::Disk(file) => Source::File(PathBuf::from(&**file)),
+        FcFontSource::Memory(data) => Source::Binary(Arc::new(data.clone())),
+    };
+    // same as `FcFontCache::families`, fonts without a family use their full name
+    let families = pattern
+        .family
+        .as_ref()
+        .or(pattern.name.as_ref())
+        .map(|family| (family.clone(), Language::English_UnitedStates))
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    FaceInfo {
+        id: ID::dummy(),
+        source,
+        index: path.font_index as u32,
+        families,
+        post_script_name: pattern.name.clone().unwrap_or_default(),
+        style: FcFontdbStyle(pattern),
+        weight: Weight(FcPatternWeight(pattern).0),
+        stretch: FcFontdbStretch(pattern),
+        monospaced: pattern.monospace.is_true(),
+    }
+}
+
+// The weight of the pattern, from `bold` if the weight isn't set
+fn FcPatternWeight(pattern: &FcPattern) -> FcWeight {
+    match pattern.weight {
+        FcWeight(0) if pattern.bold.is_true() => FcWeight::BOLD,
+        FcWeight(0) => FcWeight::REGULAR,
+        weight => weight,
+    }
+}
+
+fn FcFontdbStyle(pattern: &FcPattern) -> Style {
+    if pattern.italic.is_true() {
+        Style::Italic
+    } else if pattern.oblique.is_true() {
+        Style::Oblique
+    } else {
+        Style::Normal
+    }
+}
+
+fn FcFontdbStretch(pattern: &FcPattern) -> Stretch {
+    if pattern.condensed.is_true() {
+        Stretch::Condensed
+    } else {
+        Stretch::Normal
+    }
+}
+
+fn FcGenericFamily(family: &str) -> Option<GenericFamily> {
+    [
+        GenericFamily::Serif,
+        GenericFamily::SansSerif,
+        GenericFamily::Monospace,
+        GenericFamily::Cursive,
+        GenericFamily::Fantasy,
+    ]
+    .iter()
+    .copied()
+    .find(|generic| generic.as_str().eq_ignore_ascii_case(family))
+}
+
+fn FcFontdbGeneric(generic: GenericFamily) -> Family<'static> {
+    match generic {
+        GenericFamily::Serif => Family::Serif,
+        GenericFamily::SansSerif => Family::SansSerif,
+        GenericFamily::Monospace => Family::Monospace,
+        GenericFamily::Cursive => Family::Cursive,
+        GenericFamily::Fantasy => Family::Fantasy,
+    }
+}
@@ -0,0 +1,136 @@
+//! Lazily loaded fonts, see `FcFontCache::query_handle`
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::sync::{Arc, Weak};
+#[cfg(feature = "scan")]
+use alloc::vec::Vec;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::bytes::FcFontBytes;
+use crate::{FcFontCache, FcFontPath, FcFontSource, FcPattern};
+
+/// Font returned by `FcFontCache::query_handle`, the font data is only loaded on first use
+///
+/// The data is shared by all handles of the same file (of the same cache) that are alive,
+/// so using the same font again doesn't read the file again. Clones of a handle share the
+/// data as well.
+#[derive(Debug, Clone)]
+pub struct FcFontHandle {
+    path: FcFontPath,
+    // files loaded by handles of the cache
+    loaded: Arc<FcLoadedFonts>,
+    // `None` if the file couldn't be read
+    bytes: Arc<OnceLock<Option<Arc<FcFontBytes>>>>,
+    // decompressed data of WOFF / WOFF2 / gzip files for `table_data`, `None` if the file
+    // is SFNT data already
+    #[cfg(feature = "scan")]
+    sfnt: Arc<OnceLock<Option<Vec<u8>>>>,
+}
+
+impl FcFontHandle {
+    /// Returns the font file (or in-memory data) and the index of the font
+    pub fn path(&self) -> &FcFontPath {
+        &self.path
+    }
+
+    /// Returns the index of the font in the font file (font collections)
+    pub fn font_index(&self) -> usize {
+        self.path.font_index
+    }
+
+    /// Returns the data of the font file (all faces of a collection), loads it on first use
+    ///
+    /// Returns `None` if the file can't be read anymore.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        self.shared_bytes().map(|bytes| &***bytes)
+    }
+
+    /// Same as `bytes`, but returns the shared data, i.e. to keep the font
+    /// loaded after the handle is dropped
+    pub fn shared_bytes(&self) -> Option<&Arc<FcFontBytes>> {
+        self.bytes
+            .get_or_init(|| self.loaded.load(&self.path.source))
+            .as_ref()
+    }
+
+    /// Returns the data of a table of the font (i.e. `*b"cmap"` as big-endian `u32`),
+    /// `None` if the font doesn't have the table or can't be parsed
+    #[cfg(feature = "scan")]
+    pub fn table_data(&self, tag: u32) -> Option<Vec<u8>> {
+        let bytes = self.bytes()?;
+        let sfnt = self.sfnt.get_or_init(|| match crate::FcSfntData(bytes) {
+            alloc::borrow::Cow::Owned(sfnt) => Some(sfnt),
+            alloc::borrow::Cow::Borrowed(_) => None,
+        });
+        crate::FcSfntTableData(sfnt.as_deref().unwrap_or(bytes), self.path.font_index, tag)
+    }
+}
+
+// Data of the font files that are used by handles, only kept while a handle uses it
+#[derive(Default)]
+pub(crate) struct FcLoadedFonts(Mutex<BTreeMap<Arc<str>, Weak<FcFontBytes>>>);
+
+impl FcLoadedFonts {
+    fn load(&self, source: &FcFontSource) -> Option<Arc<FcFontBytes>> {
+        let file = match source {
+            FcFontSource::Disk(file) => file,
+            FcFontSource::Memory(_) => return FcFontBytes::load(source).map(Arc::new),
+        };
+
+        let mut loaded = self.0.lock().ok()?;
+        if let Some(bytes) = loaded.get(file).and_then(Weak::upgrade) {
+            return Some(bytes);
+        }
+
+        let bytes = Arc::new(FcFontBytes::open(Path::new(&**file))?);
+        loaded.retain(|_, bytes| bytes.strong_count() > 0);
+        loaded.insert(file.clone(), Arc::downgrade(&bytes));
+        Some(bytes)
+    }
+}
+
+// The loaded files don't take part in comparisons
+impl core::fmt::Debug for FcLoadedFonts {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FcLoadedFonts").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for FcLoadedFonts {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for FcLoadedFonts {}
+
+impl PartialOrd for FcLoadedFonts {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FcLoadedFonts {
+    fn cmp(&self, _: &Self) -> core::cmp::Ordering {
+        core::cmp::Ordering::Equal
+    }
+}
+
+impl FcFontCache {
+    /// Same as `query`, but returns a handle that loads the font data on first use
+    pub fn query_handle(&self, pattern: &FcPattern) -> Option<FcFontHandle> {
+        self.query(pattern).map(|path| self.handle(path))
+    }
+
+    /// Returns a handle for a font of the cache (i.e. of `query_all`), see `FcFontHandle`
+    pub fn handle(&self, path: &FcFontPath) -> FcFontHandle {
+        FcFontHandle {
+            path: path.clone(),
+            loaded: self.loaded.clone(),
+            bytes: Arc::default(),
+            #[cfg(feature = "scan")]
+            sfnt: Arc::default(),
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Splitting text into runs of the same font, see `FcFontCache::itemize`
+
+use alloc::borrow::Cow;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::Range;
+use std::collections::HashSet;
+
+use crate::{FcCharset, FcCjkLocale, FcFontCache, FcFontPath, FcPattern, FcScript, FcWeight};
+
+impl FcFontCache {
+    /// Splits `text` into runs that can each be rendered with a single font, returns the
+    /// byte range of every run and its font
+    ///
+    /// Every character gets the first font that supports it: the fonts of the family / name
+    /// of the pattern, then the fallback fonts for `lang` (or for the language of the script
+    /// of the character if `lang` is `None`) in platform order (see `query_sorted`), then
+    /// all remaining fonts, the ones closest to the style of the pattern first. Fonts that are
+    /// designed for the script of the character (see `supports_script`) are preferred over
+    /// fonts that merely support it, fonts without the glyph substitutions for a complex
+    /// script (see `FcFontMetadata::can_shape`) are only used if no other font supports the
+    /// character. For Han, kana, Hangul and Bopomofo characters, fonts that draw them with
+    /// the regional conventions of `lang` (see `FcCjkInfo::supports`) come first, so that
+    /// Japanese text doesn't get the glyphs of a Chinese font. Spaces and ASCII punctuation
+    /// stay in the current run if its font supports them, characters that extend the
+    /// previous character (combining marks, variation selectors, joiners) always do, so that
+    /// runs aren't split needlessly.
+    /// Characters that no font supports are added to the current run (the first font of the
+    /// order at the start of the text).
+    ///
+    /// The characters of the fonts come from `charset`. Returns an empty list for an empty
+    /// text or a cache without fonts.
+    pub fn itemize(
+        &self,
+        text: &str,
+        pattern: &FcPattern,
+        lang: Option<&str>,
+    ) -> Vec<(Range<usize>, FcFontPath)> {
+        let mut itemizer = FcItemizer {
+            cache: self,
+            pattern,
+            lang,
+            locale: lang.and_then(FcCjkLocale::from_lang),
+            orders: BTreeMap::new(),
+            patterns: BTreeMap::new(),
+            coverages: BTreeMap::new(),
+            fonts: BTreeMap::new(),
+        };
+
+        let mut runs: Vec<(Range<usize>, &FcFontPath)> = Vec::new();
+        for (i, c) in text.char_indices() {
+            let current = runs.last().map(|(_, font)| *font);
+            let keeps_run = match current {
+                Some(_) if FcExtendsCluster(c) => true,
+                Some(font) if c.is_whitespace() || c.is_ascii_punctuation() => {
+                    itemizer.covers(font, c)
+                }
+                _ => false,
+            };
+
+            let font = if keeps_run { current } else { itemizer.font(c) };
+            let font = match font.or(current) {
+                Some(font) => font,
+                None => match itemizer.order(FcScript::of(c)).first() {
+                    Some(font) => *font,
+                    None => return Vec::new(),
+                },
+            };
+
+            let end = i + c.len_utf8();
+            match runs.last_mut() {
+                Some((range, current)) if *current == font => range.end = end,
+                _ => runs.push((i..end, font)),
+            }
+        }
+
+        runs.into_iter()
+            .map(|(range, font)| (range, font.clone()))
+            .collect()
+    }
+
+    /// Returns the font for a single character that is missing from the font in use, the
+    /// font `itemize` would pick for it
+    ///
+    /// The fonts of the family / name of `pattern` come first, then the platform fallback
+    /// fonts for the language of the script of the character, then all other fonts, the ones
+    /// closest to the style of `pattern` (regular if `None`) first. Among these, the first
+    /// font that supports the character wins, fonts designed
+    /// for its script and fonts that can shape it are preferred (see `itemize`). Returns
+    /// `None` if no font supports the character.
+    pub fn query_fallback_for_codepoint(
+        &self,
+        c: char,
+        pattern: Option<&FcPattern>,
+    ) -> Option<FcFontPath> {
+        let default = FcPattern::default();
+        let pattern = pattern.unwrap_or(&default);
+        let mut itemizer = FcItemizer {
+            cache: self,
+            pattern,
+            lang: None,
+            locale: None,
+            orders: BTreeMap::new(),
+            coverages: BTreeMap::new(),
+            fonts: BTreeMap::new(),
+            patterns: BTreeMap::new(),
+        };
+        itemizer.font(c).cloned()
+    }
+}
+
+// State of a single `itemize` call
+struct FcItemizer<'a> {
+    cache: &'a FcFontCache,
+    pattern: &'a FcPattern,
+    lang: Option<&'a str>,
+    // regional conventions for the CJK characters, from `lang`
+    locale: Option<FcCjkLocale>,
+    // fonts in order of preference, per language of the fallback fonts
+    orders: BTreeMap<Option<&'a str>, Vec<&'a FcFontPath>>,
+    coverages: BTreeMap<&'a FcFontPath, Cow<'a, FcCharset>>,
+    // patterns of the fonts, filled on the first CJK or complex character
+    patterns: BTreeMap<&'a FcFontPath, &'a FcPattern>,
+    // font picked for a character
+    fonts: BTreeMap<char, Option<&'a FcFontPath>>,
+}
+
+impl<'a> FcItemizer<'a> {
+    fn covers(&mut self, font: &'a FcFontPath, c: char) -> bool {
+        let cache = self.cache;
+        // the coverage filters reject most fonts without the character cheaply
+        if !cache.map.might_contain(font, c) {
+            return false;
+        }
+        self.coverages
+            .entry(font)
+            .or_insert_with(|| cache.charset(font))
+            .contains(c)
+    }
+
+    // Fonts to pick from for characters of a script, in order of preference
+    fn order(&mut self, script: FcScript) -> &[&'a FcFontPath] {
+        let (cache, pattern) = (self.cache, self.pattern);
+        let lang = self.lang.or_else(|| script.lang());
+        self.orders.entry(lang).or_insert_with(|| {
+            // without a family every font matches the pattern, the platform order decides
+            let requested = match pattern.family.is_some() || pattern.name.is_some() {
+                true => cache.query_all(pattern),
+                false => Vec::new(),
+            };
+
+            let mut seen = HashSet::new();
+            let mut order = requested
+                .into_iter()
+                .chain(cache.fallback_order(pattern, lang))
+                .filter(|path| seen.insert(*path))
+                .collect::<Vec<_>>();
+
+            // stable, fonts at the same distance stay in pattern order
+            let mut rest = cache
+                .list()
+                .iter()
+                .filter(|(_, path)| !seen.contains(path))
+                .collect::<Vec<_>>();
+            rest.sort_by_key(|(font, _)| FcStyleDistance(pattern, font));
+            order.extend(rest.into_iter().map(|(_, path)| path));
+            order
+        })
+    }
+
+    fn pattern(&mut self, font: &'a FcFontPath) -> Option<&'a FcPattern> {
+        if self.patterns.is_empty() {
+            let list = self.cache.list();
+            self.patterns = list.iter().map(|(pattern, path)| (path, pattern)).collect();
+        }
+        self.patterns.get(font).copied()
+    }
+
+    // Whether the font draws CJK characters with the conventions of `locale`
+    fn supports_locale(&mut self, font: &'a FcFontPath, locale: FcCjkLocale) -> bool {
+        self.pattern(font)
+            .is_some_and(|pattern| pattern.metadata.cjk.supports(locale))
+    }
+
+    fn can_shape(&mut self, font: &'a FcFontPath, script: FcScript) -> bool {
+        !script.is_complex()
+            || self
+                .pattern(font)
+                .is_some_and(|pattern| pattern.metadata.can_shape(script))
+    }
+
+    // First font that supports the character, preferring fonts that can shape its script,
+    // then fonts designed for its script, then for CJK characters fonts that draw them with
+    // the conventions of `locale`
+    fn font(&mut self, c: char) -> Option<&'a FcFontPath> {
+        if let Some(font) = self.fonts.get(&c) {
+            return *font;
+        }
+
+        let script = FcScript::of(c);
+        let locale = match script {
+            FcScript::Han
+            | FcScript::Hiragana
+            | FcScript::Katakana
+            | FcScript::Hangul
+            | FcScript::Bopomofo => self.locale,
+            _ => None,
+        };
+        let order = self.order(script).to_vec();
+        // (font, can shape the script, designed for the script, conventions of `locale`)
+        let mut best: Option<(&'a FcFontPath, bool, bool, bool)> = None;
+        for font in order {
+            if !self.covers(font, c) {
+                continue;
+            }
+            let shapes = self.can_shape(font, script);
+            let designed = script == FcScript::Common || self.cache.supports_script(font, script);
+            let localized = match locale {
+                Some(locale) => designed && self.supports_locale(font, locale),
+                None => true,
+            };
+            let rank = (shapes, designed, localized);
+            if best.is_none_or(|(_, s, d, l)| rank > (s, d, l)) {
+                best = Some((font, shapes, designed, localized));
+            }
+            if rank == (true, true, true) {
+                break;
+            }
+        }
+        let first = best.map(|(font, ..)| font);
+
+        self.fonts.insert(c, first);
+        first
+    }
+}
+
+// How far the style of a font is from the style of the pattern, the slant counts more
+// than the weight (same as `query_owned`), patterns without a style ask for regular
+fn FcStyleDistance(pattern: &FcPattern, font: &FcPattern) -> u32 {
+    let slanted = |p: &FcPattern| p.italic.is_true() || p.oblique.is_true();
+    let weight = |p: &FcPattern| match p.weight {
+        FcWeight(0) if p.bold.is_true() => FcWeight::BOLD.0,
+        FcWeight(0) => FcWeight::REGULAR.0,
+        weight => weight.0,
+    };
+
+    let slant_distance = u32::from(slanted(pattern) != slanted(font)) * 1000;
+    slant_distance + u32::from(weight(pattern).abs_diff(weight(font)))
+}
+
+// Characters that belong to the cluster of the previous character
+fn FcExtendsCluster(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        // combining diacritical marks (and extended / supplement / for symbols / half marks)
+        0x0300..=0x036F
+            | 0x1AB0..=0x1AFF
+            | 0x1DC0..=0x1DFF
+            | 0x20D0..=0x20FF
+            | 0xFE20..=0xFE2F
+            // zero width non-joiner / joiner
+            | 0x200C..=0x200D
+            // variation selectors
+            | 0xFE00..=0xFE0F
+            | 0xE0100..=0xE01EF
+            // emoji skin tone modifiers and tags
+            | 0x1F3FB..=0x1F3FF
+            | 0xE0020..=0xE007F
+    )
+}
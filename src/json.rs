@@ -0,0 +1,1028 @@
+//! JSON export / import of the font cache, see `FcFontCache::to_json` / `FcFontCache::from_json`
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::Write;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+use crate::persist::FcIntern;
+use crate::store::FcFontStore;
+use crate::{
+    FcCharset, FcCjkInfo, FcCjkLocale, FcFallbackFamily, FcFeatureInfo, FcFigureStyle, FcFontCache,
+    FcFontContainer, FcFontFormat, FcFontMetadata, FcFontPath, FcFontSource, FcGaspRange,
+    FcHintingInfo, FcKerningInfo, FcPattern, FcScript, FcSkipReason, FcSkippedFile, FcWeight,
+    PatternMatch,
+};
+
+// NOTE: only increment for changes that old readers can't ignore
+const SCHEMA_VERSION: u64 = 1;
+
+// Nesting limit of the parser, the schema itself is only 5 levels deep
+const MAX_DEPTH: usize = 32;
+
+impl FcFontCache {
+    /// Serializes the font cache into JSON
+    ///
+    /// Unlike `to_bytes`, the schema is stable across crate versions, so that other tools
+    /// (and non-Rust processes) can read the fonts that were found. Schema version 1:
+    ///
+    /// ```text
+    /// {
+    ///   "version": 1,
+    ///   "fonts": [
+    ///     {
+    ///       "path": "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    ///                                          in-memory fonts have "data" (base64) instead
+    ///       "font_index": 0,
+    ///       "name": "DejaVu Sans Bold",        string or null
+    ///       "family": "DejaVu Sans",           string or null
+    ///       "italic": false,                   true / false / null (= don't care)
+    ///       "oblique": false,
+    ///       "bold": true,
+    ///       "monospace": false,
+    ///       "condensed": false,
+    ///       "scalable": true,
+    ///       "hinted": true,
+    ///       "small_caps": false,
+    ///       "tabular_figures": true,
+    ///       "weight": 700,
+    ///       "unicode_range": [0, 0],
+    ///       "vendor_ids": null,                array of strings or null
+    ///       "formats": null,                   array of `format` strings or null
+    ///       "vendor_id": "PfEd",               string or null
+    ///       "hinting": { "fpgm": true, "prep": true, "cvt": true, "gasp": [[max_ppem, behavior], ...] },
+    ///       "cjk": { "default": "ja", "locl": ["zh-CN", "ko"] },  default: language tag or null
+    ///       "shaping": ["Arab", "Deva"],       ISO 15924 codes of the scripts
+    ///       "charset": [[32, 126], [160, 383], ...]   ranges of code points (inclusive)
+    ///       "italic_angle": -786432            `italicAngle` of the post table, 16.16 fixed point
+    ///       "container": "sfnt"                "sfnt" | "woff" | "woff2" | "type1" | "pcf"
+    ///       "format": "truetype"               "truetype" | "cff" | "collection" | "woff" | "woff2" | "type1" | "bitmap"
+    ///       "strikes": [13, 16]                pixel sizes of the bitmap strikes
+    ///       "kerning": { "kern": true, "gpos": true }   `kern` table pairs, `GPOS` kern feature
+    ///       "features": { "smcp": false, "c2sc": false, "tnum": false, "mono_digits": true,
+    ///                     "onum": false, "lnum": false, "figures": "lining" }  figures: "lining" | "old_style" | null
+    ///       "style": "Bold"                    subfamily name, string or null
+    ///     }, ...
+    ///   ],
+    ///   "fallback": [{ "langs": ["ja"], "fonts": [{ "path": ..., "font_index": 0 }, ...] }, ...],
+    ///   "substitutes": { "family": "substitute family", ... },
+    ///   "font_links": { "family": [{ "path": ..., "font_index": 0 }, ...], ... },
+    ///   "aliases": { "family": ["preferred family", ...], ... },
+    ///   "scanned_dirs": { "path": [mtime secs, mtime nanos], ... },
+    ///   "scanned_files": { "path": [mtime secs, mtime nanos], ... },
+    ///   "pending_files": ["path", ...],
+    ///   "skipped_files": [{ "path": ..., "reason": "too_large" | "too_many_faces" | "parser_panic" }, ...],
+    ///   "failed_files": { "path": [mtime secs, mtime nanos, size], ... },
+    ///   "alternate_paths": { "path": ["other path", ...], ... }
+    /// }
+    /// ```
+    ///
+    /// Family keys are lowercase. Numbers are integers. When reading, missing
+    /// keys get their default value (the defaults of `FcPattern` for the fonts) and unknown
+    /// keys are ignored, so that files written by other tools don't need every field.
+    pub fn to_json(&self) -> String {
+        let mut w = JsonWriter::default();
+        w.0.push('{');
+        w.key("version");
+        w.u64(SCHEMA_VERSION);
+
+        w.next_key("fonts");
+        w.array(self.map.iter(), |w, (pattern, path)| {
+            w.0.push('{');
+            w.path_fields(path);
+            w.pattern(pattern);
+            w.0.push('}');
+        });
+
+        w.next_key("fallback");
+        w.array(self.fallback.iter(), |w, family| {
+            w.0.push('{');
+            w.key("langs");
+            w.strs(&family.langs);
+            w.next_key("fonts");
+            w.paths(&family.fonts);
+            w.0.push('}');
+        });
+
+        w.next_key("substitutes");
+        w.object(self.substitutes.iter(), |w, substitute| w.str(substitute));
+        w.next_key("font_links");
+        w.object(self.font_links.iter(), |w, fonts| w.paths(fonts));
+        w.next_key("aliases");
+        w.object(self.aliases.iter(), |w, preferred| w.strs(preferred));
+
+        w.next_key("scanned_dirs");
+        w.object(self.scanned_dirs.iter(), |w, (secs, nanos)| {
+            w.array([*secs, u64::from(*nanos)].iter(), |w, n| w.u64(*n));
+        });
+        w.next_key("scanned_files");
+        w.object(self.scanned_files.iter(), |w, (secs, nanos)| {
+            w.array([*secs, u64::from(*nanos)].iter(), |w, n| w.u64(*n));
+        });
+        w.next_key("pending_files");
+        w.strs(&self.pending_files);
+        w.next_key("skipped_files");
+        w.array(self.skipped_files.iter(), |w, skipped_file| {
+            w.0.push('{');
+            w.key("path");
+            w.str(&skipped_file.path);
+            w.next_key("reason");
+            w.str(match skipped_file.reason {
+                FcSkipReason::TooLarge => "too_large",
+                FcSkipReason::TooManyFaces => "too_many_faces",
+                FcSkipReason::ParserPanic => "parser_panic",
+            });
+            w.0.push('}');
+        });
+        w.next_key("failed_files");
+        w.object(self.failed_files.iter(), |w, (secs, nanos, size)| {
+            w.array([*secs, u64::from(*nanos), *size].iter(), |w, n| w.u64(*n));
+        });
+        w.next_key("alternate_paths");
+        w.object(self.alternate_paths.iter(), |w, paths| w.strs(paths));
+
+        w.0.push('}');
+        w.0
+    }
+
+    /// Deserializes a font cache from JSON, returns `None` if the JSON is malformed,
+    /// doesn't follow the schema or has a newer schema version
+    pub fn from_json(json: &str) -> Option<Self> {
+        let mut parser = JsonParser {
+            input: json.as_bytes(),
+            pos: 0,
+        };
+        let root = parser.value(0)?;
+        parser.whitespace();
+        if parser.pos != parser.input.len() {
+            return None;
+        }
+
+        let version = root.get("version")?.as_u64()?;
+        if version == 0 || version > SCHEMA_VERSION {
+            return None;
+        }
+
+        let mut sources = JsonSources::default();
+
+        // patterns can be stored more than once, see `FcMergePolicy::KeepBoth`
+        let mut fonts = Vec::new();
+        for font in array_or_empty(root.get("fonts"))? {
+            let path = font_path(font, &mut sources)?;
+            fonts.push((pattern(font)?, path));
+        }
+
+        let mut fallback = Vec::new();
+        for family in array_or_empty(root.get("fallback"))? {
+            fallback.push(FcFallbackFamily {
+                langs: strs(family.get("langs"))?,
+                fonts: font_paths(family.get("fonts"), &mut sources)?,
+            });
+        }
+
+        let mut substitutes = BTreeMap::new();
+        for (family, substitute) in object_or_empty(root.get("substitutes"))? {
+            substitutes.insert(family.clone(), String::from(substitute.as_str()?));
+        }
+
+        let mut font_links = BTreeMap::new();
+        for (family, fonts) in object_or_empty(root.get("font_links"))? {
+            font_links.insert(family.clone(), font_paths(Some(fonts), &mut sources)?);
+        }
+
+        let mut aliases = BTreeMap::new();
+        for (family, preferred) in object_or_empty(root.get("aliases"))? {
+            aliases.insert(family.clone(), strs(Some(preferred))?);
+        }
+
+        let scanned_dirs = mtimes(root.get("scanned_dirs"))?;
+        let scanned_files = mtimes(root.get("scanned_files"))?;
+        let pending_files = strs(root.get("pending_files"))?;
+
+        let mut skipped_files = Vec::new();
+        for skipped_file in array_or_empty(root.get("skipped_files"))? {
+            let reason = match skipped_file.get("reason")?.as_str()? {
+                "too_large" => FcSkipReason::TooLarge,
+                "too_many_faces" => FcSkipReason::TooManyFaces,
+                "parser_panic" => FcSkipReason::ParserPanic,
+                _ => return None,
+            };
+            skipped_files.push(FcSkippedFile {
+                path: String::from(skipped_file.get("path")?.as_str()?),
+                reason,
+            });
+        }
+
+        let mut failed_files = BTreeMap::new();
+        for (file, stamp) in object_or_empty(root.get("failed_files"))? {
+            match stamp.as_array()? {
+                [secs, nanos, size] => failed_files.insert(
+                    file.clone(),
+                    (
+                        secs.as_u64()?,
+                        u32::try_from(nanos.as_u64()?).ok()?,
+                        size.as_u64()?,
+                    ),
+                ),
+                _ => return None,
+            };
+        }
+
+        let mut alternate_paths = BTreeMap::new();
+        for (path, alternates) in object_or_empty(root.get("alternate_paths"))? {
+            alternate_paths.insert(path.clone(), strs(Some(alternates))?);
+        }
+
+        Some(FcFontCache {
+            map: FcFontStore::from_entries(fonts),
+            fallback,
+            substitutes,
+            font_links,
+            aliases,
+            scanned_dirs,
+            scanned_files,
+            pending_files,
+            skipped_files,
+            failed_files,
+            alternate_paths,
+            #[cfg(feature = "std")]
+            loaded: Default::default(),
+            #[cfg(all(feature = "std", feature = "scan"))]
+            coverage: Default::default(),
+            #[cfg(all(feature = "std", feature = "scan"))]
+            scan_options: Default::default(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct JsonWriter(String);
+
+impl JsonWriter {
+    fn key(&mut self, key: &str) {
+        self.str(key);
+        self.0.push(':');
+    }
+
+    fn next_key(&mut self, key: &str) {
+        self.0.push(',');
+        self.key(key);
+    }
+
+    fn u64(&mut self, value: u64) {
+        let _ = write!(self.0, "{}", value);
+    }
+
+    fn i64(&mut self, value: i64) {
+        let _ = write!(self.0, "{}", value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.0.push_str(if value { "true" } else { "false" });
+    }
+
+    fn null(&mut self) {
+        self.0.push_str("null");
+    }
+
+    fn str(&mut self, s: &str) {
+        self.0.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.0.push_str("\\\""),
+                '\\' => self.0.push_str("\\\\"),
+                '\n' => self.0.push_str("\\n"),
+                '\r' => self.0.push_str("\\r"),
+                '\t' => self.0.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    let _ = write!(self.0, "\\u{:04x}", c as u32);
+                }
+                c => self.0.push(c),
+            }
+        }
+        self.0.push('"');
+    }
+
+    fn opt_str(&mut self, s: &Option<String>) {
+        match s {
+            Some(s) => self.str(s),
+            None => self.null(),
+        }
+    }
+
+    fn strs(&mut self, strs: &[String]) {
+        self.array(strs.iter(), |w, s| w.str(s));
+    }
+
+    fn array<T, I: Iterator<Item = T>, F: FnMut(&mut Self, T)>(&mut self, items: I, mut f: F) {
+        self.0.push('[');
+        for (i, item) in items.enumerate() {
+            if i > 0 {
+                self.0.push(',');
+            }
+            f(self, item);
+        }
+        self.0.push(']');
+    }
+
+    fn object<'a, T: 'a, I: Iterator<Item = (&'a String, &'a T)>, F: FnMut(&mut Self, &'a T)>(
+        &mut self,
+        entries: I,
+        mut f: F,
+    ) {
+        self.0.push('{');
+        for (i, (key, value)) in entries.enumerate() {
+            if i > 0 {
+                self.0.push(',');
+            }
+            self.key(key);
+            f(self, value);
+        }
+        self.0.push('}');
+    }
+
+    fn pattern_match(&mut self, value: &PatternMatch) {
+        match value {
+            PatternMatch::True => self.bool(true),
+            PatternMatch::False => self.bool(false),
+            PatternMatch::DontCare => self.null(),
+        }
+    }
+
+    // `path` (or `data`) and `font_index` keys of a font
+    fn path_fields(&mut self, path: &FcFontPath) {
+        match &path.source {
+            FcFontSource::Disk(file) => {
+                self.key("path");
+                self.str(file);
+            }
+            FcFontSource::Memory(data) => {
+                self.key("data");
+                self.str(&STANDARD.encode(data));
+            }
+        }
+        self.next_key("font_index");
+        self.u64(path.font_index as u64);
+    }
+
+    fn path(&mut self, path: &FcFontPath) {
+        self.0.push('{');
+        self.path_fields(path);
+        self.0.push('}');
+    }
+
+    fn paths(&mut self, paths: &[FcFontPath]) {
+        self.array(paths.iter(), |w, path| w.path(path));
+    }
+
+    // fields of a pattern, appended to the object of its font
+    fn pattern(&mut self, pattern: &FcPattern) {
+        self.next_key("name");
+        self.opt_str(&pattern.name);
+        self.next_key("family");
+        self.opt_str(&pattern.family);
+        let properties = [
+            ("italic", &pattern.italic),
+            ("oblique", &pattern.oblique),
+            ("bold", &pattern.bold),
+            ("monospace", &pattern.monospace),
+            ("condensed", &pattern.condensed),
+            ("scalable", &pattern.scalable),
+            ("hinted", &pattern.hinted),
+            ("small_caps", &pattern.small_caps),
+            ("tabular_figures", &pattern.tabular_figures),
+        ];
+        for (key, value) in properties.iter() {
+            self.next_key(key);
+            self.pattern_match(value);
+        }
+        self.next_key("weight");
+        self.u64(u64::from(pattern.weight.0));
+        self.next_key("unicode_range");
+        self.array(pattern.unicode_range.iter(), |w, n| w.u64(*n as u64));
+        self.next_key("vendor_ids");
+        match &pattern.vendor_ids {
+            Some(vendor_ids) => self.strs(vendor_ids),
+            None => self.null(),
+        }
+        self.next_key("formats");
+        match &pattern.formats {
+            Some(formats) => self.array(formats.iter(), |w, format| w.str(format.as_str())),
+            None => self.null(),
+        }
+        self.next_key("vendor_id");
+        self.opt_str(&pattern.metadata.vendor_id);
+
+        let hinting = &pattern.metadata.hinting;
+        self.next_key("hinting");
+        self.0.push('{');
+        self.key("fpgm");
+        self.bool(hinting.has_fpgm);
+        self.next_key("prep");
+        self.bool(hinting.has_prep);
+        self.next_key("cvt");
+        self.bool(hinting.has_cvt);
+        self.next_key("gasp");
+        self.array(hinting.gasp_ranges.iter(), |w, range| {
+            w.array([range.max_ppem, range.behavior].iter(), |w, n| {
+                w.u64(u64::from(*n))
+            });
+        });
+        self.0.push('}');
+
+        let cjk = &pattern.metadata.cjk;
+        self.next_key("cjk");
+        self.0.push('{');
+        self.key("default");
+        match cjk.default_locale {
+            Some(locale) => self.str(locale.lang()),
+            None => self.null(),
+        }
+        self.next_key("locl");
+        self.array(cjk.locl.iter(), |w, locale| w.str(locale.lang()));
+        self.0.push('}');
+
+        self.next_key("shaping");
+        self.array(pattern.metadata.shaping.iter(), |w, script| {
+            w.str(script.code())
+        });
+
+        self.next_key("charset");
+        self.array(pattern.metadata.charset.ranges(), |w, range| {
+            w.array([*range.start(), *range.end()].iter(), |w, c| {
+                w.u64(u64::from(u32::from(*c)))
+            });
+        });
+
+        self.next_key("italic_angle");
+        self.i64(i64::from(pattern.metadata.italic_angle));
+
+        self.next_key("container");
+        self.str(match pattern.metadata.container {
+            FcFontContainer::Sfnt => "sfnt",
+            FcFontContainer::Woff => "woff",
+            FcFontContainer::Woff2 => "woff2",
+            FcFontContainer::Type1 => "type1",
+            FcFontContainer::Pcf => "pcf",
+        });
+
+        self.next_key("format");
+        self.str(pattern.metadata.format.as_str());
+
+        self.next_key("strikes");
+        self.array(pattern.metadata.strikes.iter(), |w, strike| {
+            w.u64(u64::from(*strike))
+        });
+
+        let kerning = &pattern.metadata.kerning;
+        self.next_key("kerning");
+        self.0.push('{');
+        self.key("kern");
+        self.bool(kerning.has_kern_table);
+        self.next_key("gpos");
+        self.bool(kerning.has_gpos_kerning);
+        self.0.push('}');
+
+        let features = &pattern.metadata.features;
+        self.next_key("features");
+        self.0.push('{');
+        self.key("smcp");
+        self.bool(features.small_caps);
+        self.next_key("c2sc");
+        self.bool(features.caps_to_small_caps);
+        self.next_key("tnum");
+        self.bool(features.tabular_figures);
+        self.next_key("mono_digits");
+        self.bool(features.monospaced_digits);
+        self.next_key("onum");
+        self.bool(features.old_style_figures);
+        self.next_key("lnum");
+        self.bool(features.lining_figures);
+        self.next_key("figures");
+        match features.default_figures {
+            Some(style) => self.str(style.as_str()),
+            None => self.null(),
+        }
+        self.0.push('}');
+
+        self.next_key("style");
+        self.opt_str(&pattern.metadata.style);
+    }
+}
+
+// Parsed JSON value, numbers are limited to integers
+enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(u64),
+    // absolute value of a negative integer
+    NegativeNumber(u64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    // value of a key, `None` if the key is missing or this isn't an object
+    fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            // same as most parsers, the last duplicate key wins
+            JsonValue::Object(entries) => {
+                entries.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonValue::Number(n) => i64::try_from(*n).ok(),
+            JsonValue::NegativeNumber(n) => 0i64.checked_sub_unsigned(*n),
+            _ => None,
+        }
+    }
+
+    fn as_usize(&self) -> Option<usize> {
+        usize::try_from(self.as_u64()?).ok()
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+// Missing keys and `null` are empty arrays / objects
+fn array_or_empty(value: Option<&JsonValue>) -> Option<&[JsonValue]> {
+    match value {
+        None | Some(JsonValue::Null) => Some(&[]),
+        Some(value) => value.as_array(),
+    }
+}
+
+fn object_or_empty(value: Option<&JsonValue>) -> Option<&[(String, JsonValue)]> {
+    match value {
+        None | Some(JsonValue::Null) => Some(&[]),
+        Some(value) => value.as_object(),
+    }
+}
+
+fn strs(value: Option<&JsonValue>) -> Option<Vec<String>> {
+    array_or_empty(value)?
+        .iter()
+        .map(|s| s.as_str().map(String::from))
+        .collect()
+}
+
+fn opt_str(value: Option<&JsonValue>) -> Option<Option<String>> {
+    match value {
+        None | Some(JsonValue::Null) => Some(None),
+        Some(value) => Some(Some(String::from(value.as_str()?))),
+    }
+}
+
+fn pattern_match(value: Option<&JsonValue>, default: PatternMatch) -> Option<PatternMatch> {
+    match value {
+        None => Some(default),
+        Some(JsonValue::Null) => Some(PatternMatch::DontCare),
+        Some(JsonValue::Bool(true)) => Some(PatternMatch::True),
+        Some(JsonValue::Bool(false)) => Some(PatternMatch::False),
+        Some(_) => None,
+    }
+}
+
+fn mtimes(value: Option<&JsonValue>) -> Option<BTreeMap<String, (u64, u32)>> {
+    let mut mtimes = BTreeMap::new();
+    for (path, mtime) in object_or_empty(value)? {
+        match mtime.as_array()? {
+            [secs, nanos] => mtimes.insert(
+                path.clone(),
+                (secs.as_u64()?, u32::try_from(nanos.as_u64()?).ok()?),
+            ),
+            _ => return None,
+        };
+    }
+    Some(mtimes)
+}
+
+// Font paths and in-memory fonts read so far, so that the faces and styles of a file
+// share their path / data
+#[derive(Default)]
+struct JsonSources {
+    paths: BTreeSet<Arc<str>>,
+    memory_fonts: BTreeSet<Arc<[u8]>>,
+}
+
+// `path` (or `data`) and `font_index` of an object
+fn font_path(value: &JsonValue, sources: &mut JsonSources) -> Option<FcFontPath> {
+    let source = match value.get("data") {
+        Some(data) => {
+            let data = STANDARD.decode(data.as_str()?).ok()?;
+            FcFontSource::Memory(FcIntern(&mut sources.memory_fonts, &data[..]))
+        }
+        None => {
+            let path = value.get("path")?.as_str()?;
+            FcFontSource::Disk(FcIntern(&mut sources.paths, path))
+        }
+    };
+    let font_index = match value.get("font_index") {
+        Some(font_index) => font_index.as_usize()?,
+        None => 0,
+    };
+    Some(FcFontPath { source, font_index })
+}
+
+fn font_paths(value: Option<&JsonValue>, sources: &mut JsonSources) -> Option<Vec<FcFontPath>> {
+    array_or_empty(value)?
+        .iter()
+        .map(|path| font_path(path, sources))
+        .collect()
+}
+
+fn pattern(value: &JsonValue) -> Option<FcPattern> {
+    let default = FcPattern::default();
+
+    let unicode_range = match value.get("unicode_range") {
+        Some(range) => match range.as_array()? {
+            [start, end] => [start.as_usize()?, end.as_usize()?],
+            _ => return None,
+        },
+        None => default.unicode_range,
+    };
+    let vendor_ids = match value.get("vendor_ids") {
+        None | Some(JsonValue::Null) => None,
+        vendor_ids => Some(strs(vendor_ids)?),
+    };
+    let formats = match value.get("formats") {
+        None | Some(JsonValue::Null) => None,
+        formats => Some(
+            strs(formats)?
+                .iter()
+                .map(|format| format.parse().ok())
+                .collect::<Option<Vec<FcFontFormat>>>()?,
+        ),
+    };
+
+    let hinting = match value.get("hinting") {
+        Some(hinting) => {
+            let flag = |key| match hinting.get(key) {
+                Some(flag) => flag.as_bool(),
+                None => Some(false),
+            };
+            let mut gasp_ranges = Vec::new();
+            for range in array_or_empty(hinting.get("gasp"))? {
+                match range.as_array()? {
+                    [max_ppem, behavior] => gasp_ranges.push(FcGaspRange {
+                        max_ppem: u16::try_from(max_ppem.as_u64()?).ok()?,
+                        behavior: u16::try_from(behavior.as_u64()?).ok()?,
+                    }),
+                    _ => return None,
+                }
+            }
+            FcHintingInfo {
+                has_fpgm: flag("fpgm")?,
+                has_prep: flag("prep")?,
+                has_cvt: flag("cvt")?,
+                gasp_ranges,
+            }
+        }
+        None => FcHintingInfo::default(),
+    };
+
+    let kerning = match value.get("kerning") {
+        Some(kerning) => {
+            let flag = |key| match kerning.get(key) {
+                Some(flag) => flag.as_bool(),
+                None => Some(false),
+            };
+            FcKerningInfo {
+                has_kern_table: flag("kern")?,
+                has_gpos_kerning: flag("gpos")?,
+            }
+        }
+        None => FcKerningInfo::default(),
+    };
+
+    let features = match value.get("features") {
+        Some(features) => {
+            let flag = |key| match features.get(key) {
+                Some(flag) => flag.as_bool(),
+                None => Some(false),
+            };
+            FcFeatureInfo {
+                small_caps: flag("smcp")?,
+                caps_to_small_caps: flag("c2sc")?,
+                tabular_figures: flag("tnum")?,
+                monospaced_digits: flag("mono_digits")?,
+                old_style_figures: flag("onum")?,
+                lining_figures: flag("lnum")?,
+                default_figures: match opt_str(features.get("figures"))?.as_deref() {
+                    Some("lining") => Some(FcFigureStyle::Lining),
+                    Some("old_style") => Some(FcFigureStyle::OldStyle),
+                    Some(_) => return None,
+                    None => None,
+                },
+            }
+        }
+        None => FcFeatureInfo::default(),
+    };
+
+    let cjk = match value.get("cjk") {
+        Some(cjk) => FcCjkInfo {
+            default_locale: match opt_str(cjk.get("default"))? {
+                Some(lang) => Some(FcCjkLocale::from_lang(&lang)?),
+                None => None,
+            },
+            locl: strs(cjk.get("locl"))?
+                .iter()
+                .map(|lang| FcCjkLocale::from_lang(lang))
+                .collect::<Option<_>>()?,
+        },
+        None => FcCjkInfo::default(),
+    };
+    let shaping = strs(value.get("shaping"))?
+        .iter()
+        .map(|code| FcScript::from_code(code))
+        .collect::<Option<_>>()?;
+    let strikes = array_or_empty(value.get("strikes"))?
+        .iter()
+        .map(|strike| u16::try_from(strike.as_u64()?).ok())
+        .collect::<Option<_>>()?;
+    let mut charset = FcCharset::new();
+    for range in array_or_empty(value.get("charset"))? {
+        match range.as_array()? {
+            [start, end] => {
+                let start = u32::try_from(start.as_u64()?).ok()?;
+                let end = u32::try_from(end.as_u64()?).ok()?;
+                if start > end || end > 0x10FFFF {
+                    return None;
+                }
+                charset.insert_range(start, end);
+            }
+            _ => return None,
+        }
+    }
+
+    Some(FcPattern {
+        name: opt_str(value.get("name"))?,
+        family: opt_str(value.get("family"))?,
+        italic: pattern_match(value.get("italic"), default.italic)?,
+        oblique: pattern_match(value.get("oblique"), default.oblique)?,
+        bold: pattern_match(value.get("bold"), default.bold)?,
+        monospace: pattern_match(value.get("monospace"), default.monospace)?,
+        condensed: pattern_match(value.get("condensed"), default.condensed)?,
+        weight: match value.get("weight") {
+            Some(weight) => FcWeight(u16::try_from(weight.as_usize()?).ok()?),
+            None => default.weight,
+        },
+        unicode_range,
+        scalable: pattern_match(value.get("scalable"), default.scalable)?,
+        hinted: pattern_match(value.get("hinted"), default.hinted)?,
+        small_caps: pattern_match(value.get("small_caps"), default.small_caps)?,
+        tabular_figures: pattern_match(value.get("tabular_figures"), default.tabular_figures)?,
+        vendor_ids,
+        formats,
+        metadata: FcFontMetadata {
+            hinting,
+            vendor_id: opt_str(value.get("vendor_id"))?,
+            cjk,
+            shaping,
+            charset,
+            italic_angle: match value.get("italic_angle") {
+                Some(angle) => i32::try_from(angle.as_i64()?).ok()?,
+                None => 0,
+            },
+            container: match opt_str(value.get("container"))?.as_deref() {
+                None | Some("sfnt") => FcFontContainer::Sfnt,
+                Some("woff") => FcFontContainer::Woff,
+                Some("woff2") => FcFontContainer::Woff2,
+                Some("type1") => FcFontContainer::Type1,
+                Some("pcf") => FcFontContainer::Pcf,
+                Some(_) => return None,
+            },
+            format: match opt_str(value.get("format"))? {
+                Some(format) => format.parse().ok()?,
+                None => FcFontFormat::TrueType,
+            },
+            strikes,
+            kerning,
+            features,
+            style: opt_str(value.get("style"))?,
+        },
+    })
+}
+
+struct JsonParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.whitespace();
+        self.input.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, literal: &[u8]) -> Option<()> {
+        if self.input.get(self.pos..)?.starts_with(literal) {
+            self.pos += literal.len();
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Option<JsonValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        match self.peek()? {
+            b'n' => self.expect(b"null").map(|_| JsonValue::Null),
+            b't' => self.expect(b"true").map(|_| JsonValue::Bool(true)),
+            b'f' => self.expect(b"false").map(|_| JsonValue::Bool(false)),
+            b'"' => self.string().map(JsonValue::String),
+            b'0'..=b'9' => self.number().map(JsonValue::Number),
+            b'-' => {
+                self.pos += 1;
+                match self.input.get(self.pos) {
+                    Some(b'0'..=b'9') => self.number().map(JsonValue::NegativeNumber),
+                    _ => None,
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if self.peek()? == b']' {
+                    self.pos += 1;
+                    return Some(JsonValue::Array(values));
+                }
+                loop {
+                    values.push(self.value(depth + 1)?);
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        b']' => {
+                            self.pos += 1;
+                            return Some(JsonValue::Array(values));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            b'{' => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                if self.peek()? == b'}' {
+                    self.pos += 1;
+                    return Some(JsonValue::Object(entries));
+                }
+                loop {
+                    if self.peek()? != b'"' {
+                        return None;
+                    }
+                    let key = self.string()?;
+                    if self.peek()? != b':' {
+                        return None;
+                    }
+                    self.pos += 1;
+                    entries.push((key, self.value(depth + 1)?));
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        b'}' => {
+                            self.pos += 1;
+                            return Some(JsonValue::Object(entries));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // non-negative integers only (the sign is parsed by `value`), without leading zeros
+    fn number(&mut self) -> Option<u64> {
+        let start = self.pos;
+        let mut value: u64 = 0;
+        while let Some(digit @ b'0'..=b'9') = self.input.get(self.pos) {
+            value = value
+                .checked_mul(10)?
+                .checked_add(u64::from(digit - b'0'))?;
+            self.pos += 1;
+        }
+        let is_leading_zero = self.input[start] == b'0' && self.pos - start > 1;
+        match self.input.get(self.pos) {
+            Some(b'.' | b'e' | b'E') => None,
+            _ if is_leading_zero => None,
+            _ => Some(value),
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        // opening quote
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(&b) = self.input.get(self.pos) {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // the input is a `&str` and the run ends at an ASCII byte, so it is valid UTF-8
+            s.push_str(core::str::from_utf8(&self.input[start..self.pos]).ok()?);
+
+            match *self.input.get(self.pos)? {
+                b'"' => {
+                    self.pos += 1;
+                    return Some(s);
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    let escape = *self.input.get(self.pos)?;
+                    self.pos += 1;
+                    s.push(match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return None,
+                    });
+                }
+                // unescaped control character
+                _ => return None,
+            }
+        }
+    }
+
+    // `\uXXXX`, characters outside of the BMP are escaped as a UTF-16 surrogate pair
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high);
+        }
+        self.expect(b"\\u")?;
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return None;
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.input.get(self.pos..self.pos + 4)?;
+        if !digits.iter().all(u8::is_ascii_hexdigit) {
+            return None;
+        }
+        let digits = core::str::from_utf8(digits).ok()?;
+        let value = u32::from_str_radix(digits, 16).ok()?;
+        self.pos += 4;
+        Some(value)
+    }
+}
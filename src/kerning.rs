@@ -0,0 +1,130 @@
+//! Kerning tables of fonts, see `FcFontMetadata::kerning`
+
+#[cfg(all(feature = "std", feature = "scan"))]
+use crate::utils::{read_u16, read_u32};
+
+/// Where a font stores its kerning, see `FcFontMetadata::kerning`
+///
+/// Renderers without a shaper can only apply the pairs of a legacy `kern` table, fonts
+/// that only kern through `GPOS` render without kerning there.
+#[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[repr(C)]
+pub struct FcKerningInfo {
+    // `kern` table with a horizontal pair list (format 0 subtable), which can be read
+    // without a shaper
+    pub has_kern_table: bool,
+    // `kern` feature of the `GPOS` table with pair adjustment lookups
+    pub has_gpos_kerning: bool,
+}
+
+impl FcKerningInfo {
+    /// Returns whether the font has kerning pairs in either table
+    pub fn has_kerning(&self) -> bool {
+        self.has_kern_table || self.has_gpos_kerning
+    }
+}
+
+// Detects the kerning of a font from its kern and GPOS tables
+#[cfg(all(feature = "std", feature = "scan"))]
+pub(crate) fn FcParseKerningInfo(
+    kern_data: Option<&[u8]>,
+    gpos_data: Option<&[u8]>,
+) -> FcKerningInfo {
+    FcKerningInfo {
+        has_kern_table: kern_data.is_some_and(FcHasKernPairs),
+        has_gpos_kerning: gpos_data.is_some_and(FcHasGposKerning),
+    }
+}
+
+// Whether a `kern` table has a horizontal format 0 subtable with pairs, in the Microsoft
+// (version 0) or the Apple (version 1.0) layout, see
+// https://learn.microsoft.com/en-us/typography/opentype/spec/kern and
+// https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6kern.html
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcHasKernPairs(kern_data: &[u8]) -> bool {
+    const APPLE_VERSION: u32 = 0x0001_0000;
+
+    let is_apple = read_u32(kern_data, 0) == Some(APPLE_VERSION);
+    let (table_count, mut subtable) = match is_apple {
+        true => (read_u32(kern_data, 4), 8),
+        false => (read_u16(kern_data, 2).map(u32::from), 4),
+    };
+
+    for _ in 0..table_count.unwrap_or(0) {
+        let (length, coverage, header_len) = match is_apple {
+            true => (
+                read_u32(kern_data, subtable).map(|length| length as usize),
+                read_u16(kern_data, subtable + 4),
+                8,
+            ),
+            false => (
+                read_u16(kern_data, subtable + 2).map(usize::from),
+                read_u16(kern_data, subtable + 4),
+                6,
+            ),
+        };
+        let (length, coverage) = match (length, coverage) {
+            (Some(length), Some(coverage)) if length >= header_len => (length, coverage),
+            _ => return false,
+        };
+        // horizontal, no cross-stream / minimum / variation values, format 0
+        let is_pair_list = match is_apple {
+            true => coverage & 0xE000 == 0 && coverage & 0x00FF == 0,
+            false => coverage & 0x0007 == 0x0001 && coverage >> 8 == 0,
+        };
+        let pair_count = read_u16(kern_data, subtable + header_len).unwrap_or(0);
+        if is_pair_list && pair_count > 0 {
+            return true;
+        }
+        subtable += length;
+    }
+    false
+}
+
+// Whether the `kern` feature of a `GPOS` table has a pair adjustment lookup (type 2, or an
+// extension lookup of type 9 wrapping one), see
+// https://learn.microsoft.com/en-us/typography/opentype/spec/gpos
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcHasGposKerning(gpos_data: &[u8]) -> bool {
+    const LOOKUP_PAIR: u16 = 2;
+    const LOOKUP_EXTENSION: u16 = 9;
+
+    let (feature_list, lookup_list) = match (read_u16(gpos_data, 6), read_u16(gpos_data, 8)) {
+        (Some(features), Some(lookups)) => (usize::from(features), usize::from(lookups)),
+        _ => return false,
+    };
+
+    let is_pair_lookup = |lookup_index: u16| {
+        let lookup = match read_u16(gpos_data, lookup_list + 2 + usize::from(lookup_index) * 2) {
+            Some(offset) => lookup_list + usize::from(offset),
+            None => return false,
+        };
+        match read_u16(gpos_data, lookup) {
+            Some(LOOKUP_PAIR) => true,
+            // extensions have the type of their lookup in every subtable
+            Some(LOOKUP_EXTENSION) => {
+                read_u16(gpos_data, lookup + 6)
+                    .and_then(|offset| read_u16(gpos_data, lookup + usize::from(offset) + 2))
+                    == Some(LOOKUP_PAIR)
+            }
+            _ => false,
+        }
+    };
+
+    let feature_count = read_u16(gpos_data, feature_list).unwrap_or(0);
+    (0..usize::from(feature_count)).any(|i| {
+        let record = feature_list + 2 + i * 6;
+        if gpos_data.get(record..record + 4) != Some(b"kern") {
+            return false;
+        }
+        let feature = match read_u16(gpos_data, record + 4) {
+            Some(offset) => feature_list + usize::from(offset),
+            None => return false,
+        };
+        let lookup_count = read_u16(gpos_data, feature + 2).unwrap_or(0);
+        (0..usize::from(lookup_count))
+            .any(|j| read_u16(gpos_data, feature + 4 + j * 2).is_some_and(is_pair_lookup))
+    })
+}
@@ -0,0 +1,268 @@
+//! Languages supported by fonts, see `FcLangSet`
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{FcCharset, FcError};
+
+// Basic letters of the languages a `FcLangSet` is built for (`a-z` is a range), a font
+// supports a language if it has all of them. Latin scripts only list the lowercase letters,
+// Chinese and Japanese a few of the most frequent ideographs of their variant.
+const LANG_EXEMPLARS: &[(&str, &str)] = &[
+    ("am", "ሀ-ቆ"),
+    ("ar", "ء-غف-ي"),
+    ("be", "а-шы-яёіў"),
+    ("bg", "а-ъьюя"),
+    ("bn", "অ-ঌএঐও-নপ-রলশ-হ"),
+    ("bo", "ཀ-ཇཉ-ཪ"),
+    ("cs", "a-záéíóúýčďěňřšťůž"),
+    ("da", "a-zåæø"),
+    ("de", "a-zßäöü"),
+    ("el", "α-ω"),
+    ("en", "a-z"),
+    ("es", "a-záéíñóúü"),
+    ("et", "a-zäõöüšž"),
+    ("fa", "ء-غف-وپچژکگی"),
+    ("fi", "a-zäö"),
+    ("fr", "a-zàâç-ëîïôùûüÿœ"),
+    ("gu", "અ-ઋએઐઓ-નપ-રલળવ-હ"),
+    ("he", "א-ת"),
+    ("hi", "अ-ऌएऐओ-नप-रलव-ह"),
+    ("hr", "a-zćčđšž"),
+    ("hu", "a-záéíóöúüőű"),
+    ("hy", "ա-ֆ"),
+    ("is", "a-záæéíðóöúýþ"),
+    ("it", "a-zàèéìòù"),
+    ("ja", "ぁ-んァ-ヶ人年日本私語"),
+    ("ka", "ა-ჰ"),
+    ("km", "ក-អ"),
+    ("kn", "ಅ-ಌಎ-ಐಒ-ನಪ-ಳವ-ಹ"),
+    ("ko", "가나다라마바사아자차카타파하"),
+    ("lo", "ກຂຄງຈຊຍດ-ທນ-ຟມ-ຣລວສຫອຮ"),
+    ("lt", "a-ząčėęįšūųž"),
+    ("lv", "a-zāčēģīķļņšūž"),
+    ("mk", "а-ик-шѓѕј-њќџ"),
+    ("ml", "അ-ഌഎ-ഐഒ-നപ-ഹ"),
+    ("mr", "अ-ऌएऐओ-नप-रलळव-ह"),
+    ("my", "က-ဠ"),
+    ("nb", "a-zåæø"),
+    ("ne", "अ-ऌएऐओ-नप-रलव-ह"),
+    ("nl", "a-z"),
+    ("or", "ଅ-ଌଏଐଓ-ନପ-ରଲଳଵ-ହ"),
+    ("pa", "ਅ-ਊਏਐਓ-ਨਪ-ਰਲਵਸਹ"),
+    ("pl", "a-zóąćęłńśźż"),
+    ("pt", "a-zà-ãçéêíó-õú"),
+    ("ro", "a-zâîășț"),
+    ("ru", "а-яё"),
+    ("si", "අ-ඖක-නඳ-රලව-ෆ"),
+    ("sk", "a-záäéíóôúýčďĺľňŕšťž"),
+    ("sl", "a-zčšž"),
+    ("sr", "а-ик-шђј-ћџ"),
+    ("sv", "a-zäåö"),
+    ("ta", "அ-ஊஎ-ஐஒ-கஙசஜஞடணதந-பம-ஹ"),
+    ("te", "అ-ఌఎ-ఐఒ-నప-ళవ-హ"),
+    ("th", "ก-ฮ"),
+    ("tr", "a-zçöüğış"),
+    ("uk", "а-щьюяєіїґ"),
+    ("ur", "ء-غف-وٹپچڈڑژکگںھہیے"),
+    ("vi", "a-zà-ãè-êìíò-õùúýăđĩũơưạ-ỹ"),
+    ("zh-cn", "个为们会国对时来说这"),
+    ("zh-hk", "來個們冇咗喺嘅國對時會為說這"),
+    ("zh-tw", "來個們國對時會為說這"),
+];
+
+/// How well a language matches a language set, see `FcLangSet::has_lang` (best first)
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcLangResult {
+    /// The set has the language with the same territory (`"zh-tw"` for `"zh-TW"`)
+    Equal,
+    /// The set has the language, but only for another territory or without one (`"zh-cn"`
+    /// for `"zh-tw"`, `"en"` for `"en-us"`)
+    DifferentTerritory,
+    /// The set doesn't have the language
+    DifferentLang,
+}
+
+/// Set of languages, i.e. the languages a font supports (see `FcLangSet::from_charset`),
+/// same as the `FcLangSet` of fontconfig
+///
+/// Languages are lowercase language tags with an optional territory (`"en"`, `"zh-tw"`).
+/// Sets compare equal if they have the same languages, see `compare` for fontconfig's
+/// language matching.
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FcLangSet {
+    // normalized tags, sorted
+    langs: Vec<String>,
+}
+
+impl FcLangSet {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the languages whose basic letters are all in `charset`
+    ///
+    /// The letters of a language are a small exemplar set (i.e. a-z for English, the
+    /// letters with diacritics for French, the kana and a few ideographs for Japanese),
+    /// not the full orthography that fontconfig checks, so fonts that only cover the
+    /// basics of a language still support it.
+    pub fn from_charset(charset: &FcCharset) -> Self {
+        let langs = LANG_EXEMPLARS
+            .iter()
+            .filter(|(_, exemplars)| FcExemplarChars(exemplars).all(|c| charset.contains(c)))
+            .map(|(lang, _)| String::from(*lang))
+            .collect();
+        FcLangSet { langs }
+    }
+
+    /// Returns the languages `from_charset` can detect
+    pub fn known_langs() -> impl Iterator<Item = &'static str> {
+        LANG_EXEMPLARS.iter().map(|(lang, _)| *lang)
+    }
+
+    /// Adds a language (`"ja"`, `"zh-TW"`, `"pt_BR"`), returns whether it wasn't in the
+    /// set yet
+    pub fn add(&mut self, lang: &str) -> bool {
+        let lang = FcNormalizeLang(lang);
+        match self.langs.binary_search(&lang) {
+            Ok(_) => false,
+            Err(index) => {
+                self.langs.insert(index, lang);
+                true
+            }
+        }
+    }
+
+    /// Returns the best match of a language in the set
+    ///
+    /// Case and `-` / `_` are ignored, a language without a territory matches all
+    /// territories of the language as `DifferentTerritory` (and the other way around).
+    pub fn has_lang(&self, lang: &str) -> FcLangResult {
+        let lang = FcNormalizeLang(lang);
+        self.langs
+            .iter()
+            .map(|l| FcCompareLang(l, &lang))
+            .min()
+            .unwrap_or(FcLangResult::DifferentLang)
+    }
+
+    /// Returns the best match between any language of this set and any language of
+    /// `other`, i.e. whether a font with these languages suits a text in `other`
+    pub fn compare(&self, other: &FcLangSet) -> FcLangResult {
+        other
+            .langs
+            .iter()
+            .map(|lang| self.has_lang(lang))
+            .min()
+            .unwrap_or(FcLangResult::DifferentLang)
+    }
+
+    /// Returns whether every language of `other` is in this set (with the same territory)
+    pub fn contains(&self, other: &FcLangSet) -> bool {
+        other
+            .langs
+            .iter()
+            .all(|lang| self.langs.binary_search(lang).is_ok())
+    }
+
+    /// Returns the languages of the set, sorted
+    pub fn langs(&self) -> impl Iterator<Item = &str> {
+        self.langs.iter().map(|lang| lang.as_str())
+    }
+
+    /// Returns the number of languages in the set
+    pub fn len(&self) -> usize {
+        self.langs.len()
+    }
+
+    /// Returns whether the set has no languages
+    pub fn is_empty(&self) -> bool {
+        self.langs.is_empty()
+    }
+}
+
+/// Writes the languages separated by `|`, the format of fontconfig (`"en|fr|zh-tw"`)
+impl fmt::Display for FcLangSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, lang) in self.langs.iter().enumerate() {
+            if i > 0 {
+                f.write_str("|")?;
+            }
+            f.write_str(lang)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses languages separated by `|`, see `Display`
+impl FromStr for FcLangSet {
+    type Err = FcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = FcLangSet::new();
+        for lang in s.split('|').filter(|lang| !lang.is_empty()) {
+            let valid = lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(FcError::InvalidValue { value: s.into() });
+            }
+            set.add(lang);
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl crate::FcFontCache {
+    /// Returns the languages a font of the cache supports, see `FcLangSet::from_charset`
+    pub fn lang_set(&self, path: &crate::FcFontPath) -> FcLangSet {
+        FcLangSet::from_charset(&self.charset(path))
+    }
+}
+
+// Characters of an exemplar string, expands the `a-z` ranges
+fn FcExemplarChars(exemplars: &str) -> impl Iterator<Item = char> + '_ {
+    let mut chars = exemplars.chars().peekable();
+    core::iter::from_fn(move || {
+        let start = chars.next()?;
+        if chars.peek() != Some(&'-') {
+            return Some(start..=start);
+        }
+        chars.next();
+        let end = chars.next()?;
+        Some(start..=end)
+    })
+    .flatten()
+}
+
+// Lowercase tag with `-` between the language and the territory
+fn FcNormalizeLang(lang: &str) -> String {
+    lang.chars()
+        .map(|c| match c {
+            '_' => '-',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+// Match of two normalized tags
+fn FcCompareLang(a: &str, b: &str) -> FcLangResult {
+    fn language(tag: &str) -> Option<&str> {
+        tag.split('-').next()
+    }
+
+    if a == b {
+        FcLangResult::Equal
+    } else if language(a) == language(b) {
+        FcLangResult::DifferentTerritory
+    } else {
+        FcLangResult::DifferentLang
+    }
+}
@@ -0,0 +1,131 @@
+//! Lazy builds, see `FcFontCacheBuilder::lazy`
+//!
+//! The directories are listed as usual, but the font files are only parsed once
+//! they are needed. Font files are usually named after their family
+//! ("DejaVuSans-Bold.ttf", "NotoSansCJK-Regular.ttc"), so a query for a family
+//! first parses the files whose name contains the family name, and only parses
+//! all remaining files if that doesn't find a match.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::{FcFontCache, FcFontPath, FcParseFontFiles, FcPattern, FcScanOptions, FcSkippedFiles};
+
+/// Files collected instead of being parsed during a lazy build
+#[derive(Debug, Default)]
+pub(crate) struct FcPendingFiles(Mutex<Vec<PathBuf>>);
+
+// only compared as part of `FcScanOptions`, every build has its own list
+impl PartialEq for FcPendingFiles {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+impl Eq for FcPendingFiles {}
+
+impl FcPendingFiles {
+    pub(crate) fn extend(&self, files: &[PathBuf]) {
+        if let Ok(mut pending) = self.0.lock() {
+            pending.extend_from_slice(files);
+        }
+    }
+
+    pub(crate) fn take(&self) -> Vec<String> {
+        let mut files = match self.0.lock() {
+            Ok(mut pending) => core::mem::take(&mut *pending),
+            Err(_) => Vec::new(),
+        };
+        files.sort();
+        files.dedup();
+        files
+            .into_iter()
+            .map(|file| file.to_string_lossy().to_string())
+            .collect()
+    }
+}
+
+impl FcFontCache {
+    /// Returns the number of font files of a lazy build that haven't been parsed yet
+    pub fn pending_files(&self) -> usize {
+        self.pending_files.len()
+    }
+
+    /// Parses the not yet parsed font files that likely contain the given family
+    /// (see `FcFontCacheBuilder::lazy`), files are only parsed once
+    pub fn load_family(&mut self, family: &str) -> &mut Self {
+        let family = FcNormalizeFileName(family);
+        if family.is_empty() {
+            return self;
+        }
+
+        let (candidates, rest) = core::mem::take(&mut self.pending_files)
+            .into_iter()
+            .partition::<Vec<_>, _>(|file| {
+                let name = std::path::Path::new(file)
+                    .file_stem()
+                    .map(|stem| FcNormalizeFileName(&stem.to_string_lossy()))
+                    .unwrap_or_default();
+                name.contains(&family)
+            });
+        self.pending_files = rest;
+
+        self.parse_pending(candidates)
+    }
+
+    /// Parses all font files that haven't been parsed yet
+    pub fn load_all(&mut self) -> &mut Self {
+        let files = core::mem::take(&mut self.pending_files);
+        self.parse_pending(files)
+    }
+
+    /// Same as `query`, but parses the pending font files of a lazy build that are needed
+    /// to answer the query first
+    ///
+    /// Files named after the requested family (or name) are parsed first, if there's no match
+    /// all remaining files are parsed. The parsed fonts stay in the cache.
+    pub fn query_lazy(&mut self, pattern: &FcPattern) -> Option<&FcFontPath> {
+        if !self.pending_files.is_empty() {
+            for name in [pattern.family.as_ref(), pattern.name.as_ref()]
+                .iter()
+                .flatten()
+            {
+                self.load_family(name);
+            }
+
+            if self.query(pattern).is_none() {
+                self.load_all();
+            }
+        }
+
+        self.query(pattern)
+    }
+
+    fn parse_pending(&mut self, files: Vec<String>) -> &mut Self {
+        if files.is_empty() {
+            return self;
+        }
+
+        // same limits and parser as the files parsed during the build
+        let skipped_files = Arc::new(FcSkippedFiles::default());
+        let options = FcScanOptions {
+            skipped_files: Some(skipped_files.clone()),
+            ..self.scan_options.0.clone()
+        };
+        let files = files.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+        self.map.extend(FcParseFontFiles(&files, &options));
+        self.skipped_files.extend(skipped_files.take());
+        self
+    }
+}
+
+// "DejaVu Sans" and "DejaVuSans-Bold" -> "dejavusans", "dejavusansbold"
+fn FcNormalizeFileName(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
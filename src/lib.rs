@@ -21,6 +21,12 @@
 #![allow(non_snake_case)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(all(
+    feature = "scan",
+    not(any(feature = "parsing", feature = "ttf-parser"))
+))]
+compile_error!("the `scan` feature needs a font parser, enable `parsing` or `ttf-parser`");
+
 #[cfg(feature = "parsing")]
 extern crate allsorts;
 #[cfg(all(not(target_family = "wasm"), feature = "std"))]
@@ -30,14 +36,159 @@ extern crate xmlparser;
 extern crate alloc;
 extern crate core;
 
+#[cfg(all(target_os = "android", feature = "std", feature = "scan"))]
+mod android;
+mod bloom;
+#[cfg(feature = "std")]
+mod build_async;
+#[cfg(feature = "std")]
+mod bytes;
+mod charset;
+mod cjk;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod codegen;
+mod container;
+#[cfg(all(
+    target_os = "macos",
+    feature = "coretext",
+    feature = "std",
+    feature = "scan"
+))]
+mod coretext;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod coverage;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod dedupe;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod dfont;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod dircache;
+#[cfg(all(
+    target_os = "windows",
+    feature = "directwrite",
+    feature = "std",
+    feature = "scan"
+))]
+mod directwrite;
+mod embedding;
+mod error;
+mod families;
+#[cfg(all(
+    any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ),
+    feature = "std",
+    feature = "scan"
+))]
+mod fccache;
+mod features;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "fontdb")]
+mod fontdb_interop;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod itemize;
+mod json;
+mod kerning;
+mod langset;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod lazy;
+mod merge;
+mod name;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod parser;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod pcf;
+mod persist;
+#[cfg(feature = "std")]
+mod provider;
+mod script;
+mod serialized;
+#[cfg(feature = "std")]
+mod shared;
+mod store;
+#[cfg(all(feature = "std", feature = "ttf-parser"))]
+mod ttf;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod type1;
+#[cfg(all(feature = "std", feature = "scan"))]
+mod utils;
+mod validate;
+#[cfg(all(feature = "wasm", feature = "std", feature = "scan"))]
+mod wasm;
+#[cfg(all(
+    feature = "watch",
+    not(target_family = "wasm"),
+    feature = "std",
+    feature = "scan"
+))]
+mod watch;
+mod weight;
+#[cfg(all(target_os = "windows", feature = "std", feature = "scan"))]
+mod windows;
+#[cfg(all(feature = "woff", feature = "std", feature = "scan"))]
+mod woff;
+
 use alloc::borrow::ToOwned;
 use alloc::collections::btree_map::BTreeMap;
 use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 #[cfg(feature = "std")]
 use std::path::PathBuf;
 
+#[cfg(all(feature = "std", feature = "ttf-parser"))]
+use ttf::{FcFaceTableData, FcFontNames};
+
+#[cfg(feature = "std")]
+pub use build_async::FcBuildFuture;
+#[cfg(feature = "std")]
+pub use bytes::FcFontBytes;
+pub use charset::FcCharset;
+pub use cjk::{FcCjkInfo, FcCjkLocale};
+#[cfg(all(feature = "std", feature = "scan"))]
+pub use codegen::FcCacheCodegen;
+pub use container::{FcFontContainer, FcFontFormat};
+pub use embedding::{FcEmbeddingInfo, FcEmbeddingPermission, FcOutlineFormat};
+pub use error::FcError;
+pub use families::{FcFaceInfo, FcFamily};
+pub use features::{FcFeatureInfo, FcFigureStyle};
+#[cfg(feature = "std")]
+pub use handle::FcFontHandle;
+pub use kerning::FcKerningInfo;
+pub use langset::{FcLangResult, FcLangSet};
+pub use merge::FcMergePolicy;
+#[cfg(all(feature = "std", feature = "parsing"))]
+pub use parser::FcAllsortsParser;
+#[cfg(all(feature = "std", feature = "scan"))]
+pub use parser::FcMetadataParser;
+#[cfg(all(feature = "std", feature = "ttf-parser"))]
+pub use parser::FcTtfParser;
+#[cfg(feature = "std")]
+pub use provider::{FcFontProvider, FcProvidedFont};
+pub use script::FcScript;
+#[cfg(feature = "std")]
+pub use shared::FcSharedFontCache;
+pub use validate::{FcValidationIssue, FcValidationReport};
+pub use weight::FcWeight;
+
+#[cfg(all(
+    feature = "watch",
+    not(target_family = "wasm"),
+    feature = "std",
+    feature = "scan"
+))]
+pub use watch::FcFontCacheWatcher;
+
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub enum PatternMatch {
     True,
@@ -45,138 +196,1815 @@ pub enum PatternMatch {
     DontCare,
 }
 
-impl PatternMatch {
-    fn needs_to_match(&self) -> bool {
-        matches!(self, PatternMatch::True | PatternMatch::False)
+impl PatternMatch {
+    fn needs_to_match(&self) -> bool {
+        matches!(self, PatternMatch::True | PatternMatch::False)
+    }
+
+    /// Returns `true` for `PatternMatch::True`
+    pub fn is_true(&self) -> bool {
+        matches!(self, PatternMatch::True)
+    }
+
+    /// Returns `true` for `PatternMatch::False`
+    pub fn is_false(&self) -> bool {
+        matches!(self, PatternMatch::False)
+    }
+
+    /// Returns the property as `bool`, `None` for `PatternMatch::DontCare`
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PatternMatch::True => Some(true),
+            PatternMatch::False => Some(false),
+            PatternMatch::DontCare => None,
+        }
+    }
+}
+
+impl Default for PatternMatch {
+    fn default() -> Self {
+        PatternMatch::DontCare
+    }
+}
+
+impl From<bool> for PatternMatch {
+    fn from(value: bool) -> Self {
+        if value {
+            PatternMatch::True
+        } else {
+            PatternMatch::False
+        }
+    }
+}
+
+/// `None` is `PatternMatch::DontCare`
+impl From<Option<bool>> for PatternMatch {
+    fn from(value: Option<bool>) -> Self {
+        value.map_or(PatternMatch::DontCare, PatternMatch::from)
+    }
+}
+
+/// Generic font family, as used by CSS and fontconfig
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum GenericFamily {
+    Serif,
+    SansSerif,
+    Monospace,
+    Cursive,
+    Fantasy,
+}
+
+impl GenericFamily {
+    /// Returns the CSS / fontconfig name of the generic family ("sans-serif", "monospace", ...)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GenericFamily::Serif => "serif",
+            GenericFamily::SansSerif => "sans-serif",
+            GenericFamily::Monospace => "monospace",
+            GenericFamily::Cursive => "cursive",
+            GenericFamily::Fantasy => "fantasy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[repr(C)]
+pub struct FcPattern {
+    // font name
+    pub name: Option<String>,
+    // family name
+    pub family: Option<String>,
+    // "italic" property
+    pub italic: PatternMatch,
+    // "oblique" property
+    pub oblique: PatternMatch,
+    // "bold" property
+    pub bold: PatternMatch,
+    // "monospace" property
+    pub monospace: PatternMatch,
+    // "condensed" property
+    pub condensed: PatternMatch,
+    // font weight, informational: queries ignore it (use `bold` to filter), `itemize`
+    // prefers the fonts closest to it, `FcWeight(0)` if unknown / not specified
+    pub weight: FcWeight,
+    // start..end unicode range
+    pub unicode_range: [usize; 2],
+    // "scalable" property, `False` for bitmap-only fonts (EBDT / EBLC without glyf / CFF)
+    pub scalable: PatternMatch,
+    // "hinted" property, `True` for fonts with TrueType hinting instructions (`fpgm` / `prep`,
+    // see `FcHintingInfo::is_hinted`), `DontCare` for fonts where that isn't known
+    pub hinted: PatternMatch,
+    // "small caps" property, `True` for fonts with real small capitals (`smcp` feature, see
+    // `FcFeatureInfo::small_caps`), `DontCare` for fonts where that isn't known
+    pub small_caps: PatternMatch,
+    // "tabular figures" property, `True` for fonts with digits of equal width (`tnum` feature
+    // or monospaced default digits, see `FcFeatureInfo::has_tabular_figures`), `DontCare` for
+    // fonts where that isn't known
+    pub tabular_figures: PatternMatch,
+    // OS/2 vendor IDs (`achVendID`) to restrict matches to, `None` matches any vendor
+    pub vendor_ids: Option<Vec<String>>,
+    // font formats to restrict matches to (see `FcFontMetadata::format`), `None` matches any
+    // format
+    pub formats: Option<Vec<FcFontFormat>>,
+    // additional font information, not used for matching
+    pub metadata: FcFontMetadata,
+}
+
+impl FcPattern {
+    /// Pattern for the regular (not bold, not italic) style of `family`
+    pub fn regular(family: &str) -> Self {
+        FcPattern::style(family, false, false)
+    }
+
+    /// Pattern for the bold style of `family`
+    pub fn bold(family: &str) -> Self {
+        FcPattern::style(family, true, false)
+    }
+
+    /// Pattern for the italic style of `family`
+    pub fn italic(family: &str) -> Self {
+        FcPattern::style(family, false, true)
+    }
+
+    /// Pattern for the bold italic style of `family`
+    pub fn bold_italic(family: &str) -> Self {
+        FcPattern::style(family, true, true)
+    }
+
+    fn style(family: &str, bold: bool, italic: bool) -> Self {
+        FcPattern {
+            family: Some(family.to_owned()),
+            bold: bold.into(),
+            italic: italic.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for FcPattern {
+    /// Default pattern, bitmap-only fonts are excluded unless `scalable`
+    /// is explicitly set to `False` or `DontCare` (same as fontconfig)
+    fn default() -> Self {
+        FcPattern {
+            name: None,
+            family: None,
+            italic: PatternMatch::default(),
+            oblique: PatternMatch::default(),
+            bold: PatternMatch::default(),
+            monospace: PatternMatch::default(),
+            condensed: PatternMatch::default(),
+            weight: FcWeight(0),
+            unicode_range: [0; 2],
+            scalable: PatternMatch::True,
+            hinted: PatternMatch::default(),
+            small_caps: PatternMatch::default(),
+            tabular_figures: PatternMatch::default(),
+            vendor_ids: None,
+            formats: None,
+            metadata: FcFontMetadata::default(),
+        }
+    }
+}
+
+/// Additional information about a font that is reported
+/// in the cache, but ignored when matching patterns
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[repr(C)]
+pub struct FcFontMetadata {
+    // TrueType hinting instructions and gasp ranges
+    pub hinting: FcHintingInfo,
+    // font vendor ID from the OS/2 table (`achVendID`), without trailing padding
+    pub vendor_id: Option<String>,
+    // regional glyph conventions of CJK fonts, see `FcCjkInfo::supports`
+    pub cjk: FcCjkInfo,
+    // complex scripts the font has `GSUB` lookups for, sorted, see `FcFontMetadata::can_shape`
+    pub shaping: Vec<FcScript>,
+    // characters of the font from its `cmap` table, see `FcFontCache::charset`
+    pub charset: FcCharset,
+    // `italicAngle` of the `post` table, counter-clockwise degrees from the vertical in 16.16
+    // fixed point (negative for fonts that lean to the right), see `FcFontMetadata::italic_angle`
+    pub italic_angle: i32,
+    // format of the font file, the patterns of WOFF / WOFF2 files are parsed from the
+    // decompressed font (`woff` feature)
+    pub container: FcFontContainer,
+    // format of the font (outline format, collection, web font, ...), see `FcFontFormat`
+    pub format: FcFontFormat,
+    // pixel sizes (ppem) of the embedded bitmap strikes (`EBLC` / `CBLC` tables, the pixel
+    // size of PCF fonts), sorted, see `FcFontMetadata::has_strike`
+    pub strikes: Vec<u16>,
+    // legacy `kern` table and `GPOS` kerning, see `FcKerningInfo`
+    pub kerning: FcKerningInfo,
+    // typographic features of the `GSUB` table (small caps, figures, ...), see `FcFeatureInfo`
+    pub features: FcFeatureInfo,
+    // subfamily name of the `name` table ("Book", "Bold Oblique"), the typographic subfamily
+    // (name ID 17) or else the legacy one (name ID 2), see `FcPattern::to_fc_list`
+    pub style: Option<String>,
+}
+
+impl FcFontMetadata {
+    /// Returns whether the font can render a script correctly (always `true` for scripts that
+    /// aren't complex, see `FcScript::is_complex`)
+    ///
+    /// A font that supports the characters of Arabic or an Indic script without the glyph
+    /// substitutions for it renders disconnected or misordered glyphs.
+    pub fn can_shape(&self, script: FcScript) -> bool {
+        !script.is_complex() || self.shaping.contains(&script)
+    }
+
+    /// Returns the `italicAngle` of the font in degrees (`-12.0` for a font that leans 12°
+    /// to the right, `0.0` for upright fonts)
+    pub fn italic_angle(&self) -> f32 {
+        self.italic_angle as f32 / 65536.0
+    }
+
+    /// Returns whether the font has a bitmap strike of exactly `pixel_size` pixels per em,
+    /// i.e. renders crisp bitmaps at that size instead of scaled outlines or bitmaps
+    pub fn has_strike(&self, pixel_size: u16) -> bool {
+        self.strikes.binary_search(&pixel_size).is_ok()
+    }
+}
+
+/// Hinting information of a font (fpgm / prep / cvt tables and the gasp table)
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[repr(C)]
+pub struct FcHintingInfo {
+    // font program (`fpgm` table) is present
+    pub has_fpgm: bool,
+    // control value program (`prep` table) is present
+    pub has_prep: bool,
+    // control value table (`cvt ` table) is present
+    pub has_cvt: bool,
+    // ranges from the `gasp` table, sorted by increasing `max_ppem`
+    pub gasp_ranges: Vec<FcGaspRange>,
+}
+
+impl FcHintingInfo {
+    /// Returns whether the font contains TrueType bytecode hinting instructions
+    pub fn is_hinted(&self) -> bool {
+        self.has_fpgm || self.has_prep
+    }
+}
+
+/// Single range from the `gasp` table
+///
+/// See: https://learn.microsoft.com/en-us/typography/opentype/spec/gasp
+#[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcGaspRange {
+    // upper limit of the range, in pixels per em
+    pub max_ppem: u16,
+    // `GASP_*` flags for the range
+    pub behavior: u16,
+}
+
+impl FcGaspRange {
+    pub const GASP_GRIDFIT: u16 = 0x0001;
+    pub const GASP_DOGRAY: u16 = 0x0002;
+    pub const GASP_SYMMETRIC_GRIDFIT: u16 = 0x0004;
+    pub const GASP_SYMMETRIC_SMOOTHING: u16 = 0x0008;
+
+    /// Returns whether grid-fitting (hinting) should be used for this range
+    pub fn gridfit(&self) -> bool {
+        self.behavior & (Self::GASP_GRIDFIT | Self::GASP_SYMMETRIC_GRIDFIT) != 0
+    }
+
+    /// Returns whether grayscale rendering should be used for this range
+    pub fn grayscale(&self) -> bool {
+        self.behavior & (Self::GASP_DOGRAY | Self::GASP_SYMMETRIC_SMOOTHING) != 0
+    }
+}
+
+/// Font of the cache: its file (or in-memory data) and the index of the font in the file
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcFontPath {
+    pub source: FcFontSource,
+    pub font_index: usize,
+}
+
+impl FcFontPath {
+    /// Returns the path of the font file, `None` for in-memory fonts
+    pub fn path(&self) -> Option<&str> {
+        self.source.path()
+    }
+}
+
+/// Where the data of a font comes from
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcFontSource {
+    /// Font file on disk (the path is a string, so that caches also work without `std`)
+    Disk(Arc<str>),
+    /// Font file in memory, see `FcFontCache::with_memory_fonts`
+    Memory(Arc<[u8]>),
+}
+
+impl FcFontSource {
+    /// Returns the path of the font file, `None` for in-memory fonts
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            FcFontSource::Disk(path) => Some(path),
+            FcFontSource::Memory(_) => None,
+        }
+    }
+}
+
+/// Font returned by `FcFontCache::query_owned`, independent of the lifetime of the cache
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcFontMatch {
+    // pattern of the matched font (not the queried pattern)
+    pub pattern: FcPattern,
+    // index of the font in the file (font collections)
+    pub index: usize,
+    // file or in-memory data of the font, cheap to clone
+    pub source: FcFontSource,
+    // the query asked for bold but the font isn't, renderers should embolden the glyphs
+    // (fontconfig's `FC_EMBOLDEN`)
+    pub needs_synthetic_bold: bool,
+    // the query asked for italic / oblique but the font is upright, renderers should
+    // shear the glyphs (fontconfig's `FC_MATRIX`)
+    pub needs_synthetic_oblique: bool,
+    // clockwise angle to shear the glyphs by when `needs_synthetic_oblique` (0 otherwise),
+    // in degrees in 16.16 fixed point, see `FcFontMatch::oblique_angle`
+    pub oblique_angle: i32,
+}
+
+impl FcFontMatch {
+    /// Returns the path of the font file, `None` for in-memory fonts
+    pub fn path(&self) -> Option<&str> {
+        self.source.path()
+    }
+
+    /// Returns the angle to shear the glyphs by in degrees (clockwise from the vertical),
+    /// `0.0` if the font doesn't need a synthetic oblique
+    ///
+    /// This is the slant of the italic / oblique faces of the same family, or 12° if it has
+    /// none (or if their slant isn't known, i.e. fonts of fontconfig caches), so that
+    /// synthetic obliques match the real ones on every platform.
+    pub fn oblique_angle(&self) -> f32 {
+        self.oblique_angle as f32 / 65536.0
+    }
+
+    /// Returns the matrix `[xx, xy, yx, yy]` to transform the glyphs by (`x' = xx * x + xy * y`,
+    /// `y' = yx * x + yy * y`, y going up), same as fontconfig's `FC_MATRIX`: the shear of
+    /// `oblique_angle`, the identity if the font doesn't need a synthetic oblique
+    #[cfg(feature = "std")]
+    pub fn oblique_matrix(&self) -> [f32; 4] {
+        [1.0, self.oblique_angle().to_radians().tan(), 0.0, 1.0]
+    }
+}
+
+/// Represent an in-memory font file
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcFont {
+    pub bytes: Vec<u8>,
+    pub font_index: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FcFontCache {
+    map: store::FcFontStore,
+    // platform fallback order, see `query_sorted`
+    fallback: Vec<FcFallbackFamily>,
+    // lowercase family name -> substitute family name (i.e. Windows "FontSubstitutes")
+    substitutes: BTreeMap<String, String>,
+    // lowercase family name -> fonts to fall back to for that family (i.e. Windows "FontLink")
+    font_links: BTreeMap<String, Vec<FcFontPath>>,
+    // lowercase family name -> preferred families, in order (i.e. fontconfig `<alias>` for "sans-serif")
+    aliases: BTreeMap<String, Vec<String>>,
+    // modification times of the scanned directories and font files, see `refresh`
+    scanned_dirs: BTreeMap<String, (u64, u32)>,
+    scanned_files: BTreeMap<String, (u64, u32)>,
+    // font files of a lazy build that haven't been parsed yet, see `query_lazy`
+    pending_files: Vec<String>,
+    // font files that were skipped during the build, see `skipped_files`
+    skipped_files: Vec<FcSkippedFile>,
+    // files that couldn't be parsed -> modification time and size, only parsed again
+    // once they change, see `refresh`
+    failed_files: BTreeMap<String, (u64, u32, u64)>,
+    // path of a font file -> other paths of the same file, see `alternate_paths`
+    alternate_paths: BTreeMap<String, Vec<String>>,
+    // font files loaded by `FcFontHandle`s, shared with the clones of the cache
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    loaded: Arc<handle::FcLoadedFonts>,
+    // characters of the fonts used by `itemize`, shared with the clones of the cache
+    #[cfg(all(feature = "std", feature = "scan"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    coverage: Arc<coverage::FcCoverageCache>,
+    // options of the build, also used for the fonts found after the build (`refresh`,
+    // `add_font_file` / `add_font_dir`, `query_lazy`)
+    #[cfg(all(feature = "std", feature = "scan"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    scan_options: FcBuildOptions,
+}
+
+/// Reason why a font file was skipped during the build, see `FcFontCache::skipped_files`
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcSkipReason {
+    /// The file is larger than `FcFontCacheBuilder::max_file_size`
+    TooLarge,
+    /// The collection contains more faces than `FcFontCacheBuilder::max_faces`
+    TooManyFaces,
+    /// The font parser panicked on the file
+    ParserPanic,
+}
+
+/// Font file that was skipped during the build
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcSkippedFile {
+    pub path: String,
+    pub reason: FcSkipReason,
+}
+
+/// One step of the platform font fallback order (i.e. a `<family>` in Android's fonts.xml)
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcFallbackFamily {
+    // BCP 47 language tags this family is used for ("ja", "und-Arab"), empty if used for any language
+    pub langs: Vec<String>,
+    // fonts of the family, in platform order
+    pub fonts: Vec<FcFontPath>,
+}
+
+impl FcFallbackFamily {
+    /// Returns whether this family is meant to be used for text in the given language
+    pub fn supports_lang(&self, lang: &str) -> bool {
+        self.langs.iter().any(|l| FcLangMatches(l, lang))
+    }
+}
+
+// Options for scanning font directories, see `FcFontCacheBuilder`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FcScanOptions {
+    // number of subdirectory levels to descend into, `None` = unlimited
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    // lowercase file extensions (without the dot) to parse, `None` = all files
+    extensions: Option<Vec<String>>,
+    excluded_dirs: Vec<PathBuf>,
+    system_fonts: bool,
+    user_fonts: bool,
+    // records of the previous build, see `FcFontCacheBuilder::disk_cache`
+    #[cfg(feature = "scan")]
+    dir_cache: Option<std::sync::Arc<dircache::FcDirCache>>,
+    // see `FcFontCacheBuilder::progress`
+    #[cfg(feature = "scan")]
+    progress: Option<std::sync::Arc<FcProgressReporter>>,
+    cancellation: Option<FcCancellationToken>,
+    // files collected instead of being parsed, see `FcFontCacheBuilder::lazy`
+    #[cfg(feature = "scan")]
+    pending_files: Option<std::sync::Arc<lazy::FcPendingFiles>>,
+    // limits for a single font file, in bytes / faces
+    max_file_size: Option<u64>,
+    max_faces: Option<usize>,
+    // files skipped because of the limits or parser panics, see `FcFontCache::skipped_files`
+    #[cfg(feature = "scan")]
+    skipped_files: Option<std::sync::Arc<FcSkippedFiles>>,
+    // see `FcFontCacheBuilder::parser`, the backend of the enabled features if `None`
+    #[cfg(feature = "scan")]
+    parser: Option<parser::FcParserHandle>,
+    // see `FcFontCacheBuilder::fontconfig_caches`
+    fontconfig_caches: bool,
+}
+
+#[cfg(feature = "std")]
+impl Default for FcScanOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            follow_symlinks: true,
+            extensions: None,
+            excluded_dirs: Vec::new(),
+            system_fonts: true,
+            user_fonts: true,
+            #[cfg(feature = "scan")]
+            dir_cache: None,
+            #[cfg(feature = "scan")]
+            progress: None,
+            cancellation: None,
+            #[cfg(feature = "scan")]
+            pending_files: None,
+            max_file_size: None,
+            max_faces: None,
+            #[cfg(feature = "scan")]
+            skipped_files: None,
+            #[cfg(feature = "scan")]
+            parser: None,
+            fontconfig_caches: false,
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl FcScanOptions {
+    // whether the scan can be answered from the fontconfig caches, which always cover
+    // the whole directory tree
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))]
+    fn is_unbounded(&self) -> bool {
+        self.max_depth.is_none() && self.follow_symlinks
+    }
+
+    fn parser(&self) -> &dyn FcMetadataParser {
+        match self.parser.as_ref() {
+            Some(parser) => &*parser.0,
+            None => &parser::FC_DEFAULT_PARSER,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+    }
+
+    // reports a file that was skipped, see `FcSkipReason`
+    fn skip_file(&self, file: &std::path::Path, reason: FcSkipReason) {
+        if let Some(skipped_files) = self.skipped_files.as_ref() {
+            skipped_files.push(FcSkippedFile {
+                path: file.to_string_lossy().to_string(),
+                reason,
+            });
+        }
+    }
+
+    fn includes_dir(&self, dir: &std::path::Path) -> bool {
+        !self
+            .excluded_dirs
+            .iter()
+            .any(|excluded| dir.starts_with(excluded))
+    }
+
+    // checks the extension whitelist and the excluded directories
+    fn accepts_file(&self, file: &str) -> bool {
+        let file = std::path::Path::new(file);
+
+        let extension_ok = match self.extensions.as_ref() {
+            Some(extensions) => file
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+                }),
+            None => true,
+        };
+
+        extension_ok && self.includes_dir(file)
+    }
+
+    // checks whether fonts from this location (system or per-user) should be included
+    fn accepts_location(&self, file: &str) -> bool {
+        if FcIsUserPath(std::path::Path::new(file)) {
+            self.user_fonts
+        } else {
+            self.system_fonts
+        }
+    }
+}
+
+/// Handle for stopping a running build from another thread, see `FcFontCacheBuilder::cancellation`
+///
+/// Clones share the same flag.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone)]
+pub struct FcCancellationToken(std::sync::Arc<core::sync::atomic::AtomicBool>);
+
+#[cfg(feature = "std")]
+impl FcCancellationToken {
+    /// Creates a token that isn't cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all builds using this token
+    pub fn cancel(&self) {
+        self.0.store(true, core::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Returns whether `cancel` was called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(core::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// Uses an existing flag as the token, `true` = cancelled
+#[cfg(feature = "std")]
+impl From<std::sync::Arc<core::sync::atomic::AtomicBool>> for FcCancellationToken {
+    fn from(flag: std::sync::Arc<core::sync::atomic::AtomicBool>) -> Self {
+        Self(flag)
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for FcCancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for FcCancellationToken {}
+
+/// Progress of a running font scan, see `FcFontCacheBuilder::progress`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct FcScanProgress {
+    // font directories found so far
+    pub dirs_found: usize,
+    // font files found so far, grows while directories are being listed
+    pub files_found: usize,
+    // font files parsed so far
+    pub files_parsed: usize,
+    // directory or font file that was just scanned
+    pub current_path: PathBuf,
+}
+
+// User callback of `FcFontCacheBuilder::progress`, compared by identity
+#[cfg(feature = "std")]
+#[derive(Clone)]
+struct FcProgressCallback(std::sync::Arc<std::sync::Mutex<dyn FnMut(FcScanProgress) + Send>>);
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for FcProgressCallback {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("FcProgressCallback")
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for FcProgressCallback {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for FcProgressCallback {}
+
+// Threads that parse the font files, see `FcFontCacheBuilder::threads` / `thread_pool`
+#[cfg(all(feature = "std", feature = "multithreading"))]
+#[derive(Debug, Clone)]
+enum FcScanThreads {
+    Count(usize),
+    Pool(std::sync::Arc<rayon::ThreadPool>),
+}
+
+#[cfg(all(feature = "std", feature = "multithreading"))]
+impl PartialEq for FcScanThreads {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FcScanThreads::Count(a), FcScanThreads::Count(b)) => a == b,
+            (FcScanThreads::Pool(a), FcScanThreads::Pool(b)) => std::sync::Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "multithreading"))]
+impl Eq for FcScanThreads {}
+
+// Counters of a single build, shared between the scanning threads
+#[cfg(all(feature = "std", feature = "scan"))]
+#[derive(Debug)]
+struct FcProgressReporter {
+    dirs_found: core::sync::atomic::AtomicUsize,
+    files_found: core::sync::atomic::AtomicUsize,
+    files_parsed: core::sync::atomic::AtomicUsize,
+    callback: FcProgressCallback,
+}
+
+// only compared as part of `FcScanOptions`, every build has its own reporter
+#[cfg(all(feature = "std", feature = "scan"))]
+impl PartialEq for FcProgressReporter {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl Eq for FcProgressReporter {}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl FcProgressReporter {
+    fn new(callback: FcProgressCallback) -> Self {
+        Self {
+            dirs_found: Default::default(),
+            files_found: Default::default(),
+            files_parsed: Default::default(),
+            callback,
+        }
+    }
+
+    fn found_dir(&self, dir: &std::path::Path, files: usize) {
+        use core::sync::atomic::Ordering;
+        self.dirs_found.fetch_add(1, Ordering::Relaxed);
+        self.files_found.fetch_add(files, Ordering::Relaxed);
+        self.report(dir);
+    }
+
+    fn parsed_file(&self, file: &std::path::Path) {
+        use core::sync::atomic::Ordering;
+        self.files_parsed.fetch_add(1, Ordering::Relaxed);
+        self.report(file);
+    }
+
+    fn report(&self, path: &std::path::Path) {
+        use core::sync::atomic::Ordering;
+        // the lock serializes calls from the rayon threads
+        if let Ok(mut callback) = self.callback.0.lock() {
+            callback(FcScanProgress {
+                dirs_found: self.dirs_found.load(Ordering::Relaxed),
+                files_found: self.files_found.load(Ordering::Relaxed),
+                files_parsed: self.files_parsed.load(Ordering::Relaxed),
+                current_path: path.to_path_buf(),
+            });
+        }
+    }
+}
+
+// Files skipped during a single build, shared between the scanning threads
+#[cfg(all(feature = "std", feature = "scan"))]
+#[derive(Debug, Default)]
+struct FcSkippedFiles(std::sync::Mutex<Vec<FcSkippedFile>>);
+
+// only compared as part of `FcScanOptions`, every build has its own list
+#[cfg(all(feature = "std", feature = "scan"))]
+impl PartialEq for FcSkippedFiles {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl Eq for FcSkippedFiles {}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl FcSkippedFiles {
+    fn push(&self, skipped_file: FcSkippedFile) {
+        if let Ok(mut skipped_files) = self.0.lock() {
+            skipped_files.push(skipped_file);
+        }
+    }
+
+    fn take(&self) -> Vec<FcSkippedFile> {
+        let mut skipped_files = match self.0.lock() {
+            Ok(mut skipped_files) => core::mem::take(&mut *skipped_files),
+            Err(_) => Vec::new(),
+        };
+        // files are parsed in parallel
+        skipped_files.sort();
+        skipped_files
+    }
+}
+
+// Scan options kept in the cache, without the state of the build itself
+#[cfg(all(feature = "std", feature = "scan"))]
+#[derive(Debug, Default, Clone)]
+struct FcBuildOptions(FcScanOptions);
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl FcBuildOptions {
+    fn new(options: &FcScanOptions) -> Self {
+        Self(FcScanOptions {
+            dir_cache: None,
+            progress: None,
+            cancellation: None,
+            pending_files: None,
+            skipped_files: None,
+            ..options.clone()
+        })
+    }
+}
+
+// The options don't take part in comparisons
+#[cfg(all(feature = "std", feature = "scan"))]
+impl PartialEq for FcBuildOptions {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl Eq for FcBuildOptions {}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl PartialOrd for FcBuildOptions {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl Ord for FcBuildOptions {
+    fn cmp(&self, _: &Self) -> core::cmp::Ordering {
+        core::cmp::Ordering::Equal
+    }
+}
+
+// Drops the platform font directories of the locations (system or per-user fonts) the
+// build excludes, so that they aren't scanned at all
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcRetainLocations(dirs: &mut Vec<(Option<String>, String)>, options: &FcScanOptions) {
+    if options.system_fonts && options.user_fonts {
+        return;
+    }
+    dirs.retain(|(prefix, dir)| {
+        process_path(prefix, PathBuf::from(dir), false)
+            .is_some_and(|path| options.accepts_location(&path.to_string_lossy()))
+    });
+}
+
+// Whether the path is inside the home directory of the current user
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcIsUserPath(path: &std::path::Path) -> bool {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .is_some_and(|home| path.starts_with(home))
+}
+
+/// Builder for a `FcFontCache` with control over how the font directories are scanned
+///
+/// ```rust,no_run
+/// use dafont::FcFontCacheBuilder;
+///
+/// let cache = FcFontCacheBuilder::new()
+///     .max_depth(2)
+///     .extensions(&["ttf", "otf"])
+///     .user_fonts(false)
+///     .build();
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FcFontCacheBuilder {
+    options: FcScanOptions,
+    // additional directories, scanned regardless of `system_fonts` / `user_fonts`
+    dirs: Vec<PathBuf>,
+    // custom font sources, see `add_provider`
+    providers: Vec<provider::FcProviderHandle>,
+    // file for the directory records, see `disk_cache`
+    cache_file: Option<PathBuf>,
+    progress: Option<FcProgressCallback>,
+    lazy: bool,
+    // also deduplicate files by their contents, see `deduplicate_contents`
+    deduplicate_contents: bool,
+    // rayon's global pool if `None`
+    #[cfg(feature = "multithreading")]
+    threads: Option<FcScanThreads>,
+}
+
+#[cfg(feature = "std")]
+impl FcFontCacheBuilder {
+    /// Creates a builder that behaves like `FcFontCache::build`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits how many levels of subdirectories are scanned below each font directory
+    /// (0 = only the files directly inside the font directories), unlimited by default
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.options.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets whether symbolic links to files and directories are followed (default: true)
+    ///
+    /// Directories are only scanned once per font directory, so symlink loops are safe
+    /// to follow.
+    pub fn follow_symlinks(&mut self, follow_symlinks: bool) -> &mut Self {
+        self.options.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Only parses files with one of the given extensions (case-insensitive, without the dot),
+    /// by default every file is parsed
+    pub fn extensions(&mut self, extensions: &[&str]) -> &mut Self {
+        self.options.extensions = Some(
+            extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Skips the given directory and all of its subdirectories
+    pub fn exclude_dir<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.options.excluded_dirs.push(dir.into());
+        self
+    }
+
+    /// Sets whether the system-wide font locations are included (default: true)
+    pub fn system_fonts(&mut self, system_fonts: bool) -> &mut Self {
+        self.options.system_fonts = system_fonts;
+        self
+    }
+
+    /// Sets whether the per-user font locations (inside the home directory) are included
+    /// (default: true)
+    pub fn user_fonts(&mut self, user_fonts: bool) -> &mut Self {
+        self.options.user_fonts = user_fonts;
+        self
+    }
+
+    /// Additionally scans the given directory (and its subdirectories)
+    pub fn add_dir<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    /// Parses the font files with a custom backend instead of the backend of the enabled
+    /// features (i.e. to mock the parser in tests)
+    ///
+    /// Fonts that are read from a `disk_cache` aren't parsed again, fontconfig caches aren't
+    /// used with a custom parser (see `fontconfig_caches`). `FcFontCache::refresh` and
+    /// `add_memory_font` use the default backend.
+    #[cfg(feature = "scan")]
+    pub fn parser<P: FcMetadataParser + 'static>(&mut self, parser: P) -> &mut Self {
+        self.options.parser = Some(parser::FcParserHandle(
+            std::sync::Arc::new(parser),
+            core::any::type_name::<P>(),
+        ));
+        self
+    }
+
+    /// Additionally adds the fonts of a custom font source (see `FcFontProvider`),
+    /// regardless of `system_fonts` / `user_fonts`
+    pub fn add_provider<P: FcFontProvider + 'static>(&mut self, provider: P) -> &mut Self {
+        self.providers
+            .push(provider::FcProviderHandle(std::sync::Arc::new(provider)));
+        self
+    }
+
+    /// Keeps a record of every scanned directory (modification time, subdirectories and fonts)
+    /// in the given file, so that the next build only parses the fonts of directories that
+    /// changed since (see `FcFontCache::build_cached`)
+    ///
+    /// Files that couldn't be parsed are recorded as well and aren't parsed again until
+    /// they are modified, so a parser panic is only reported in `FcFontCache::skipped_files`
+    /// by the build that first encountered the file. The file is ignored (and every
+    /// directory scanned again) if it was written with other scan options, another `parser`
+    /// or a build of dafont with other parsing features (`woff`, `gzip`, ...).
+    pub fn disk_cache<P: Into<PathBuf>>(&mut self, file: P) -> &mut Self {
+        self.cache_file = Some(file.into());
+        self
+    }
+
+    /// Takes the fonts of up-to-date fontconfig caches (as written by `fc-cache`) instead
+    /// of parsing the font files of the cached directories, on Linux and the BSDs (default:
+    /// false)
+    ///
+    /// This is much faster, but fontconfig doesn't store most of what dafont reads from the
+    /// font files: the fonts only have a family, name, style, weight, slant, spacing and the
+    /// `charset`, `container`, `format`, `strikes` and `style` of `FcPattern::metadata`. They
+    /// don't match queries for `hinted`, `small_caps`, `tabular_figures` or `vendor_ids`,
+    /// and `itemize` can't prefer them for their shaping or CJK support. The caches aren't
+    /// used with a custom `parser`.
+    pub fn fontconfig_caches(&mut self, enabled: bool) -> &mut Self {
+        self.options.fontconfig_caches = enabled;
+        self
+    }
+
+    /// Calls `callback` while the font directories are scanned, so that applications can
+    /// show the progress of the build
+    ///
+    /// The callback is called once per directory and once per parsed font file,
+    /// potentially from multiple threads (but never concurrently).
+    pub fn progress<F: FnMut(FcScanProgress) + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) -> &mut Self {
+        self.progress = Some(FcProgressCallback(std::sync::Arc::new(
+            std::sync::Mutex::new(callback),
+        )));
+        self
+    }
+
+    /// Stops the build as soon as the token is cancelled: no more font files are
+    /// parsed and `build` returns the fonts parsed so far
+    pub fn cancellation(&mut self, token: FcCancellationToken) -> &mut Self {
+        self.options.cancellation = Some(token);
+        self
+    }
+
+    /// Only lists the font files instead of parsing them, files are parsed on demand by
+    /// `FcFontCache::query_lazy` (or `load_family` / `load_all`)
+    ///
+    /// Startup gets much faster for applications that only use a few fonts, but `query`
+    /// and `list` only see the fonts that were loaded so far. Fonts that are read from
+    /// caches (fontconfig or `disk_cache`) are available immediately, the directory
+    /// records of `disk_cache` and `FcFontCache::refresh` aren't updated by lazy builds.
+    pub fn lazy(&mut self, lazy: bool) -> &mut Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Parses the font files on a dedicated pool of `threads` threads instead of rayon's
+    /// global pool (0 = one thread per CPU)
+    #[cfg(feature = "multithreading")]
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = Some(FcScanThreads::Count(threads));
+        self
+    }
+
+    /// Parses the font files on the given pool instead of rayon's global pool, i.e. to
+    /// share a pool with the rest of the application
+    #[cfg(feature = "multithreading")]
+    pub fn thread_pool(&mut self, pool: std::sync::Arc<rayon::ThreadPool>) -> &mut Self {
+        self.threads = Some(FcScanThreads::Pool(pool));
+        self
+    }
+
+    /// Also treats font files with identical contents as the same file (not only paths that
+    /// resolve to the same file), see `FcFontCache::alternate_paths`
+    ///
+    /// Files of the same size are read and hashed at the end of the build.
+    pub fn deduplicate_contents(&mut self, deduplicate_contents: bool) -> &mut Self {
+        self.deduplicate_contents = deduplicate_contents;
+        self
+    }
+
+    /// Skips font files larger than `max_file_size` bytes, reported in
+    /// `FcFontCache::skipped_files` (no limit by default)
+    pub fn max_file_size(&mut self, max_file_size: u64) -> &mut Self {
+        self.options.max_file_size = Some(max_file_size);
+        self
+    }
+
+    /// Skips font collections (TTC / OTC files, .dfont suitcases) with more than `max_faces`
+    /// faces, reported in `FcFontCache::skipped_files` (no limit by default)
+    pub fn max_faces(&mut self, max_faces: usize) -> &mut Self {
+        self.options.max_faces = Some(max_faces);
+        self
+    }
+
+    /// Builds the font cache
+    #[cfg(not(feature = "scan"))]
+    pub fn build(&self) -> FcFontCache {
+        let mut cache = FcFontCache::default();
+        cache.add_provided_fonts(&self.providers, &self.options);
+        cache
+    }
+
+    /// Builds the font cache
+    ///
+    /// NOTE: Performance-intensive, should only be called on startup!
+    #[cfg(feature = "scan")]
+    pub fn build(&self) -> FcFontCache {
+        // only fails if `strict`
+        self.build_with(false).unwrap_or_default()
+    }
+
+    // Builds the font cache, with `strict` a fontconfig configuration that can't be used is
+    // an error instead of falling back to the default font directories, see `try_build`
+    #[cfg(feature = "scan")]
+    fn build_with(&self, strict: bool) -> Result<FcFontCache, FcError> {
+        #[cfg(feature = "multithreading")]
+        {
+            let pool = match self.threads.as_ref() {
+                Some(FcScanThreads::Count(threads)) => rayon::ThreadPoolBuilder::new()
+                    .num_threads(*threads)
+                    .thread_name(|index| alloc::format!("dafont-scan-{}", index))
+                    .build()
+                    .ok()
+                    .map(std::sync::Arc::new),
+                Some(FcScanThreads::Pool(pool)) => Some(pool.clone()),
+                None => None,
+            };
+            // falls back to the global pool if the threads can't be spawned
+            if let Some(pool) = pool {
+                return pool.install(|| self.scan(strict));
+            }
+        }
+
+        self.scan(strict)
+    }
+
+    /// Same as `build`, but fails instead of silently skipping what can't be read
+    ///
+    /// Returns an error if one of the `add_dir` directories can't be read, if the build was
+    /// cancelled, or (on fontconfig platforms, with `system_fonts`) if the fontconfig
+    /// configuration is missing or can't be read / parsed, where `build` falls back to the
+    /// default font directories. Font files that can't be parsed are still only reported
+    /// in `FcFontCache::skipped_files`.
+    pub fn try_build(&self) -> Result<FcFontCache, FcError> {
+        for dir in self.dirs.iter() {
+            std::fs::read_dir(dir).map_err(|error| FcError::io(dir, error))?;
+        }
+
+        #[cfg(feature = "scan")]
+        let cache = self.build_with(true)?;
+        #[cfg(not(feature = "scan"))]
+        let cache = self.build();
+        let cancellation = self.options.cancellation.as_ref();
+        if cancellation.is_some_and(|token| token.is_cancelled()) {
+            return Err(FcError::Cancelled);
+        }
+        Ok(cache)
+    }
+
+    #[cfg(feature = "scan")]
+    fn scan(&self, strict: bool) -> Result<FcFontCache, FcError> {
+        // also collects the scanned directories for `FcFontCache::refresh`
+        let dir_cache = std::sync::Arc::new(match self.cache_file.as_ref() {
+            Some(file) => dircache::FcDirCache::load(file, &self.options),
+            None => dircache::FcDirCache::default(),
+        });
+        let pending_files = match self.lazy {
+            true => Some(std::sync::Arc::new(lazy::FcPendingFiles::default())),
+            false => None,
+        };
+        let skipped_files = std::sync::Arc::new(FcSkippedFiles::default());
+        let options = &FcScanOptions {
+            dir_cache: Some(dir_cache.clone()),
+            pending_files: pending_files.clone(),
+            skipped_files: Some(skipped_files.clone()),
+            progress: self
+                .progress
+                .as_ref()
+                .map(|callback| std::sync::Arc::new(FcProgressReporter::new(callback.clone()))),
+            ..self.options.clone()
+        };
+
+        let mut cache = if options.system_fonts || options.user_fonts {
+            // the platform directories of excluded locations aren't scanned, this filters the
+            // fonts of the platform APIs (DirectWrite, CoreText) and of the fontconfig caches
+            let mut cache = FcFontCache::build_platform(options, strict)?;
+            cache.map.retain(|_, path| {
+                path.path()
+                    .is_none_or(|path| options.accepts_file(path) && options.accepts_location(path))
+            });
+            cache
+        } else {
+            FcFontCache::default()
+        };
+
+        if !self.dirs.is_empty() {
+            let font_dirs = self
+                .dirs
+                .iter()
+                .map(|dir| (None, dir.to_string_lossy().to_string()))
+                .collect::<Vec<_>>();
+            cache
+                .map
+                .extend(FcScanDirectoriesInner(&font_dirs, options));
+        }
+        cache.add_provided_fonts(&self.providers, options);
+
+        cache.skipped_files = skipped_files.take();
+        cache.scan_options = FcBuildOptions::new(options);
+
+        // the directory records of a cancelled or lazy build are incomplete
+        if options.is_cancelled() {
+            return Ok(cache);
+        }
+        if let Some(pending_files) = pending_files {
+            // same filter as for the parsed fonts of the platform directories above
+            cache.pending_files = pending_files
+                .take()
+                .into_iter()
+                .filter(|file| {
+                    options.accepts_location(file)
+                        || self
+                            .dirs
+                            .iter()
+                            .any(|dir| std::path::Path::new(file).starts_with(dir))
+                })
+                .collect();
+            return Ok(cache);
+        }
+
+        cache.scanned_dirs = dir_cache.scanned_dirs();
+        cache.failed_files = dir_cache.failed_files();
+        cache.scanned_files = dircache::FcScannedFiles(cache.map.paths());
+
+        // every path the fonts were found under, the cache only has one path per pattern
+        let mut files = dir_cache.font_files();
+        files.retain(|file| {
+            options.accepts_file(file)
+                && (options.accepts_location(file)
+                    || self
+                        .dirs
+                        .iter()
+                        .any(|dir| std::path::Path::new(file).starts_with(dir)))
+        });
+        files.extend(
+            cache
+                .map
+                .paths()
+                .filter_map(|path| path.path())
+                .map(String::from),
+        );
+        dedupe::FcDeduplicateFiles(&mut cache, files, self.deduplicate_contents);
+
+        if let Some(file) = self.cache_file.as_ref() {
+            // the cache is only an optimization, the next build will scan again
+            let _ = dir_cache.save(file, &self.options);
+        }
+
+        Ok(cache)
+    }
+}
+
+// Families of the platform user interface font, in order of preference
+#[cfg(target_os = "windows")]
+const UI_FONT_FAMILIES: &[&str] = &["Segoe UI", "Tahoma", "Microsoft Sans Serif"];
+// the system font files have hidden names (starting with a dot)
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const UI_FONT_FAMILIES: &[&str] = &[
+    ".AppleSystemUIFont",
+    "System Font",
+    ".SF NS",
+    ".SF NS Text",
+    "SF Pro",
+    "SF Pro Text",
+    "Helvetica Neue",
+    "Lucida Grande",
+    "Helvetica",
+];
+#[cfg(target_os = "android")]
+const UI_FONT_FAMILIES: &[&str] = &["Roboto", "Noto Sans"];
+// fontconfig platforms, only used if there is no "sans-serif" alias
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+const UI_FONT_FAMILIES: &[&str] = &[
+    "DejaVu Sans",
+    "Noto Sans",
+    "Liberation Sans",
+    "Cantarell",
+    "FreeSans",
+];
+
+// Families the platform uses for a generic family, in order of preference
+#[cfg(target_os = "windows")]
+fn FcGenericFamilyDefaults(generic: GenericFamily) -> &'static [&'static str] {
+    match generic {
+        GenericFamily::Serif => &["Times New Roman", "Cambria", "Georgia"],
+        GenericFamily::SansSerif => &["Arial", "Segoe UI", "Tahoma"],
+        GenericFamily::Monospace => &["Consolas", "Cascadia Mono", "Courier New", "Lucida Console"],
+        GenericFamily::Cursive => &["Comic Sans MS", "Segoe Script"],
+        GenericFamily::Fantasy => &["Impact", "Gabriola"],
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn FcGenericFamilyDefaults(generic: GenericFamily) -> &'static [&'static str] {
+    match generic {
+        GenericFamily::Serif => &["Times", "Times New Roman", "New York", "Georgia"],
+        GenericFamily::SansSerif => &["Helvetica", "Helvetica Neue", "Arial"],
+        GenericFamily::Monospace => &["Menlo", "SF Mono", "Monaco", "Courier"],
+        GenericFamily::Cursive => &["Apple Chancery", "Snell Roundhand"],
+        GenericFamily::Fantasy => &["Papyrus", "Chalkduster"],
+    }
+}
+
+#[cfg(target_os = "android")]
+fn FcGenericFamilyDefaults(generic: GenericFamily) -> &'static [&'static str] {
+    match generic {
+        GenericFamily::Serif => &["Noto Serif", "Droid Serif"],
+        GenericFamily::SansSerif => &["Roboto", "Noto Sans"],
+        GenericFamily::Monospace => &["Droid Sans Mono", "Cutive Mono", "Roboto Mono"],
+        GenericFamily::Cursive => &["Dancing Script"],
+        GenericFamily::Fantasy => &[],
+    }
+}
+
+// fontconfig platforms, only used if there is no alias for the generic family
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+fn FcGenericFamilyDefaults(generic: GenericFamily) -> &'static [&'static str] {
+    match generic {
+        GenericFamily::Serif => &[
+            "DejaVu Serif",
+            "Noto Serif",
+            "Liberation Serif",
+            "FreeSerif",
+        ],
+        GenericFamily::SansSerif => &["DejaVu Sans", "Noto Sans", "Liberation Sans", "FreeSans"],
+        GenericFamily::Monospace => &[
+            "DejaVu Sans Mono",
+            "Noto Sans Mono",
+            "Liberation Mono",
+            "FreeMono",
+        ],
+        GenericFamily::Cursive => &["TeX Gyre Chorus", "URW Chancery L"],
+        GenericFamily::Fantasy => &[],
+    }
+}
+
+// Loose BCP 47 comparison: "ja" matches "ja-JP", "zh-Hant" matches "zh-Hant-TW" (case-insensitive)
+fn FcLangMatches(a: &str, b: &str) -> bool {
+    fn is_prefix(prefix: &str, tag: &str) -> bool {
+        tag.len() > prefix.len()
+            && tag.as_bytes()[prefix.len()] == b'-'
+            && tag[..prefix.len()].eq_ignore_ascii_case(prefix)
+    }
+    a.eq_ignore_ascii_case(b) || is_prefix(a, b) || is_prefix(b, a)
+}
+
+// Patterns to query in order when no font has the style of `pattern`: the slant is kept
+// before the weight, italic falls back to oblique (and the other way around) then upright,
+// bold falls back to not bold
+fn FcSynthesisPatterns(pattern: &FcPattern) -> Vec<FcPattern> {
+    let mut slants = vec![(pattern.italic.clone(), pattern.oblique.clone())];
+    if pattern.italic.is_true() {
+        slants.push((PatternMatch::DontCare, PatternMatch::True));
+    } else if pattern.oblique.is_true() {
+        slants.push((PatternMatch::True, PatternMatch::DontCare));
+    }
+    if pattern.italic.is_true() || pattern.oblique.is_true() {
+        // most fonts don't tell whether they are oblique
+        slants.push((PatternMatch::False, PatternMatch::DontCare));
+    }
+
+    let mut weights = vec![pattern.bold.clone()];
+    if pattern.bold.is_true() {
+        weights.push(PatternMatch::False);
+    }
+
+    let mut patterns = Vec::new();
+    for (italic, oblique) in slants {
+        for bold in weights.iter() {
+            patterns.push(FcPattern {
+                italic: italic.clone(),
+                oblique: oblique.clone(),
+                bold: bold.clone(),
+                ..pattern.clone()
+            });
+        }
+    }
+    // the first one is `pattern` itself
+    patterns.remove(0);
+    patterns
+}
+
+impl FcFontCache {
+    /// Adds in-memory font files (see `FcFontSource::Memory`)
+    pub fn with_memory_fonts(&mut self, f: &[(FcPattern, FcFont)]) -> &mut Self {
+        self.map.extend(f.iter().map(|(k, v)| {
+            (
+                k.clone(),
+                FcFontPath {
+                    source: FcFontSource::Memory(v.bytes.as_slice().into()),
+                    font_index: v.font_index,
+                },
+            )
+        }));
+        self
+    }
+
+    /// Adds an in-memory font file, the patterns of its fonts (all faces of a collection)
+    /// are read from the font, same as for font files on disk
+    ///
+    /// Data that isn't a font that can be parsed is ignored.
+    #[cfg(all(feature = "std", feature = "scan"))]
+    pub fn add_memory_font<B: Into<Arc<[u8]>>>(&mut self, bytes: B) -> &mut Self {
+        let bytes: Arc<[u8]> = bytes.into();
+        let source = FcFontSource::Memory(bytes.clone());
+
+        // same as for files, a malformed font that makes the parser panic is ignored
+        let fonts = std::panic::catch_unwind(|| {
+            let bytes = FcUncompressedData(&bytes);
+            let container = FcFontContainer::of(&bytes);
+            match container {
+                FcFontContainer::Type1 => {
+                    return FcSingleFont(type1::FcParseType1(&bytes, None), source.clone());
+                }
+                FcFontContainer::Pcf => {
+                    return FcSingleFont(pcf::FcParsePcf(&bytes), source.clone())
+                }
+                _ => {}
+            }
+            let sfnt = FcSfntData(&bytes);
+            let fonts = FcParseFontFaces(FcFontFaces(&sfnt), &source, &parser::FC_DEFAULT_PARSER);
+            FcWithFileFormat(fonts, container, &sfnt)
+        })
+        .unwrap_or_default();
+        self.map.extend(fonts);
+        self
+    }
+
+    /// Builds a new font cache
+    #[cfg(not(all(feature = "std", feature = "scan")))]
+    pub fn build() -> Self {
+        Self::default()
+    }
+
+    /// Builds a new font cache from all fonts discovered on the system
+    ///
+    /// Use `FcFontCacheBuilder` to control which directories and files are scanned (and
+    /// `FcFontCacheBuilder::fontconfig_caches` to read the fonts from fontconfig's caches).
+    ///
+    /// NOTE: Performance-intensive, should only be called on startup!
+    #[cfg(all(feature = "std", feature = "scan"))]
+    pub fn build() -> Self {
+        FcFontCacheBuilder::new().build()
+    }
+
+    /// Builds a new font cache like `build`, but reuses the fonts of a previous call from an
+    /// on-disk cache for all font directories that haven't changed since
+    ///
+    /// The cache is stored in `$XDG_CACHE_HOME/dafont` (`~/Library/Caches/dafont` on macOS,
+    /// `%LOCALAPPDATA%\dafont` on Windows), see `FcFontCacheBuilder::disk_cache` for a
+    /// custom location. Directories are only rescanned if their modification time changed,
+    /// the same model `fc-cache` uses.
+    #[cfg(all(feature = "std", feature = "scan"))]
+    pub fn build_cached() -> Self {
+        let mut builder = FcFontCacheBuilder::new();
+        if let Some(file) = dircache::FcDefaultDiskCacheFile() {
+            builder.disk_cache(file);
+        }
+        builder.build()
+    }
+
+    /// Builds a new font cache
+    #[cfg(not(all(feature = "std", feature = "scan")))]
+    pub fn build_cached() -> Self {
+        Self::default()
+    }
+
+    /// Returns the font cache of the process, built with `build` on first use
+    ///
+    /// Threads that call `global` while the cache is built wait for the build. Use
+    /// `FcSharedFontCache` for a cache that can be rebuilt later on.
+    #[cfg(feature = "std")]
+    pub fn global() -> &'static FcFontCache {
+        static GLOBAL: std::sync::OnceLock<FcFontCache> = std::sync::OnceLock::new();
+        GLOBAL.get_or_init(FcFontCache::build)
+    }
+
+    // Scans the font locations of the current platform, with `strict` a fontconfig
+    // configuration that can't be used is an error (fontconfig platforms)
+    #[cfg(all(feature = "std", feature = "scan"))]
+    fn build_platform(options: &FcScanOptions, strict: bool) -> Result<Self, FcError> {
+        #[cfg(not(any(
+            target_os = "linux",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly"
+        )))]
+        let _ = strict;
+
+        #[cfg(target_os = "linux")]
+        {
+            // Termux (or a proot distribution inside Termux) keeps its fonts under `$PREFIX`
+            let mut font_dirs = FcTermuxFontDirectories();
+
+            // containers and minimal (i.e. musl) images often don't ship a fontconfig configuration
+            let fontconfig = match FcReadFontconfig() {
+                Ok(fontconfig) => Some(fontconfig),
+                Err(error) if strict && options.system_fonts => return Err(error),
+                Err(_) => None,
+            };
+            let system_dirs = fontconfig
+                .as_ref()
+                .map(|fontconfig| fontconfig.dirs.clone())
+                .unwrap_or_else(FcDefaultFontDirectories);
+
+            // Flatpak / Snap mount the fonts of the host system outside of the usual directories,
+            // Nix / Guix profiles are only listed in `XDG_DATA_DIRS`
+            for dir in system_dirs
+                .into_iter()
+                .chain(FcXdgDataFontDirectories())
+                .chain(FcSandboxFontDirectories())
+            {
+                if !font_dirs.contains(&dir) {
+                    font_dirs.push(dir);
+                }
+            }
+
+            FcRetainLocations(&mut font_dirs, options);
+            let mut fonts = fccache::FcScanDirectoriesCached(&font_dirs, options);
+            if let Some(fontconfig) = fontconfig.as_ref() {
+                fonts.retain(|(_, path)| {
+                    path.path().is_none_or(|path| fontconfig.accepts_file(path))
+                });
+            }
+
+            Ok(FcFontCache {
+                map: fonts.into_iter().collect(),
+                aliases: fontconfig
+                    .map(|fontconfig| fontconfig.aliases())
+                    .unwrap_or_default(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            // DirectWrite sees exactly what Windows applications see, scanning is the fallback
+            #[cfg(feature = "directwrite")]
+            let fonts = directwrite::FcScanDirectWriteFonts(options)
+                .unwrap_or_else(|| windows::FcScanWindowsFonts(options));
+            #[cfg(not(feature = "directwrite"))]
+            let fonts = windows::FcScanWindowsFonts(options);
+
+            Ok(FcFontCache {
+                font_links: windows::FcRegistryFontLinks(&fonts),
+                map: fonts.into_iter().collect(),
+                substitutes: windows::FcRegistrySubstitutes(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            // `/System/Library/Fonts/Supplemental` is covered by the recursive scan
+            let mut font_dirs = vec![
+                (None, "~/Library/Fonts".to_owned()),
+                (None, "/System/Library/Fonts".to_owned()),
+                (None, "/Library/Fonts".to_owned()),
+            ];
+            font_dirs.extend(FcMacOsAssetFontDirs());
+            FcRetainLocations(&mut font_dirs, options);
+
+            // CoreText also knows about fonts activated outside of the font directories
+            #[cfg(feature = "coretext")]
+            let fonts = coretext::FcScanCoreTextFonts(options)
+                .unwrap_or_else(|| FcScanDirectoriesInner(&font_dirs, options));
+            #[cfg(not(feature = "coretext"))]
+            let fonts = FcScanDirectoriesInner(&font_dirs, options);
+
+            Ok(FcFontCache {
+                map: fonts.into_iter().collect(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(any(
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly"
+        ))]
+        {
+            let fontconfig = match FcReadFontconfig() {
+                Ok(fontconfig) => Some(fontconfig),
+                Err(error) if strict && options.system_fonts => return Err(error),
+                Err(_) => None,
+            };
+            let mut font_dirs = fontconfig
+                .as_ref()
+                .map(|fontconfig| fontconfig.dirs.clone())
+                .unwrap_or_default();
+            for dir in BSD_FONT_DIRS {
+                if !font_dirs.iter().any(|(_, d)| d == dir) {
+                    font_dirs.push((None, (*dir).to_owned()));
+                }
+            }
+
+            FcRetainLocations(&mut font_dirs, options);
+            let mut fonts = fccache::FcScanDirectoriesCached(&font_dirs, options);
+            if let Some(fontconfig) = fontconfig.as_ref() {
+                fonts.retain(|(_, path)| {
+                    path.path().is_none_or(|path| fontconfig.accepts_file(path))
+                });
+            }
+
+            Ok(FcFontCache {
+                map: fonts.into_iter().collect(),
+                aliases: fontconfig
+                    .map(|fontconfig| fontconfig.aliases())
+                    .unwrap_or_default(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(target_os = "ios")]
+        {
+            // on-device system fonts are spread across subdirectories (Core, CoreAddition, ...)
+            let mut font_dirs = vec![(None, "/System/Library/Fonts".to_owned())];
+
+            // fonts shipped as resources of the app bundle, which contains the executable
+            if let Some(bundle_dir) = std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(|dir| dir.to_string_lossy().to_string()))
+            {
+                font_dirs.push((None, bundle_dir));
+            }
+            FcRetainLocations(&mut font_dirs, options);
+
+            Ok(FcFontCache {
+                map: FcScanDirectoriesInner(&font_dirs, options)
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(target_os = "haiku")]
+        {
+            let mut font_dirs = vec![
+                (None, "/system/data/fonts".to_owned()),
+                (None, "/system/non-packaged/data/fonts".to_owned()),
+                (None, "~/config/data/fonts".to_owned()),
+                (None, "~/config/non-packaged/data/fonts".to_owned()),
+            ];
+            FcRetainLocations(&mut font_dirs, options);
+
+            Ok(FcFontCache {
+                map: FcScanDirectoriesInner(&font_dirs, options)
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(target_os = "redox")]
+        {
+            // Orbital ships its fonts in `/ui/fonts`, packages install to `/usr/share/fonts`
+            let mut font_dirs = vec![
+                (None, "/ui/fonts".to_owned()),
+                (None, "/usr/share/fonts".to_owned()),
+            ];
+            FcRetainLocations(&mut font_dirs, options);
+
+            Ok(FcFontCache {
+                map: FcScanDirectoriesInner(&font_dirs, options)
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            let (fonts, fallback) = android::FcScanAndroidFonts(options);
+            Ok(FcFontCache {
+                map: fonts.into_iter().collect(),
+                fallback,
+                ..Default::default()
+            })
+        }
+
+        #[cfg(target_os = "wasi")]
+        {
+            let mut font_dirs = WASI_FONT_DIRS
+                .iter()
+                .map(|dir| (None, (*dir).to_owned()))
+                .collect::<Vec<_>>();
+            FcRetainLocations(&mut font_dirs, options);
+
+            Ok(FcFontCache {
+                map: FcScanDirectoriesInner(&font_dirs, options)
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            })
+        }
+
+        #[cfg(all(target_family = "wasm", not(target_os = "wasi")))]
+        {
+            let _ = options;
+            Ok(Self::default())
+        }
     }
-}
 
-impl Default for PatternMatch {
-    fn default() -> Self {
-        PatternMatch::DontCare
+    /// Builds a new font cache from the fonts in the given preopened directories
+    ///
+    /// WASI modules can only see the directories that the runtime has preopened
+    /// (i.e. `wasmtime --dir /host/fonts::/fonts`), pass the guest paths here.
+    /// Directories that aren't preopened are skipped.
+    #[cfg(all(target_os = "wasi", feature = "std", feature = "scan"))]
+    pub fn build_wasi(preopened_dirs: &[&str]) -> Self {
+        let font_dirs = preopened_dirs.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        Self::build_from_dirs(&font_dirs)
     }
-}
 
-#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
-#[repr(C)]
-pub struct FcPattern {
-    // font name
-    pub name: Option<String>,
-    // family name
-    pub family: Option<String>,
-    // "italic" property
-    pub italic: PatternMatch,
-    // "oblique" property
-    pub oblique: PatternMatch,
-    // "bold" property
-    pub bold: PatternMatch,
-    // "monospace" property
-    pub monospace: PatternMatch,
-    // "condensed" property
-    pub condensed: PatternMatch,
-    // font weight
-    pub weight: usize,
-    // start..end unicode range
-    pub unicode_range: [usize; 2],
-}
+    /// Builds a new font cache from the fonts in the given directories (and their subdirectories)
+    /// only, without reading any system configuration or scanning the system font directories
+    #[cfg(all(feature = "std", feature = "scan"))]
+    pub fn build_from_dirs(dirs: &[PathBuf]) -> Self {
+        let mut builder = FcFontCacheBuilder::new();
+        builder.system_fonts(false).user_fonts(false);
+        for dir in dirs {
+            builder.add_dir(dir);
+        }
+        builder.build()
+    }
 
-#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
-#[repr(C)]
-pub struct FcFontPath {
-    pub path: String,
-    pub font_index: usize,
-}
+    /// Returns the font files that were skipped during the build because they exceeded
+    /// the limits of `FcFontCacheBuilder` (`max_file_size`, `max_faces`) or couldn't be parsed
+    pub fn skipped_files(&self) -> &[FcSkippedFile] {
+        &self.skipped_files
+    }
 
-/// Represent an in-memory font file
-#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
-#[repr(C)]
-pub struct FcFont {
-    pub bytes: Vec<u8>,
-    pub font_index: usize,
-}
+    /// Returns the other paths of a font file, i.e. through symlinked or bind-mounted
+    /// font directories
+    ///
+    /// Fonts are only listed and returned by queries under one path per file, the first
+    /// one in sorted order.
+    pub fn alternate_paths(&self, path: &str) -> &[String] {
+        self.alternate_paths.get(path).map_or(&[], Vec::as_slice)
+    }
 
-#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
-pub struct FcFontCache {
-    map: BTreeMap<FcPattern, FcFontPath>,
-}
+    /// Remembers the results of the last `capacity` distinct patterns passed to `query`,
+    /// so that repeated queries (i.e. during text layout) don't filter the fonts again
+    ///
+    /// The results are forgotten whenever fonts are added or removed. Disabled (0) by default.
+    #[cfg(feature = "std")]
+    pub fn memoize_queries(&mut self, capacity: usize) -> &mut Self {
+        self.map.set_memo_capacity(capacity);
+        self
+    }
 
-impl FcFontCache {
-    /// Adds in-memory font files (`path` will be base64 encoded)
-    pub fn with_memory_fonts(&mut self, f: &[(FcPattern, FcFont)]) -> &mut Self {
-        use base64::{engine::general_purpose::URL_SAFE, Engine as _};
-        self.map.extend(f.iter().map(|(k, v)| {
-            (
-                k.clone(),
-                FcFontPath {
-                    path: {
-                        let mut s = String::from("base64:");
-                        s.push_str(&URL_SAFE.encode(&v.bytes));
-                        s
-                    },
-                    font_index: v.font_index,
-                },
-            )
-        }));
+    /// Replaces the index of `query_for_char` by Bloom filters of the characters of the
+    /// fonts, for memory-constrained targets with many fonts
+    ///
+    /// A filter takes about 10 bits per page of 256 code points that a font has characters
+    /// in, instead of the 4 bytes per page of the default index (the charsets of the
+    /// patterns are kept either way). `query_for_char`, `itemize` and
+    /// `query_fallback_for_codepoint` check the filter of a font before its charset, which
+    /// rejects the fonts without characters in the page of the character except for about
+    /// 1% of them. Disabled by default, kept when fonts are added or removed.
+    pub fn use_coverage_filters(&mut self, enabled: bool) -> &mut Self {
+        self.map.set_coverage_filters(enabled);
         self
     }
 
-    /// Builds a new font cache
-    #[cfg(not(all(feature = "std", feature = "parsing")))]
-    pub fn build() -> Self {
-        Self::default()
+    /// Returns the list of fonts and font patterns, sorted by pattern
+    pub fn list(&self) -> &[(FcPattern, FcFontPath)] {
+        self.map.entries()
     }
 
-    /// Builds a new font cache from all fonts discovered on the system
+    /// Only keeps the fonts for which `keep` returns true, i.e. to restrict the cache to
+    /// monospace fonts before handing it to a terminal
     ///
-    /// NOTE: Performance-intensive, should only be called on startup!
-    #[cfg(all(feature = "std", feature = "parsing"))]
-    pub fn build() -> Self {
-        #[cfg(target_os = "linux")]
-        {
-            FcFontCache {
-                map: FcScanDirectories()
-                    .unwrap_or_default()
-                    .into_iter()
-                    .collect(),
+    /// Removed fonts are removed from the fallback order and the font links as well.
+    pub fn retain<F: FnMut(&FcPattern, &FcFontPath) -> bool>(&mut self, mut keep: F) -> &mut Self {
+        let mut removed = alloc::collections::btree_set::BTreeSet::new();
+        self.map.retain(|pattern, path| {
+            let kept = keep(pattern, path);
+            if !kept {
+                removed.insert(path.clone());
             }
+            kept
+        });
+        // the same face can be kept under another pattern
+        for path in self.map.paths() {
+            removed.remove(path);
         }
-
-        #[cfg(target_os = "windows")]
-        {
-            // `~` isn't actually valid on Windows, but it will be converted by `process_path`
-            let font_dirs = vec![
-                (None, "C:\\Windows\\Fonts\\".to_owned()),
-                (
-                    None,
-                    "~\\AppData\\Local\\Microsoft\\Windows\\Fonts\\".to_owned(),
-                ),
-            ];
-            FcFontCache {
-                map: FcScanDirectoriesInner(&font_dirs).into_iter().collect(),
-            }
+        if removed.is_empty() {
+            return self;
         }
 
-        #[cfg(target_os = "macos")]
-        {
-            let font_dirs = vec![
-                (None, "~/Library/Fonts".to_owned()),
-                (None, "/System/Library/Fonts".to_owned()),
-                (None, "/Library/Fonts".to_owned()),
-            ];
-            FcFontCache {
-                map: FcScanDirectoriesInner(&font_dirs).into_iter().collect(),
-            }
+        for family in self.fallback.iter_mut() {
+            family.fonts.retain(|font| !removed.contains(font));
         }
+        self.fallback.retain(|family| !family.fonts.is_empty());
 
-        #[cfg(target_family = "wasm")]
-        {
-            Self::default()
+        for fonts in self.font_links.values_mut() {
+            fonts.retain(|font| !removed.contains(font));
         }
+        self.font_links.retain(|_, fonts| !fonts.is_empty());
+
+        self
     }
 
-    /// Returns the list of fonts and font patterns
-    pub fn list(&self) -> &BTreeMap<FcPattern, FcFontPath> {
-        &self.map
+    /// Same as `retain`, but returns a restricted copy of the cache
+    pub fn filtered<F: FnMut(&FcPattern, &FcFontPath) -> bool>(&self, keep: F) -> FcFontCache {
+        let mut cache = self.clone();
+        cache.retain(keep);
+        cache
     }
 
     fn query_matches_internal(k: &FcPattern, pattern: &FcPattern) -> bool {
@@ -187,6 +2015,10 @@ impl FcFontCache {
         let oblique_needs_to_match = pattern.oblique.needs_to_match();
         let bold_needs_to_match = pattern.bold.needs_to_match();
         let monospace_needs_to_match = pattern.monospace.needs_to_match();
+        let scalable_needs_to_match = pattern.scalable.needs_to_match();
+        let hinted_needs_to_match = pattern.hinted.needs_to_match();
+        let small_caps_needs_to_match = pattern.small_caps.needs_to_match();
+        let tabular_figures_needs_to_match = pattern.tabular_figures.needs_to_match();
 
         let name_matches = k.name == pattern.name;
         let family_matches = k.family == pattern.family;
@@ -194,6 +2026,10 @@ impl FcFontCache {
         let oblique_matches = k.oblique == pattern.oblique;
         let bold_matches = k.bold == pattern.bold;
         let monospace_matches = k.monospace == pattern.monospace;
+        let scalable_matches = k.scalable == pattern.scalable;
+        let hinted_matches = k.hinted == pattern.hinted;
+        let small_caps_matches = k.small_caps == pattern.small_caps;
+        let tabular_figures_matches = k.tabular_figures == pattern.tabular_figures;
 
         if name_needs_to_match && !name_matches {
             return false;
@@ -227,24 +2063,352 @@ impl FcFontCache {
             return false;
         }
 
+        if scalable_needs_to_match && !scalable_matches {
+            return false;
+        }
+
+        if hinted_needs_to_match && !hinted_matches {
+            return false;
+        }
+
+        if small_caps_needs_to_match && !small_caps_matches {
+            return false;
+        }
+
+        if tabular_figures_needs_to_match && !tabular_figures_matches {
+            return false;
+        }
+
+        if let Some(vendor_ids) = pattern.vendor_ids.as_ref() {
+            let vendor_matches = k.metadata.vendor_id.as_ref().is_some_and(|vendor_id| {
+                vendor_ids
+                    .iter()
+                    .any(|v| v.trim_end() == vendor_id.as_str())
+            });
+            if !vendor_matches {
+                return false;
+            }
+        }
+
+        if let Some(formats) = pattern.formats.as_ref() {
+            if !formats.contains(&k.metadata.format) {
+                return false;
+            }
+        }
+
         true
     }
 
+    /// Registers `substitute` as the family to use when a query for `family` has no results
+    /// (family names are compared case-insensitively)
+    pub fn add_substitute(&mut self, family: &str, substitute: &str) -> &mut Self {
+        self.substitutes
+            .insert(family.to_lowercase(), substitute.to_owned());
+        self
+    }
+
+    /// Returns the substitute family for `family`, if any
+    pub fn substitute(&self, family: &str) -> Option<&str> {
+        self.substitutes
+            .get(&family.to_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Registers fonts that should be used (in order) when a font of `family` lacks a glyph
+    pub fn add_font_link(&mut self, family: &str, fonts: &[FcFontPath]) -> &mut Self {
+        self.font_links
+            .entry(family.to_lowercase())
+            .or_default()
+            .extend(fonts.iter().cloned());
+        self
+    }
+
+    /// Returns the fonts linked to `family` (empty if there are none)
+    pub fn font_links(&self, family: &str) -> &[FcFontPath] {
+        self.font_links
+            .get(&family.to_lowercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Registers families that should be preferred (in order) when querying `family`,
+    /// usually for generic families like "sans-serif" (family names are compared case-insensitively)
+    pub fn add_alias(&mut self, family: &str, preferred: &[&str]) -> &mut Self {
+        self.aliases
+            .entry(family.to_lowercase())
+            .or_default()
+            .extend(preferred.iter().map(|f| (*f).to_owned()));
+        self
+    }
+
+    /// Returns the families preferred for `family` (empty if there are none)
+    pub fn aliases(&self, family: &str) -> &[String] {
+        self.aliases
+            .get(&family.to_lowercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the font that the platform uses for its user interface: Segoe UI on Windows,
+    /// the system font (San Francisco) on macOS / iOS, Roboto on Android and the
+    /// fontconfig "sans-serif" default elsewhere
+    pub fn default_ui_font(&self) -> Option<&FcFontPath> {
+        self.query_generic_family(GenericFamily::SansSerif, UI_FONT_FAMILIES)
+    }
+
+    /// Returns the platform default font for a generic family, i.e. Consolas (Windows),
+    /// Menlo (macOS) or the fontconfig "monospace" default (DejaVu Sans Mono) for `Monospace`
+    pub fn default_font(&self, generic: GenericFamily) -> Option<&FcFontPath> {
+        self.query_generic_family(generic, FcGenericFamilyDefaults(generic))
+    }
+
+    // Resolves a generic family: the configured aliases (fontconfig) come first, then fonts
+    // registered under the generic name itself (Android), then the platform defaults
+    fn query_generic_family(
+        &self,
+        generic: GenericFamily,
+        platform_families: &[&str],
+    ) -> Option<&FcFontPath> {
+        let generic = generic.as_str();
+
+        let families = self
+            .aliases(generic)
+            .iter()
+            .map(String::as_str)
+            .chain(core::iter::once(generic))
+            .chain(platform_families.iter().copied());
+
+        self.query_first_family(families)
+    }
+
+    // Returns the upright, non-bold face of the first family that is installed
+    fn query_first_family<'a, I>(&self, families: I) -> Option<&FcFontPath>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        families.into_iter().find_map(|family| {
+            self.query(&FcPattern {
+                family: Some(family.to_owned()),
+                italic: PatternMatch::False,
+                bold: PatternMatch::False,
+                ..Default::default()
+            })
+        })
+    }
+
+    // Replaces the name / family of the pattern with their substitutes, returns `None` if there are none
+    fn substitute_pattern(&self, pattern: &FcPattern) -> Option<FcPattern> {
+        if self.substitutes.is_empty() {
+            return None;
+        }
+
+        let name = pattern.name.as_deref().and_then(|n| self.substitute(n));
+        let family = pattern.family.as_deref().and_then(|f| self.substitute(f));
+
+        if name.is_none() && family.is_none() {
+            return None;
+        }
+
+        let mut substituted = pattern.clone();
+        if let Some(name) = name {
+            substituted.name = Some(name.to_owned());
+        }
+        if let Some(family) = family {
+            substituted.family = Some(family.to_owned());
+        }
+        Some(substituted)
+    }
+
     /// Queries a font from the in-memory `font -> file` mapping, returns all matching fonts
     pub fn query_all(&self, pattern: &FcPattern) -> Vec<&FcFontPath> {
+        let results = self.query_all_internal(pattern);
+        if !results.is_empty() {
+            return results;
+        }
+
+        match self.substitute_pattern(pattern) {
+            Some(substituted) => self.query_all_internal(&substituted),
+            None => results,
+        }
+    }
+
+    fn query_all_internal(&self, pattern: &FcPattern) -> Vec<&FcFontPath> {
+        self.map.filter(pattern)
+    }
+
+    /// Same as `query_all`, but also returns the patterns of the fonts
+    pub fn query_all_entries(&self, pattern: &FcPattern) -> Vec<&(FcPattern, FcFontPath)> {
+        let results = self.map.filter_entries(pattern);
+        if !results.is_empty() {
+            return results;
+        }
+
+        match self.substitute_pattern(pattern) {
+            Some(substituted) => self.map.filter_entries(&substituted),
+            None => results,
+        }
+    }
+
+    /// Returns the fonts matching the pattern that support a character, in the order of
+    /// `query_all` (`FcPattern::default()` for all fonts with the character)
+    ///
+    /// The fonts are looked up in an index of the `charset` of every font that is built
+    /// with the cache (and on every `refresh` / `merge`), so the cost only depends on the
+    /// number of fonts with characters near `c`, not on the size of the cache.
+    pub fn query_for_char(&self, pattern: &FcPattern, c: char) -> Vec<&FcFontPath> {
         self.map
-            .iter() // TODO: par_iter!
+            .with_char(c)
             .filter(|(k, _)| Self::query_matches_internal(k, pattern))
-            .map(|(_, v)| v)
+            .map(|(_, path)| path)
             .collect()
     }
 
     /// Queries a font from the in-memory `font -> file` mapping, returns the first found font (early return)
     pub fn query(&self, pattern: &FcPattern) -> Option<&FcFontPath> {
+        self.query_entry(pattern).map(|(_, path)| path)
+    }
+
+    /// Same as `query`, but returns an owned copy of the result (including the pattern of
+    /// the font), so that it can outlive the borrow of the cache
+    ///
+    /// If no font has the bold / italic / oblique style the pattern asks for, the closest
+    /// style of the same fonts is returned instead (the slant is kept before the weight,
+    /// same as fontconfig) and `needs_synthetic_bold` / `needs_synthetic_oblique` tell
+    /// what the renderer has to synthesize.
+    pub fn query_owned(&self, pattern: &FcPattern) -> Option<FcFontMatch> {
+        let (font, path) = self.query_entry(pattern).or_else(|| {
+            FcSynthesisPatterns(pattern)
+                .iter()
+                .find_map(|pattern| self.query_entry(pattern))
+        })?;
+
+        let slanted = |p: &FcPattern| p.italic.is_true() || p.oblique.is_true();
+        let needs_synthetic_oblique = slanted(pattern) && !slanted(font);
+        Some(FcFontMatch {
+            pattern: font.clone(),
+            index: path.font_index,
+            source: path.source.clone(),
+            needs_synthetic_bold: pattern.bold.is_true() && !font.bold.is_true(),
+            needs_synthetic_oblique,
+            oblique_angle: match needs_synthetic_oblique {
+                true => self.synthetic_oblique_angle(font),
+                false => 0,
+            },
+        })
+    }
+
+    // Clockwise slant of the first italic / oblique face of the family of `font` that leans
+    // to the right (16.16 fixed point), 12° if there is none. Only the recorded angles are
+    // used, the patterns of fontconfig caches don't have one.
+    fn synthetic_oblique_angle(&self, font: &FcPattern) -> i32 {
+        const DEFAULT_OBLIQUE_ANGLE: i32 = 12 << 16;
+
+        let family = FcPattern {
+            family: font.family.clone(),
+            ..Default::default()
+        };
+        if family.family.is_none() {
+            return DEFAULT_OBLIQUE_ANGLE;
+        }
         self.map
-            .iter() // TODO: par_iter!
-            .find(|(k, _)| Self::query_matches_internal(k, pattern))
-            .map(|(_, v)| v)
+            .filter_entries(&family)
+            .iter()
+            .filter(|(sibling, _)| sibling.italic.is_true() || sibling.oblique.is_true())
+            .map(|(sibling, _)| sibling.metadata.italic_angle)
+            .find(|angle| *angle < 0)
+            .map_or(DEFAULT_OBLIQUE_ANGLE, |angle| angle.saturating_neg())
+    }
+
+    fn query_entry(&self, pattern: &FcPattern) -> Option<&(FcPattern, FcFontPath)> {
+        self.map.find(pattern).or_else(|| {
+            self.substitute_pattern(pattern)
+                .and_then(|substituted| self.map.find(&substituted))
+        })
+    }
+
+    /// Same as `query`, but returns `FcError::NoMatch` instead of `None`
+    pub fn try_query(&self, pattern: &FcPattern) -> Result<&FcFontPath, FcError> {
+        self.query(pattern).ok_or(FcError::NoMatch)
+    }
+
+    /// Returns the platform fallback order (empty on platforms that don't define one)
+    pub fn fallback_families(&self) -> &[FcFallbackFamily] {
+        &self.fallback
+    }
+
+    /// Queries all fonts matching the pattern, followed by the platform fallback fonts
+    /// (similar to fontconfig's `FcFontSort`)
+    ///
+    /// Fonts linked to the family / name of the pattern come first, then the fallback
+    /// families for `lang`, then language-independent families, then the remaining families,
+    /// each in platform order. Fonts are only returned once, linked and fallback fonts are
+    /// restricted to the `formats` and `hinted` property of the pattern as well.
+    pub fn query_sorted(&self, pattern: &FcPattern, lang: Option<&str>) -> Vec<&FcFontPath> {
+        let mut results = self.query_all(pattern);
+        // a set instead of `results.contains` (quadratic with many fallback fonts), a
+        // `BTreeSet` as this is available without `std`
+        let mut seen = results
+            .iter()
+            .copied()
+            .collect::<alloc::collections::btree_set::BTreeSet<_>>();
+        for font in self.fallback_order(pattern, lang) {
+            if seen.insert(font) {
+                results.push(font);
+            }
+        }
+        results
+    }
+
+    // Fonts linked to the family / name of the pattern, then the platform fallback fonts for
+    // `lang` (see `query_sorted`), fonts can appear more than once
+    pub(crate) fn fallback_order(
+        &self,
+        pattern: &FcPattern,
+        lang: Option<&str>,
+    ) -> Vec<&FcFontPath> {
+        let mut results = Vec::new();
+
+        let constraints_match = |font: &FcFontPath| {
+            if pattern.formats.is_none() && !pattern.hinted.needs_to_match() {
+                return true;
+            }
+            self.map.with_path(font).any(|(k, _)| {
+                let formats = pattern.formats.as_ref();
+                (!pattern.hinted.needs_to_match() || k.hinted == pattern.hinted)
+                    && formats.is_none_or(|formats| formats.contains(&k.metadata.format))
+            })
+        };
+
+        let linked_families = [pattern.family.as_deref(), pattern.name.as_deref()];
+        for family in linked_families.iter().flatten() {
+            for font in self.font_links(family) {
+                if constraints_match(font) {
+                    results.push(font);
+                }
+            }
+        }
+
+        let lang_families = self
+            .fallback
+            .iter()
+            .filter(|f| lang.is_some_and(|lang| f.supports_lang(lang)));
+        let default_families = self.fallback.iter().filter(|f| f.langs.is_empty());
+        let other_families = self
+            .fallback
+            .iter()
+            .filter(|f| !f.langs.is_empty() && !lang.is_some_and(|lang| f.supports_lang(lang)));
+
+        for font in lang_families
+            .chain(default_families)
+            .chain(other_families)
+            .flat_map(|f| f.fonts.iter())
+        {
+            if constraints_match(font) {
+                results.push(font);
+            }
+        }
+
+        results
     }
 }
 
@@ -306,120 +2470,487 @@ fn process_path(
         }
     }
 
-    // Resolve prefix values
-    match prefix {
-        Some(prefix) => match prefix.as_str() {
-            PREFIX_CWD | PREFIX_DEFAULT => {
-                let mut new_path = PathBuf::from(CWD_PATH);
-                new_path.push(path);
+    // Resolve prefix values
+    match prefix {
+        Some(prefix) => match prefix.as_str() {
+            PREFIX_CWD | PREFIX_DEFAULT => {
+                let mut new_path = PathBuf::from(CWD_PATH);
+                new_path.push(path);
+
+                Some(new_path)
+            }
+            PREFIX_XDG => {
+                if is_include_path {
+                    get_xdg_config_home_value()
+                        .map(|xdg_config_home_path| xdg_config_home_path.join(path))
+                } else {
+                    get_xdg_data_home_value()
+                        .map(|xdg_data_home_path| xdg_data_home_path.join(path))
+                }
+            }
+            _ => None, // Unsupported prefix
+        },
+        None => Some(path),
+    }
+}
+
+// Fonts that macOS downloads on demand ("Songti SC", ...) are stored as mobile assets, i.e.
+// `/System/Library/AssetsV2/com_apple_MobileAsset_Font7/<hash>.asset/AssetData/*.ttc`
+#[cfg(all(target_os = "macos", feature = "std", feature = "scan"))]
+fn FcMacOsAssetFontDirs() -> Vec<(Option<String>, String)> {
+    const ASSET_DIRS: &[&str] = &["/System/Library/AssetsV2", "/System/Library/Assets"];
+    const FONT_ASSET_PREFIX: &str = "com_apple_MobileAsset_Font";
+
+    ASSET_DIRS
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(FONT_ASSET_PREFIX)
+        })
+        .map(|entry| (None, entry.path().to_string_lossy().to_string()))
+        .collect()
+}
+
+// Locations of the main fontconfig configuration file, the first one that exists is used
+#[cfg(all(feature = "std", feature = "scan"))]
+#[cfg(not(any(
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+)))]
+const FONTCONFIG_PATHS: &[&str] = &["/etc/fonts/fonts.conf"];
+
+// BSDs install fontconfig from ports / pkgsrc or as part of X11 (xenocara on OpenBSD)
+#[cfg(all(feature = "std", feature = "scan"))]
+#[cfg(any(
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+const FONTCONFIG_PATHS: &[&str] = &[
+    "/usr/local/etc/fonts/fonts.conf",
+    "/usr/pkg/etc/fonts/fonts.conf",
+    "/usr/X11R7/etc/fonts/fonts.conf",
+    "/usr/X11R6/etc/fonts/fonts.conf",
+    "/etc/fonts/fonts.conf",
+];
+
+// Default font directories on BSDs, scanned in addition to the ones from fonts.conf
+#[cfg(all(feature = "std", feature = "scan"))]
+#[cfg(any(
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+const BSD_FONT_DIRS: &[&str] = &[
+    "/usr/local/share/fonts",
+    "/usr/pkg/share/fonts",
+    "/usr/X11R7/lib/X11/fonts",
+    "/usr/X11R6/lib/X11/fonts",
+    "~/.fonts",
+    "~/.local/share/fonts",
+];
+
+// Guest paths that `build()` tries on WASI, a runtime usually preopens host fonts at one of these
+#[cfg(all(target_os = "wasi", feature = "std", feature = "scan"))]
+const WASI_FONT_DIRS: &[&str] = &["/fonts", "/usr/share/fonts", "/usr/local/share/fonts"];
+
+// Font directories to scan if there is no fontconfig configuration (same defaults as fontconfig)
+#[cfg(all(target_os = "linux", feature = "std", feature = "scan"))]
+fn FcDefaultFontDirectories() -> Vec<(Option<String>, String)> {
+    vec![
+        (None, "/usr/share/fonts".to_owned()),
+        (None, "/usr/local/share/fonts".to_owned()),
+        (None, "~/.fonts".to_owned()),
+        // $XDG_DATA_HOME/fonts
+        (Some("xdg".to_owned()), "fonts".to_owned()),
+    ]
+}
+
+// `$XDG_DATA_DIRS/fonts` for every directory in `XDG_DATA_DIRS`,
+// which defaults to `/usr/local/share:/usr/share` (see the XDG Base Directory Specification)
+#[cfg(all(target_os = "linux", feature = "std", feature = "scan"))]
+fn FcXdgDataFontDirectories() -> Vec<(Option<String>, String)> {
+    const XDG_DATA_DIRS_DEFAULT: &str = "/usr/local/share:/usr/share";
+
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| XDG_DATA_DIRS_DEFAULT.to_owned());
+
+    data_dirs
+        .split(':')
+        .map(|dir| dir.trim_end_matches('/'))
+        // relative paths are invalid and have to be ignored according to the specification
+        .filter(|dir| dir.starts_with('/'))
+        .map(|dir| (None, format!("{}/fonts", dir)))
+        .collect()
+}
+
+// Host font directories that are mounted into a Flatpak or Snap sandbox, empty outside of a sandbox
+#[cfg(all(target_os = "linux", feature = "std", feature = "scan"))]
+fn FcSandboxFontDirectories() -> Vec<(Option<String>, String)> {
+    const FLATPAK_FONT_DIRS: &[&str] = &[
+        "/run/host/fonts",
+        "/run/host/local-fonts",
+        "/run/host/user-fonts",
+    ];
+    const SNAP_FONT_DIRS: &[&str] = &[
+        "/var/lib/snapd/hostfs/usr/share/fonts",
+        "/var/lib/snapd/hostfs/usr/local/share/fonts",
+    ];
+
+    let is_flatpak =
+        std::env::var_os("FLATPAK_ID").is_some() || std::path::Path::new("/.flatpak-info").exists();
+    let is_snap = std::env::var_os("SNAP").is_some();
+
+    let mut dirs: Vec<&str> = Vec::new();
+    if is_flatpak {
+        dirs.extend(FLATPAK_FONT_DIRS);
+    }
+    if is_snap {
+        dirs.extend(SNAP_FONT_DIRS);
+    }
+
+    dirs.into_iter()
+        .filter(|dir| std::path::Path::new(dir).is_dir())
+        .map(|dir| (None, dir.to_owned()))
+        .collect()
+}
+
+// Font directories of a Termux installation: `$PREFIX/share/fonts` and the directories declared
+// in `$PREFIX/etc/fonts/fonts.conf`, empty outside of Termux
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    feature = "std",
+    feature = "scan"
+))]
+fn FcTermuxFontDirectories() -> Vec<(Option<String>, String)> {
+    use std::env::var;
+
+    // `PREFIX` alone is too common (build scripts, package managers) to mean Termux
+    let prefix = match var("PREFIX") {
+        Ok(prefix) if var("TERMUX_VERSION").is_ok() || prefix.contains("/com.termux/") => {
+            PathBuf::from(prefix)
+        }
+        _ => return Vec::new(),
+    };
+
+    let mut font_dirs = vec![(
+        None,
+        prefix.join("share/fonts").to_string_lossy().to_string(),
+    )];
+    let termux_dirs = FcReadFontconfigFrom(prefix.join("etc/fonts/fonts.conf"))
+        .map(|fontconfig| fontconfig.dirs)
+        .unwrap_or_default();
+    for dir in termux_dirs {
+        if !font_dirs.contains(&dir) {
+            font_dirs.push(dir);
+        }
+    }
+
+    font_dirs
+}
+
+// Candidates for the main fontconfig configuration file, in order of preference
+//
+// Same as fontconfig: `FONTCONFIG_FILE` replaces the file name (`fonts.conf`) or the whole path
+// if it is absolute, relative names are searched in the colon-separated `FONTCONFIG_PATH`
+// directories first, then in the default configuration directories
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcFontconfigFileCandidates() -> Vec<PathBuf> {
+    use std::env::var;
+    use std::path::Path;
+
+    const FONTCONFIG_FILE_ENV_VAR: &str = "FONTCONFIG_FILE";
+    const FONTCONFIG_PATH_ENV_VAR: &str = "FONTCONFIG_PATH";
+
+    let config_file = var(FONTCONFIG_FILE_ENV_VAR)
+        .ok()
+        .filter(|file| !file.is_empty());
+
+    let config_file = match config_file {
+        Some(file) if Path::new(&file).is_absolute() || file.starts_with('~') => {
+            return vec![PathBuf::from(file)];
+        }
+        Some(file) => Some(file),
+        None => None,
+    };
+
+    let config_dirs = var(FONTCONFIG_PATH_ENV_VAR)
+        .ok()
+        .map(|dirs| {
+            dirs.split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut candidates = Vec::new();
+    if let Some(file) = config_file.as_ref() {
+        candidates.extend(config_dirs.iter().map(|dir| dir.join(file)));
+        candidates.extend(
+            FONTCONFIG_PATHS
+                .iter()
+                .filter_map(|path| Path::new(path).parent())
+                .map(|dir| dir.join(file)),
+        );
+    } else {
+        const DEFAULT_FONTCONFIG_FILE: &str = "fonts.conf";
+        candidates.extend(
+            config_dirs
+                .iter()
+                .map(|dir| dir.join(DEFAULT_FONTCONFIG_FILE)),
+        );
+        candidates.extend(FONTCONFIG_PATHS.iter().map(PathBuf::from));
+    }
+
+    candidates
+}
 
-                Some(new_path)
-            }
-            PREFIX_XDG => {
-                if is_include_path {
-                    get_xdg_config_home_value()
-                        .map(|xdg_config_home_path| xdg_config_home_path.join(path))
-                } else {
-                    get_xdg_data_home_value()
-                        .map(|xdg_data_home_path| xdg_data_home_path.join(path))
+// Settings read from the fontconfig configuration
+#[cfg(all(feature = "std", feature = "scan"))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct FcFontconfig {
+    // font directories (`<dir>`), as `(prefix, path)`
+    dirs: Vec<(Option<String>, String)>,
+    // file name globs of `<selectfont><acceptfont>`, these override `reject_globs`
+    accept_globs: Vec<String>,
+    // file name globs of `<selectfont><rejectfont>`
+    reject_globs: Vec<String>,
+    // lowercase family name -> `<alias><prefer>` families, in configuration order
+    alias_prefer: BTreeMap<String, Vec<String>>,
+    // lowercase family name -> `<alias><accept>` families, in configuration order
+    alias_accept: BTreeMap<String, Vec<String>>,
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl FcFontconfig {
+    // Returns whether a font file isn't excluded by the `<selectfont>` rules
+    // (same as fontconfig's `FcConfigAcceptFilename`)
+    fn accepts_file(&self, path: &str) -> bool {
+        let matches_any = |globs: &[String]| globs.iter().any(|glob| FcGlobMatches(glob, path));
+        matches_any(&self.accept_globs) || !matches_any(&self.reject_globs)
+    }
+
+    // Lowercase family name -> preferred families (`<prefer>` before `<accept>` families),
+    // `<default>` families are not included since they describe the reverse direction
+    fn aliases(&self) -> BTreeMap<String, Vec<String>> {
+        let mut aliases = self.alias_prefer.clone();
+        for (family, accept) in self.alias_accept.iter() {
+            let preferred = aliases.entry(family.clone()).or_default();
+            for f in accept {
+                if !preferred.contains(f) {
+                    preferred.push(f.clone());
                 }
             }
-            _ => None, // Unsupported prefix
-        },
-        None => Some(path),
+        }
+        aliases
     }
 }
 
-#[cfg(all(feature = "std", feature = "parsing"))]
-fn FcScanDirectories() -> Option<Vec<(FcPattern, FcFontPath)>> {
-    use std::fs;
-    use std::path::Path;
+// Shell-style glob match (`*` matches any sequence including `/`, `?` matches one character),
+// same as fontconfig's `FcStrGlobMatch`
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcGlobMatches(glob: &str, s: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let s = s.chars().collect::<Vec<_>>();
+
+    let (mut g, mut i) = (0, 0);
+    // position of the last `*` in `glob` and the position in `s` it is currently matched up to
+    let mut backtrack = None;
+
+    while i < s.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, i));
+                g += 1;
+            }
+            Some(c) if *c == '?' || *c == s[i] => {
+                g += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    g = star + 1;
+                    i = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
 
-    const BASE_FONTCONFIG_PATH: &str = "/etc/fonts/fonts.conf";
+    glob[g..].iter().all(|c| *c == '*')
+}
 
-    if !Path::new(BASE_FONTCONFIG_PATH).exists() {
-        return None;
+// Walks the fontconfig configuration, starting at the first existing candidate
+// of `FcFontconfigFileCandidates`, the error tells why the configuration can't be used
+#[cfg(all(
+    any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ),
+    feature = "std",
+    feature = "scan"
+))]
+fn FcReadFontconfig() -> Result<FcFontconfig, FcError> {
+    let base_fontconfig_path = FcFontconfigFileCandidates()
+        .into_iter()
+        .filter_map(|path| process_path(&None, path, true))
+        .find(|path| path.exists())
+        .ok_or(FcError::NoFontConfig)?;
+
+    if let Err(error) =
+        std::fs::metadata(&base_fontconfig_path).and_then(|metadata| match metadata.is_file() {
+            true => std::fs::read(&base_fontconfig_path).map(|_| ()),
+            false => std::fs::read_dir(&base_fontconfig_path).map(|_| ()),
+        })
+    {
+        return Err(FcError::io(&base_fontconfig_path, error));
     }
 
-    let mut font_paths = Vec::with_capacity(32);
-    let mut paths_to_visit = vec![(None, PathBuf::from(BASE_FONTCONFIG_PATH))];
+    FcReadFontconfigFrom(base_fontconfig_path.clone()).ok_or_else(|| FcError::Parse {
+        path: base_fontconfig_path.to_string_lossy().to_string(),
+    })
+}
 
-    while let Some((prefix, mut path_to_visit)) = paths_to_visit.pop() {
-        path_to_visit = match process_path(&prefix, path_to_visit, true) {
-            Some(path) => path,
-            None => continue,
-        };
+// Walks the fontconfig configuration starting at `base_fontconfig_path`,
+// returns `None` if it doesn't declare any font directories
+//
+// Same as fontconfig, the configuration is invalid (and the defaults are used instead)
+// if it can't be parsed or if an `<include>` without `ignore_missing="yes"` is missing
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcReadFontconfigFrom(base_fontconfig_path: PathBuf) -> Option<FcFontconfig> {
+    let mut fontconfig = FcFontconfig::default();
+    let mut visited = Vec::new();
 
-        let metadata = match fs::metadata(path_to_visit.as_path()) {
-            Ok(metadata) => metadata,
-            Err(_) => continue,
-        };
+    let base_fontconfig_path = process_path(&None, base_fontconfig_path, true)?;
+    FcLoadFontconfigPath(&base_fontconfig_path, &mut fontconfig, &mut visited)?;
 
-        if metadata.is_file() {
-            let xml_utf8 = match fs::read_to_string(path_to_visit.as_path()) {
-                Ok(xml_utf8) => xml_utf8,
-                Err(_) => continue,
-            };
+    if fontconfig.dirs.is_empty() {
+        return None;
+    }
 
-            ParseFontsConf(xml_utf8.as_str(), &mut paths_to_visit, &mut font_paths);
-        } else if metadata.is_dir() {
-            let dir_entries = match fs::read_dir(path_to_visit) {
-                Ok(dir_entries) => dir_entries,
-                Err(_) => continue,
-            };
+    Some(fontconfig)
+}
 
-            for dir_entry in dir_entries {
-                if let Ok(dir_entry) = dir_entry {
-                    let entry_path = dir_entry.path();
-
-                    // `fs::metadata` traverses symbolic links
-                    let metadata = match fs::metadata(entry_path.as_path()) {
-                        Ok(metadata) => metadata,
-                        Err(_) => continue,
-                    };
-
-                    if metadata.is_file() {
-                        if let Some(file_name) = entry_path.file_name() {
-                            let file_name_str = file_name.to_string_lossy();
-                            if file_name_str.starts_with(|c: char| c.is_ascii_digit())
-                                && file_name_str.ends_with(".conf")
-                            {
-                                paths_to_visit.push((None, entry_path));
-                            }
-                        }
+// Loads a configuration file, or all `.conf` files of a configuration directory (`conf.d`)
+// in lexical order, returns `None` if the path doesn't exist or can't be parsed
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcLoadFontconfigPath(
+    path: &std::path::Path,
+    fontconfig: &mut FcFontconfig,
+    visited: &mut Vec<PathBuf>,
+) -> Option<()> {
+    use std::fs;
+
+    // every file is only loaded once, which also breaks include cycles
+    let canonical_path = fs::canonicalize(path).ok()?;
+    if visited.contains(&canonical_path) {
+        return Some(());
+    }
+    visited.push(canonical_path);
+
+    // `fs::metadata` traverses symbolic links
+    let metadata = fs::metadata(path).ok()?;
+
+    if metadata.is_file() {
+        let xml_utf8 = fs::read_to_string(path).ok()?;
+        let config_dir = path.parent().unwrap_or(path);
+
+        ParseFontsConf(
+            xml_utf8.as_str(),
+            fontconfig,
+            |fontconfig, prefix, include, ignore_missing| {
+                let include = match process_path(&prefix, include, true) {
+                    // relative includes ("conf.d") are relative to the including file
+                    Some(include) if prefix.is_none() && include.is_relative() => {
+                        config_dir.join(include)
                     }
-                } else {
-                    return None;
-                }
-            }
+                    Some(include) => include,
+                    None => return ignore_missing,
+                };
+
+                FcLoadFontconfigPath(&include, fontconfig, visited).is_some() || ignore_missing
+            },
+        )
+    } else if metadata.is_dir() {
+        let mut config_files = fs::read_dir(path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry_path| {
+                entry_path
+                    .file_name()
+                    .is_some_and(|file_name| file_name.to_string_lossy().ends_with(".conf"))
+                    && entry_path.is_file()
+            })
+            .collect::<Vec<_>>();
+        config_files.sort();
+
+        for config_file in config_files {
+            FcLoadFontconfigPath(&config_file, fontconfig, visited)?;
         }
-    }
 
-    if font_paths.is_empty() {
-        return None;
+        Some(())
+    } else {
+        None
     }
-
-    Some(FcScanDirectoriesInner(font_paths.as_slice()))
 }
 
 // Parses the fonts.conf file
-#[cfg(all(feature = "std", feature = "parsing"))]
-fn ParseFontsConf(
-    input: &str,
-    paths_to_visit: &mut Vec<(Option<String>, PathBuf)>,
-    font_paths: &mut Vec<(Option<String>, String)>,
-) -> Option<()> {
+//
+// `include` is called for every `<include>` with `(prefix, path, ignore_missing)`, parsing
+// fails if it returns `false`. Included files are loaded where they are declared, so that
+// the order of `fontconfig.dirs` is the same as in fontconfig.
+#[cfg(all(feature = "std", feature = "scan"))]
+fn ParseFontsConf<F>(input: &str, fontconfig: &mut FcFontconfig, mut include: F) -> Option<()>
+where
+    F: FnMut(&mut FcFontconfig, Option<String>, PathBuf, bool) -> bool,
+{
     use xmlparser::Token::*;
     use xmlparser::Tokenizer;
 
     const TAG_INCLUDE: &str = "include";
     const TAG_DIR: &str = "dir";
+    const TAG_ACCEPTFONT: &str = "acceptfont";
+    const TAG_REJECTFONT: &str = "rejectfont";
+    const TAG_GLOB: &str = "glob";
+    const TAG_ALIAS: &str = "alias";
+    const TAG_FAMILY: &str = "family";
+    const TAG_PREFER: &str = "prefer";
+    const TAG_ACCEPT: &str = "accept";
+    const TAG_DEFAULT: &str = "default";
     const ATTRIBUTE_PREFIX: &str = "prefix";
+    const ATTRIBUTE_IGNORE_MISSING: &str = "ignore_missing";
 
     let mut current_prefix: Option<&str> = None;
+    let mut current_ignore_missing = false;
     let mut current_path: Option<&str> = None;
     let mut is_in_include = false;
     let mut is_in_dir = false;
+    // `Some(true)` inside of `<acceptfont>`, `Some(false)` inside of `<rejectfont>`
+    let mut is_in_selectfont: Option<bool> = None;
+    let mut is_in_glob = false;
+    // `<alias>`: aliased family, current list (`<prefer>` / `<accept>` / `<default>`) and families
+    let mut is_in_alias = false;
+    let mut is_in_family = false;
+    let mut alias_family: Option<&str> = None;
+    let mut alias_list: Option<&str> = None;
+    let mut alias_prefer = Vec::new();
+    let mut alias_accept = Vec::new();
 
     for token in Tokenizer::from(input) {
         let token = token.ok()?;
@@ -436,6 +2967,29 @@ fn ParseFontsConf(
                     TAG_DIR => {
                         is_in_dir = true;
                     }
+                    TAG_ACCEPTFONT => {
+                        is_in_selectfont = Some(true);
+                        continue;
+                    }
+                    TAG_REJECTFONT => {
+                        is_in_selectfont = Some(false);
+                        continue;
+                    }
+                    TAG_GLOB if is_in_selectfont.is_some() => {
+                        is_in_glob = true;
+                    }
+                    TAG_ALIAS => {
+                        is_in_alias = true;
+                        continue;
+                    }
+                    TAG_FAMILY if is_in_alias => {
+                        is_in_family = true;
+                        continue;
+                    }
+                    tag @ (TAG_PREFER | TAG_ACCEPT | TAG_DEFAULT) if is_in_alias => {
+                        alias_list = Some(tag);
+                        continue;
+                    }
                     _ => continue,
                 }
 
@@ -446,17 +3000,27 @@ fn ParseFontsConf(
                 if text.is_empty() {
                     continue;
                 }
-                if is_in_include || is_in_dir {
+                if is_in_include || is_in_dir || is_in_glob {
                     current_path = Some(text);
                 }
+                if is_in_family {
+                    match alias_list {
+                        None => alias_family = Some(text),
+                        Some(TAG_PREFER) => alias_prefer.push(text.to_owned()),
+                        Some(TAG_ACCEPT) => alias_accept.push(text.to_owned()),
+                        Some(_) => {}
+                    }
+                }
             }
             Attribute { local, value, .. } => {
                 if !is_in_include && !is_in_dir {
                     continue;
                 }
                 // attribute on <include> or <dir> node
-                if local.as_str() == ATTRIBUTE_PREFIX {
-                    current_prefix = Some(value.as_str());
+                match local.as_str() {
+                    ATTRIBUTE_PREFIX => current_prefix = Some(value.as_str()),
+                    ATTRIBUTE_IGNORE_MISSING => current_ignore_missing = value.as_str() == "yes",
+                    _ => {}
                 }
             }
             ElementEnd { end, .. } => {
@@ -466,16 +3030,73 @@ fn ParseFontsConf(
                 };
 
                 match end_tag.as_str() {
+                    TAG_FAMILY => {
+                        is_in_family = false;
+                        continue;
+                    }
+                    TAG_PREFER | TAG_ACCEPT | TAG_DEFAULT => {
+                        alias_list = None;
+                        continue;
+                    }
+                    TAG_ALIAS => {
+                        if let Some(family) = alias_family.take() {
+                            let family = family.to_lowercase();
+                            fontconfig
+                                .alias_prefer
+                                .entry(family.clone())
+                                .or_default()
+                                .append(&mut alias_prefer);
+                            fontconfig
+                                .alias_accept
+                                .entry(family)
+                                .or_default()
+                                .append(&mut alias_accept);
+                        }
+
+                        is_in_alias = false;
+                        alias_prefer.clear();
+                        alias_accept.clear();
+                        continue;
+                    }
+                    TAG_ACCEPTFONT | TAG_REJECTFONT => {
+                        is_in_selectfont = None;
+                        continue;
+                    }
+                    TAG_GLOB => {
+                        if !is_in_glob {
+                            continue;
+                        }
+
+                        if let (Some(current_path), Some(accept)) =
+                            (current_path.as_ref(), is_in_selectfont)
+                        {
+                            let globs = if accept {
+                                &mut fontconfig.accept_globs
+                            } else {
+                                &mut fontconfig.reject_globs
+                            };
+                            globs.push((*current_path).to_owned());
+                        }
+
+                        is_in_glob = false;
+                        current_path = None;
+                        continue;
+                    }
                     TAG_INCLUDE => {
                         if !is_in_include {
                             continue;
                         }
 
                         if let Some(current_path) = current_path.as_ref() {
-                            paths_to_visit.push((
+                            let is_loaded = include(
+                                fontconfig,
                                 current_prefix.map(ToOwned::to_owned),
                                 PathBuf::from(*current_path),
-                            ));
+                                current_ignore_missing,
+                            );
+                            if !is_loaded {
+                                return None;
+                            }
                         }
                     }
                     TAG_DIR => {
@@ -484,7 +3105,7 @@ fn ParseFontsConf(
                         }
 
                         if let Some(current_path) = current_path.as_ref() {
-                            font_paths.push((
+                            fontconfig.dirs.push((
                                 current_prefix.map(ToOwned::to_owned),
                                 (*current_path).to_owned(),
                             ));
@@ -497,6 +3118,7 @@ fn ParseFontsConf(
                 is_in_dir = false;
                 current_path = None;
                 current_prefix = None;
+                current_ignore_missing = false;
             }
             _ => {}
         }
@@ -505,8 +3127,11 @@ fn ParseFontsConf(
     Some(())
 }
 
-#[cfg(all(feature = "std", feature = "parsing"))]
-fn FcScanDirectoriesInner(paths: &[(Option<String>, String)]) -> Vec<(FcPattern, FcFontPath)> {
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcScanDirectoriesInner(
+    paths: &[(Option<String>, String)],
+    options: &FcScanOptions,
+) -> Vec<(FcPattern, FcFontPath)> {
     #[cfg(feature = "multithreading")]
     {
         use rayon::prelude::*;
@@ -516,7 +3141,7 @@ fn FcScanDirectoriesInner(paths: &[(Option<String>, String)]) -> Vec<(FcPattern,
             .par_iter()
             .filter_map(|(prefix, p)| {
                 if let Some(path) = process_path(prefix, PathBuf::from(p), false) {
-                    Some(FcScanSingleDirectoryRecursive(path))
+                    Some(FcScanSingleDirectoryRecursive(path, options))
                 } else {
                     None
                 }
@@ -530,7 +3155,7 @@ fn FcScanDirectoriesInner(paths: &[(Option<String>, String)]) -> Vec<(FcPattern,
             .iter()
             .filter_map(|(prefix, p)| {
                 if let Some(path) = process_path(prefix, PathBuf::from(p), false) {
-                    Some(FcScanSingleDirectoryRecursive(path))
+                    Some(FcScanSingleDirectoryRecursive(path, options))
                 } else {
                     None
                 }
@@ -540,46 +3165,210 @@ fn FcScanDirectoriesInner(paths: &[(Option<String>, String)]) -> Vec<(FcPattern,
     }
 }
 
-#[cfg(all(feature = "std", feature = "parsing"))]
-fn FcScanSingleDirectoryRecursive(dir: PathBuf) -> Vec<(FcPattern, FcFontPath)> {
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcScanSingleDirectoryRecursive(
+    dir: PathBuf,
+    options: &FcScanOptions,
+) -> Vec<(FcPattern, FcFontPath)> {
+    if !options.includes_dir(&dir) {
+        return Vec::new();
+    }
+
     let mut files_to_parse = Vec::new();
     let mut dirs_to_parse = vec![dir];
+    let mut depth = 0;
+
+    // fonts of unchanged directories, and the directories that have to be recorded
+    // once their files are parsed
+    let mut cached_fonts = Vec::new();
+    let mut dirs_to_record = BTreeMap::new();
+
+    // directories reached through a symlink loop (i.e. "fonts/fonts -> ..") are only scanned once
+    let mut visited_dirs = alloc::collections::btree_set::BTreeSet::new();
 
     'outer: loop {
         let mut new_dirs_to_parse = Vec::new();
 
         'inner: for dir in dirs_to_parse.clone() {
-            let dir = match std::fs::read_dir(dir) {
+            if options.is_cancelled() {
+                return Vec::new();
+            }
+
+            match FcDirIdentity(&dir) {
+                Some(identity) if visited_dirs.insert(identity) => {}
+                _ => continue 'inner,
+            }
+
+            let dir_cache = options.dir_cache.as_ref();
+
+            if let Some(record) = dir_cache.and_then(|cache| cache.fresh_record(&dir)) {
+                cached_fonts.extend(
+                    record
+                        .fonts
+                        .iter()
+                        .filter(|(_, path)| {
+                            path.path().is_none_or(|path| options.accepts_file(path))
+                        })
+                        .cloned(),
+                );
+                new_dirs_to_parse.extend(
+                    record
+                        .subdirs
+                        .iter()
+                        .filter(|subdir| options.includes_dir(subdir))
+                        .cloned(),
+                );
+                // modifying a file doesn't change the mtime of its directory
+                let modified = dir_cache
+                    .map(|cache| cache.modified_failures(&dir))
+                    .unwrap_or_default();
+                if let Some(progress) = options.progress.as_ref() {
+                    progress.found_dir(&dir, modified.len());
+                }
+                if let Some(cache) = dir_cache {
+                    if modified.is_empty() {
+                        cache.insert(dir, record.clone());
+                    } else {
+                        // recorded once the fonts of the modified files are known
+                        dirs_to_record.insert(dir, record.clone());
+                    }
+                }
+                files_to_parse.extend(modified);
+                continue 'inner;
+            }
+
+            // taken before listing the directory, so a change while scanning is noticed next time
+            let mtime = dir_cache.and_then(|_| dircache::FcModifiedTime(&dir));
+            let mut subdirs = Vec::new();
+            let files_before = files_to_parse.len();
+
+            let entries = match std::fs::read_dir(&dir) {
                 Ok(o) => o,
                 Err(_) => continue 'inner,
             };
 
-            for (path, pathbuf) in dir.filter_map(|entry| {
+            for (path, pathbuf) in entries.filter_map(|entry| {
                 let entry = entry.ok()?;
+                if !options.follow_symlinks && entry.file_type().ok()?.is_symlink() {
+                    return None;
+                }
                 let path = entry.path();
                 let pathbuf = path.to_path_buf();
                 Some((path, pathbuf))
             }) {
                 if path.is_dir() {
-                    new_dirs_to_parse.push(pathbuf);
-                } else {
+                    subdirs.push(pathbuf.clone());
+                    if options.includes_dir(&pathbuf) {
+                        new_dirs_to_parse.push(pathbuf);
+                    }
+                } else if options.accepts_file(&pathbuf.to_string_lossy()) {
                     files_to_parse.push(pathbuf);
                 }
             }
+
+            if let Some(progress) = options.progress.as_ref() {
+                progress.found_dir(&dir, files_to_parse.len() - files_before);
+            }
+
+            if let Some(mtime) = mtime {
+                dirs_to_record.insert(
+                    dir,
+                    dircache::FcDirRecord {
+                        mtime,
+                        subdirs,
+                        fonts: Vec::new(),
+                    },
+                );
+            }
         }
 
-        if new_dirs_to_parse.is_empty() {
+        if new_dirs_to_parse.is_empty() || options.max_depth.is_some_and(|max| depth >= max) {
             break 'outer;
         } else {
             dirs_to_parse = new_dirs_to_parse;
+            depth += 1;
+        }
+    }
+
+    let mut fonts = FcParseFontFiles(&files_to_parse, options);
+
+    if let Some(cache) = options.dir_cache.as_ref() {
+        for (pattern, path) in fonts.iter() {
+            let dir = path
+                .path()
+                .and_then(|path| std::path::Path::new(path).parent());
+            if let Some(record) = dir.and_then(|dir| dirs_to_record.get_mut(dir)) {
+                record.fonts.push((pattern.clone(), path.clone()));
+            }
+        }
+        for (dir, record) in dirs_to_record {
+            cache.insert(dir, record);
         }
     }
 
-    FcParseFontFiles(&files_to_parse)
+    fonts.extend(cached_fonts);
+    fonts
 }
 
-#[cfg(all(feature = "std", feature = "parsing"))]
-fn FcParseFontFiles(files_to_parse: &[PathBuf]) -> Vec<(FcPattern, FcFontPath)> {
+// Identifies a directory independent of the path it was reached through, bind mounts
+// have the same device and inode as the mounted directory as well
+#[cfg(all(feature = "std", feature = "scan", unix))]
+fn FcDirIdentity(dir: &std::path::Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(dir).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(all(feature = "std", feature = "scan", not(unix)))]
+fn FcDirIdentity(dir: &std::path::Path) -> Option<PathBuf> {
+    std::fs::canonicalize(dir).ok()
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcParseFontFiles(
+    files_to_parse: &[PathBuf],
+    options: &FcScanOptions,
+) -> Vec<(FcPattern, FcFontPath)> {
+    if let Some(pending_files) = options.pending_files.as_ref() {
+        pending_files.extend(files_to_parse);
+        return Vec::new();
+    }
+
+    let dir_cache = options.dir_cache.as_ref();
+
+    let parse = |file: &PathBuf| {
+        // checked between files, the remaining files are skipped quickly
+        if options.is_cancelled() {
+            return None;
+        }
+
+        // unchanged files that couldn't be parsed last time aren't tried again
+        if dir_cache.is_some_and(|cache| cache.is_known_failure(file)) {
+            return None;
+        }
+
+        // a malformed font that makes the parser panic must not abort the whole build
+        let fonts =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| FcParseFont(file, options)))
+                .unwrap_or_else(|_| {
+                    options.skip_file(file, FcSkipReason::ParserPanic);
+                    Some(Vec::new())
+                });
+        if let Some(progress) = options.progress.as_ref() {
+            progress.parsed_file(file);
+        }
+        match fonts {
+            Some(fonts) if fonts.is_empty() => {
+                if let Some(cache) = dir_cache {
+                    cache.insert_failed(file);
+                }
+                None
+            }
+            fonts => fonts,
+        }
+    };
+
     let result = {
         #[cfg(feature = "multithreading")]
         {
@@ -587,14 +3376,14 @@ fn FcParseFontFiles(files_to_parse: &[PathBuf]) -> Vec<(FcPattern, FcFontPath)>
 
             files_to_parse
                 .par_iter()
-                .filter_map(|file| FcParseFont(file))
+                .filter_map(parse)
                 .collect::<Vec<Vec<_>>>()
         }
         #[cfg(not(feature = "multithreading"))]
         {
             files_to_parse
                 .iter()
-                .filter_map(|file| FcParseFont(file))
+                .filter_map(parse)
                 .collect::<Vec<Vec<_>>>()
         }
     };
@@ -602,8 +3391,188 @@ fn FcParseFontFiles(files_to_parse: &[PathBuf]) -> Vec<(FcPattern, FcFontPath)>
     result.into_iter().flat_map(|f| f.into_iter()).collect()
 }
 
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcParseFont(
+    filepath: &std::path::Path,
+    options: &FcScanOptions,
+) -> Option<Vec<(FcPattern, FcFontPath)>> {
+    let font_bytes = bytes::FcFontBytes::open(filepath)?;
+    if let Some(max_file_size) = options.max_file_size {
+        if font_bytes.len() as u64 > max_file_size {
+            options.skip_file(filepath, FcSkipReason::TooLarge);
+            return None;
+        }
+    }
+
+    // shared by all faces and styles of the file
+    let source = FcFontSource::Disk(filepath.to_string_lossy().into());
+
+    let font_bytes = FcUncompressedData(&font_bytes);
+    let container = FcFontContainer::of(&font_bytes);
+    match container {
+        FcFontContainer::Type1 => {
+            let font = type1::FcParseType1File(filepath, &font_bytes);
+            return Some(FcSingleFont(font, source));
+        }
+        FcFontContainer::Pcf => return Some(FcSingleFont(pcf::FcParsePcf(&font_bytes), source)),
+        _ => {}
+    }
+
+    // the gzip data is already decompressed
+    let sfnt = FcDecodedWebFont(font_bytes);
+    let faces = FcFontFaces(&sfnt);
+    if let Some(max_faces) = options.max_faces {
+        if faces.len() > max_faces {
+            options.skip_file(filepath, FcSkipReason::TooManyFaces);
+            return None;
+        }
+    }
+
+    // empty if the file was read, but isn't a font that can be parsed
+    let fonts = FcParseFontFaces(faces, &source, options.parser());
+    Some(FcWithFileFormat(fonts, container, &sfnt))
+}
+
+// The single font of a Type 1 / PCF file, if it could be parsed
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcSingleFont(font: Option<FcPattern>, source: FcFontSource) -> Vec<(FcPattern, FcFontPath)> {
+    font.map(|pattern| {
+        (
+            pattern,
+            FcFontPath {
+                source,
+                font_index: 0,
+            },
+        )
+    })
+    .into_iter()
+    .collect()
+}
+
+// Font data of a file as SFNT (a font or a collection), WOFF / WOFF2 files are
+// decompressed with the `woff` feature, gzip-compressed files with the `gzip` feature
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcSfntData(font_bytes: &[u8]) -> alloc::borrow::Cow<'_, [u8]> {
+    FcDecodedWebFont(FcUncompressedData(font_bytes))
+}
+
+// Data of a WOFF / WOFF2 font decompressed with the `woff` feature, other data as is
+// (`font_bytes` is already uncompressed, see `FcUncompressedData`)
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcDecodedWebFont(font_bytes: alloc::borrow::Cow<'_, [u8]>) -> alloc::borrow::Cow<'_, [u8]> {
+    #[cfg(feature = "woff")]
+    {
+        if let Some(sfnt) = woff::FcDecodeWoff(&font_bytes) {
+            return alloc::borrow::Cow::Owned(sfnt);
+        }
+    }
+    font_bytes
+}
+
+// Data of a gzip-compressed file (`.pcf.gz`, ...) decompressed with the `gzip` feature,
+// other data as is
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcUncompressedData(font_bytes: &[u8]) -> alloc::borrow::Cow<'_, [u8]> {
+    #[cfg(feature = "gzip")]
+    {
+        use std::io::Read;
+
+        // see https://www.rfc-editor.org/rfc/rfc1952#page-5
+        const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+        // files that decompress to more than this aren't fonts (but decompression bombs)
+        const MAX_SIZE: u64 = 256 << 20;
+
+        if font_bytes.starts_with(&GZIP_MAGIC) {
+            let mut data = Vec::new();
+            let mut decoder = flate2::read::GzDecoder::new(font_bytes).take(MAX_SIZE + 1);
+            if decoder.read_to_end(&mut data).is_ok() && data.len() as u64 <= MAX_SIZE {
+                return alloc::borrow::Cow::Owned(data);
+            }
+        }
+    }
+    alloc::borrow::Cow::Borrowed(font_bytes)
+}
+
+// Records the container of the file the fonts were parsed from (`sfnt` is its decompressed
+// data), the parsers only know the outline format of web fonts and collections
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcWithFileFormat(
+    mut fonts: Vec<(FcPattern, FcFontPath)>,
+    container: FcFontContainer,
+    sfnt: &[u8],
+) -> Vec<(FcPattern, FcFontPath)> {
+    const TAG_TTCF: u32 = u32::from_be_bytes(*b"ttcf");
+
+    let is_collection = utils::read_u32(sfnt, 0) == Some(TAG_TTCF);
+    for (pattern, _) in fonts.iter_mut() {
+        pattern.metadata.container = container;
+        pattern.metadata.format = match container {
+            FcFontContainer::Woff => FcFontFormat::Woff,
+            FcFontContainer::Woff2 => FcFontFormat::Woff2,
+            _ if is_collection => FcFontFormat::Collection,
+            _ => pattern.metadata.format,
+        };
+    }
+    fonts
+}
+
+// Faces of a font file, as the data and index of every face (in the data)
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcFontFaces(font_bytes: &[u8]) -> Vec<(&[u8], usize)> {
+    // .dfont files and suitcases store every face as a separate resource
+    match dfont::FcResourceForkFaces(font_bytes) {
+        Some(faces) => faces.into_iter().map(|face| (face, 0)).collect(),
+        None => (0..FcCollectionFaceCount(font_bytes))
+            .map(|index| (font_bytes, index))
+            .collect(),
+    }
+}
+
+// Parses the patterns of all faces (see `FcFontFaces`), the index of a face in the
+// list is its `font_index`
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcParseFontFaces(
+    faces: Vec<(&[u8], usize)>,
+    source: &FcFontSource,
+    parser: &dyn FcMetadataParser,
+) -> Vec<(FcPattern, FcFontPath)> {
+    let mut results = Vec::new();
+    for (font_index, (face_bytes, provider_index)) in faces.into_iter().enumerate() {
+        let patterns = match parser.parse_face(face_bytes, provider_index) {
+            Some(s) => s,
+            None => continue,
+        };
+        results.extend(patterns.into_iter().map(|pattern| {
+            (
+                pattern,
+                FcFontPath {
+                    source: source.clone(),
+                    font_index,
+                },
+            )
+        }));
+    }
+    results
+}
+
+// Number of fonts in a TrueType / OpenType collection (`numFonts` of the `ttcf` header),
+// 1 for single fonts
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcCollectionFaceCount(font_bytes: &[u8]) -> usize {
+    const TAG_TTCF: u32 = u32::from_be_bytes(*b"ttcf");
+
+    match utils::read_u32(font_bytes, 0) {
+        // a broken header can't claim more fonts than offsets fit into the data
+        Some(TAG_TTCF) => utils::read_u32(font_bytes, 8).map_or(1, |count| {
+            (count as usize).min(font_bytes.len().saturating_sub(12) / 4)
+        }),
+        _ => 1,
+    }
+}
+
+// Parses the patterns of a single face of a font file, see `FcAllsortsParser`
 #[cfg(all(feature = "std", feature = "parsing"))]
-fn FcParseFont(filepath: &PathBuf) -> Option<Vec<(FcPattern, FcFontPath)>> {
+fn FcAllsortsParseFontFace(font_bytes: &[u8], font_index: usize) -> Option<Vec<FcPattern>> {
     use allsorts::{
         binary::read::ReadScope,
         font_data::FontData,
@@ -614,24 +3583,32 @@ fn FcParseFont(filepath: &PathBuf) -> Option<Vec<(FcPattern, FcFontPath)>> {
         },
         tag,
     };
-    #[cfg(all(not(target_family = "wasm"), feature = "std"))]
-    use mmapio::MmapOptions;
     use std::collections::BTreeSet;
-    use std::fs::File;
 
     const FONT_SPECIFIER_NAME_ID: u16 = 4;
     const FONT_SPECIFIER_FAMILY_ID: u16 = 1;
 
-    // font_index = 0 - TODO: iterate through fonts in font file properly!
-    let font_index = 0;
+    // outline tables, a font without any of these can only be rendered from its bitmap strikes
+    const TAG_GLYF: u32 = u32::from_be_bytes(*b"glyf");
+    const TAG_CFF: u32 = u32::from_be_bytes(*b"CFF ");
+    const TAG_CFF2: u32 = u32::from_be_bytes(*b"CFF2");
+    const TAG_LOCA: u32 = u32::from_be_bytes(*b"loca");
 
-    // try parsing the font file and see if the postscript name matches
-    let file = File::open(filepath).ok()?;
-    #[cfg(all(not(target_family = "wasm"), feature = "std"))]
-    let font_bytes = unsafe { MmapOptions::new().map(&file).ok()? };
-    #[cfg(not(all(not(target_family = "wasm"), feature = "std")))]
-    let font_bytes = std::fs::read(filepath).ok()?;
-    let scope = ReadScope::new(&font_bytes[..]);
+    // bitmap strike locations of monochrome / grayscale and color bitmaps
+    const TAG_EBLC: u32 = u32::from_be_bytes(*b"EBLC");
+    const TAG_CBLC: u32 = u32::from_be_bytes(*b"CBLC");
+
+    // legacy kerning pairs and OpenType positioning
+    const TAG_KERN: u32 = u32::from_be_bytes(*b"kern");
+    const TAG_GPOS: u32 = u32::from_be_bytes(*b"GPOS");
+
+    // hinting tables
+    const TAG_FPGM: u32 = u32::from_be_bytes(*b"fpgm");
+    const TAG_PREP: u32 = u32::from_be_bytes(*b"prep");
+    const TAG_CVT: u32 = u32::from_be_bytes(*b"cvt ");
+    const TAG_GASP: u32 = u32::from_be_bytes(*b"gasp");
+
+    let scope = ReadScope::new(font_bytes);
     let font_file = scope.read::<FontData<'_>>().ok()?;
     let provider = font_file.table_provider(font_index).ok()?;
 
@@ -641,11 +3618,13 @@ fn FcParseFont(filepath: &PathBuf) -> Option<Vec<(FcPattern, FcFontPath)>> {
     let is_bold = head_table.is_bold();
     let is_italic = head_table.is_italic();
     let mut detected_monospace = None;
+    let mut italic_angle = 0;
 
     let post_data = provider.table_data(tag::POST).ok()??;
     if let Ok(post_table) = ReadScope::new(&post_data).read::<PostTable>() {
         // isFixedPitch here - https://learn.microsoft.com/en-us/typography/opentype/spec/post#header
         detected_monospace = Some(post_table.header.is_fixed_pitch != 0);
+        italic_angle = post_table.header.italic_angle;
     }
 
     if detected_monospace.is_none() {
@@ -688,9 +3667,102 @@ fn FcParseFont(filepath: &PathBuf) -> Option<Vec<(FcPattern, FcFontPath)>> {
 
     let is_monospace = detected_monospace.unwrap_or(false);
 
+    let format = if provider.has_table(TAG_GLYF) {
+        FcFontFormat::TrueType
+    } else if provider.has_table(TAG_CFF) || provider.has_table(TAG_CFF2) {
+        FcFontFormat::Cff
+    } else {
+        FcFontFormat::Bitmap
+    };
+    let is_scalable = format != FcFontFormat::Bitmap;
+
+    // achVendID, see https://learn.microsoft.com/en-us/typography/opentype/spec/os2#achvendid
+    let vendor_id = provider
+        .table_data(tag::OS_2)
+        .ok()
+        .flatten()
+        .and_then(|os2_data| {
+            let vendor = os2_data.get(58..62)?;
+            let vendor = String::from_utf8_lossy(vendor);
+            let vendor = vendor.trim_end_matches([' ', '\0']);
+            if vendor.is_empty() {
+                None
+            } else {
+                Some(vendor.to_owned())
+            }
+        });
+
+    // usWeightClass, see https://learn.microsoft.com/en-us/typography/opentype/spec/os2#usweightclass
+    let weight = provider
+        .table_data(tag::OS_2)
+        .ok()
+        .flatten()
+        .and_then(|os2_data| utils::read_u16(&os2_data, 4))
+        .map(FcWeight::from)
+        .unwrap_or_default();
+
+    let hinting = FcHintingInfo {
+        has_fpgm: provider.has_table(TAG_FPGM),
+        has_prep: provider.has_table(TAG_PREP),
+        has_cvt: provider.has_table(TAG_CVT),
+        gasp_ranges: provider
+            .table_data(TAG_GASP)
+            .ok()
+            .flatten()
+            .map(|gasp_data| FcParseGaspRanges(&gasp_data))
+            .unwrap_or_default(),
+    };
+
     let name_data = provider.table_data(tag::NAME).ok()??.into_owned();
     let name_table = ReadScope::new(&name_data).read::<NameTable>().ok()?;
 
+    let cjk = cjk::FcParseCjkInfo(
+        provider.table_data(tag::OS_2).ok().flatten().as_deref(),
+        Some(&name_data),
+        provider.table_data(tag::GSUB).ok().flatten().as_deref(),
+    );
+    let shaping = provider
+        .table_data(tag::GSUB)
+        .ok()
+        .flatten()
+        .map(|gsub_data| script::FcShapingScripts(&gsub_data))
+        .unwrap_or_default();
+    let charset = provider
+        .table_data(tag::CMAP)
+        .ok()
+        .flatten()
+        .map(|cmap_data| coverage::FcParseCharset(&cmap_data))
+        .unwrap_or_default();
+    let strikes = FcParseStrikes(
+        provider.table_data(TAG_EBLC).ok().flatten().as_deref(),
+        provider.table_data(TAG_CBLC).ok().flatten().as_deref(),
+    );
+    let kerning = kerning::FcParseKerningInfo(
+        provider.table_data(TAG_KERN).ok().flatten().as_deref(),
+        provider.table_data(TAG_GPOS).ok().flatten().as_deref(),
+    );
+    let cmap_data = provider.table_data(tag::CMAP).ok().flatten();
+    let features = FcFeatureInfo {
+        default_figures: features::FcParseFigureStyle(
+            cmap_data.as_deref(),
+            provider.table_data(tag::HEAD).ok().flatten().as_deref(),
+            provider.table_data(TAG_LOCA).ok().flatten().as_deref(),
+            provider.table_data(TAG_GLYF).ok().flatten().as_deref(),
+        ),
+        ..features::FcParseFeatureInfo(
+            provider.table_data(tag::GSUB).ok().flatten().as_deref(),
+            cmap_data.as_deref(),
+            provider.table_data(tag::HHEA).ok().flatten().as_deref(),
+            provider.table_data(tag::HMTX).ok().flatten().as_deref(),
+        )
+    };
+
+    // typographic subfamily, or else the legacy subfamily
+    let style = [17, 2].iter().find_map(|id| {
+        let style = fontcode_get_name(&name_data, *id).ok()??;
+        Some(String::from_utf8_lossy(style.to_bytes()).to_string()).filter(|s| !s.is_empty())
+    });
+
     // one font can support multiple patterns
     let mut f_family = None;
 
@@ -709,29 +3781,34 @@ fn FcParseFont(filepath: &PathBuf) -> Option<Vec<(FcPattern, FcFontPath)>> {
                 if name.to_bytes().is_empty() {
                     None
                 } else {
-                    Some((
-                        FcPattern {
-                            name: Some(String::from_utf8_lossy(name.to_bytes()).to_string()),
-                            family: Some(String::from_utf8_lossy(family.as_bytes()).to_string()),
-                            bold: if is_bold {
-                                PatternMatch::True
-                            } else {
-                                PatternMatch::False
-                            },
-                            italic: if is_italic {
-                                PatternMatch::True
-                            } else {
-                                PatternMatch::False
-                            },
-                            monospace: if is_monospace {
-                                PatternMatch::True
-                            } else {
-                                PatternMatch::False
-                            },
-                            ..Default::default() // TODO!
+                    Some(FcPattern {
+                        name: Some(String::from_utf8_lossy(name.to_bytes()).to_string()),
+                        family: Some(String::from_utf8_lossy(family.as_bytes()).to_string()),
+                        bold: PatternMatch::from(is_bold),
+                        italic: PatternMatch::from(is_italic),
+                        monospace: PatternMatch::from(is_monospace),
+                        scalable: PatternMatch::from(is_scalable),
+                        hinted: PatternMatch::from(hinting.is_hinted()),
+                        small_caps: PatternMatch::from(features.small_caps),
+                        tabular_figures: PatternMatch::from(features.has_tabular_figures()),
+                        weight,
+                        metadata: FcFontMetadata {
+                            hinting: hinting.clone(),
+                            vendor_id: vendor_id.clone(),
+                            cjk: cjk.clone(),
+                            shaping: shaping.clone(),
+                            charset: charset.clone(),
+                            italic_angle,
+                            // the parsers see the decompressed font, see `FcWithFileFormat`
+                            container: FcFontContainer::Sfnt,
+                            format,
+                            strikes: strikes.clone(),
+                            kerning,
+                            features,
+                            style: style.clone(),
                         },
-                        font_index,
-                    ))
+                        ..Default::default() // TODO!
+                    })
                 }
             } else {
                 None
@@ -739,42 +3816,76 @@ fn FcParseFont(filepath: &PathBuf) -> Option<Vec<(FcPattern, FcFontPath)>> {
         })
         .collect::<BTreeSet<_>>();
 
-    Some(
-        patterns
-            .into_iter()
-            .map(|(pat, index)| {
-                (
-                    pat,
-                    FcFontPath {
-                        path: filepath.to_string_lossy().to_string(),
-                        font_index: index,
-                    },
-                )
+    Some(patterns.into_iter().collect())
+}
+
+// Parses the pixel sizes (`ppemY`) of the `BitmapSize` records of the `EBLC` and `CBLC`
+// tables, sorted and without duplicates, see
+// https://learn.microsoft.com/en-us/typography/opentype/spec/eblc
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcParseStrikes(eblc_data: Option<&[u8]>, cblc_data: Option<&[u8]>) -> Vec<u16> {
+    use crate::utils::read_u32;
+
+    // `BitmapSize` records follow the version and `numSizes`, `ppemY` is at offset 45
+    const BITMAP_SIZE_LEN: usize = 48;
+    const PPEM_Y: usize = 45;
+
+    let mut strikes = [eblc_data, cblc_data]
+        .iter()
+        .flatten()
+        .flat_map(|data| {
+            let num_sizes = read_u32(data, 4).unwrap_or(0) as usize;
+            (0..num_sizes).map_while(move |i| data.get(8 + i * BITMAP_SIZE_LEN + PPEM_Y))
+        })
+        .filter(|ppem| **ppem != 0)
+        .map(|ppem| u16::from(*ppem))
+        .collect::<Vec<_>>();
+    strikes.sort_unstable();
+    strikes.dedup();
+    strikes
+}
+
+// Parses the ranges of a `gasp` table, see https://learn.microsoft.com/en-us/typography/opentype/spec/gasp
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcParseGaspRanges(gasp_data: &[u8]) -> Vec<FcGaspRange> {
+    use crate::utils::read_u16;
+
+    let num_ranges = match read_u16(gasp_data, 2) {
+        Some(n) => n as usize,
+        None => return Vec::new(),
+    };
+
+    (0..num_ranges)
+        .map_while(|i| {
+            let offset = 4 + i * 4;
+            Some(FcGaspRange {
+                max_ppem: read_u16(gasp_data, offset)?,
+                behavior: read_u16(gasp_data, offset + 2)?,
             })
-            .collect(),
-    )
+        })
+        .collect()
 }
 
-#[cfg(all(feature = "std", feature = "parsing"))]
+/// Returns the family and full name of the font, read from its `name` table (works for
+/// fonts on disk and in-memory fonts)
+#[cfg(all(feature = "std", feature = "scan"))]
 pub fn get_font_name(font_path: &FcFontPath) -> Option<(String, String)> {
-    use allsorts::{
-        binary::read::ReadScope,
-        font_data::FontData,
-        get_name::fontcode_get_name,
-        tables::{FontTableProvider, NameTable},
-        tag,
-    };
+    const TAG_NAME: u32 = u32::from_be_bytes(*b"name");
+
+    let font_bytes = bytes::FcFontBytes::load(&font_path.source)?;
+    let name_data = FcFontTableData(&font_bytes, font_path.font_index, TAG_NAME)?;
+    FcFontNames(&name_data)
+}
+
+// Family and full name of a `name` table
+#[cfg(all(feature = "std", feature = "parsing", not(feature = "ttf-parser")))]
+fn FcFontNames(name_data: &[u8]) -> Option<(String, String)> {
+    use allsorts::{binary::read::ReadScope, get_name::fontcode_get_name, tables::NameTable};
 
     const FONT_SPECIFIER_NAME_ID: u16 = 4;
     const FONT_SPECIFIER_FAMILY_ID: u16 = 1;
 
-    let font_bytes = std::fs::read(&font_path.path).ok()?;
-    let scope = ReadScope::new(&font_bytes[..]);
-    let font_file = scope.read::<FontData<'_>>().ok()?;
-    let provider = font_file.table_provider(font_path.font_index).ok()?;
-
-    let name_data = provider.table_data(tag::NAME).ok()??.into_owned();
-    let name_table = ReadScope::new(&name_data).read::<NameTable>().ok()?;
+    let name_table = ReadScope::new(name_data).read::<NameTable>().ok()?;
 
     let mut font_family = None;
     let mut font_name = None;
@@ -782,12 +3893,12 @@ pub fn get_font_name(font_path: &FcFontPath) -> Option<(String, String)> {
     for name_record in name_table.name_records.iter() {
         match name_record.name_id {
             FONT_SPECIFIER_FAMILY_ID => {
-                if let Ok(Some(family)) = fontcode_get_name(&name_data, FONT_SPECIFIER_FAMILY_ID) {
+                if let Ok(Some(family)) = fontcode_get_name(name_data, FONT_SPECIFIER_FAMILY_ID) {
                     font_family = Some(String::from_utf8_lossy(family.as_bytes()).to_string());
                 }
             }
             FONT_SPECIFIER_NAME_ID => {
-                if let Ok(Some(name)) = fontcode_get_name(&name_data, FONT_SPECIFIER_NAME_ID) {
+                if let Ok(Some(name)) = fontcode_get_name(name_data, FONT_SPECIFIER_NAME_ID) {
                     font_name = Some(String::from_utf8_lossy(name.to_bytes()).to_string());
                 }
             }
@@ -805,3 +3916,32 @@ pub fn get_font_name(font_path: &FcFontPath) -> Option<(String, String)> {
         None
     }
 }
+
+// Data of a table of the font at `font_index` in the font file
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcFontTableData(font_bytes: &[u8], font_index: usize, tag: u32) -> Option<Vec<u8>> {
+    FcSfntTableData(&FcSfntData(font_bytes), font_index, tag)
+}
+
+// Same as `FcFontTableData` for data that is already decompressed (see `FcSfntData`), for
+// reading several tables without decompressing the file every time
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcSfntTableData(font_bytes: &[u8], font_index: usize, tag: u32) -> Option<Vec<u8>> {
+    // faces of .dfont files are separate resources instead of a font collection
+    let (face_bytes, provider_index) = match dfont::FcResourceForkFaces(font_bytes) {
+        Some(faces) => (*faces.get(font_index)?, 0),
+        None => (font_bytes, font_index),
+    };
+    FcFaceTableData(face_bytes, provider_index, tag)
+}
+
+// Data of a table of a single face (of a font collection)
+#[cfg(all(feature = "std", feature = "parsing", not(feature = "ttf-parser")))]
+fn FcFaceTableData(face_bytes: &[u8], provider_index: usize, tag: u32) -> Option<Vec<u8>> {
+    use allsorts::{binary::read::ReadScope, font_data::FontData, tables::FontTableProvider};
+
+    let scope = ReadScope::new(face_bytes);
+    let font_file = scope.read::<FontData<'_>>().ok()?;
+    let provider = font_file.table_provider(provider_index).ok()?;
+    Some(provider.table_data(tag).ok()??.into_owned())
+}
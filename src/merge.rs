@@ -0,0 +1,102 @@
+//! Combining font caches, see `FcFontCache::merge`
+
+use alloc::vec::Vec;
+
+use crate::FcFontCache;
+
+/// What `FcFontCache::merge` does with fonts (and substitutes, font links and aliases)
+/// that are in both caches
+#[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcMergePolicy {
+    /// Keeps the entries of the cache that is merged into
+    #[default]
+    PreferSelf,
+    /// Replaces the entries with the ones of the merged cache
+    PreferOther,
+    /// Keeps the entries of both caches, the font links and aliases of the cache that is
+    /// merged into come first
+    KeepBoth,
+}
+
+impl FcFontCache {
+    /// Adds the fonts of another cache, i.e. to combine the system fonts with fonts bundled
+    /// with the application that were cached separately
+    ///
+    /// `policy` decides which font is kept if both caches have a font with the same pattern.
+    /// With `KeepBoth`, `query_all` returns both fonts and `query` the one with the
+    /// smaller path. The fallback order of `self` comes first.
+    pub fn merge(&mut self, other: FcFontCache, policy: FcMergePolicy) -> &mut Self {
+        let FcFontCache {
+            map,
+            fallback,
+            substitutes,
+            font_links,
+            aliases,
+            scanned_dirs,
+            scanned_files,
+            pending_files,
+            skipped_files,
+            failed_files,
+            alternate_paths,
+            // the font files loaded by handles stay with the cache that loaded them
+            ..
+        } = other;
+
+        self.map.merge(map, policy);
+
+        for family in fallback {
+            if !self.fallback.contains(&family) {
+                self.fallback.push(family);
+            }
+        }
+
+        for (family, substitute) in substitutes {
+            // a family only has one substitute
+            if policy == FcMergePolicy::PreferOther || !self.substitutes.contains_key(&family) {
+                self.substitutes.insert(family, substitute);
+            }
+        }
+
+        for (family, fonts) in font_links {
+            FcMergeList(self.font_links.entry(family).or_default(), fonts, policy);
+        }
+
+        for (family, preferred) in aliases {
+            FcMergeList(self.aliases.entry(family).or_default(), preferred, policy);
+        }
+
+        // the files of both caches are kept up to date by `refresh`
+        self.scanned_dirs.extend(scanned_dirs);
+        self.scanned_files.extend(scanned_files);
+        self.failed_files.extend(failed_files);
+        self.alternate_paths.extend(alternate_paths);
+
+        self.pending_files.extend(pending_files);
+        self.pending_files.sort();
+        self.pending_files.dedup();
+
+        self.skipped_files.extend(skipped_files);
+        self.skipped_files.sort();
+        self.skipped_files.dedup();
+
+        self
+    }
+}
+
+// Merges the list of a family, an empty list counts as missing
+fn FcMergeList<T: PartialEq>(list: &mut Vec<T>, other: Vec<T>, policy: FcMergePolicy) {
+    match policy {
+        FcMergePolicy::PreferSelf if list.is_empty() => *list = other,
+        FcMergePolicy::PreferOther if !other.is_empty() => *list = other,
+        FcMergePolicy::PreferSelf | FcMergePolicy::PreferOther => {}
+        FcMergePolicy::KeepBoth => {
+            for item in other {
+                if !list.contains(&item) {
+                    list.push(item);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Human-readable patterns and font paths, similar to fontconfig's font names
+//! (`DejaVu Sans:style=Bold Italic:file=/usr/share/fonts/DejaVuSans-BoldOblique.ttf`)
+//!
+//! A name is a list of elements separated by `:`, the first element is the family, all
+//! others are `key=value` properties. `\`, `:`, `,` and `=` in values are escaped with `\`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::str::FromStr;
+
+use crate::{
+    FcError, FcFaceInfo, FcFontMatch, FcFontPath, FcFontSource, FcPattern, FcWeight, PatternMatch,
+};
+
+/// Writes the family and all properties of the pattern that differ from the default pattern,
+/// the `metadata` isn't written
+///
+/// `bold` and `italic` are written as `style` (`Regular`, `Bold`, `Italic`, `Bold Italic`) if
+/// both are set, the other properties as `true` / `false` / `dontcare`.
+impl fmt::Display for FcPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(family) = &self.family {
+            FcWriteEscaped(f, family)?;
+        }
+        if let Some(name) = &self.name {
+            f.write_str(":fullname=")?;
+            FcWriteEscaped(f, name)?;
+        }
+
+        match (self.bold.as_bool(), self.italic.as_bool()) {
+            (Some(bold), Some(italic)) => {
+                let style = match (bold, italic) {
+                    (false, false) => "Regular",
+                    (true, false) => "Bold",
+                    (false, true) => "Italic",
+                    (true, true) => "Bold Italic",
+                };
+                write!(f, ":style={}", style)?;
+            }
+            _ => {
+                FcWriteMatch(f, "bold", &self.bold, &PatternMatch::DontCare)?;
+                FcWriteMatch(f, "italic", &self.italic, &PatternMatch::DontCare)?;
+            }
+        }
+        FcWriteMatch(f, "oblique", &self.oblique, &PatternMatch::DontCare)?;
+        FcWriteMatch(f, "monospace", &self.monospace, &PatternMatch::DontCare)?;
+        FcWriteMatch(f, "condensed", &self.condensed, &PatternMatch::DontCare)?;
+        FcWriteMatch(f, "scalable", &self.scalable, &PatternMatch::True)?;
+        FcWriteMatch(f, "hinted", &self.hinted, &PatternMatch::DontCare)?;
+        FcWriteMatch(f, "small_caps", &self.small_caps, &PatternMatch::DontCare)?;
+        FcWriteMatch(
+            f,
+            "tabular_figures",
+            &self.tabular_figures,
+            &PatternMatch::DontCare,
+        )?;
+
+        if self.weight != FcWeight::default() {
+            write!(f, ":weight={}", self.weight)?;
+        }
+        if self.unicode_range != [0; 2] {
+            write!(
+                f,
+                ":unicode_range={}-{}",
+                self.unicode_range[0], self.unicode_range[1]
+            )?;
+        }
+        if let Some(vendor_ids) = &self.vendor_ids {
+            f.write_str(":vendor=")?;
+            for (i, vendor_id) in vendor_ids.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                FcWriteEscaped(f, vendor_id)?;
+            }
+        }
+        if let Some(formats) = &self.formats {
+            f.write_str(":format=")?;
+            for (i, format) in formats.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                f.write_str(format.as_str())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a pattern written by `Display`
+///
+/// `style` also accepts other style names ("Bold Condensed", "Semibold Oblique", ...),
+/// `bold` and `italic` are set from the words `Bold` and `Italic`, `oblique` and `condensed`
+/// are set if the style has the words `Oblique` / `Condensed`. `weight` also accepts weight
+/// names ("semibold").
+impl FromStr for FcPattern {
+    type Err = FcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pattern = FcPattern::default();
+
+        for (i, (key, value)) in FcNameElements(s).into_iter().enumerate() {
+            let key = match key {
+                Some(key) => key,
+                None if i == 0 => {
+                    if !value.is_empty() {
+                        pattern.family = Some(FcUnescape(value));
+                    }
+                    continue;
+                }
+                None => return Err(FcInvalid(value)),
+            };
+
+            match key {
+                "fullname" => pattern.name = Some(FcUnescape(value)),
+                "style" => {
+                    let style = FcUnescape(value);
+                    let has_word = |word: &str| {
+                        style
+                            .split_whitespace()
+                            .any(|w| w.eq_ignore_ascii_case(word))
+                    };
+                    pattern.bold = has_word("bold").into();
+                    pattern.italic = has_word("italic").into();
+                    if has_word("oblique") {
+                        pattern.oblique = PatternMatch::True;
+                    }
+                    if has_word("condensed") {
+                        pattern.condensed = PatternMatch::True;
+                    }
+                }
+                "bold" => pattern.bold = FcParseMatch(value)?,
+                "italic" => pattern.italic = FcParseMatch(value)?,
+                "oblique" => pattern.oblique = FcParseMatch(value)?,
+                "monospace" => pattern.monospace = FcParseMatch(value)?,
+                "condensed" => pattern.condensed = FcParseMatch(value)?,
+                "scalable" => pattern.scalable = FcParseMatch(value)?,
+                "hinted" => pattern.hinted = FcParseMatch(value)?,
+                "small_caps" => pattern.small_caps = FcParseMatch(value)?,
+                "tabular_figures" => pattern.tabular_figures = FcParseMatch(value)?,
+                "weight" => pattern.weight = FcUnescape(value).parse()?,
+                "unicode_range" => {
+                    let (start, end) = value.split_once('-').ok_or_else(|| FcInvalid(value))?;
+                    let parse = |n: &str| n.trim().parse().map_err(|_| FcInvalid(value));
+                    pattern.unicode_range = [parse(start)?, parse(end)?];
+                }
+                "vendor" => {
+                    pattern.vendor_ids = Some(if value.is_empty() {
+                        Vec::new()
+                    } else {
+                        FcSplitEscaped(value, ',')
+                            .into_iter()
+                            .map(FcUnescape)
+                            .collect()
+                    })
+                }
+                "format" => {
+                    pattern.formats = Some(if value.is_empty() {
+                        Vec::new()
+                    } else {
+                        FcSplitEscaped(value, ',')
+                            .into_iter()
+                            .map(|format| FcUnescape(format).parse())
+                            .collect::<Result<_, _>>()?
+                    })
+                }
+                _ => return Err(FcInvalid(key)),
+            }
+        }
+
+        Ok(pattern)
+    }
+}
+
+impl FcPattern {
+    /// Returns the font in the default output format of `fc-list`
+    /// (`/usr/share/fonts/DejaVuSans-Bold.ttf: DejaVu Sans:style=Bold`), so scripts that
+    /// parse fc-list output can read it unchanged
+    ///
+    /// The style is the subfamily name of the font ("Book", see `FcFontMetadata::style`),
+    /// for fonts without one the full name without the family ("Condensed Bold"), or the
+    /// style of `FcFaceInfo::style` if the full name doesn't start with the family. Same as fc-list,
+    /// the index of the font in a collection isn't written, in-memory fonts are written
+    /// without a file.
+    pub fn to_fc_list(&self, path: &FcFontPath) -> String {
+        // characters fontconfig escapes in families and in other values
+        const FAMILY_ESCAPES: &str = "\\-:,";
+        const VALUE_ESCAPES: &str = "\\=_:,";
+
+        let mut line = String::new();
+        if let Some(file) = path.path() {
+            line.push_str(file);
+            line.push_str(": ");
+        }
+        if let Some(family) = &self.family {
+            FcPushEscaped(&mut line, family, FAMILY_ESCAPES);
+        }
+
+        let style = match (&self.metadata.style, &self.family, &self.name) {
+            (Some(style), _, _) => Some(style.clone()),
+            (None, Some(family), Some(name)) => name
+                .strip_prefix(family.as_str())
+                .and_then(|style| style.strip_prefix(' '))
+                .filter(|style| !style.is_empty())
+                .map(String::from),
+            _ => None,
+        };
+        let style = style.unwrap_or_else(|| FcFaceInfo::new(self, path).style());
+        line.push_str(":style=");
+        FcPushEscaped(&mut line, &style, VALUE_ESCAPES);
+        line
+    }
+}
+
+/// Writes `file=<path>` (`memory=<size in bytes>` for in-memory fonts) and the
+/// `index` of the font if it isn't the first font of the file
+impl fmt::Display for FcFontPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            FcFontSource::Disk(file) => {
+                f.write_str("file=")?;
+                FcWriteEscaped(f, file)?;
+            }
+            FcFontSource::Memory(data) => write!(f, "memory={}", data.len())?,
+        }
+        if self.font_index != 0 {
+            write!(f, ":index={}", self.font_index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a font path written by `Display`, in-memory fonts can't be parsed
+impl FromStr for FcFontPath {
+    type Err = FcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut file = None;
+        let mut font_index = 0;
+
+        for (key, value) in FcNameElements(s) {
+            match key {
+                Some("file") => file = Some(FcUnescape(value)),
+                Some("index") => font_index = value.parse().map_err(|_| FcInvalid(value))?,
+                _ => return Err(FcInvalid(s)),
+            }
+        }
+
+        Ok(FcFontPath {
+            source: FcFontSource::Disk(file.ok_or_else(|| FcInvalid(s))?.into()),
+            font_index,
+        })
+    }
+}
+
+/// Writes the pattern followed by the font path
+/// (`DejaVu Sans:style=Bold:file=/usr/share/fonts/DejaVuSans-Bold.ttf`)
+impl fmt::Display for FcFontMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = FcFontPath {
+            source: self.source.clone(),
+            font_index: self.index,
+        };
+        write!(f, "{}:{}", self.pattern, path)
+    }
+}
+
+fn FcInvalid(value: &str) -> FcError {
+    FcError::InvalidValue {
+        value: String::from(value),
+    }
+}
+
+// Appends the value with the given characters escaped with `\`
+fn FcPushEscaped(line: &mut String, value: &str, escapes: &str) {
+    for c in value.chars() {
+        if escapes.contains(c) {
+            line.push('\\');
+        }
+        line.push(c);
+    }
+}
+
+fn FcWriteEscaped(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    for c in value.chars() {
+        if matches!(c, '\\' | ':' | ',' | '=') {
+            f.write_char('\\')?;
+        }
+        f.write_char(c)?;
+    }
+    Ok(())
+}
+
+// Writes `:key=value` if the property isn't `default`
+fn FcWriteMatch(
+    f: &mut fmt::Formatter<'_>,
+    key: &str,
+    value: &PatternMatch,
+    default: &PatternMatch,
+) -> fmt::Result {
+    if value == default {
+        return Ok(());
+    }
+    let value = match value {
+        PatternMatch::True => "true",
+        PatternMatch::False => "false",
+        PatternMatch::DontCare => "dontcare",
+    };
+    write!(f, ":{}={}", key, value)
+}
+
+fn FcParseMatch(value: &str) -> Result<PatternMatch, FcError> {
+    match value {
+        "true" => Ok(PatternMatch::True),
+        "false" => Ok(PatternMatch::False),
+        "dontcare" => Ok(PatternMatch::DontCare),
+        _ => Err(FcInvalid(value)),
+    }
+}
+
+// Elements of a name as (key, escaped value), the key is `None` for elements without `=`
+fn FcNameElements(s: &str) -> Vec<(Option<&str>, &str)> {
+    if s.is_empty() {
+        return Vec::new();
+    }
+    FcSplitEscaped(s, ':')
+        .into_iter()
+        .map(|element| match FcSplitEscaped(element, '=').as_slice() {
+            [key, _, ..] => (Some(*key), &element[key.len() + 1..]),
+            _ => (None, element),
+        })
+        .collect()
+}
+
+// Splits `s` at the occurrences of `separator` that aren't escaped, the parts stay escaped
+fn FcSplitEscaped(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn FcUnescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut escaped = false;
+    for c in value.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            unescaped.push(c);
+            escaped = false;
+        }
+    }
+    unescaped
+}
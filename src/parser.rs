@@ -0,0 +1,73 @@
+//! Font parser backends, see `FcMetadataParser`
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::FcPattern;
+
+/// Backend that reads the patterns (names, style, weight, ...) from the data of a font,
+/// see `FcFontCacheBuilder::parser`
+///
+/// The scanning and matching don't depend on the backend: `FcAllsortsParser` (`parsing`
+/// feature) and `FcTtfParser` (`ttf-parser` feature) yield the same patterns. A custom
+/// backend can i.e. use another parser or return fixed patterns in tests.
+pub trait FcMetadataParser: Send + Sync {
+    /// Parses the patterns of a single face, `face_index` is the index of the face in a
+    /// font collection (TTC / OTC), 0 otherwise
+    ///
+    /// Returns `None` if the data isn't a font that can be parsed. Panics are caught and
+    /// reported like for the built-in backends.
+    fn parse_face(&self, face_bytes: &[u8], face_index: usize) -> Option<Vec<FcPattern>>;
+}
+
+/// Parser backend based on allsorts (`parsing` feature)
+#[cfg(feature = "parsing")]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FcAllsortsParser;
+
+#[cfg(feature = "parsing")]
+impl FcMetadataParser for FcAllsortsParser {
+    fn parse_face(&self, face_bytes: &[u8], face_index: usize) -> Option<Vec<FcPattern>> {
+        crate::FcAllsortsParseFontFace(face_bytes, face_index)
+    }
+}
+
+/// Parser backend based on ttf-parser (`ttf-parser` feature)
+#[cfg(feature = "ttf-parser")]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FcTtfParser;
+
+#[cfg(feature = "ttf-parser")]
+impl FcMetadataParser for FcTtfParser {
+    fn parse_face(&self, face_bytes: &[u8], face_index: usize) -> Option<Vec<FcPattern>> {
+        crate::ttf::FcParseFontFace(face_bytes, face_index)
+    }
+}
+
+// backend of builds without `FcFontCacheBuilder::parser`, ttf-parser if both are enabled
+#[cfg(feature = "ttf-parser")]
+pub(crate) static FC_DEFAULT_PARSER: FcTtfParser = FcTtfParser;
+#[cfg(not(feature = "ttf-parser"))]
+pub(crate) static FC_DEFAULT_PARSER: FcAllsortsParser = FcAllsortsParser;
+
+// Parser of `FcFontCacheBuilder::parser`, compared by identity, and the type name of the
+// parser (identifies the parser in `FcFontCacheBuilder::disk_cache` files)
+#[derive(Clone)]
+pub(crate) struct FcParserHandle(
+    pub(crate) Arc<dyn FcMetadataParser>,
+    pub(crate) &'static str,
+);
+
+impl core::fmt::Debug for FcParserHandle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("FcParserHandle")
+    }
+}
+
+impl PartialEq for FcParserHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for FcParserHandle {}
@@ -0,0 +1,220 @@
+//! X11 bitmap fonts (`.pcf`), see `FcFontContainer::Pcf`
+//!
+//! Most distributions ship them gzip-compressed (`.pcf.gz`), which are decompressed before
+//! parsing with the `gzip` feature. The patterns are read from the XLFD properties of the
+//! font (`FAMILY_NAME`, `WEIGHT_NAME`, `SLANT`, ...), the charset from the encoding table
+//! of fonts with a Unicode (`ISO10646-1`) or Latin-1 (`ISO8859-1`) encoding, same as
+//! FreeType.
+//!
+//! See: https://fontforge.org/docs/techref/pcf-format.html
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{
+    FcCharset, FcFontContainer, FcFontFormat, FcFontMetadata, FcPattern, FcWeight, PatternMatch,
+};
+
+const PCF_MAGIC: &[u8; 4] = b"\x01fcp";
+
+// table types of the table of contents
+const PCF_PROPERTIES: u32 = 1 << 0;
+const PCF_BDF_ENCODINGS: u32 = 1 << 5;
+
+// format bit of tables that store their integers big-endian
+const PCF_BYTE_MASK: u32 = 1 << 2;
+
+// encoding table value of characters without a glyph
+const PCF_NO_GLYPH: u16 = 0xffff;
+
+// Parses the pattern of a PCF font, `None` if it isn't one or has no family name
+pub(crate) fn FcParsePcf(font_bytes: &[u8]) -> Option<FcPattern> {
+    let properties = FcPcfProperties(&FcFindPcfTable(font_bytes, PCF_PROPERTIES)?)?;
+    let property = |name: &str| {
+        properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .filter(|value| !value.is_empty())
+    };
+
+    let family = property("FAMILY_NAME")?;
+    let weight_name = property("WEIGHT_NAME");
+    let weight = weight_name.map_or(FcWeight::REGULAR, FcWeight::from_style_name);
+    let slant = property("SLANT").unwrap_or("R").to_ascii_uppercase();
+    let setwidth = property("SETWIDTH_NAME");
+    let is_condensed = setwidth.is_some_and(|setwidth| {
+        let setwidth = setwidth.to_ascii_lowercase();
+        setwidth.contains("condensed") || setwidth.contains("narrow")
+    });
+    // monospaced and character cell fonts
+    let is_monospace = property("SPACING").is_some_and(|spacing| {
+        spacing.eq_ignore_ascii_case("m") || spacing.eq_ignore_ascii_case("c")
+    });
+
+    // style words of the name, same as FreeType builds the style name
+    let mut style = Vec::new();
+    style.extend(weight_name.filter(|weight| !weight.eq_ignore_ascii_case("medium")));
+    style.extend(match slant.as_str() {
+        "I" | "RI" => Some("Italic"),
+        "O" | "RO" => Some("Oblique"),
+        _ => None,
+    });
+    style.extend(setwidth.filter(|setwidth| !setwidth.eq_ignore_ascii_case("normal")));
+    style.extend(property("ADD_STYLE_NAME"));
+    if style.is_empty() {
+        style.push("Regular");
+    }
+    let name = format!("{} {}", family, style.join(" "));
+
+    // the only strike of the font
+    let strikes = property("PIXEL_SIZE")
+        .and_then(|size| size.parse::<u16>().ok())
+        .filter(|size| *size != 0)
+        .into_iter()
+        .collect();
+
+    let registry = property("CHARSET_REGISTRY").unwrap_or_default();
+    let is_unicode = matches!(
+        (
+            registry.to_ascii_uppercase().as_str(),
+            property("CHARSET_ENCODING")
+        ),
+        ("ISO10646", Some("1")) | ("ISO8859", Some("1"))
+    );
+    let charset = match is_unicode {
+        true => FcFindPcfTable(font_bytes, PCF_BDF_ENCODINGS)
+            .and_then(|table| FcPcfCharset(&table))
+            .unwrap_or_default(),
+        false => FcCharset::new(),
+    };
+
+    Some(FcPattern {
+        name: Some(name),
+        family: Some(family.into()),
+        italic: PatternMatch::from(matches!(slant.as_str(), "I" | "RI")),
+        oblique: PatternMatch::from(matches!(slant.as_str(), "O" | "RO")),
+        bold: PatternMatch::from(weight.is_bold()),
+        monospace: PatternMatch::from(is_monospace),
+        condensed: PatternMatch::from(is_condensed),
+        scalable: PatternMatch::False,
+        weight,
+        metadata: FcFontMetadata {
+            charset,
+            container: FcFontContainer::Pcf,
+            format: FcFontFormat::Bitmap,
+            strikes,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+// A table of a PCF font, with the byte order of its format
+struct FcPcfTable<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl FcPcfTable<'_> {
+    fn u8(&self, offset: usize) -> Option<u8> {
+        self.data.get(offset).copied()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = [*self.data.get(offset)?, *self.data.get(offset + 1)?];
+        Some(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Some(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+}
+
+// Table of a type from the table of contents (type, format, size and offset per table, the
+// header and the format at the start of every table are always little-endian)
+fn FcFindPcfTable(font_bytes: &[u8], table_type: u32) -> Option<FcPcfTable<'_>> {
+    if !font_bytes.starts_with(PCF_MAGIC) {
+        return None;
+    }
+    let toc = FcPcfTable {
+        data: font_bytes,
+        big_endian: false,
+    };
+    // at most the entries that fit in the file, the count of a damaged file can be anything
+    let count = (toc.u32(4)? as usize).min((font_bytes.len() - 8) / 16);
+    let entry = (0..count)
+        .map(|i| 8 + i * 16)
+        .find(|entry| toc.u32(*entry) == Some(table_type))?;
+    let size = toc.u32(entry + 8)? as usize;
+    let offset = toc.u32(entry + 12)? as usize;
+    let data = font_bytes.get(offset..offset.checked_add(size)?)?;
+    let format = FcPcfTable {
+        data,
+        big_endian: false,
+    }
+    .u32(0)?;
+    Some(FcPcfTable {
+        data,
+        big_endian: format & PCF_BYTE_MASK != 0,
+    })
+}
+
+// Name and value of all properties, integer values as decimal strings
+fn FcPcfProperties(table: &FcPcfTable<'_>) -> Option<Vec<(String, String)>> {
+    let count = table.u32(4)? as usize;
+    // 9 bytes per property, padded to 4 bytes, then the size and data of the strings
+    let strings_start = (8 + count.checked_mul(9)?).next_multiple_of(4) + 4;
+    let strings = table.data.get(strings_start..)?;
+    let string_at = |offset: u32| -> Option<String> {
+        let bytes = strings.get(offset as usize..)?;
+        let end = bytes.iter().position(|byte| *byte == 0)?;
+        // Latin-1, same as XLFD names
+        Some(bytes[..end].iter().map(|byte| char::from(*byte)).collect())
+    };
+
+    (0..count)
+        .map(|i| {
+            let entry = 8 + i * 9;
+            let name = string_at(table.u32(entry)?)?;
+            let value = table.u32(entry + 5)?;
+            let value = match table.u8(entry + 4)? != 0 {
+                true => string_at(value)?,
+                false => (value as i32).to_string(),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+// Characters of the encoding table, the code of a glyph is `byte1 << 8 | byte2`
+fn FcPcfCharset(table: &FcPcfTable<'_>) -> Option<FcCharset> {
+    let (min_byte2, max_byte2) = (table.u16(4)?, table.u16(6)?);
+    let (min_byte1, max_byte1) = (table.u16(8)?, table.u16(10)?);
+    if min_byte2 > max_byte2 || min_byte1 > max_byte1 {
+        return None;
+    }
+
+    let mut charset = FcCharset::new();
+    let mut offset = 14;
+    for byte1 in min_byte1..=max_byte1 {
+        for byte2 in min_byte2..=max_byte2 {
+            let glyph = table.u16(offset)?;
+            offset += 2;
+            let code = u32::from(byte1) << 8 | u32::from(byte2);
+            if glyph != PCF_NO_GLYPH {
+                charset.extend(char::from_u32(code));
+            }
+        }
+    }
+    Some(charset)
+}
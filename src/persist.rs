@@ -0,0 +1,984 @@
+//! Binary serialization of the font cache, see `FcFontCache::save` / `FcFontCache::load`
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! magic           b"DAFONTC\0"
+//! format version  u32
+//! crate version   string (CARGO_PKG_VERSION of the writer)
+//! fonts           count, (pattern, path)*
+//! fallback        count, (langs, paths)*
+//! substitutes     count, (family, substitute)*
+//! font links      count, (family, paths)*
+//! aliases         count, (family, families)*
+//! scanned dirs    count, (path, mtime secs u64, mtime nanos u32)*
+//! scanned files   count, (path, mtime secs u64, mtime nanos u32)*
+//! pending files   count, path*
+//! skipped files   count, (path, reason u8)*
+//! failed files    count, (path, mtime secs u64, mtime nanos u32, size u64)*
+//! alternate paths count, (path, paths)*
+//! build options   max depth, follow symlinks, extensions, excluded dirs, system fonts,
+//!                 user fonts, max file size, max faces, fontconfig caches
+//! ```
+//!
+//! Strings are stored as a u32 byte length followed by UTF-8, counts as u32.
+//! Font paths are a u8 tag followed by the file path (0) or the font data of an
+//! in-memory font as u32 byte length and bytes (1), then the font index as u64.
+//! Optional values are a u8 flag (1 = present) followed by the value. The build options
+//! are stored without the `scan` feature too (the defaults), so the layout doesn't depend
+//! on the enabled features.
+//! Files written by a different format or crate version are rejected, since
+//! the parsed font information may have changed between versions.
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::store::FcFontStore;
+use crate::{
+    FcCharset, FcCjkInfo, FcCjkLocale, FcFallbackFamily, FcFeatureInfo, FcFigureStyle, FcFontCache,
+    FcFontContainer, FcFontFormat, FcFontMetadata, FcFontPath, FcFontSource, FcGaspRange,
+    FcHintingInfo, FcKerningInfo, FcPattern, FcScript, FcSkipReason, FcSkippedFile, FcWeight,
+    PatternMatch,
+};
+
+const MAGIC: &[u8; 8] = b"DAFONTC\0";
+
+// NOTE: increment whenever the layout or the contents of the patterns change
+pub(crate) const FORMAT_VERSION: u32 = 28;
+
+pub(crate) const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+impl FcFontCache {
+    /// Serializes the font cache (including in-memory fonts) into a versioned binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.bytes(MAGIC);
+        w.u32(FORMAT_VERSION);
+        w.str(CRATE_VERSION);
+
+        w.len(self.map.len());
+        for (pattern, path) in self.map.iter() {
+            w.pattern(pattern);
+            w.path(path);
+        }
+
+        w.len(self.fallback.len());
+        for family in self.fallback.iter() {
+            w.strs(&family.langs);
+            w.paths(&family.fonts);
+        }
+
+        w.len(self.substitutes.len());
+        for (family, substitute) in self.substitutes.iter() {
+            w.str(family);
+            w.str(substitute);
+        }
+
+        w.len(self.font_links.len());
+        for (family, fonts) in self.font_links.iter() {
+            w.str(family);
+            w.paths(fonts);
+        }
+
+        w.len(self.aliases.len());
+        for (family, preferred) in self.aliases.iter() {
+            w.str(family);
+            w.strs(preferred);
+        }
+
+        w.mtimes(&self.scanned_dirs);
+        w.mtimes(&self.scanned_files);
+        w.strs(&self.pending_files);
+
+        w.len(self.skipped_files.len());
+        for skipped_file in self.skipped_files.iter() {
+            w.str(&skipped_file.path);
+            w.u8(match skipped_file.reason {
+                FcSkipReason::TooLarge => 0,
+                FcSkipReason::TooManyFaces => 1,
+                FcSkipReason::ParserPanic => 2,
+            });
+        }
+
+        w.len(self.failed_files.len());
+        for (file, (secs, nanos, size)) in self.failed_files.iter() {
+            w.str(file);
+            w.u64(*secs);
+            w.u32(*nanos);
+            w.u64(*size);
+        }
+
+        w.len(self.alternate_paths.len());
+        for (path, alternates) in self.alternate_paths.iter() {
+            w.str(path);
+            w.strs(alternates);
+        }
+
+        #[cfg(all(feature = "std", feature = "scan"))]
+        w.build_options(&FcSavedOptions::from(&self.scan_options.0));
+        #[cfg(not(all(feature = "std", feature = "scan")))]
+        w.build_options(&FcSavedOptions::default());
+
+        w.0
+    }
+
+    /// Deserializes a font cache written by `to_bytes`, returns `None` if the data is
+    /// malformed or was written by a different version of this crate
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(bytes);
+        if r.bytes(MAGIC.len())? != MAGIC || r.u32()? != FORMAT_VERSION || r.str()? != CRATE_VERSION
+        {
+            return None;
+        }
+
+        // patterns can be stored more than once, see `FcMergePolicy::KeepBoth`
+        let mut fonts = Vec::new();
+        for _ in 0..r.len()? {
+            let pattern = r.pattern()?;
+            let path = r.path()?;
+            fonts.push((pattern, path));
+        }
+
+        let mut fallback = Vec::new();
+        for _ in 0..r.len()? {
+            fallback.push(FcFallbackFamily {
+                langs: r.strs()?,
+                fonts: r.paths()?,
+            });
+        }
+
+        let mut substitutes = BTreeMap::new();
+        for _ in 0..r.len()? {
+            substitutes.insert(r.str()?, r.str()?);
+        }
+
+        let mut font_links = BTreeMap::new();
+        for _ in 0..r.len()? {
+            font_links.insert(r.str()?, r.paths()?);
+        }
+
+        let mut aliases = BTreeMap::new();
+        for _ in 0..r.len()? {
+            aliases.insert(r.str()?, r.strs()?);
+        }
+
+        let scanned_dirs = r.mtimes()?;
+        let scanned_files = r.mtimes()?;
+        let pending_files = r.strs()?;
+
+        let mut skipped_files = Vec::new();
+        for _ in 0..r.len()? {
+            let path = r.str()?;
+            let reason = match r.u8()? {
+                0 => FcSkipReason::TooLarge,
+                1 => FcSkipReason::TooManyFaces,
+                2 => FcSkipReason::ParserPanic,
+                _ => return None,
+            };
+            skipped_files.push(FcSkippedFile { path, reason });
+        }
+
+        let mut failed_files = BTreeMap::new();
+        for _ in 0..r.len()? {
+            failed_files.insert(r.str()?, (r.u64()?, r.u32()?, r.u64()?));
+        }
+
+        let mut alternate_paths = BTreeMap::new();
+        for _ in 0..r.len()? {
+            alternate_paths.insert(r.str()?, r.strs()?);
+        }
+
+        // only restored with the `scan` feature, other builds can't rescan anyway
+        #[cfg_attr(not(all(feature = "std", feature = "scan")), allow(unused_variables))]
+        let build_options = r.build_options()?;
+
+        // trailing garbage means the file was not written by `to_bytes`
+        if r.pos != r.data.len() {
+            return None;
+        }
+
+        Some(FcFontCache {
+            map: FcFontStore::from_entries(fonts),
+            fallback,
+            substitutes,
+            font_links,
+            aliases,
+            scanned_dirs,
+            scanned_files,
+            pending_files,
+            skipped_files,
+            failed_files,
+            alternate_paths,
+            #[cfg(feature = "std")]
+            loaded: Default::default(),
+            #[cfg(all(feature = "std", feature = "scan"))]
+            coverage: Default::default(),
+            #[cfg(all(feature = "std", feature = "scan"))]
+            scan_options: build_options.into_build_options(),
+        })
+    }
+
+    /// Writes the font cache to a file, so that the next start of the application
+    /// can `load` it instead of scanning the system fonts again
+    ///
+    /// The file is written next to `path` first and then renamed, so a concurrent
+    /// `load` never sees a partially written cache.
+    #[cfg(feature = "std")]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        std::fs::write(&tmp_path, self.to_bytes())?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    /// Reads a font cache written by `save`, returns `None` if the file doesn't exist,
+    /// is corrupted or was written by a different version of this crate
+    ///
+    /// The options of the `FcFontCacheBuilder` that built the cache (extensions, limits,
+    /// excluded directories, ...) are restored for `refresh` and `add_font_file`, except a
+    /// custom `parser`: the loaded cache parses new fonts with the backend of the enabled
+    /// features.
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+// Serializable part of the options of the builder (`FcBuildOptions`), the progress,
+// cancellation and parser can't be stored
+#[derive(Debug)]
+struct FcSavedOptions {
+    max_depth: Option<u64>,
+    follow_symlinks: bool,
+    extensions: Option<Vec<String>>,
+    excluded_dirs: Vec<String>,
+    system_fonts: bool,
+    user_fonts: bool,
+    max_file_size: Option<u64>,
+    max_faces: Option<u64>,
+    fontconfig_caches: bool,
+}
+
+// The defaults of `FcFontCacheBuilder`
+impl Default for FcSavedOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            follow_symlinks: true,
+            extensions: None,
+            excluded_dirs: Vec::new(),
+            system_fonts: true,
+            user_fonts: true,
+            max_file_size: None,
+            max_faces: None,
+            fontconfig_caches: false,
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl From<&crate::FcScanOptions> for FcSavedOptions {
+    fn from(options: &crate::FcScanOptions) -> Self {
+        Self {
+            max_depth: options.max_depth.map(|depth| depth as u64),
+            follow_symlinks: options.follow_symlinks,
+            extensions: options.extensions.clone(),
+            excluded_dirs: options
+                .excluded_dirs
+                .iter()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .collect(),
+            system_fonts: options.system_fonts,
+            user_fonts: options.user_fonts,
+            max_file_size: options.max_file_size,
+            max_faces: options.max_faces.map(|max| max as u64),
+            fontconfig_caches: options.fontconfig_caches,
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl FcSavedOptions {
+    // values that don't fit in a usize are clamped, they are limits anyway
+    fn into_build_options(self) -> crate::FcBuildOptions {
+        crate::FcBuildOptions(crate::FcScanOptions {
+            max_depth: self
+                .max_depth
+                .map(|depth| usize::try_from(depth).unwrap_or(usize::MAX)),
+            follow_symlinks: self.follow_symlinks,
+            extensions: self.extensions,
+            excluded_dirs: self.excluded_dirs.into_iter().map(Into::into).collect(),
+            system_fonts: self.system_fonts,
+            user_fonts: self.user_fonts,
+            max_file_size: self.max_file_size,
+            max_faces: self
+                .max_faces
+                .map(|max| usize::try_from(max).unwrap_or(usize::MAX)),
+            fontconfig_caches: self.fontconfig_caches,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Writer(pub(crate) Vec<u8>);
+
+impl Writer {
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    pub(crate) fn len(&mut self, len: usize) {
+        // nothing in a font cache comes close to 4 billion entries / bytes
+        self.u32(len as u32);
+    }
+
+    pub(crate) fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    // unsigned LEB128, see `FcFontCache::to_serialized`
+    pub(crate) fn var(&mut self, value: usize) {
+        let mut value = value as u64;
+        while value >= 0x80 {
+            self.u8(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.u8(value as u8);
+    }
+
+    pub(crate) fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.bytes(s.as_bytes());
+    }
+
+    pub(crate) fn opt_str(&mut self, s: &Option<String>) {
+        match s {
+            Some(s) => {
+                self.u8(1);
+                self.str(s);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    pub(crate) fn opt_u64(&mut self, value: Option<u64>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.u64(value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    pub(crate) fn strs(&mut self, strs: &[String]) {
+        self.len(strs.len());
+        for s in strs {
+            self.str(s);
+        }
+    }
+
+    pub(crate) fn pattern_match(&mut self, value: &PatternMatch) {
+        self.u8(match value {
+            PatternMatch::True => 0,
+            PatternMatch::False => 1,
+            PatternMatch::DontCare => 2,
+        });
+    }
+
+    pub(crate) fn pattern(&mut self, pattern: &FcPattern) {
+        self.opt_str(&pattern.name);
+        self.opt_str(&pattern.family);
+        self.pattern_match(&pattern.italic);
+        self.pattern_match(&pattern.oblique);
+        self.pattern_match(&pattern.bold);
+        self.pattern_match(&pattern.monospace);
+        self.pattern_match(&pattern.condensed);
+        self.usize(usize::from(pattern.weight.0));
+        self.usize(pattern.unicode_range[0]);
+        self.usize(pattern.unicode_range[1]);
+        self.pattern_match(&pattern.scalable);
+        self.pattern_match(&pattern.hinted);
+        self.pattern_match(&pattern.small_caps);
+        self.pattern_match(&pattern.tabular_figures);
+        match &pattern.vendor_ids {
+            Some(vendor_ids) => {
+                self.u8(1);
+                self.strs(vendor_ids);
+            }
+            None => self.u8(0),
+        }
+        self.formats(&pattern.formats);
+
+        let hinting = &pattern.metadata.hinting;
+        self.bool(hinting.has_fpgm);
+        self.bool(hinting.has_prep);
+        self.bool(hinting.has_cvt);
+        self.len(hinting.gasp_ranges.len());
+        for range in hinting.gasp_ranges.iter() {
+            self.u16(range.max_ppem);
+            self.u16(range.behavior);
+        }
+        self.opt_str(&pattern.metadata.vendor_id);
+        self.cjk(&pattern.metadata.cjk);
+        self.scripts(&pattern.metadata.shaping);
+        self.charset(&pattern.metadata.charset);
+        self.u32(pattern.metadata.italic_angle as u32);
+        self.container(pattern.metadata.container);
+        self.format(pattern.metadata.format);
+        self.strikes(&pattern.metadata.strikes);
+        self.bool(pattern.metadata.kerning.has_kern_table);
+        self.bool(pattern.metadata.kerning.has_gpos_kerning);
+        self.bool(pattern.metadata.features.small_caps);
+        self.bool(pattern.metadata.features.caps_to_small_caps);
+        self.bool(pattern.metadata.features.tabular_figures);
+        self.bool(pattern.metadata.features.monospaced_digits);
+        self.bool(pattern.metadata.features.old_style_figures);
+        self.bool(pattern.metadata.features.lining_figures);
+        self.u8(match pattern.metadata.features.default_figures {
+            None => 0,
+            Some(FcFigureStyle::Lining) => 1,
+            Some(FcFigureStyle::OldStyle) => 2,
+        });
+        self.opt_str(&pattern.metadata.style);
+    }
+
+    // count, then the pixel sizes
+    pub(crate) fn strikes(&mut self, strikes: &[u16]) {
+        self.var(strikes.len());
+        for strike in strikes.iter() {
+            self.u16(*strike);
+        }
+    }
+
+    pub(crate) fn container(&mut self, container: FcFontContainer) {
+        self.u8(match container {
+            FcFontContainer::Sfnt => 0,
+            FcFontContainer::Woff => 1,
+            FcFontContainer::Woff2 => 2,
+            FcFontContainer::Type1 => 3,
+            FcFontContainer::Pcf => 4,
+        });
+    }
+
+    pub(crate) fn formats(&mut self, formats: &Option<Vec<FcFontFormat>>) {
+        match formats {
+            Some(formats) => {
+                self.u8(1);
+                self.len(formats.len());
+                for format in formats.iter() {
+                    self.format(*format);
+                }
+            }
+            None => self.u8(0),
+        }
+    }
+
+    pub(crate) fn format(&mut self, format: FcFontFormat) {
+        self.u8(match format {
+            FcFontFormat::TrueType => 0,
+            FcFontFormat::Cff => 1,
+            FcFontFormat::Collection => 2,
+            FcFontFormat::Woff => 3,
+            FcFontFormat::Woff2 => 4,
+            FcFontFormat::Type1 => 5,
+            FcFontFormat::Bitmap => 6,
+        });
+    }
+
+    // ranges as the distance to the end of the previous range and their length
+    pub(crate) fn charset(&mut self, charset: &FcCharset) {
+        let ranges = charset.ranges().collect::<Vec<_>>();
+        self.var(ranges.len());
+        let mut next = 0;
+        for range in ranges {
+            let (start, end) = (u32::from(*range.start()), u32::from(*range.end()));
+            self.var((start - next) as usize);
+            self.var((end - start) as usize);
+            next = end + 1;
+        }
+    }
+
+    // scripts by their position in `FcScript::ALL`
+    pub(crate) fn scripts(&mut self, scripts: &[FcScript]) {
+        self.u8(scripts.len() as u8);
+        for script in scripts.iter() {
+            let id = FcScript::ALL.iter().position(|s| s == script).unwrap_or(0);
+            self.u8(id as u8);
+        }
+    }
+
+    // default locale as 0 (none) or 1 + locale, then the count and the `locl` locales
+    pub(crate) fn cjk(&mut self, cjk: &FcCjkInfo) {
+        self.u8(cjk
+            .default_locale
+            .map_or(0, |locale| FcCjkLocaleId(locale) + 1));
+        self.u8(cjk.locl.len() as u8);
+        for locale in cjk.locl.iter() {
+            self.u8(FcCjkLocaleId(*locale));
+        }
+    }
+
+    pub(crate) fn mtimes(&mut self, mtimes: &BTreeMap<String, (u64, u32)>) {
+        self.len(mtimes.len());
+        for (path, (secs, nanos)) in mtimes.iter() {
+            self.str(path);
+            self.u64(*secs);
+            self.u32(*nanos);
+        }
+    }
+
+    pub(crate) fn path(&mut self, path: &FcFontPath) {
+        match &path.source {
+            FcFontSource::Disk(file) => {
+                self.u8(0);
+                self.str(file);
+            }
+            FcFontSource::Memory(data) => {
+                self.u8(1);
+                self.len(data.len());
+                self.bytes(data);
+            }
+        }
+        self.usize(path.font_index);
+    }
+
+    pub(crate) fn paths(&mut self, paths: &[FcFontPath]) {
+        self.len(paths.len());
+        for path in paths {
+            self.path(path);
+        }
+    }
+
+    fn build_options(&mut self, options: &FcSavedOptions) {
+        self.opt_u64(options.max_depth);
+        self.bool(options.follow_symlinks);
+        match options.extensions.as_ref() {
+            Some(extensions) => {
+                self.u8(1);
+                self.strs(extensions);
+            }
+            None => self.u8(0),
+        }
+        self.strs(&options.excluded_dirs);
+        self.bool(options.system_fonts);
+        self.bool(options.user_fonts);
+        self.opt_u64(options.max_file_size);
+        self.opt_u64(options.max_faces);
+        self.bool(options.fontconfig_caches);
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) pos: usize,
+    // font paths and in-memory fonts read so far, so that the faces and styles of a file
+    // share their path / data
+    paths: BTreeSet<Arc<str>>,
+    memory_fonts: BTreeSet<Arc<[u8]>>,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Reader {
+            data,
+            pos: 0,
+            paths: BTreeSet::new(),
+            memory_fonts: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(
+            <[u8; 2]>::try_from(self.bytes(2)?).ok()?,
+        ))
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(
+            <[u8; 4]>::try_from(self.bytes(4)?).ok()?,
+        ))
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(
+            <[u8; 8]>::try_from(self.bytes(8)?).ok()?,
+        ))
+    }
+
+    pub(crate) fn usize(&mut self) -> Option<usize> {
+        usize::try_from(self.u64()?).ok()
+    }
+
+    pub(crate) fn len(&mut self) -> Option<usize> {
+        usize::try_from(self.u32()?).ok()
+    }
+
+    pub(crate) fn var(&mut self) -> Option<usize> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return usize::try_from(value).ok();
+            }
+        }
+        None
+    }
+
+    pub(crate) fn memory_font(&mut self, len: usize) -> Option<Arc<[u8]>> {
+        let data = self.bytes(len)?;
+        Some(FcIntern(&mut self.memory_fonts, data))
+    }
+
+    pub(crate) fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn str(&mut self) -> Option<String> {
+        let len = self.len()?;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    pub(crate) fn opt_str(&mut self) -> Option<Option<String>> {
+        match self.bool()? {
+            true => Some(Some(self.str()?)),
+            false => Some(None),
+        }
+    }
+
+    pub(crate) fn opt_u64(&mut self) -> Option<Option<u64>> {
+        match self.bool()? {
+            true => Some(Some(self.u64()?)),
+            false => Some(None),
+        }
+    }
+
+    pub(crate) fn strs(&mut self) -> Option<Vec<String>> {
+        // no `with_capacity`, the count isn't trusted
+        let mut strs = Vec::new();
+        for _ in 0..self.len()? {
+            strs.push(self.str()?);
+        }
+        Some(strs)
+    }
+
+    pub(crate) fn pattern_match(&mut self) -> Option<PatternMatch> {
+        match self.u8()? {
+            0 => Some(PatternMatch::True),
+            1 => Some(PatternMatch::False),
+            2 => Some(PatternMatch::DontCare),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn pattern(&mut self) -> Option<FcPattern> {
+        let name = self.opt_str()?;
+        let family = self.opt_str()?;
+        let italic = self.pattern_match()?;
+        let oblique = self.pattern_match()?;
+        let bold = self.pattern_match()?;
+        let monospace = self.pattern_match()?;
+        let condensed = self.pattern_match()?;
+        let weight = FcWeight(u16::try_from(self.usize()?).ok()?);
+        let unicode_range = [self.usize()?, self.usize()?];
+        let scalable = self.pattern_match()?;
+        let hinted = self.pattern_match()?;
+        let small_caps = self.pattern_match()?;
+        let tabular_figures = self.pattern_match()?;
+        let vendor_ids = match self.bool()? {
+            true => Some(self.strs()?),
+            false => None,
+        };
+        let formats = self.formats()?;
+
+        let has_fpgm = self.bool()?;
+        let has_prep = self.bool()?;
+        let has_cvt = self.bool()?;
+        let mut gasp_ranges = Vec::new();
+        for _ in 0..self.len()? {
+            gasp_ranges.push(FcGaspRange {
+                max_ppem: self.u16()?,
+                behavior: self.u16()?,
+            });
+        }
+        let vendor_id = self.opt_str()?;
+        let cjk = self.cjk()?;
+        let shaping = self.scripts()?;
+        let charset = self.charset()?;
+        let italic_angle = self.u32()? as i32;
+        let container = self.container()?;
+        let format = self.format()?;
+        let strikes = self.strikes()?;
+        let kerning = FcKerningInfo {
+            has_kern_table: self.bool()?,
+            has_gpos_kerning: self.bool()?,
+        };
+        let features = FcFeatureInfo {
+            small_caps: self.bool()?,
+            caps_to_small_caps: self.bool()?,
+            tabular_figures: self.bool()?,
+            monospaced_digits: self.bool()?,
+            old_style_figures: self.bool()?,
+            lining_figures: self.bool()?,
+            default_figures: match self.u8()? {
+                0 => None,
+                1 => Some(FcFigureStyle::Lining),
+                2 => Some(FcFigureStyle::OldStyle),
+                _ => return None,
+            },
+        };
+        let style = self.opt_str()?;
+
+        Some(FcPattern {
+            name,
+            family,
+            italic,
+            oblique,
+            bold,
+            monospace,
+            condensed,
+            weight,
+            unicode_range,
+            scalable,
+            hinted,
+            small_caps,
+            tabular_figures,
+            vendor_ids,
+            formats,
+            metadata: FcFontMetadata {
+                hinting: FcHintingInfo {
+                    has_fpgm,
+                    has_prep,
+                    has_cvt,
+                    gasp_ranges,
+                },
+                vendor_id,
+                cjk,
+                shaping,
+                charset,
+                italic_angle,
+                container,
+                format,
+                strikes,
+                kerning,
+                features,
+                style,
+            },
+        })
+    }
+
+    pub(crate) fn strikes(&mut self) -> Option<Vec<u16>> {
+        let mut strikes = Vec::new();
+        for _ in 0..self.var()? {
+            strikes.push(self.u16()?);
+        }
+        Some(strikes)
+    }
+
+    pub(crate) fn charset(&mut self) -> Option<FcCharset> {
+        let mut charset = FcCharset::new();
+        let mut next = 0u32;
+        for _ in 0..self.var()? {
+            let start = next.checked_add(u32::try_from(self.var()?).ok()?)?;
+            let end = start.checked_add(u32::try_from(self.var()?).ok()?)?;
+            if end > 0x10FFFF {
+                return None;
+            }
+            charset.insert_range(start, end);
+            next = end + 1;
+        }
+        Some(charset)
+    }
+
+    pub(crate) fn container(&mut self) -> Option<FcFontContainer> {
+        Some(match self.u8()? {
+            0 => FcFontContainer::Sfnt,
+            1 => FcFontContainer::Woff,
+            2 => FcFontContainer::Woff2,
+            3 => FcFontContainer::Type1,
+            4 => FcFontContainer::Pcf,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn formats(&mut self) -> Option<Option<Vec<FcFontFormat>>> {
+        if !self.bool()? {
+            return Some(None);
+        }
+        let mut formats = Vec::new();
+        for _ in 0..self.len()? {
+            formats.push(self.format()?);
+        }
+        Some(Some(formats))
+    }
+
+    pub(crate) fn format(&mut self) -> Option<FcFontFormat> {
+        Some(match self.u8()? {
+            0 => FcFontFormat::TrueType,
+            1 => FcFontFormat::Cff,
+            2 => FcFontFormat::Collection,
+            3 => FcFontFormat::Woff,
+            4 => FcFontFormat::Woff2,
+            5 => FcFontFormat::Type1,
+            6 => FcFontFormat::Bitmap,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn scripts(&mut self) -> Option<Vec<FcScript>> {
+        let mut scripts = Vec::new();
+        for _ in 0..self.u8()? {
+            scripts.push(*FcScript::ALL.get(usize::from(self.u8()?))?);
+        }
+        Some(scripts)
+    }
+
+    pub(crate) fn cjk(&mut self) -> Option<FcCjkInfo> {
+        let default_locale = match self.u8()? {
+            0 => None,
+            id => Some(FcCjkLocaleFromId(id - 1)?),
+        };
+        let mut locl = Vec::new();
+        for _ in 0..self.u8()? {
+            locl.push(FcCjkLocaleFromId(self.u8()?)?);
+        }
+        Some(FcCjkInfo {
+            default_locale,
+            locl,
+        })
+    }
+
+    pub(crate) fn mtimes(&mut self) -> Option<BTreeMap<String, (u64, u32)>> {
+        let mut mtimes = BTreeMap::new();
+        for _ in 0..self.len()? {
+            mtimes.insert(self.str()?, (self.u64()?, self.u32()?));
+        }
+        Some(mtimes)
+    }
+
+    pub(crate) fn path(&mut self) -> Option<FcFontPath> {
+        let source = match self.u8()? {
+            0 => {
+                let path = self.str()?;
+                FcFontSource::Disk(FcIntern(&mut self.paths, path.as_str()))
+            }
+            1 => {
+                let len = self.len()?;
+                FcFontSource::Memory(self.memory_font(len)?)
+            }
+            _ => return None,
+        };
+        Some(FcFontPath {
+            source,
+            font_index: self.usize()?,
+        })
+    }
+
+    pub(crate) fn paths(&mut self) -> Option<Vec<FcFontPath>> {
+        let mut paths = Vec::new();
+        for _ in 0..self.len()? {
+            paths.push(self.path()?);
+        }
+        Some(paths)
+    }
+
+    fn build_options(&mut self) -> Option<FcSavedOptions> {
+        Some(FcSavedOptions {
+            max_depth: self.opt_u64()?,
+            follow_symlinks: self.bool()?,
+            extensions: match self.bool()? {
+                true => Some(self.strs()?),
+                false => None,
+            },
+            excluded_dirs: self.strs()?,
+            system_fonts: self.bool()?,
+            user_fonts: self.bool()?,
+            max_file_size: self.opt_u64()?,
+            max_faces: self.opt_u64()?,
+            fontconfig_caches: self.bool()?,
+        })
+    }
+}
+
+fn FcCjkLocaleId(locale: FcCjkLocale) -> u8 {
+    match locale {
+        FcCjkLocale::SimplifiedChinese => 0,
+        FcCjkLocale::TraditionalChinese => 1,
+        FcCjkLocale::HongKong => 2,
+        FcCjkLocale::Japanese => 3,
+        FcCjkLocale::Korean => 4,
+    }
+}
+
+fn FcCjkLocaleFromId(id: u8) -> Option<FcCjkLocale> {
+    match id {
+        0 => Some(FcCjkLocale::SimplifiedChinese),
+        1 => Some(FcCjkLocale::TraditionalChinese),
+        2 => Some(FcCjkLocale::HongKong),
+        3 => Some(FcCjkLocale::Japanese),
+        4 => Some(FcCjkLocale::Korean),
+        _ => None,
+    }
+}
+
+// Returns the shared copy of `value`
+pub(crate) fn FcIntern<T: Ord + ?Sized>(interned: &mut BTreeSet<Arc<T>>, value: &T) -> Arc<T>
+where
+    Arc<T>: for<'a> From<&'a T>,
+{
+    match interned.get(value) {
+        Some(interned) => interned.clone(),
+        None => {
+            let value: Arc<T> = value.into();
+            interned.insert(value.clone());
+            value
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Custom font discovery backends, see `FcFontProvider`
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::path::PathBuf;
+
+use crate::{FcFontCache, FcFontPath, FcPattern, FcScanOptions};
+
+/// A font found by a `FcFontProvider`
+// fonts are moved into the cache right away, boxing the pattern isn't worth it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FcProvidedFont {
+    /// Font file on disk, parsed like the files of the font directories (all faces of a
+    /// collection are added)
+    File(PathBuf),
+    /// Data of a font file (i.e. downloaded or packaged with the application), parsed
+    /// like the data of `FcFontCache::add_memory_font`
+    Memory(Arc<[u8]>),
+    /// Font whose pattern is already known, added as is
+    Pattern(FcPattern, FcFontPath),
+}
+
+/// Custom font discovery backend (i.e. a network font server, a database or the fonts
+/// packaged with the application), see `FcFontCacheBuilder::add_provider`
+///
+/// `fonts` is called once per build, on the thread that builds the cache. Files and data
+/// can only be parsed with the `parsing` / `ttf-parser` feature, without it only `FcProvidedFont::Pattern`
+/// fonts are added.
+///
+/// ```rust,no_run
+/// use dafont::{FcFontCacheBuilder, FcProvidedFont};
+///
+/// let cache = FcFontCacheBuilder::new()
+///     .system_fonts(false)
+///     .add_provider(|| vec![FcProvidedFont::File("assets/Inter.ttf".into())])
+///     .build();
+/// ```
+pub trait FcFontProvider: Send + Sync {
+    /// Returns the fonts of the backend
+    fn fonts(&self) -> Vec<FcProvidedFont>;
+}
+
+impl<F: Fn() -> Vec<FcProvidedFont> + Send + Sync> FcFontProvider for F {
+    fn fonts(&self) -> Vec<FcProvidedFont> {
+        self()
+    }
+}
+
+// Provider of `FcFontCacheBuilder::add_provider`, compared by identity
+#[derive(Clone)]
+pub(crate) struct FcProviderHandle(pub(crate) Arc<dyn FcFontProvider>);
+
+impl core::fmt::Debug for FcProviderHandle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("FcProviderHandle")
+    }
+}
+
+impl PartialEq for FcProviderHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for FcProviderHandle {}
+
+impl FcFontCache {
+    // Adds the fonts of the providers, after the scanned fonts
+    pub(crate) fn add_provided_fonts(
+        &mut self,
+        providers: &[FcProviderHandle],
+        options: &FcScanOptions,
+    ) {
+        let mut files = Vec::new();
+        for provider in providers {
+            for font in provider.0.fonts() {
+                match font {
+                    FcProvidedFont::File(file) => files.push(file),
+                    #[cfg(feature = "scan")]
+                    FcProvidedFont::Memory(data) => {
+                        self.add_memory_font(data);
+                    }
+                    #[cfg(not(feature = "scan"))]
+                    FcProvidedFont::Memory(_) => {}
+                    FcProvidedFont::Pattern(pattern, path) => self.map.extend([(pattern, path)]),
+                }
+            }
+        }
+
+        // also parsed by lazy builds, the pending files are only files of font directories
+        #[cfg(feature = "scan")]
+        {
+            let options = FcScanOptions {
+                pending_files: None,
+                ..options.clone()
+            };
+            self.map.extend(crate::FcParseFontFiles(&files, &options));
+        }
+        #[cfg(not(feature = "scan"))]
+        let _ = (files, options);
+    }
+}
@@ -0,0 +1,418 @@
+//! Unicode scripts of characters, see `FcScript`
+
+#[cfg(all(feature = "std", feature = "scan"))]
+use alloc::vec::Vec;
+
+/// Writing system of a character, approximated from its Unicode block
+///
+/// Used to prefer fonts that are designed for the script of a character over fonts that
+/// merely support it (see `FcFontCache::supports_script`), similar to the language coverage
+/// of fontconfig.
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcScript {
+    /// Characters shared by all scripts (digits, punctuation, symbols, spaces, ...)
+    Common,
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Syriac,
+    Thaana,
+    Devanagari,
+    Bengali,
+    Gurmukhi,
+    Gujarati,
+    Oriya,
+    Tamil,
+    Telugu,
+    Kannada,
+    Malayalam,
+    Sinhala,
+    Thai,
+    Lao,
+    Tibetan,
+    Myanmar,
+    Georgian,
+    Hangul,
+    Ethiopic,
+    Cherokee,
+    Khmer,
+    Mongolian,
+    Han,
+    Hiragana,
+    Katakana,
+    Bopomofo,
+    Yi,
+}
+
+// Unicode blocks (sorted, inclusive) -> script
+const SCRIPT_BLOCKS: &[(u32, u32, FcScript)] = &[
+    (0x0041, 0x005A, FcScript::Latin),
+    (0x0061, 0x007A, FcScript::Latin),
+    (0x00C0, 0x02AF, FcScript::Latin),
+    (0x0370, 0x03FF, FcScript::Greek),
+    (0x0400, 0x052F, FcScript::Cyrillic),
+    (0x0530, 0x058F, FcScript::Armenian),
+    (0x0590, 0x05FF, FcScript::Hebrew),
+    (0x0600, 0x06FF, FcScript::Arabic),
+    (0x0700, 0x074F, FcScript::Syriac),
+    (0x0750, 0x077F, FcScript::Arabic),
+    (0x0780, 0x07BF, FcScript::Thaana),
+    (0x0900, 0x097F, FcScript::Devanagari),
+    (0x0980, 0x09FF, FcScript::Bengali),
+    (0x0A00, 0x0A7F, FcScript::Gurmukhi),
+    (0x0A80, 0x0AFF, FcScript::Gujarati),
+    (0x0B00, 0x0B7F, FcScript::Oriya),
+    (0x0B80, 0x0BFF, FcScript::Tamil),
+    (0x0C00, 0x0C7F, FcScript::Telugu),
+    (0x0C80, 0x0CFF, FcScript::Kannada),
+    (0x0D00, 0x0D7F, FcScript::Malayalam),
+    (0x0D80, 0x0DFF, FcScript::Sinhala),
+    (0x0E00, 0x0E7F, FcScript::Thai),
+    (0x0E80, 0x0EFF, FcScript::Lao),
+    (0x0F00, 0x0FFF, FcScript::Tibetan),
+    (0x1000, 0x109F, FcScript::Myanmar),
+    (0x10A0, 0x10FF, FcScript::Georgian),
+    (0x1100, 0x11FF, FcScript::Hangul),
+    (0x1200, 0x139F, FcScript::Ethiopic),
+    (0x13A0, 0x13FF, FcScript::Cherokee),
+    (0x1780, 0x17FF, FcScript::Khmer),
+    (0x1800, 0x18AF, FcScript::Mongolian),
+    (0x1E00, 0x1EFF, FcScript::Latin),
+    (0x1F00, 0x1FFF, FcScript::Greek),
+    (0x2D00, 0x2D2F, FcScript::Georgian),
+    (0x2E80, 0x2FDF, FcScript::Han),
+    (0x3040, 0x309F, FcScript::Hiragana),
+    (0x30A0, 0x30FF, FcScript::Katakana),
+    (0x3100, 0x312F, FcScript::Bopomofo),
+    (0x3130, 0x318F, FcScript::Hangul),
+    (0x31A0, 0x31BF, FcScript::Bopomofo),
+    (0x31F0, 0x31FF, FcScript::Katakana),
+    (0x3400, 0x4DBF, FcScript::Han),
+    (0x4E00, 0x9FFF, FcScript::Han),
+    (0xA000, 0xA4CF, FcScript::Yi),
+    (0xA960, 0xA97F, FcScript::Hangul),
+    (0xAC00, 0xD7FF, FcScript::Hangul),
+    (0xF900, 0xFAFF, FcScript::Han),
+    (0xFB1D, 0xFB4F, FcScript::Hebrew),
+    (0xFB50, 0xFDFF, FcScript::Arabic),
+    (0xFE70, 0xFEFF, FcScript::Arabic),
+    (0xFF21, 0xFF3A, FcScript::Latin),
+    (0xFF41, 0xFF5A, FcScript::Latin),
+    (0xFF66, 0xFF9F, FcScript::Katakana),
+    (0xFFA0, 0xFFDC, FcScript::Hangul),
+    (0x20000, 0x323AF, FcScript::Han),
+];
+
+impl FcScript {
+    // every script, the position is the id of the script in the cache files
+    pub(crate) const ALL: [FcScript; 34] = [
+        FcScript::Common,
+        FcScript::Latin,
+        FcScript::Greek,
+        FcScript::Cyrillic,
+        FcScript::Armenian,
+        FcScript::Hebrew,
+        FcScript::Arabic,
+        FcScript::Syriac,
+        FcScript::Thaana,
+        FcScript::Devanagari,
+        FcScript::Bengali,
+        FcScript::Gurmukhi,
+        FcScript::Gujarati,
+        FcScript::Oriya,
+        FcScript::Tamil,
+        FcScript::Telugu,
+        FcScript::Kannada,
+        FcScript::Malayalam,
+        FcScript::Sinhala,
+        FcScript::Thai,
+        FcScript::Lao,
+        FcScript::Tibetan,
+        FcScript::Myanmar,
+        FcScript::Georgian,
+        FcScript::Hangul,
+        FcScript::Ethiopic,
+        FcScript::Cherokee,
+        FcScript::Khmer,
+        FcScript::Mongolian,
+        FcScript::Han,
+        FcScript::Hiragana,
+        FcScript::Katakana,
+        FcScript::Bopomofo,
+        FcScript::Yi,
+    ];
+
+    /// Returns the script of a character, `Common` for characters that aren't specific to
+    /// a script
+    pub fn of(c: char) -> FcScript {
+        let c = u32::from(c);
+        let index = SCRIPT_BLOCKS.partition_point(|(_, end, _)| *end < c);
+        match SCRIPT_BLOCKS.get(index) {
+            Some((start, _, script)) if *start <= c => *script,
+            _ => FcScript::Common,
+        }
+    }
+
+    /// Returns the language the script is most commonly used for (BCP 47), i.e. to select the
+    /// platform fallback fonts of the script with `FcFontCache::query_sorted`
+    pub fn lang(&self) -> Option<&'static str> {
+        Some(match self {
+            FcScript::Common => return None,
+            FcScript::Latin => "en",
+            FcScript::Greek => "el",
+            FcScript::Cyrillic => "ru",
+            FcScript::Armenian => "hy",
+            FcScript::Hebrew => "he",
+            FcScript::Arabic => "ar",
+            FcScript::Syriac => "syr",
+            FcScript::Thaana => "dv",
+            FcScript::Devanagari => "hi",
+            FcScript::Bengali => "bn",
+            FcScript::Gurmukhi => "pa",
+            FcScript::Gujarati => "gu",
+            FcScript::Oriya => "or",
+            FcScript::Tamil => "ta",
+            FcScript::Telugu => "te",
+            FcScript::Kannada => "kn",
+            FcScript::Malayalam => "ml",
+            FcScript::Sinhala => "si",
+            FcScript::Thai => "th",
+            FcScript::Lao => "lo",
+            FcScript::Tibetan => "bo",
+            FcScript::Myanmar => "my",
+            FcScript::Georgian => "ka",
+            FcScript::Hangul => "ko",
+            FcScript::Ethiopic => "am",
+            FcScript::Cherokee => "chr",
+            FcScript::Khmer => "km",
+            FcScript::Mongolian => "mn",
+            FcScript::Han => "zh",
+            FcScript::Hiragana | FcScript::Katakana => "ja",
+            FcScript::Bopomofo => "zh-TW",
+            FcScript::Yi => "ii",
+        })
+    }
+
+    /// Returns the ISO 15924 code of the script (`"Latn"`, `"Arab"`, `"Deva"`, ...), `"Zyyy"`
+    /// for `Common`
+    pub fn code(&self) -> &'static str {
+        match self {
+            FcScript::Common => "Zyyy",
+            FcScript::Latin => "Latn",
+            FcScript::Greek => "Grek",
+            FcScript::Cyrillic => "Cyrl",
+            FcScript::Armenian => "Armn",
+            FcScript::Hebrew => "Hebr",
+            FcScript::Arabic => "Arab",
+            FcScript::Syriac => "Syrc",
+            FcScript::Thaana => "Thaa",
+            FcScript::Devanagari => "Deva",
+            FcScript::Bengali => "Beng",
+            FcScript::Gurmukhi => "Guru",
+            FcScript::Gujarati => "Gujr",
+            FcScript::Oriya => "Orya",
+            FcScript::Tamil => "Taml",
+            FcScript::Telugu => "Telu",
+            FcScript::Kannada => "Knda",
+            FcScript::Malayalam => "Mlym",
+            FcScript::Sinhala => "Sinh",
+            FcScript::Thai => "Thai",
+            FcScript::Lao => "Laoo",
+            FcScript::Tibetan => "Tibt",
+            FcScript::Myanmar => "Mymr",
+            FcScript::Georgian => "Geor",
+            FcScript::Hangul => "Hang",
+            FcScript::Ethiopic => "Ethi",
+            FcScript::Cherokee => "Cher",
+            FcScript::Khmer => "Khmr",
+            FcScript::Mongolian => "Mong",
+            FcScript::Han => "Hani",
+            FcScript::Hiragana => "Hira",
+            FcScript::Katakana => "Kana",
+            FcScript::Bopomofo => "Bopo",
+            FcScript::Yi => "Yiii",
+        }
+    }
+
+    /// Returns the script of an ISO 15924 code (case-insensitive), see `code`
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|script| script.code().eq_ignore_ascii_case(code))
+    }
+
+    /// Returns whether the script can only be rendered with the glyph substitutions of the
+    /// font, i.e. the joining forms of Arabic or the conjuncts and reordered vowels of the
+    /// Indic scripts
+    ///
+    /// A font without the `GSUB` lookups for such a script renders it as disconnected or
+    /// misordered glyphs, see `FcFontMetadata::can_shape`.
+    pub fn is_complex(&self) -> bool {
+        matches!(
+            self,
+            FcScript::Arabic
+                | FcScript::Syriac
+                | FcScript::Mongolian
+                | FcScript::Devanagari
+                | FcScript::Bengali
+                | FcScript::Gurmukhi
+                | FcScript::Gujarati
+                | FcScript::Oriya
+                | FcScript::Tamil
+                | FcScript::Telugu
+                | FcScript::Kannada
+                | FcScript::Malayalam
+                | FcScript::Sinhala
+                | FcScript::Tibetan
+                | FcScript::Myanmar
+                | FcScript::Khmer
+        )
+    }
+
+    // Basic letters of the script (inclusive), a font that supports most of them is
+    // designed for the script, `None` for `Common`
+    #[cfg(all(feature = "std", feature = "scan"))]
+    pub(crate) fn sample(&self) -> Option<(u32, u32)> {
+        Some(match self {
+            FcScript::Common => return None,
+            FcScript::Latin => (0x0041, 0x005A),
+            FcScript::Greek => (0x03B1, 0x03C9),
+            FcScript::Cyrillic => (0x0430, 0x044F),
+            FcScript::Armenian => (0x0531, 0x0556),
+            FcScript::Hebrew => (0x05D0, 0x05EA),
+            FcScript::Arabic => (0x0621, 0x064A),
+            FcScript::Syriac => (0x0710, 0x072C),
+            FcScript::Thaana => (0x0780, 0x07A5),
+            FcScript::Devanagari => (0x0915, 0x0939),
+            FcScript::Bengali => (0x0995, 0x09B9),
+            FcScript::Gurmukhi => (0x0A15, 0x0A39),
+            FcScript::Gujarati => (0x0A95, 0x0AB9),
+            FcScript::Oriya => (0x0B15, 0x0B39),
+            FcScript::Tamil => (0x0B95, 0x0BB9),
+            FcScript::Telugu => (0x0C15, 0x0C39),
+            FcScript::Kannada => (0x0C95, 0x0CB9),
+            FcScript::Malayalam => (0x0D15, 0x0D39),
+            FcScript::Sinhala => (0x0D9A, 0x0DC6),
+            FcScript::Thai => (0x0E01, 0x0E2E),
+            FcScript::Lao => (0x0E81, 0x0EAE),
+            FcScript::Tibetan => (0x0F40, 0x0F6A),
+            FcScript::Myanmar => (0x1000, 0x102A),
+            FcScript::Georgian => (0x10D0, 0x10FA),
+            FcScript::Hangul => (0xAC00, 0xAC7F),
+            FcScript::Ethiopic => (0x1200, 0x1248),
+            FcScript::Cherokee => (0x13A0, 0x13F4),
+            FcScript::Khmer => (0x1780, 0x17B3),
+            FcScript::Mongolian => (0x1820, 0x1877),
+            FcScript::Han => (0x4E00, 0x4E7F),
+            FcScript::Hiragana => (0x3041, 0x3096),
+            FcScript::Katakana => (0x30A1, 0x30FA),
+            FcScript::Bopomofo => (0x3105, 0x312F),
+            FcScript::Yi => (0xA000, 0xA07F),
+        })
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl crate::FcFontCache {
+    /// Returns whether a font of the cache is designed for a script, i.e. supports at least
+    /// half of the basic letters of the script (some blocks have unassigned code points)
+    ///
+    /// A font that only has a few characters of a script (i.e. a Latin font with some
+    /// Devanagari digits) isn't designed for it. Always `true` for `FcScript::Common`. The
+    /// characters of the font come from `charset`.
+    pub fn supports_script(&self, path: &crate::FcFontPath, script: FcScript) -> bool {
+        let (start, end) = match script.sample() {
+            Some(sample) => sample,
+            None => return true,
+        };
+        let charset = self.charset(path);
+        let supported = (start..=end)
+            .filter_map(char::from_u32)
+            .filter(|c| charset.contains(*c))
+            .count();
+        supported * 2 >= (end - start + 1) as usize
+    }
+}
+
+// Complex scripts (see `FcScript::is_complex`) that a `GSUB` table has lookups for, sorted
+//
+// A script counts if `GSUB` has a script record for one of its OpenType tags, the joining
+// scripts also count if the default script has the `init`, `medi` and `fina` features.
+#[cfg(all(feature = "std", feature = "scan"))]
+pub(crate) fn FcShapingScripts(gsub_data: &[u8]) -> Vec<FcScript> {
+    use crate::utils::read_u16;
+
+    // OpenType script tags, the Indic scripts have an old and a new (`*2`) shaping model
+    const SCRIPT_TAGS: &[(&[u8; 4], FcScript)] = &[
+        (b"arab", FcScript::Arabic),
+        (b"syrc", FcScript::Syriac),
+        (b"mong", FcScript::Mongolian),
+        (b"deva", FcScript::Devanagari),
+        (b"dev2", FcScript::Devanagari),
+        (b"beng", FcScript::Bengali),
+        (b"bng2", FcScript::Bengali),
+        (b"guru", FcScript::Gurmukhi),
+        (b"gur2", FcScript::Gurmukhi),
+        (b"gujr", FcScript::Gujarati),
+        (b"gjr2", FcScript::Gujarati),
+        (b"orya", FcScript::Oriya),
+        (b"ory2", FcScript::Oriya),
+        (b"taml", FcScript::Tamil),
+        (b"tml2", FcScript::Tamil),
+        (b"telu", FcScript::Telugu),
+        (b"tel2", FcScript::Telugu),
+        (b"knda", FcScript::Kannada),
+        (b"knd2", FcScript::Kannada),
+        (b"mlym", FcScript::Malayalam),
+        (b"mlm2", FcScript::Malayalam),
+        (b"sinh", FcScript::Sinhala),
+        (b"tibt", FcScript::Tibetan),
+        (b"mymr", FcScript::Myanmar),
+        (b"mym2", FcScript::Myanmar),
+        (b"khmr", FcScript::Khmer),
+    ];
+
+    let mut scripts = Vec::new();
+    let script_list = match read_u16(gsub_data, 4) {
+        Some(offset) => usize::from(offset),
+        None => return scripts,
+    };
+    let feature_list = match read_u16(gsub_data, 6) {
+        Some(offset) => usize::from(offset),
+        None => return scripts,
+    };
+
+    let script_count = read_u16(gsub_data, script_list).unwrap_or(0);
+    for i in 0..usize::from(script_count) {
+        let record = script_list + 2 + i * 6;
+        let tag = match gsub_data.get(record..record + 4) {
+            Some(tag) => tag,
+            None => break,
+        };
+        if let Some((_, script)) = SCRIPT_TAGS.iter().find(|(t, _)| &t[..] == tag) {
+            scripts.push(*script);
+        }
+    }
+
+    // fonts that only have the positional forms under `DFLT` (older Arabic fonts)
+    let feature_count = read_u16(gsub_data, feature_list).unwrap_or(0);
+    let has_feature = |feature: &[u8; 4]| {
+        (0..usize::from(feature_count)).any(|i| {
+            let record = feature_list + 2 + i * 6;
+            gsub_data.get(record..record + 4) == Some(&feature[..])
+        })
+    };
+    if has_feature(b"init") && has_feature(b"medi") && has_feature(b"fina") {
+        scripts.extend([FcScript::Arabic, FcScript::Syriac, FcScript::Mongolian]);
+    }
+
+    scripts.sort_unstable();
+    scripts.dedup();
+    scripts
+}
@@ -0,0 +1,481 @@
+//! Compact, query-only serialization of the font cache, see `FcFontCache::to_serialized`
+//!
+//! Layout (integers are unsigned LEB128 varints unless noted):
+//!
+//! ```text
+//! magic           b"DAFONTQ\0"
+//! format version  u32 little-endian, same as `to_bytes`
+//! crate version   string (CARGO_PKG_VERSION of the writer)
+//! strings         count, (byte length, UTF-8)*
+//! fonts           count, (pattern, path)*
+//! fallback        count, (langs, paths)*
+//! substitutes     count, (family, substitute)*
+//! font links      count, (family, paths)*
+//! aliases         count, (family, families)*
+//! ```
+//!
+//! All strings (names, families, file paths, ...) are stored once in the string table and
+//! referenced by their index, optional strings as index + 1 (0 is `None`). The properties
+//! of a pattern are packed into a u32, two bits per `PatternMatch` and one bit per hinting
+//! table, kerning table and feature. Font paths are a u8 tag followed by the string index
+//! of the file (0) or the font data of an in-memory font as byte length and bytes (1), then
+//! the font index.
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::persist::{Reader, Writer, CRATE_VERSION, FORMAT_VERSION};
+use crate::store::FcFontStore;
+use crate::{
+    FcFallbackFamily, FcFeatureInfo, FcFigureStyle, FcFontCache, FcFontMetadata, FcFontPath,
+    FcFontSource, FcGaspRange, FcHintingInfo, FcKerningInfo, FcPattern, FcWeight, PatternMatch,
+};
+
+const MAGIC: &[u8; 8] = b"DAFONTQ\0";
+
+// bits of the packed pattern properties, after the nine `PatternMatch`es
+const FLAG_FPGM: u32 = 1 << 18;
+const FLAG_PREP: u32 = 1 << 19;
+const FLAG_CVT: u32 = 1 << 20;
+const FLAG_KERN: u32 = 1 << 21;
+const FLAG_GPOS_KERN: u32 = 1 << 22;
+const FLAG_SMCP: u32 = 1 << 23;
+const FLAG_C2SC: u32 = 1 << 24;
+const FLAG_TNUM: u32 = 1 << 25;
+const FLAG_MONO_DIGITS: u32 = 1 << 26;
+const FLAG_ONUM: u32 = 1 << 27;
+const FLAG_LNUM: u32 = 1 << 28;
+// default figures, neither bit if the style isn't known
+const FLAG_LINING_DEFAULT: u32 = 1 << 29;
+const FLAG_OLD_STYLE_DEFAULT: u32 = 1 << 30;
+
+impl FcFontCache {
+    /// Serializes the fonts of the cache into a compact format that only keeps what is
+    /// needed for queries, see `from_serialized`
+    ///
+    /// Unlike `to_bytes`, the directory records of the build (modification times, failed
+    /// and skipped files) and the files of a lazy build that weren't parsed yet aren't
+    /// stored, so a deserialized cache can't be refreshed. Every string is only stored
+    /// once, which makes the data a fraction of the size of `to_bytes`.
+    pub fn to_serialized(&self) -> Vec<u8> {
+        let mut strings = FcStringTable::default();
+        let mut fonts = Writer::default();
+        fonts.var(self.map.len());
+        for (pattern, path) in self.map.iter() {
+            strings.pattern(&mut fonts, pattern);
+            strings.path(&mut fonts, path);
+        }
+
+        let mut rest = Writer::default();
+        rest.var(self.fallback.len());
+        for family in self.fallback.iter() {
+            strings.strs(&mut rest, &family.langs);
+            strings.paths(&mut rest, &family.fonts);
+        }
+
+        rest.var(self.substitutes.len());
+        for (family, substitute) in self.substitutes.iter() {
+            strings.str(&mut rest, family);
+            strings.str(&mut rest, substitute);
+        }
+
+        rest.var(self.font_links.len());
+        for (family, fonts) in self.font_links.iter() {
+            strings.str(&mut rest, family);
+            strings.paths(&mut rest, fonts);
+        }
+
+        rest.var(self.aliases.len());
+        for (family, preferred) in self.aliases.iter() {
+            strings.str(&mut rest, family);
+            strings.strs(&mut rest, preferred);
+        }
+
+        let mut w = Writer::default();
+        w.bytes(MAGIC);
+        w.u32(FORMAT_VERSION);
+        w.str(CRATE_VERSION);
+        w.var(strings.strings.len());
+        for s in strings.strings.iter() {
+            w.var(s.len());
+            w.bytes(s.as_bytes());
+        }
+        w.bytes(&fonts.0);
+        w.bytes(&rest.0);
+        w.0
+    }
+
+    /// Deserializes a font cache written by `to_serialized`, returns `None` if the data is
+    /// malformed or was written by a different version of this crate
+    ///
+    /// Doesn't need `std`: a cache can be generated on a host (i.e. in a build script) and
+    /// embedded with `include_bytes!`, so that a `no_std` target can query it without
+    /// scanning or parsing any font.
+    pub fn from_serialized(bytes: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(bytes);
+        if r.bytes(MAGIC.len())? != MAGIC || r.u32()? != FORMAT_VERSION || r.str()? != CRATE_VERSION
+        {
+            return None;
+        }
+
+        // no `with_capacity`, the counts aren't trusted
+        let mut strings = Vec::new();
+        for _ in 0..r.var()? {
+            let len = r.var()?;
+            let s = core::str::from_utf8(r.bytes(len)?).ok()?;
+            strings.push(Arc::<str>::from(s));
+        }
+        let mut r = FcStringReader { r, strings };
+
+        let mut fonts = Vec::new();
+        for _ in 0..r.r.var()? {
+            let pattern = r.pattern()?;
+            let path = r.path()?;
+            fonts.push((pattern, path));
+        }
+
+        let mut fallback = Vec::new();
+        for _ in 0..r.r.var()? {
+            fallback.push(FcFallbackFamily {
+                langs: r.strs()?,
+                fonts: r.paths()?,
+            });
+        }
+
+        let mut substitutes = BTreeMap::new();
+        for _ in 0..r.r.var()? {
+            substitutes.insert(r.str()?, r.str()?);
+        }
+
+        let mut font_links = BTreeMap::new();
+        for _ in 0..r.r.var()? {
+            font_links.insert(r.str()?, r.paths()?);
+        }
+
+        let mut aliases = BTreeMap::new();
+        for _ in 0..r.r.var()? {
+            aliases.insert(r.str()?, r.strs()?);
+        }
+
+        if r.r.pos != r.r.data.len() {
+            return None;
+        }
+
+        Some(FcFontCache {
+            map: FcFontStore::from_entries(fonts),
+            fallback,
+            substitutes,
+            font_links,
+            aliases,
+            ..Default::default()
+        })
+    }
+}
+
+// Strings of the data being written -> index in the string table
+#[derive(Default)]
+struct FcStringTable<'a> {
+    strings: Vec<&'a str>,
+    indices: BTreeMap<&'a str, usize>,
+}
+
+impl<'a> FcStringTable<'a> {
+    fn index(&mut self, s: &'a str) -> usize {
+        if let Some(index) = self.indices.get(s) {
+            return *index;
+        }
+        let index = self.strings.len();
+        self.strings.push(s);
+        self.indices.insert(s, index);
+        index
+    }
+
+    fn str(&mut self, w: &mut Writer, s: &'a str) {
+        w.var(self.index(s));
+    }
+
+    fn opt_str(&mut self, w: &mut Writer, s: Option<&'a str>) {
+        match s {
+            Some(s) => w.var(self.index(s) + 1),
+            None => w.var(0),
+        }
+    }
+
+    fn strs(&mut self, w: &mut Writer, strs: &'a [String]) {
+        w.var(strs.len());
+        for s in strs {
+            self.str(w, s);
+        }
+    }
+
+    fn pattern(&mut self, w: &mut Writer, pattern: &'a FcPattern) {
+        self.opt_str(w, pattern.name.as_deref());
+        self.opt_str(w, pattern.family.as_deref());
+
+        let hinting = &pattern.metadata.hinting;
+        let mut flags = [
+            &pattern.italic,
+            &pattern.oblique,
+            &pattern.bold,
+            &pattern.monospace,
+            &pattern.condensed,
+            &pattern.scalable,
+            &pattern.hinted,
+            &pattern.small_caps,
+            &pattern.tabular_figures,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |flags, (i, value)| flags | FcPackMatch(value) << (i * 2));
+        if hinting.has_fpgm {
+            flags |= FLAG_FPGM;
+        }
+        if hinting.has_prep {
+            flags |= FLAG_PREP;
+        }
+        if hinting.has_cvt {
+            flags |= FLAG_CVT;
+        }
+        if pattern.metadata.kerning.has_kern_table {
+            flags |= FLAG_KERN;
+        }
+        if pattern.metadata.kerning.has_gpos_kerning {
+            flags |= FLAG_GPOS_KERN;
+        }
+        if pattern.metadata.features.small_caps {
+            flags |= FLAG_SMCP;
+        }
+        if pattern.metadata.features.caps_to_small_caps {
+            flags |= FLAG_C2SC;
+        }
+        if pattern.metadata.features.tabular_figures {
+            flags |= FLAG_TNUM;
+        }
+        if pattern.metadata.features.monospaced_digits {
+            flags |= FLAG_MONO_DIGITS;
+        }
+        if pattern.metadata.features.old_style_figures {
+            flags |= FLAG_ONUM;
+        }
+        if pattern.metadata.features.lining_figures {
+            flags |= FLAG_LNUM;
+        }
+        flags |= match pattern.metadata.features.default_figures {
+            Some(FcFigureStyle::Lining) => FLAG_LINING_DEFAULT,
+            Some(FcFigureStyle::OldStyle) => FLAG_OLD_STYLE_DEFAULT,
+            None => 0,
+        };
+        w.u32(flags);
+
+        w.var(usize::from(pattern.weight.0));
+        w.var(pattern.unicode_range[0]);
+        w.var(pattern.unicode_range[1]);
+        match &pattern.vendor_ids {
+            Some(vendor_ids) => {
+                w.u8(1);
+                self.strs(w, vendor_ids);
+            }
+            None => w.u8(0),
+        }
+        w.formats(&pattern.formats);
+
+        w.var(hinting.gasp_ranges.len());
+        for range in hinting.gasp_ranges.iter() {
+            w.u16(range.max_ppem);
+            w.u16(range.behavior);
+        }
+        self.opt_str(w, pattern.metadata.vendor_id.as_deref());
+        w.cjk(&pattern.metadata.cjk);
+        w.scripts(&pattern.metadata.shaping);
+        w.charset(&pattern.metadata.charset);
+        w.u32(pattern.metadata.italic_angle as u32);
+        w.container(pattern.metadata.container);
+        w.format(pattern.metadata.format);
+        w.strikes(&pattern.metadata.strikes);
+        self.opt_str(w, pattern.metadata.style.as_deref());
+    }
+
+    fn path(&mut self, w: &mut Writer, path: &'a FcFontPath) {
+        match &path.source {
+            FcFontSource::Disk(file) => {
+                w.u8(0);
+                self.str(w, file);
+            }
+            FcFontSource::Memory(data) => {
+                w.u8(1);
+                w.var(data.len());
+                w.bytes(data);
+            }
+        }
+        w.var(path.font_index);
+    }
+
+    fn paths(&mut self, w: &mut Writer, paths: &'a [FcFontPath]) {
+        w.var(paths.len());
+        for path in paths {
+            self.path(w, path);
+        }
+    }
+}
+
+fn FcPackMatch(value: &PatternMatch) -> u32 {
+    match value {
+        PatternMatch::True => 0,
+        PatternMatch::False => 1,
+        PatternMatch::DontCare => 2,
+    }
+}
+
+fn FcUnpackMatch(flags: u32, i: usize) -> Option<PatternMatch> {
+    match (flags >> (i * 2)) & 0b11 {
+        0 => Some(PatternMatch::True),
+        1 => Some(PatternMatch::False),
+        2 => Some(PatternMatch::DontCare),
+        _ => None,
+    }
+}
+
+// Reader of the data after the string table
+struct FcStringReader<'a> {
+    r: Reader<'a>,
+    strings: Vec<Arc<str>>,
+}
+
+impl FcStringReader<'_> {
+    fn arc_str(&mut self) -> Option<Arc<str>> {
+        self.strings.get(self.r.var()?).cloned()
+    }
+
+    fn str(&mut self) -> Option<String> {
+        self.arc_str().map(|s| String::from(&*s))
+    }
+
+    fn opt_str(&mut self) -> Option<Option<String>> {
+        match self.r.var()? {
+            0 => Some(None),
+            index => self
+                .strings
+                .get(index - 1)
+                .map(|s| Some(String::from(&**s))),
+        }
+    }
+
+    fn strs(&mut self) -> Option<Vec<String>> {
+        let mut strs = Vec::new();
+        for _ in 0..self.r.var()? {
+            strs.push(self.str()?);
+        }
+        Some(strs)
+    }
+
+    fn pattern(&mut self) -> Option<FcPattern> {
+        let name = self.opt_str()?;
+        let family = self.opt_str()?;
+        let flags = self.r.u32()?;
+        let weight = FcWeight(u16::try_from(self.r.var()?).ok()?);
+        let unicode_range = [self.r.var()?, self.r.var()?];
+        let vendor_ids = match self.r.bool()? {
+            true => Some(self.strs()?),
+            false => None,
+        };
+        let formats = self.r.formats()?;
+        let mut gasp_ranges = Vec::new();
+        for _ in 0..self.r.var()? {
+            gasp_ranges.push(FcGaspRange {
+                max_ppem: self.r.u16()?,
+                behavior: self.r.u16()?,
+            });
+        }
+        let vendor_id = self.opt_str()?;
+        let cjk = self.r.cjk()?;
+        let shaping = self.r.scripts()?;
+        let charset = self.r.charset()?;
+        let italic_angle = self.r.u32()? as i32;
+        let container = self.r.container()?;
+        let format = self.r.format()?;
+        let strikes = self.r.strikes()?;
+        let style = self.opt_str()?;
+
+        Some(FcPattern {
+            name,
+            family,
+            italic: FcUnpackMatch(flags, 0)?,
+            oblique: FcUnpackMatch(flags, 1)?,
+            bold: FcUnpackMatch(flags, 2)?,
+            monospace: FcUnpackMatch(flags, 3)?,
+            condensed: FcUnpackMatch(flags, 4)?,
+            scalable: FcUnpackMatch(flags, 5)?,
+            hinted: FcUnpackMatch(flags, 6)?,
+            small_caps: FcUnpackMatch(flags, 7)?,
+            tabular_figures: FcUnpackMatch(flags, 8)?,
+            weight,
+            unicode_range,
+            vendor_ids,
+            formats,
+            metadata: FcFontMetadata {
+                hinting: FcHintingInfo {
+                    has_fpgm: flags & FLAG_FPGM != 0,
+                    has_prep: flags & FLAG_PREP != 0,
+                    has_cvt: flags & FLAG_CVT != 0,
+                    gasp_ranges,
+                },
+                vendor_id,
+                cjk,
+                shaping,
+                charset,
+                italic_angle,
+                container,
+                format,
+                strikes,
+                kerning: FcKerningInfo {
+                    has_kern_table: flags & FLAG_KERN != 0,
+                    has_gpos_kerning: flags & FLAG_GPOS_KERN != 0,
+                },
+                features: FcFeatureInfo {
+                    small_caps: flags & FLAG_SMCP != 0,
+                    caps_to_small_caps: flags & FLAG_C2SC != 0,
+                    tabular_figures: flags & FLAG_TNUM != 0,
+                    monospaced_digits: flags & FLAG_MONO_DIGITS != 0,
+                    old_style_figures: flags & FLAG_ONUM != 0,
+                    lining_figures: flags & FLAG_LNUM != 0,
+                    default_figures: match (
+                        flags & FLAG_LINING_DEFAULT != 0,
+                        flags & FLAG_OLD_STYLE_DEFAULT != 0,
+                    ) {
+                        (false, false) => None,
+                        (true, false) => Some(FcFigureStyle::Lining),
+                        (false, true) => Some(FcFigureStyle::OldStyle),
+                        (true, true) => return None,
+                    },
+                },
+                style,
+            },
+        })
+    }
+
+    fn path(&mut self) -> Option<FcFontPath> {
+        let source = match self.r.u8()? {
+            0 => FcFontSource::Disk(self.arc_str()?),
+            1 => {
+                let len = self.r.var()?;
+                FcFontSource::Memory(self.r.memory_font(len)?)
+            }
+            _ => return None,
+        };
+        Some(FcFontPath {
+            source,
+            font_index: self.r.var()?,
+        })
+    }
+
+    fn paths(&mut self) -> Option<Vec<FcFontPath>> {
+        let mut paths = Vec::new();
+        for _ in 0..self.r.var()? {
+            paths.push(self.path()?);
+        }
+        Some(paths)
+    }
+}
@@ -0,0 +1,118 @@
+//! Font cache shared between threads, see `FcSharedFontCache`
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{
+    FcError, FcFamily, FcFontCache, FcFontHandle, FcFontMatch, FcFontPath, FcPattern, GenericFamily,
+};
+
+/// Font cache that can be shared between threads (i.e. the layout thread and a thread
+/// that rebuilds the cache in the background), clones refer to the same cache
+///
+/// The queries return owned results, since the cache can be replaced right after the
+/// query. Use `read` to run several queries on the same version of the cache.
+#[derive(Debug, Clone, Default)]
+pub struct FcSharedFontCache {
+    cache: Arc<RwLock<FcFontCache>>,
+}
+
+impl FcSharedFontCache {
+    /// Shares a cache, same as `FcSharedFontCache::from(cache)`
+    pub fn new(cache: FcFontCache) -> Self {
+        FcSharedFontCache {
+            cache: Arc::new(RwLock::new(cache)),
+        }
+    }
+
+    /// Locks the cache for reading, blocks while the cache is written
+    ///
+    /// A panic while the cache was locked for writing doesn't make the cache unusable.
+    pub fn read(&self) -> RwLockReadGuard<'_, FcFontCache> {
+        self.cache.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the cache for writing (i.e. to `refresh` it), blocks while the cache is used
+    pub fn write(&self) -> RwLockWriteGuard<'_, FcFontCache> {
+        self.cache.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces the cache (i.e. with a cache that was built in the background),
+    /// returns the previous cache
+    pub fn replace(&self, cache: FcFontCache) -> FcFontCache {
+        core::mem::replace(&mut *self.write(), cache)
+    }
+
+    /// See `FcFontCache::query`
+    pub fn query(&self, pattern: &FcPattern) -> Option<FcFontPath> {
+        self.read().query(pattern).cloned()
+    }
+
+    /// See `FcFontCache::try_query`
+    pub fn try_query(&self, pattern: &FcPattern) -> Result<FcFontPath, FcError> {
+        self.read().try_query(pattern).cloned()
+    }
+
+    /// See `FcFontCache::query_all`
+    pub fn query_all(&self, pattern: &FcPattern) -> Vec<FcFontPath> {
+        self.read()
+            .query_all(pattern)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// See `FcFontCache::query_sorted`
+    pub fn query_sorted(&self, pattern: &FcPattern, lang: Option<&str>) -> Vec<FcFontPath> {
+        self.read()
+            .query_sorted(pattern, lang)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// See `FcFontCache::query_owned`
+    pub fn query_owned(&self, pattern: &FcPattern) -> Option<FcFontMatch> {
+        self.read().query_owned(pattern)
+    }
+
+    /// See `FcFontCache::query_handle`, the handle stays valid when the cache is replaced
+    pub fn query_handle(&self, pattern: &FcPattern) -> Option<FcFontHandle> {
+        self.read().query_handle(pattern)
+    }
+
+    /// See `FcFontCache::default_font`
+    pub fn default_font(&self, generic: GenericFamily) -> Option<FcFontPath> {
+        self.read().default_font(generic).cloned()
+    }
+
+    /// See `FcFontCache::default_ui_font`
+    pub fn default_ui_font(&self) -> Option<FcFontPath> {
+        self.read().default_ui_font().cloned()
+    }
+
+    /// See `FcFontCache::substitute`
+    pub fn substitute(&self, family: &str) -> Option<String> {
+        self.read().substitute(family).map(String::from)
+    }
+
+    /// See `FcFontCache::families`
+    pub fn families(&self) -> Vec<FcFamily> {
+        self.read().families()
+    }
+}
+
+impl From<FcFontCache> for FcSharedFontCache {
+    fn from(cache: FcFontCache) -> Self {
+        FcSharedFontCache::new(cache)
+    }
+}
+
+/// i.e. the cache of `FcFontCacheWatcher::cache`
+impl From<Arc<RwLock<FcFontCache>>> for FcSharedFontCache {
+    fn from(cache: Arc<RwLock<FcFontCache>>) -> Self {
+        FcSharedFontCache { cache }
+    }
+}
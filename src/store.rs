@@ -0,0 +1,578 @@
+//! Storage of the cached fonts
+//!
+//! The fonts are kept in a single vector sorted by pattern, with the indices of the
+//! entries by exact name and family, and the properties that queries match on packed
+//! into a few bits per entry. A query only visits the entries of the requested name or
+//! family, and compares the bits before the full patterns. Queries for a character
+//! only visit the entries whose charset has the 256-character page of the character.
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use crate::bloom::FcCoverageFilters;
+use crate::{FcCharset, FcFontCache, FcFontPath, FcMergePolicy, FcPattern, PatternMatch};
+
+// Below this many candidates, filtering on the current thread is faster than splitting the work
+#[cfg(feature = "multithreading")]
+const PARALLEL_QUERY_MIN_FONTS: usize = 1024;
+
+/// Fonts of a `FcFontCache`, sorted by pattern and path (every pattern is stored once,
+/// unless caches were merged with `FcMergePolicy::KeepBoth`)
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub(crate) struct FcFontStore {
+    entries: Vec<(FcPattern, FcFontPath)>,
+    // name / family -> indices of the entries, ascending
+    names: BTreeMap<String, Vec<u32>>,
+    families: BTreeMap<String, Vec<u32>>,
+    // `FcPropertyBits` of every entry
+    properties: Vec<u16>,
+    // indices of the entries sorted by path, see `with_path`
+    paths: Vec<u32>,
+    // page of 256 characters -> indices of the entries with characters in it, ascending,
+    // see `with_char` (empty if `filters` is used instead)
+    pages: BTreeMap<u32, Vec<u32>>,
+    // Bloom filters of the characters of the entries, see `FcFontCache::use_coverage_filters`
+    filters: Option<FcCoverageFilters>,
+    // results of `find`, see `FcFontCache::memoize_queries`
+    #[cfg(feature = "std")]
+    memo: FcQueryMemo,
+}
+
+// Entries that can match a query
+enum FcCandidates<'a> {
+    All(usize),
+    Indices(&'a [u32]),
+}
+
+impl FcCandidates<'_> {
+    fn len(&self) -> usize {
+        match self {
+            FcCandidates::All(len) => *len,
+            FcCandidates::Indices(indices) => indices.len(),
+        }
+    }
+
+    fn get(&self, i: usize) -> usize {
+        match self {
+            FcCandidates::All(_) => i,
+            FcCandidates::Indices(indices) => indices[i] as usize,
+        }
+    }
+}
+
+impl FcFontStore {
+    /// Creates a store that keeps all fonts, even ones with the same pattern
+    pub(crate) fn from_entries(entries: Vec<(FcPattern, FcFontPath)>) -> Self {
+        let mut store = FcFontStore::default();
+        store.set_entries(entries);
+        store
+    }
+
+    /// Replaces all fonts, keeps fonts with the same pattern
+    pub(crate) fn set_entries(&mut self, mut entries: Vec<(FcPattern, FcFontPath)>) {
+        entries.sort();
+        entries.dedup();
+        self.entries = entries;
+        self.reindex();
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_memo_capacity(&mut self, capacity: usize) {
+        self.memo = FcQueryMemo::new(capacity);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn entries(&self) -> &[(FcPattern, FcFontPath)] {
+        &self.entries
+    }
+
+    pub(crate) fn iter(&self) -> core::slice::Iter<'_, (FcPattern, FcFontPath)> {
+        self.entries.iter()
+    }
+
+    pub(crate) fn paths(&self) -> impl Iterator<Item = &FcFontPath> {
+        self.entries.iter().map(|(_, path)| path)
+    }
+
+    /// Returns the fonts that support a character, in pattern order
+    pub(crate) fn with_char(&self, c: char) -> impl Iterator<Item = &(FcPattern, FcFontPath)> {
+        let candidates = match self.filters.as_ref() {
+            Some(_) => FcCandidates::All(self.entries.len()),
+            None => match self.pages.get(&FcCharset::page(c)) {
+                Some(indices) => FcCandidates::Indices(indices),
+                None => FcCandidates::Indices(&[]),
+            },
+        };
+        (0..candidates.len())
+            .map(move |i| candidates.get(i))
+            .filter(move |i| match self.filters.as_ref() {
+                Some(filters) => filters.might_contain(*i, c),
+                None => true,
+            })
+            .map(move |i| &self.entries[i])
+            .filter(move |(pattern, _)| pattern.metadata.charset.contains(c))
+    }
+
+    /// Returns the fonts of a font file (or in-memory data) and index, in pattern order
+    pub(crate) fn with_path(
+        &self,
+        path: &FcFontPath,
+    ) -> impl Iterator<Item = &(FcPattern, FcFontPath)> {
+        self.path_indices(path)
+            .iter()
+            .map(move |i| &self.entries[*i as usize])
+    }
+
+    /// Whether a font might support a character, only false if the Bloom filters (see
+    /// `set_coverage_filters`) rule it out, without looking at its charset
+    #[cfg(all(feature = "std", feature = "scan"))]
+    pub(crate) fn might_contain(&self, path: &FcFontPath, c: char) -> bool {
+        let filters = match self.filters.as_ref() {
+            Some(filters) => filters,
+            None => return true,
+        };
+        let indices = self.path_indices(path);
+        indices.is_empty()
+            || indices
+                .iter()
+                .any(|i| filters.might_contain(*i as usize, c))
+    }
+
+    // Indices of the entries of a path, in pattern order
+    fn path_indices(&self, path: &FcFontPath) -> &[u32] {
+        let path_of = |i: &u32| &self.entries[*i as usize].1;
+        let start = self.paths.partition_point(|i| path_of(i) < path);
+        let end = self.paths.partition_point(|i| path_of(i) <= path);
+        &self.paths[start..end]
+    }
+
+    /// Switches the index of `with_char` between pages and Bloom filters
+    pub(crate) fn set_coverage_filters(&mut self, enabled: bool) {
+        self.filters = if enabled {
+            Some(FcCoverageFilters::default())
+        } else {
+            None
+        };
+        self.reindex();
+    }
+
+    pub(crate) fn retain<F: FnMut(&FcPattern, &FcFontPath) -> bool>(&mut self, mut keep: F) {
+        let len = self.entries.len();
+        self.entries.retain(|(pattern, path)| keep(pattern, path));
+        if self.entries.len() != len {
+            self.reindex();
+        }
+    }
+
+    /// Returns the first font matching the pattern, in pattern order
+    pub(crate) fn find(&self, pattern: &FcPattern) -> Option<&(FcPattern, FcFontPath)> {
+        #[cfg(feature = "std")]
+        {
+            if let Some(index) = self.memo.get(pattern) {
+                return index.map(|index| &self.entries[index]);
+            }
+            let index = self.find_index(pattern);
+            self.memo.insert(pattern, index);
+            index.map(|index| &self.entries[index])
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            self.find_index(pattern).map(|index| &self.entries[index])
+        }
+    }
+
+    fn find_index(&self, pattern: &FcPattern) -> Option<usize> {
+        let candidates = self.candidates(pattern);
+        let (mask, bits) = FcPropertyMask(pattern);
+        let matches = |i: usize| {
+            let index = candidates.get(i);
+            self.properties[index] & mask == bits
+                && FcFontCache::query_matches_internal(&self.entries[index].0, pattern)
+        };
+
+        #[cfg(feature = "multithreading")]
+        {
+            if candidates.len() >= PARALLEL_QUERY_MIN_FONTS {
+                use rayon::prelude::*;
+
+                // the first match, same as the sequential search
+                return (0..candidates.len())
+                    .into_par_iter()
+                    .find_first(|i| matches(*i))
+                    .map(|i| candidates.get(i));
+            }
+        }
+
+        (0..candidates.len())
+            .find(|i| matches(*i))
+            .map(|i| candidates.get(i))
+    }
+
+    /// Returns all fonts matching the pattern, in pattern order
+    pub(crate) fn filter(&self, pattern: &FcPattern) -> Vec<&FcFontPath> {
+        self.filter_entries(pattern)
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect()
+    }
+
+    /// Same as `filter`, but also returns the patterns of the fonts
+    pub(crate) fn filter_entries(&self, pattern: &FcPattern) -> Vec<&(FcPattern, FcFontPath)> {
+        let candidates = self.candidates(pattern);
+        let (mask, bits) = FcPropertyMask(pattern);
+        let matches = |i: &usize| {
+            let index = candidates.get(*i);
+            self.properties[index] & mask == bits
+                && FcFontCache::query_matches_internal(&self.entries[index].0, pattern)
+        };
+
+        #[cfg(feature = "multithreading")]
+        {
+            if candidates.len() >= PARALLEL_QUERY_MIN_FONTS {
+                use rayon::prelude::*;
+
+                // collecting keeps the order of the candidates
+                return (0..candidates.len())
+                    .into_par_iter()
+                    .filter(matches)
+                    .map(|i| &self.entries[candidates.get(i)])
+                    .collect();
+            }
+        }
+
+        (0..candidates.len())
+            .filter(matches)
+            .map(|i| &self.entries[candidates.get(i)])
+            .collect()
+    }
+
+    /// Adds the fonts of another store, see `FcFontCache::merge`
+    pub(crate) fn merge(&mut self, other: FcFontStore, policy: FcMergePolicy) {
+        match policy {
+            FcMergePolicy::PreferSelf => {
+                let fonts = other
+                    .entries
+                    .into_iter()
+                    .filter(|(pattern, _)| !self.contains(pattern))
+                    .collect::<Vec<_>>();
+                self.entries.extend(fonts);
+            }
+            FcMergePolicy::PreferOther => {
+                self.entries.retain(|(pattern, _)| !other.contains(pattern));
+                self.entries.extend(other.entries);
+            }
+            FcMergePolicy::KeepBoth => self.entries.extend(other.entries),
+        }
+        let entries = core::mem::take(&mut self.entries);
+        self.set_entries(entries);
+    }
+
+    fn contains(&self, pattern: &FcPattern) -> bool {
+        self.entries
+            .binary_search_by(|(p, _)| p.cmp(pattern))
+            .is_ok()
+    }
+
+    fn candidates(&self, pattern: &FcPattern) -> FcCandidates<'_> {
+        // names and families are compared exactly, see `query_matches_internal`
+        let index = match (pattern.name.as_ref(), pattern.family.as_ref()) {
+            (Some(name), _) => self.names.get(name),
+            (None, Some(family)) => self.families.get(family),
+            (None, None) => return FcCandidates::All(self.entries.len()),
+        };
+        FcCandidates::Indices(index.map_or(&[][..], |indices| &indices[..]))
+    }
+
+    fn reindex(&mut self) {
+        // the memoized results are indices of the entries
+        #[cfg(feature = "std")]
+        self.memo.clear();
+
+        self.names.clear();
+        self.families.clear();
+        self.properties.clear();
+        self.pages.clear();
+        if let Some(filters) = self.filters.as_mut() {
+            filters.clear();
+        }
+
+        for (i, (pattern, _)) in self.entries.iter().enumerate() {
+            let i = i as u32;
+            if let Some(name) = pattern.name.as_ref() {
+                self.names.entry(name.clone()).or_default().push(i);
+            }
+            if let Some(family) = pattern.family.as_ref() {
+                self.families.entry(family.clone()).or_default().push(i);
+            }
+            self.properties.push(FcPropertyBits(pattern));
+            match self.filters.as_mut() {
+                Some(filters) => filters.push(&pattern.metadata.charset),
+                None => {
+                    for page in pattern.metadata.charset.pages() {
+                        self.pages.entry(page).or_default().push(i);
+                    }
+                }
+            }
+        }
+
+        // stable, entries of the same path stay in pattern order
+        let entries = &self.entries;
+        let mut paths = (0..entries.len() as u32).collect::<Vec<_>>();
+        paths.sort_by(|a, b| entries[*a as usize].1.cmp(&entries[*b as usize].1));
+        self.paths = paths;
+    }
+}
+
+impl FromIterator<(FcPattern, FcFontPath)> for FcFontStore {
+    fn from_iter<I: IntoIterator<Item = (FcPattern, FcFontPath)>>(iter: I) -> Self {
+        let mut store = FcFontStore::default();
+        store.extend(iter);
+        store
+    }
+}
+
+impl Extend<(FcPattern, FcFontPath)> for FcFontStore {
+    /// Adds the fonts, a pattern that is already stored gets the new path
+    fn extend<I: IntoIterator<Item = (FcPattern, FcFontPath)>>(&mut self, iter: I) {
+        let fonts = iter.into_iter().collect::<BTreeMap<_, _>>();
+        if fonts.is_empty() {
+            return;
+        }
+        self.entries
+            .retain(|(pattern, _)| !fonts.contains_key(pattern));
+        self.entries.extend(fonts);
+        self.entries.sort();
+        self.reindex();
+    }
+}
+
+impl IntoIterator for FcFontStore {
+    type Item = (FcPattern, FcFontPath);
+    type IntoIter = alloc::vec::IntoIter<(FcPattern, FcFontPath)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl IntoIterator for FcFontCache {
+    type Item = (FcPattern, FcFontPath);
+    type IntoIter = alloc::vec::IntoIter<(FcPattern, FcFontPath)>;
+
+    /// Iterates over the fonts in pattern order, the rest of the cache (fallback order,
+    /// aliases, scanned directories, ...) is dropped
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FcFontCache {
+    type Item = &'a (FcPattern, FcFontPath);
+    type IntoIter = core::slice::Iter<'a, (FcPattern, FcFontPath)>;
+
+    /// Same as `FcFontCache::list().iter()`
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+impl FromIterator<(FcPattern, FcFontPath)> for FcFontCache {
+    /// Creates a cache of the given fonts, without a fallback order or aliases
+    fn from_iter<I: IntoIterator<Item = (FcPattern, FcFontPath)>>(iter: I) -> Self {
+        FcFontCache {
+            map: iter.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Extend<(FcPattern, FcFontPath)> for FcFontCache {
+    /// Adds the fonts, a pattern that is already in the cache gets the new path
+    ///
+    /// The files of the fonts aren't watched by `refresh`, same as for in-memory fonts.
+    fn extend<I: IntoIterator<Item = (FcPattern, FcFontPath)>>(&mut self, iter: I) {
+        self.map.extend(iter);
+    }
+}
+
+// Only the fonts are serialized, the indices are rebuilt (and the memo starts disabled)
+#[cfg(feature = "serde")]
+impl serde::Serialize for FcFontStore {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.entries.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FcFontStore {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<(FcPattern, FcFontPath)>::deserialize(deserializer)?;
+        Ok(FcFontStore::from_entries(entries))
+    }
+}
+
+// Properties of `query_matches_internal`, two bits each (at most 8 fit into the mask)
+const PROPERTY_COUNT: usize = 8;
+
+fn FcProperties(pattern: &FcPattern) -> [&PatternMatch; PROPERTY_COUNT] {
+    [
+        &pattern.italic,
+        &pattern.oblique,
+        &pattern.bold,
+        &pattern.monospace,
+        &pattern.scalable,
+        &pattern.hinted,
+        &pattern.small_caps,
+        &pattern.tabular_figures,
+    ]
+}
+
+fn FcPropertyBits(pattern: &FcPattern) -> u16 {
+    FcProperties(pattern)
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, property)| {
+            let value = match property {
+                PatternMatch::True => 0b01,
+                PatternMatch::False => 0b10,
+                PatternMatch::DontCare => 0b11,
+            };
+            bits | value << (i * 2)
+        })
+}
+
+// Mask of the properties the pattern needs to match and their expected bits
+fn FcPropertyMask(pattern: &FcPattern) -> (u16, u16) {
+    let bits = FcPropertyBits(pattern);
+    let mask = FcProperties(pattern)
+        .iter()
+        .enumerate()
+        .filter(|(_, property)| property.needs_to_match())
+        .fold(0, |mask, (i, _)| mask | 0b11 << (i * 2));
+    (mask, bits & mask)
+}
+
+// Least recently used query results, the mutex keeps `FcFontCache` `Sync`
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct FcQueryMemo {
+    // 0 = disabled, outside the mutex so disabled memos never lock
+    capacity: usize,
+    state: std::sync::Mutex<FcQueryMemoState>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct FcQueryMemoState {
+    // incremented on every use, to find the least recently used pattern
+    tick: u64,
+    // pattern -> index of the first match and the tick of its last use
+    results: BTreeMap<FcPattern, (Option<usize>, u64)>,
+    // tick of the last use -> pattern
+    order: BTreeMap<u64, FcPattern>,
+}
+
+#[cfg(feature = "std")]
+impl FcQueryMemo {
+    fn new(capacity: usize) -> Self {
+        FcQueryMemo {
+            capacity,
+            state: Default::default(),
+        }
+    }
+
+    fn get(&self, pattern: &FcPattern) -> Option<Option<usize>> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut state = self.state.lock().ok()?;
+        let state = &mut *state;
+        state.tick += 1;
+        let (index, last_used) = state.results.get_mut(pattern)?;
+        let pattern = state.order.remove(last_used)?;
+        *last_used = state.tick;
+        state.order.insert(state.tick, pattern);
+        Some(*index)
+    }
+
+    fn insert(&self, pattern: &FcPattern, index: Option<usize>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        while state.results.len() >= self.capacity {
+            let oldest = match state.order.keys().next() {
+                Some(oldest) => *oldest,
+                None => break,
+            };
+            if let Some(pattern) = state.order.remove(&oldest) {
+                state.results.remove(&pattern);
+            }
+        }
+
+        state.tick += 1;
+        let tick = state.tick;
+        state.results.insert(pattern.clone(), (index, tick));
+        state.order.insert(tick, pattern.clone());
+    }
+
+    fn clear(&self) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Ok(mut state) = self.state.lock() {
+            state.results.clear();
+            state.order.clear();
+        }
+    }
+}
+
+// Copies only keep the capacity, the memoized results don't take part in comparisons
+#[cfg(feature = "std")]
+impl Clone for FcQueryMemo {
+    fn clone(&self) -> Self {
+        FcQueryMemo::new(self.capacity)
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for FcQueryMemo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FcQueryMemo")
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for FcQueryMemo {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for FcQueryMemo {}
+
+#[cfg(feature = "std")]
+impl PartialOrd for FcQueryMemo {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Ord for FcQueryMemo {
+    fn cmp(&self, _: &Self) -> core::cmp::Ordering {
+        core::cmp::Ordering::Equal
+    }
+}
@@ -0,0 +1,261 @@
+//! Font parser based on `ttf-parser` (`ttf-parser` feature), a lighter alternative to
+//! allsorts (`parsing` feature) that reads the same tables and yields the same patterns
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use ttf_parser::name::{Name, Names};
+use ttf_parser::{name_id, PlatformId, RawFace, Tag};
+
+use crate::cjk::FcParseCjkInfo;
+use crate::coverage::FcParseCharset;
+use crate::features::{FcParseFeatureInfo, FcParseFigureStyle};
+use crate::kerning::FcParseKerningInfo;
+use crate::script::FcShapingScripts;
+use crate::utils::{read_u16, read_u32};
+use crate::{
+    FcFeatureInfo, FcFontContainer, FcFontFormat, FcFontMetadata, FcHintingInfo, FcParseGaspRanges,
+    FcParseStrikes, FcPattern, FcWeight, PatternMatch,
+};
+
+const TAG_HEAD: Tag = Tag::from_bytes(b"head");
+const TAG_POST: Tag = Tag::from_bytes(b"post");
+const TAG_OS2: Tag = Tag::from_bytes(b"OS/2");
+const TAG_HHEA: Tag = Tag::from_bytes(b"hhea");
+const TAG_HMTX: Tag = Tag::from_bytes(b"hmtx");
+const TAG_NAME: Tag = Tag::from_bytes(b"name");
+const TAG_GSUB: Tag = Tag::from_bytes(b"GSUB");
+const TAG_CMAP: Tag = Tag::from_bytes(b"cmap");
+
+// outline tables, a font without any of these can only be rendered from its bitmap strikes
+const TAG_GLYF: Tag = Tag::from_bytes(b"glyf");
+const TAG_CFF: Tag = Tag::from_bytes(b"CFF ");
+const TAG_CFF2: Tag = Tag::from_bytes(b"CFF2");
+const TAG_LOCA: Tag = Tag::from_bytes(b"loca");
+
+// bitmap strike locations of monochrome / grayscale and color bitmaps
+const TAG_EBLC: Tag = Tag::from_bytes(b"EBLC");
+const TAG_CBLC: Tag = Tag::from_bytes(b"CBLC");
+
+// legacy kerning pairs and OpenType positioning
+const TAG_KERN: Tag = Tag::from_bytes(b"kern");
+const TAG_GPOS: Tag = Tag::from_bytes(b"GPOS");
+
+// hinting tables
+const TAG_FPGM: Tag = Tag::from_bytes(b"fpgm");
+const TAG_PREP: Tag = Tag::from_bytes(b"prep");
+const TAG_CVT: Tag = Tag::from_bytes(b"cvt ");
+const TAG_GASP: Tag = Tag::from_bytes(b"gasp");
+
+// Parses the patterns of a single face of a font file
+pub(crate) fn FcParseFontFace(font_bytes: &[u8], font_index: usize) -> Option<Vec<FcPattern>> {
+    let face = RawFace::parse(font_bytes, u32::try_from(font_index).ok()?).ok()?;
+
+    // macStyle, see https://learn.microsoft.com/en-us/typography/opentype/spec/head
+    let mac_style = read_u16(face.table(TAG_HEAD)?, 44)?;
+    let is_bold = mac_style & 1 != 0;
+    let is_italic = mac_style & 2 != 0;
+
+    let os2_data = face.table(TAG_OS2);
+
+    // italicAngle, Fixed at offset 4 of the post table
+    let italic_angle = face
+        .table(TAG_POST)
+        .and_then(|post| read_u32(post, 4))
+        .map_or(0, |angle| angle as i32);
+
+    // isFixedPitch here - https://learn.microsoft.com/en-us/typography/opentype/spec/post#header
+    let is_monospace = match face.table(TAG_POST).and_then(|post| read_u32(post, 12)) {
+        Some(is_fixed_pitch) => is_fixed_pitch != 0,
+        // https://learn.microsoft.com/en-us/typography/opentype/spec/os2#panose
+        None => match os2_data.and_then(|os2_data| os2_data.get(32)) {
+            Some(family_type) => *family_type == 2,
+            None => FcHasEqualAdvances(&face).unwrap_or(false),
+        },
+    };
+
+    let format = if face.table(TAG_GLYF).is_some() {
+        FcFontFormat::TrueType
+    } else if face.table(TAG_CFF).is_some() || face.table(TAG_CFF2).is_some() {
+        FcFontFormat::Cff
+    } else {
+        FcFontFormat::Bitmap
+    };
+    let is_scalable = format != FcFontFormat::Bitmap;
+
+    // achVendID, see https://learn.microsoft.com/en-us/typography/opentype/spec/os2#achvendid
+    let vendor_id = os2_data.and_then(|os2_data| {
+        let vendor = os2_data.get(58..62)?;
+        let vendor = String::from_utf8_lossy(vendor);
+        let vendor = vendor.trim_end_matches([' ', '\0']);
+        if vendor.is_empty() {
+            None
+        } else {
+            Some(vendor.into())
+        }
+    });
+
+    // usWeightClass, see https://learn.microsoft.com/en-us/typography/opentype/spec/os2#usweightclass
+    let weight = os2_data
+        .and_then(|os2_data| read_u16(os2_data, 4))
+        .map(FcWeight::from)
+        .unwrap_or_default();
+
+    let hinting = FcHintingInfo {
+        has_fpgm: face.table(TAG_FPGM).is_some(),
+        has_prep: face.table(TAG_PREP).is_some(),
+        has_cvt: face.table(TAG_CVT).is_some(),
+        gasp_ranges: face
+            .table(TAG_GASP)
+            .map(FcParseGaspRanges)
+            .unwrap_or_default(),
+    };
+
+    let name_data = face.table(TAG_NAME)?;
+    let cjk = FcParseCjkInfo(os2_data, Some(name_data), face.table(TAG_GSUB));
+    let shaping = face
+        .table(TAG_GSUB)
+        .map(FcShapingScripts)
+        .unwrap_or_default();
+    let charset = face.table(TAG_CMAP).map(FcParseCharset).unwrap_or_default();
+    let strikes = FcParseStrikes(face.table(TAG_EBLC), face.table(TAG_CBLC));
+    let kerning = FcParseKerningInfo(face.table(TAG_KERN), face.table(TAG_GPOS));
+    let features = FcFeatureInfo {
+        default_figures: FcParseFigureStyle(
+            face.table(TAG_CMAP),
+            face.table(TAG_HEAD),
+            face.table(TAG_LOCA),
+            face.table(TAG_GLYF),
+        ),
+        ..FcParseFeatureInfo(
+            face.table(TAG_GSUB),
+            face.table(TAG_CMAP),
+            face.table(TAG_HHEA),
+            face.table(TAG_HMTX),
+        )
+    };
+
+    let (family, name) = match FcFontNames(name_data) {
+        Some(names) => names,
+        None => return Some(Vec::new()),
+    };
+    if name.is_empty() {
+        return Some(Vec::new());
+    }
+
+    Some(vec![FcPattern {
+        name: Some(name),
+        family: Some(family),
+        bold: PatternMatch::from(is_bold),
+        italic: PatternMatch::from(is_italic),
+        monospace: PatternMatch::from(is_monospace),
+        scalable: PatternMatch::from(is_scalable),
+        hinted: PatternMatch::from(hinting.is_hinted()),
+        small_caps: PatternMatch::from(features.small_caps),
+        tabular_figures: PatternMatch::from(features.has_tabular_figures()),
+        weight,
+        metadata: FcFontMetadata {
+            hinting,
+            vendor_id,
+            cjk,
+            shaping,
+            charset,
+            italic_angle,
+            container: FcFontContainer::Sfnt,
+            format,
+            strikes,
+            kerning,
+            features,
+            style: FcFontStyle(name_data),
+        },
+        ..Default::default()
+    }])
+}
+
+// Whether all glyphs have the same advance width (fonts without post and OS/2 tables)
+fn FcHasEqualAdvances(face: &RawFace<'_>) -> Option<bool> {
+    let num_h_metrics = read_u16(face.table(TAG_HHEA)?, 34)? as usize;
+    let hmtx_data = face.table(TAG_HMTX)?;
+
+    let mut advances = (0..num_h_metrics).map(|i| read_u16(hmtx_data, i * 4));
+    let first = advances.next().flatten();
+    for advance in advances {
+        if advance? != first? {
+            return Some(false);
+        }
+    }
+    Some(true)
+}
+
+// Family and full name of a `name` table
+pub(crate) fn FcFontNames(name_data: &[u8]) -> Option<(String, String)> {
+    let names = ttf_parser::name::Table::parse(name_data)?.names;
+    Some((
+        FcFindName(names, name_id::FAMILY)?,
+        FcFindName(names, name_id::FULL_NAME)?,
+    ))
+}
+
+// Typographic subfamily of a `name` table, or else the legacy subfamily
+fn FcFontStyle(name_data: &[u8]) -> Option<String> {
+    let names = ttf_parser::name::Table::parse(name_data)?.names;
+    [name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY]
+        .iter()
+        .find_map(|id| FcFindName(names, *id).filter(|style| !style.is_empty()))
+}
+
+// First unicode name of a name ID, or else the first Mac Roman name (fonts made for
+// classic Mac OS only have Macintosh names)
+fn FcFindName(names: Names<'_>, id: u16) -> Option<String> {
+    let unicode = names
+        .into_iter()
+        .filter(|name| name.name_id == id && name.is_unicode())
+        .find_map(|name| name.to_string());
+    unicode.or_else(|| {
+        names
+            .into_iter()
+            .filter(|name| name.name_id == id)
+            .find_map(|name| FcMacRomanName(&name))
+    })
+}
+
+// characters 0x80..=0xFF of the Mac Roman encoding (Macintosh names of encoding 0)
+#[rustfmt::skip]
+const MAC_ROMAN: [char; 128] = [
+    '\u{00C4}', '\u{00C5}', '\u{00C7}', '\u{00C9}', '\u{00D1}', '\u{00D6}', '\u{00DC}', '\u{00E1}',
+    '\u{00E0}', '\u{00E2}', '\u{00E4}', '\u{00E3}', '\u{00E5}', '\u{00E7}', '\u{00E9}', '\u{00E8}',
+    '\u{00EA}', '\u{00EB}', '\u{00ED}', '\u{00EC}', '\u{00EE}', '\u{00EF}', '\u{00F1}', '\u{00F3}',
+    '\u{00F2}', '\u{00F4}', '\u{00F6}', '\u{00F5}', '\u{00FA}', '\u{00F9}', '\u{00FB}', '\u{00FC}',
+    '\u{2020}', '\u{00B0}', '\u{00A2}', '\u{00A3}', '\u{00A7}', '\u{2022}', '\u{00B6}', '\u{00DF}',
+    '\u{00AE}', '\u{00A9}', '\u{2122}', '\u{00B4}', '\u{00A8}', '\u{2260}', '\u{00C6}', '\u{00D8}',
+    '\u{221E}', '\u{00B1}', '\u{2264}', '\u{2265}', '\u{00A5}', '\u{00B5}', '\u{2202}', '\u{2211}',
+    '\u{220F}', '\u{03C0}', '\u{222B}', '\u{00AA}', '\u{00BA}', '\u{03A9}', '\u{00E6}', '\u{00F8}',
+    '\u{00BF}', '\u{00A1}', '\u{00AC}', '\u{221A}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00AB}',
+    '\u{00BB}', '\u{2026}', '\u{00A0}', '\u{00C0}', '\u{00C3}', '\u{00D5}', '\u{0152}', '\u{0153}',
+    '\u{2013}', '\u{2014}', '\u{201C}', '\u{201D}', '\u{2018}', '\u{2019}', '\u{00F7}', '\u{25CA}',
+    '\u{00FF}', '\u{0178}', '\u{2044}', '\u{20AC}', '\u{2039}', '\u{203A}', '\u{FB01}', '\u{FB02}',
+    '\u{2021}', '\u{00B7}', '\u{201A}', '\u{201E}', '\u{2030}', '\u{00C2}', '\u{00CA}', '\u{00C1}',
+    '\u{00CB}', '\u{00C8}', '\u{00CD}', '\u{00CE}', '\u{00CF}', '\u{00CC}', '\u{00D3}', '\u{00D4}',
+    '\u{F8FF}', '\u{00D2}', '\u{00DA}', '\u{00DB}', '\u{00D9}', '\u{0131}', '\u{02C6}', '\u{02DC}',
+    '\u{00AF}', '\u{02D8}', '\u{02D9}', '\u{02DA}', '\u{00B8}', '\u{02DD}', '\u{02DB}', '\u{02C7}',
+];
+
+// Text of a Macintosh name in the Mac Roman encoding, `ttf-parser` only decodes unicode names
+fn FcMacRomanName(name: &Name<'_>) -> Option<String> {
+    if name.platform_id != PlatformId::Macintosh || name.encoding_id != 0 {
+        return None;
+    }
+    let text = name.name.iter().map(|byte| match byte {
+        0..=0x7F => char::from(*byte),
+        _ => MAC_ROMAN[usize::from(byte - 0x80)],
+    });
+    Some(text.collect())
+}
+
+// Data of a table of a single face (of a font collection)
+pub(crate) fn FcFaceTableData(face_bytes: &[u8], face_index: usize, tag: u32) -> Option<Vec<u8>> {
+    let face = RawFace::parse(face_bytes, u32::try_from(face_index).ok()?).ok()?;
+    face.table(Tag(tag)).map(<[u8]>::to_vec)
+}
@@ -0,0 +1,192 @@
+//! PostScript Type 1 fonts (`.pfb` / `.pfa`), see `FcFontContainer::Type1`
+//!
+//! The patterns are read from the clear-text part of the font program (the `FontInfo`
+//! dictionary before `eexec`), values the font doesn't have are taken from the AFM
+//! file next to it. The glyphs are encrypted, so the charset only has the printable
+//! ASCII characters of fonts with the standard encoding (empty for symbol fonts).
+//!
+//! See: https://adobe-type-tools.github.io/font-tech-notes/pdfs/T1_SPEC.pdf and
+//! https://adobe-type-tools.github.io/font-tech-notes/pdfs/5004.AFM_Spec.pdf
+
+use alloc::string::String;
+use core::str::FromStr;
+use std::path::Path;
+
+use crate::utils::read_u32;
+use crate::{
+    FcCharset, FcFontContainer, FcFontFormat, FcFontMetadata, FcPattern, FcWeight, PatternMatch,
+};
+
+// Parses the pattern of a Type 1 font file, with the AFM file of the same name (if there
+// is one) for the values the font doesn't have
+pub(crate) fn FcParseType1File(path: &Path, font_bytes: &[u8]) -> Option<FcPattern> {
+    let afm = ["afm", "AFM"]
+        .iter()
+        .find_map(|extension| std::fs::read(path.with_extension(extension)).ok());
+    FcParseType1(font_bytes, afm.as_deref())
+}
+
+// Parses the pattern of a Type 1 font, `None` if it isn't one or has no name
+pub(crate) fn FcParseType1(font_bytes: &[u8], afm: Option<&[u8]>) -> Option<FcPattern> {
+    let header = FcLatin1(FcType1Header(font_bytes)?);
+    let afm = afm.map(FcLatin1).unwrap_or_default();
+
+    let string = |key: &str, afm_key: &str| {
+        FcPsString(&header, key).or_else(|| FcAfmValue(&afm, afm_key).map(String::from))
+    };
+    let token = |key: &str, afm_key: &str| {
+        FcPsToken(&header, key)
+            .or_else(|| FcAfmValue(&afm, afm_key))
+            .map(String::from)
+    };
+
+    // /FontName is a name (`/NimbusSans-Regular`), the family and full name fall back to it
+    let font_name = FcPsToken(&header, "/FontName")
+        .map(|name| name.trim_start_matches('/'))
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .or_else(|| FcAfmValue(&afm, "FontName").map(String::from));
+    let name = string("/FullName", "FullName").or_else(|| font_name.clone())?;
+    let family = string("/FamilyName", "FamilyName")
+        .or_else(|| Some(font_name.as_ref()?.split('-').next()?.into()))
+        .unwrap_or_else(|| name.clone());
+
+    let weight = string("/Weight", "Weight").map_or(FcWeight::REGULAR, |weight| {
+        FcWeight::from_style_name(&weight)
+    });
+
+    // in degrees, counter-clockwise from the vertical
+    let italic_angle = token("/ItalicAngle", "ItalicAngle")
+        .and_then(|angle| f32::from_str(&angle).ok())
+        .map_or(0, |angle| (angle * 65536.0) as i32);
+
+    let is_monospace = token("/isFixedPitch", "IsFixedPitch").is_some_and(|value| value == "true");
+
+    let charset = match FcPsToken(&header, "/Encoding") {
+        Some("StandardEncoding") => FcCharset::from_ranges(Some(' '..='~')),
+        _ => FcCharset::new(),
+    };
+
+    Some(FcPattern {
+        name: Some(name),
+        family: Some(family),
+        // same as FreeType, Type 1 fonts are italic if they are slanted
+        italic: PatternMatch::from(italic_angle != 0),
+        bold: PatternMatch::from(weight.is_bold()),
+        monospace: PatternMatch::from(is_monospace),
+        scalable: PatternMatch::True,
+        weight,
+        metadata: FcFontMetadata {
+            charset,
+            italic_angle,
+            container: FcFontContainer::Type1,
+            format: FcFontFormat::Type1,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+// Clear-text part of the font program: the first segment of a PFB file, the text up to
+// `eexec` of a PFA file
+fn FcType1Header(font_bytes: &[u8]) -> Option<&[u8]> {
+    const PFB_ASCII_SEGMENT: [u8; 2] = [0x80, 0x01];
+
+    if font_bytes.get(..2)? == PFB_ASCII_SEGMENT {
+        // segment length, little-endian
+        let length = read_u32(font_bytes, 2)?.swap_bytes() as usize;
+        return font_bytes.get(6..6 + length);
+    }
+    let end = font_bytes
+        .windows(5)
+        .position(|window| window == b"eexec")
+        .unwrap_or(font_bytes.len());
+    Some(&font_bytes[..end])
+}
+
+// Type 1 and AFM files are Latin-1 (ISO-8859-1) text
+fn FcLatin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| char::from(*byte)).collect()
+}
+
+// Source following the key `/Name` in PostScript source, with leading whitespace removed
+fn FcPsValue<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let mut rest = header;
+    loop {
+        rest = &rest[rest.find(key)? + key.len()..];
+        // `/FullName` must not match `/FullNameX`
+        if rest.starts_with(|c: char| c.is_ascii_whitespace() || c == '(' || c == '/') {
+            return Some(rest.trim_start());
+        }
+    }
+}
+
+// Single token value of a key (number, boolean or name)
+fn FcPsToken<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let value = FcPsValue(header, key)?;
+    // names start with a slash, which also ends the previous token
+    let end = value
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_ascii_whitespace() || matches!(c, '/' | '(' | '[' | '{'))
+        .map_or(value.len(), |(i, _)| i);
+    Some(&value[..end]).filter(|token| !token.is_empty())
+}
+
+// String value of a key, `(...)` with balanced parentheses and backslash escapes
+fn FcPsString(header: &str, key: &str) -> Option<String> {
+    let mut chars = FcPsValue(header, key)?.strip_prefix('(')?.chars();
+    let mut string = String::new();
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' => depth -= 1,
+            '\\' => {
+                let escaped = chars.next()?;
+                let c = match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' | 'b' | 'f' => continue,
+                    // up to three octal digits
+                    '0'..='7' => {
+                        let mut code = escaped.to_digit(8)?;
+                        for _ in 0..2 {
+                            match chars.clone().next().and_then(|c| c.to_digit(8)) {
+                                Some(digit) => {
+                                    code = code * 8 + digit;
+                                    chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        char::from((code & 0xff) as u8)
+                    }
+                    // line continuation
+                    '\n' => continue,
+                    c => c,
+                };
+                string.push(c);
+                continue;
+            }
+            _ => {}
+        }
+        string.push(c);
+    }
+    let string = string.trim();
+    Some(String::from(string)).filter(|string| !string.is_empty())
+}
+
+// Value of a key of the header of an AFM file (`FamilyName Nimbus Sans`)
+fn FcAfmValue<'a>(afm: &'a str, key: &str) -> Option<&'a str> {
+    afm.lines()
+        .take_while(|line| !line.starts_with("StartCharMetrics"))
+        .find_map(|line| {
+            let value = line.strip_prefix(key)?;
+            value
+                .starts_with([' ', '\t'])
+                .then(|| value.trim())
+                .filter(|value| !value.is_empty())
+        })
+}
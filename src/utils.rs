@@ -0,0 +1,32 @@
+//! Helpers for reading raw (big-endian) OpenType table data
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// Checksum of a table, without the `checkSumAdjustment` of head
+pub(crate) fn table_checksum(tag: u32, data: &[u8]) -> u32 {
+    match &tag.to_be_bytes() {
+        b"head" if data.len() >= 12 => {
+            let mut head = data.to_vec();
+            head[8..12].fill(0);
+            checksum(&head)
+        }
+        _ => checksum(data),
+    }
+}
+
+// Sum of the big-endian u32 of the data (zero-padded)
+pub(crate) fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
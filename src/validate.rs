@@ -0,0 +1,492 @@
+//! Sanity checks of font files, see `FcFontCache::validate`
+//!
+//! Checks what rasterizers read before any glyph: the table directory, the table
+//! checksums, the required tables and the cmap subtables. Glyph outlines aren't checked.
+//!
+//! See: https://learn.microsoft.com/en-us/typography/opentype/spec/otff and
+//! https://learn.microsoft.com/en-us/typography/opentype/spec/cmap
+
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(all(feature = "std", feature = "scan"))]
+use crate::utils::{checksum, read_u16, read_u32, table_checksum};
+
+/// A problem of a font file found by `FcFontCache::validate`, table tags are big-endian
+/// (`u32::from_be_bytes(*b"cmap")`)
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum FcValidationIssue {
+    /// The file can't be read or isn't a font (WOFF / WOFF2 files without the `woff`
+    /// feature, gzip-compressed files without the `gzip` feature)
+    Unreadable,
+    /// The table directory is truncated or has an unknown `sfntVersion`, or the collection
+    /// has no font at the index of the path
+    InvalidTableDirectory,
+    /// A table extends past the end of the file
+    TableOutOfBounds { tag: u32 },
+    /// A required table is missing (`cmap`, `head`, `hhea`, `hmtx`, `maxp`, `name`, and
+    /// `loca` for fonts with a `glyf` table)
+    MissingTable { tag: u32 },
+    /// A table is too short or has invalid values (i.e. a cmap subtable of an unknown
+    /// format or with unsorted segments)
+    InvalidTable { tag: u32 },
+    /// The font has neither outlines (`glyf`, `CFF `, `CFF2`) nor bitmaps (`EBDT`, `CBDT`,
+    /// `sbix`, `bdat`)
+    MissingGlyphs,
+    /// The cmap maps characters to a glyph id past the `numGlyphs` of the `maxp` table
+    CmapGlyphOutOfRange { glyph: u32, num_glyphs: u16 },
+    /// The checksum of a table doesn't match the table directory (`expected` is the value
+    /// of the font, `actual` the computed one)
+    ChecksumMismatch {
+        tag: u32,
+        expected: u32,
+        actual: u32,
+    },
+    /// The `checkSumAdjustment` of the head table doesn't match the checksum of the file
+    /// (`expected` is the value of the font, `actual` the computed one)
+    ChecksumAdjustmentMismatch { expected: u32, actual: u32 },
+    /// The cmap has no Unicode or Windows symbol subtable, the characters of the font can't
+    /// be looked up
+    NoUnicodeCmap,
+}
+
+impl FcValidationIssue {
+    /// Returns whether the issue is an error (the font is corrupt), not a warning
+    ///
+    /// Wrong checksums and fonts with only legacy Mac cmaps are warnings: both are common
+    /// in fonts that render fine, FreeType and most rasterizers don't check checksums.
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            FcValidationIssue::ChecksumMismatch { .. }
+                | FcValidationIssue::ChecksumAdjustmentMismatch { .. }
+                | FcValidationIssue::NoUnicodeCmap
+        )
+    }
+}
+
+impl fmt::Display for FcValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag =
+            |tag: &u32| alloc::string::String::from_utf8_lossy(&tag.to_be_bytes()).into_owned();
+        match self {
+            FcValidationIssue::Unreadable => write!(f, "the font can't be read"),
+            FcValidationIssue::InvalidTableDirectory => write!(f, "invalid table directory"),
+            FcValidationIssue::TableOutOfBounds { tag: t } => {
+                write!(f, "table '{}' extends past the end of the file", tag(t))
+            }
+            FcValidationIssue::MissingTable { tag: t } => {
+                write!(f, "required table '{}' is missing", tag(t))
+            }
+            FcValidationIssue::InvalidTable { tag: t } => write!(f, "invalid table '{}'", tag(t)),
+            FcValidationIssue::MissingGlyphs => write!(f, "the font has no outlines or bitmaps"),
+            FcValidationIssue::CmapGlyphOutOfRange { glyph, num_glyphs } => write!(
+                f,
+                "cmap maps characters to glyph {} of {} glyphs",
+                glyph, num_glyphs
+            ),
+            FcValidationIssue::ChecksumMismatch {
+                tag: t,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "table '{}' has checksum {:#010x}, the table directory records {:#010x}",
+                tag(t),
+                actual,
+                expected
+            ),
+            FcValidationIssue::ChecksumAdjustmentMismatch { expected, actual } => write!(
+                f,
+                "the file has checksum adjustment {:#010x}, the head table records {:#010x}",
+                actual, expected
+            ),
+            FcValidationIssue::NoUnicodeCmap => write!(f, "the cmap has no Unicode subtable"),
+        }
+    }
+}
+
+/// Result of `FcFontCache::validate`
+#[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct FcValidationReport {
+    // errors and warnings, in the order they were found
+    pub issues: Vec<FcValidationIssue>,
+}
+
+impl FcValidationReport {
+    /// Returns whether the font has no errors (it may have warnings, see
+    /// `FcValidationIssue::is_error`)
+    pub fn is_valid(&self) -> bool {
+        !self.issues.iter().any(FcValidationIssue::is_error)
+    }
+
+    /// Returns the issues that are errors
+    pub fn errors(&self) -> impl Iterator<Item = &FcValidationIssue> {
+        self.issues.iter().filter(|issue| issue.is_error())
+    }
+
+    /// Returns the issues that are warnings
+    pub fn warnings(&self) -> impl Iterator<Item = &FcValidationIssue> {
+        self.issues.iter().filter(|issue| !issue.is_error())
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scan"))]
+impl crate::FcFontCache {
+    /// Checks the table directory, table checksums, required tables and cmap of a font, so
+    /// font managers can mark corrupt fonts and apps can skip them before they reach a
+    /// rasterizer
+    ///
+    /// WOFF / WOFF2 fonts are checked after decompressing them. Type 1 and PCF fonts only
+    /// have to be readable, they don't have checksums.
+    pub fn validate(&self, path: &crate::FcFontPath) -> FcValidationReport {
+        let issues = match crate::FcFontBytes::load(&path.source) {
+            Some(bytes) => FcValidateFont(&bytes, path.font_index),
+            None => alloc::vec![FcValidationIssue::Unreadable],
+        };
+        FcValidationReport { issues }
+    }
+}
+
+// Issues of the font at `font_index` in the font file
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcValidateFont(font_bytes: &[u8], font_index: usize) -> Vec<FcValidationIssue> {
+    use crate::FcFontContainer;
+
+    let data = crate::FcUncompressedData(font_bytes);
+    let is_readable = match FcFontContainer::of(&data) {
+        FcFontContainer::Type1 => Some(crate::type1::FcParseType1(&data, None).is_some()),
+        FcFontContainer::Pcf => Some(crate::pcf::FcParsePcf(&data).is_some()),
+        _ => None,
+    };
+    match is_readable {
+        // Type 1 and PCF files have a single font
+        Some(true) if font_index == 0 => return Vec::new(),
+        Some(_) => return alloc::vec![FcValidationIssue::Unreadable],
+        None => {}
+    }
+
+    let sfnt = crate::FcSfntData(&data);
+    // web fonts that couldn't be decompressed
+    if FcFontContainer::of(&sfnt) != FcFontContainer::Sfnt {
+        return alloc::vec![FcValidationIssue::Unreadable];
+    }
+
+    // faces of .dfont files are separate resources instead of a font collection
+    match crate::dfont::FcResourceForkFaces(&sfnt) {
+        Some(faces) => match faces.get(font_index) {
+            Some(face) => FcValidateSfnt(face, 0),
+            None => alloc::vec![FcValidationIssue::InvalidTableDirectory],
+        },
+        None => FcValidateSfnt(&sfnt, font_index),
+    }
+}
+
+// Issues of a face of an OpenType font or collection
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcValidateSfnt(font_bytes: &[u8], font_index: usize) -> Vec<FcValidationIssue> {
+    const TAG_TTCF: u32 = u32::from_be_bytes(*b"ttcf");
+    const SFNT_VERSIONS: [u32; 4] = [
+        0x0001_0000,
+        u32::from_be_bytes(*b"OTTO"),
+        u32::from_be_bytes(*b"true"),
+        u32::from_be_bytes(*b"typ1"),
+    ];
+    const TAG_HEAD: u32 = u32::from_be_bytes(*b"head");
+    const TAG_MAXP: u32 = u32::from_be_bytes(*b"maxp");
+    const TAG_CMAP: u32 = u32::from_be_bytes(*b"cmap");
+    const TAG_GLYF: u32 = u32::from_be_bytes(*b"glyf");
+    const TAG_LOCA: u32 = u32::from_be_bytes(*b"loca");
+    const REQUIRED_TABLES: [&[u8; 4]; 6] = [b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name"];
+    const GLYPH_TABLES: [&[u8; 4]; 7] = [
+        b"glyf", b"CFF ", b"CFF2", b"EBDT", b"CBDT", b"sbix", b"bdat",
+    ];
+    // see https://learn.microsoft.com/en-us/typography/opentype/spec/head
+    const HEAD_MAGIC: u32 = 0x5F0F_3CF5;
+    const CHECKSUM_MAGIC: u32 = 0xB1B0_AFBA;
+
+    let mut issues = Vec::new();
+
+    let is_collection = read_u32(font_bytes, 0) == Some(TAG_TTCF);
+    let directory = match is_collection {
+        true => read_u32(font_bytes, 8)
+            .filter(|count| font_index < *count as usize)
+            .and_then(|_| read_u32(font_bytes, 12 + 4 * font_index))
+            .map(|offset| offset as usize),
+        false => Some(0).filter(|_| font_index == 0),
+    };
+    let num_tables = directory
+        .filter(|directory| {
+            read_u32(font_bytes, *directory).is_some_and(|version| SFNT_VERSIONS.contains(&version))
+        })
+        .and_then(|directory| Some((directory, read_u16(font_bytes, directory + 4)?)));
+    let (directory, num_tables) = match num_tables {
+        Some((directory, num_tables))
+            if font_bytes.len() >= directory + 12 + 16 * usize::from(num_tables) =>
+        {
+            (directory, num_tables)
+        }
+        _ => {
+            issues.push(FcValidationIssue::InvalidTableDirectory);
+            return issues;
+        }
+    };
+
+    // tag and data of the tables within the file
+    let mut tables = Vec::new();
+    for i in 0..usize::from(num_tables) {
+        let record = directory + 12 + 16 * i;
+        let (tag, expected) = (
+            read_u32(font_bytes, record),
+            read_u32(font_bytes, record + 4),
+        );
+        let (offset, length) = (
+            read_u32(font_bytes, record + 8),
+            read_u32(font_bytes, record + 12),
+        );
+        let (tag, expected) = (tag.unwrap_or(0), expected.unwrap_or(0));
+        let (offset, length) = (offset.unwrap_or(0) as usize, length.unwrap_or(0) as usize);
+        let data = match font_bytes.get(offset..offset.saturating_add(length)) {
+            Some(data) => data,
+            None => {
+                issues.push(FcValidationIssue::TableOutOfBounds { tag });
+                continue;
+            }
+        };
+        let actual = table_checksum(tag, data);
+        if actual != expected {
+            issues.push(FcValidationIssue::ChecksumMismatch {
+                tag,
+                expected,
+                actual,
+            });
+        }
+        tables.push((tag, data));
+    }
+    let table = |tag: u32| {
+        tables
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, data)| *data)
+    };
+
+    // tables out of bounds were already reported
+    let has_table = |tag: u32| {
+        table(tag).is_some() || issues.contains(&FcValidationIssue::TableOutOfBounds { tag })
+    };
+
+    let mut missing: Vec<u32> = REQUIRED_TABLES
+        .iter()
+        .map(|tag| u32::from_be_bytes(**tag))
+        .filter(|tag| !has_table(*tag))
+        .collect();
+    if has_table(TAG_GLYF) && !has_table(TAG_LOCA) {
+        missing.push(TAG_LOCA);
+    }
+    let has_glyphs = GLYPH_TABLES
+        .iter()
+        .any(|tag| has_table(u32::from_be_bytes(**tag)));
+    issues.extend(
+        missing
+            .into_iter()
+            .map(|tag| FcValidationIssue::MissingTable { tag }),
+    );
+    if !has_glyphs {
+        issues.push(FcValidationIssue::MissingGlyphs);
+    }
+
+    if let Some(head) = table(TAG_HEAD) {
+        if read_u32(head, 12) != Some(HEAD_MAGIC) || head.len() < 54 {
+            issues.push(FcValidationIssue::InvalidTable { tag: TAG_HEAD });
+        } else if !is_collection {
+            // the whole file sums to the magic value, the faces of a collection share
+            // tables so their adjustments can't all be right
+            let expected = read_u32(head, 8).unwrap_or(0);
+            let actual = CHECKSUM_MAGIC.wrapping_sub(checksum(font_bytes).wrapping_sub(expected));
+            if actual != expected {
+                issues.push(FcValidationIssue::ChecksumAdjustmentMismatch { expected, actual });
+            }
+        }
+    }
+
+    let num_glyphs = match table(TAG_MAXP) {
+        Some(maxp) => {
+            let num_glyphs = read_u16(maxp, 4);
+            if num_glyphs.is_none() {
+                issues.push(FcValidationIssue::InvalidTable { tag: TAG_MAXP });
+            }
+            num_glyphs
+        }
+        None => None,
+    };
+
+    if let Some(cmap) = table(TAG_CMAP) {
+        FcValidateCmap(cmap, num_glyphs, &mut issues);
+    }
+
+    issues
+}
+
+// Checks the encoding records and subtables of a cmap table
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcValidateCmap(cmap: &[u8], num_glyphs: Option<u16>, issues: &mut Vec<FcValidationIssue>) {
+    const TAG_CMAP: u32 = u32::from_be_bytes(*b"cmap");
+    // format of Unicode variation sequences, which don't map characters on their own
+    const FORMAT_VARIATIONS: u16 = 14;
+
+    let num_records = match read_u16(cmap, 2) {
+        Some(count) if cmap.len() >= 4 + 8 * usize::from(count) => usize::from(count),
+        _ => {
+            issues.push(FcValidationIssue::InvalidTable { tag: TAG_CMAP });
+            return;
+        }
+    };
+
+    let mut is_invalid = false;
+    let mut has_unicode = false;
+    let mut max_glyph = 0;
+    for i in 0..num_records {
+        let record = 4 + 8 * i;
+        let (platform, encoding) = (read_u16(cmap, record), read_u16(cmap, record + 2));
+        let offset = read_u32(cmap, record + 4).unwrap_or(0) as usize;
+        match FcCmapSubtableMaxGlyph(cmap, offset) {
+            Some(glyph) => max_glyph = max_glyph.max(glyph),
+            None => {
+                is_invalid = true;
+                continue;
+            }
+        }
+        // Unicode, and Windows symbol / BMP / full Unicode
+        let is_unicode = matches!(
+            (platform, encoding),
+            (Some(0), _) | (Some(3), Some(0)) | (Some(3), Some(1)) | (Some(3), Some(10))
+        );
+        has_unicode |= is_unicode && read_u16(cmap, offset) != Some(FORMAT_VARIATIONS);
+    }
+
+    if is_invalid {
+        issues.push(FcValidationIssue::InvalidTable { tag: TAG_CMAP });
+    }
+    if let Some(num_glyphs) = num_glyphs {
+        if max_glyph >= u32::from(num_glyphs) {
+            issues.push(FcValidationIssue::CmapGlyphOutOfRange {
+                glyph: max_glyph,
+                num_glyphs,
+            });
+        }
+    }
+    if !has_unicode {
+        issues.push(FcValidationIssue::NoUnicodeCmap);
+    }
+}
+
+// Highest glyph id a cmap subtable maps a character to, `None` if the subtable is
+// malformed. Only formats 0, 4, 6, 12 and 13 are read for their glyph ids, the bounds of
+// the other formats are checked.
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcCmapSubtableMaxGlyph(cmap: &[u8], offset: usize) -> Option<u32> {
+    let format = read_u16(cmap, offset)?;
+    let length = match format {
+        0 | 2 | 4 | 6 => usize::from(read_u16(cmap, offset + 2)?),
+        8 | 10 | 12 | 13 => read_u32(cmap, offset + 4)? as usize,
+        14 => read_u32(cmap, offset + 2)? as usize,
+        _ => return None,
+    };
+    let subtable = cmap.get(offset..offset.checked_add(length)?)?;
+
+    match format {
+        0 => subtable
+            .get(6..262)?
+            .iter()
+            .map(|glyph| u32::from(*glyph))
+            .max(),
+        4 => FcCmapFormat4MaxGlyph(subtable),
+        6 => {
+            let count = usize::from(read_u16(subtable, 8)?);
+            (0..count)
+                .map(|i| read_u16(subtable, 10 + 2 * i).map(u32::from))
+                .try_fold(0, |max, glyph| Some(max.max(glyph?)))
+        }
+        12 | 13 => {
+            let count = read_u32(subtable, 12)? as usize;
+            if subtable.len() < count.checked_mul(12)?.checked_add(16)? {
+                return None;
+            }
+            let mut max_glyph = 0;
+            let mut next_char = 0;
+            for i in 0..count {
+                let group = 16 + 12 * i;
+                let (start, end) = (read_u32(subtable, group)?, read_u32(subtable, group + 4)?);
+                let start_glyph = read_u32(subtable, group + 8)?;
+                // groups are sorted and don't overlap
+                if start < next_char || start > end || end > 0x10FFFF {
+                    return None;
+                }
+                next_char = end + 1;
+                let glyph = match format {
+                    12 => u64::from(start_glyph) + u64::from(end - start),
+                    _ => u64::from(start_glyph),
+                };
+                max_glyph = max_glyph.max(glyph.min(u64::from(u32::MAX)) as u32);
+            }
+            Some(max_glyph)
+        }
+        _ => Some(0),
+    }
+}
+
+// Highest glyph id of a format 4 (segment mapping to delta values) subtable
+#[cfg(all(feature = "std", feature = "scan"))]
+fn FcCmapFormat4MaxGlyph(subtable: &[u8]) -> Option<u32> {
+    let seg_count = usize::from(read_u16(subtable, 6)? / 2);
+    let end_codes = 14;
+    let start_codes = end_codes + 2 * seg_count + 2;
+    let id_deltas = start_codes + 2 * seg_count;
+    let id_range_offsets = id_deltas + 2 * seg_count;
+    if subtable.len() < id_range_offsets + 2 * seg_count {
+        return None;
+    }
+
+    let mut max_glyph = 0;
+    let mut next_char = 0;
+    for i in 0..seg_count {
+        let end = read_u16(subtable, end_codes + 2 * i)?;
+        let start = read_u16(subtable, start_codes + 2 * i)?;
+        let delta = read_u16(subtable, id_deltas + 2 * i)?;
+        let range_offset = read_u16(subtable, id_range_offsets + 2 * i)?;
+        // segments are sorted and don't overlap, the last one ends at 0xFFFF
+        if u32::from(start) < next_char || start > end {
+            return None;
+        }
+        next_char = u32::from(end) + 1;
+        // the 0xFFFF segment only terminates the table
+        if start == 0xFFFF {
+            continue;
+        }
+        for c in start..=end {
+            let glyph = match range_offset {
+                0 => c.wrapping_add(delta),
+                _ => {
+                    // offset from the idRangeOffset entry into the glyph id array
+                    let address = id_range_offsets
+                        + 2 * i
+                        + usize::from(range_offset)
+                        + 2 * usize::from(c - start);
+                    match read_u16(subtable, address)? {
+                        0 => 0,
+                        glyph => glyph.wrapping_add(delta),
+                    }
+                }
+            };
+            max_glyph = max_glyph.max(u32::from(glyph));
+        }
+    }
+    if next_char != 0x10000 {
+        return None;
+    }
+    Some(max_glyph)
+}
@@ -0,0 +1,44 @@
+//! Browser integration (`wasm` feature)
+//!
+//! Browsers don't expose a font directory, but font files can still be fetched
+//! (`fetch("font.woff2").then(r => r.arrayBuffer())`) or read through the Local
+//! Font Access API (`FontData.blob().arrayBuffer()`). The buffers are parsed the
+//! same way as files on disk, so the regular pattern matching works on them.
+
+use alloc::vec::Vec;
+
+use js_sys::{ArrayBuffer, Uint8Array};
+
+use crate::parser::FC_DEFAULT_PARSER;
+use crate::{FcFont, FcFontCache, FcMetadataParser};
+
+impl FcFontCache {
+    /// Parses JS `ArrayBuffer`s containing font files (TTF, OTF, WOFF, WOFF2) and adds
+    /// them as in-memory fonts (see `with_memory_fonts`), buffers that can't be parsed are skipped
+    pub fn with_js_fonts(&mut self, buffers: &[ArrayBuffer]) -> &mut Self {
+        let mut fonts = Vec::new();
+
+        for buffer in buffers {
+            // copy the bytes out of the JS heap, the patterns are parsed from the copy
+            let bytes = Uint8Array::new(buffer).to_vec();
+
+            // font_index = 0 - TODO: iterate through fonts in font file properly!
+            let patterns = match FC_DEFAULT_PARSER.parse_face(&bytes, 0) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            fonts.extend(patterns.into_iter().map(|pattern| {
+                (
+                    pattern,
+                    FcFont {
+                        bytes: bytes.clone(),
+                        font_index: 0,
+                    },
+                )
+            }));
+        }
+
+        self.with_memory_fonts(&fonts)
+    }
+}
@@ -0,0 +1,134 @@
+//! Live cache updates (`watch` feature)
+//!
+//! The scanned font directories are watched through `notify` (inotify, FSEvents,
+//! ReadDirectoryChangesW, ...). Bursts of events, i.e. from a package installation,
+//! are coalesced and applied with a single `FcFontCache::refresh`.
+
+use alloc::borrow::ToOwned;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::string::String;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::{FcFontCache, FcSharedFontCache};
+
+// events arriving within this time of each other are handled with one refresh
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+enum WatchMessage {
+    Event(notify::Result<notify::Event>),
+    Stop,
+}
+
+/// Handle of a font cache that is kept up to date in the background, see `FcFontCache::watch`
+///
+/// Watching stops when the handle is dropped.
+#[derive(Debug)]
+pub struct FcFontCacheWatcher {
+    cache: Arc<RwLock<FcFontCache>>,
+    sender: mpsc::Sender<WatchMessage>,
+}
+
+impl FcFontCacheWatcher {
+    /// Returns the font cache, which is updated whenever fonts change on disk
+    pub fn cache(&self) -> Arc<RwLock<FcFontCache>> {
+        self.cache.clone()
+    }
+
+    /// Same as `cache`, with the query API of `FcSharedFontCache`
+    pub fn shared(&self) -> FcSharedFontCache {
+        self.cache.clone().into()
+    }
+}
+
+impl Drop for FcFontCacheWatcher {
+    fn drop(&mut self) {
+        let _ = self.sender.send(WatchMessage::Stop);
+    }
+}
+
+impl FcFontCache {
+    /// Watches the scanned font directories and keeps the cache up to date as fonts are
+    /// installed, updated or removed
+    ///
+    /// `on_change` is called on the watcher thread after every update, with the write lock
+    /// of the cache still held: use the given reference instead of locking the cache again.
+    /// Returns `None` if the file watcher of the platform can't be created.
+    pub fn watch<F>(self, mut on_change: F) -> Option<FcFontCacheWatcher>
+    where
+        F: FnMut(&FcFontCache) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let event_sender = sender.clone();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = event_sender.send(WatchMessage::Event(event));
+        })
+        .ok()?;
+
+        let mut watched = BTreeSet::new();
+        FcWatchScannedDirs(&mut watcher, &self, &mut watched);
+
+        let cache = Arc::new(RwLock::new(self));
+        let thread_cache = cache.clone();
+
+        std::thread::Builder::new()
+            .name("dafont-watch".to_owned())
+            .spawn(move || {
+                while let Ok(WatchMessage::Event(event)) = receiver.recv() {
+                    let mut changed = FcIsFontChange(&event);
+                    loop {
+                        match receiver.recv_timeout(DEBOUNCE) {
+                            Ok(WatchMessage::Event(event)) => changed |= FcIsFontChange(&event),
+                            Ok(WatchMessage::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                            Err(RecvTimeoutError::Timeout) => break,
+                        }
+                    }
+
+                    if !changed {
+                        continue;
+                    }
+
+                    let mut cache = match thread_cache.write() {
+                        Ok(cache) => cache,
+                        Err(_) => return,
+                    };
+                    cache.refresh();
+                    FcWatchScannedDirs(&mut watcher, &cache, &mut watched);
+                    on_change(&cache);
+                }
+            })
+            .ok()?;
+
+        Some(FcFontCacheWatcher { cache, sender })
+    }
+}
+
+// Reading a font doesn't change anything, errors (i.e. dropped events) might have
+fn FcIsFontChange(event: &notify::Result<notify::Event>) -> bool {
+    event.as_ref().map_or(true, |event| !event.kind.is_access())
+}
+
+// Starts watching directories that were added by the last refresh, every directory is
+// watched on its own since `refresh` also tracks every directory on its own
+fn FcWatchScannedDirs<W: Watcher>(
+    watcher: &mut W,
+    cache: &FcFontCache,
+    watched: &mut BTreeSet<String>,
+) {
+    // watches of removed directories are dropped by the OS
+    watched.retain(|dir| cache.scanned_dirs.contains_key(dir));
+
+    for dir in cache.scanned_dirs.keys() {
+        if !watched.contains(dir)
+            && watcher
+                .watch(Path::new(dir), RecursiveMode::NonRecursive)
+                .is_ok()
+        {
+            watched.insert(dir.clone());
+        }
+    }
+}
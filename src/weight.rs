@@ -0,0 +1,162 @@
+//! Font weights, see `FcWeight`
+
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::FcError;
+
+/// Weight of a font, on the OpenType / CSS scale (`usWeightClass`, 1 - 1000)
+///
+/// `FcWeight(0)` (the default) is used for patterns that don't specify a weight. The weight
+/// of a pattern doesn't restrict the fonts a query matches, see `FcPattern::weight`.
+#[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+#[repr(C)]
+pub struct FcWeight(pub u16);
+
+impl FcWeight {
+    pub const THIN: FcWeight = FcWeight(100);
+    pub const EXTRA_LIGHT: FcWeight = FcWeight(200);
+    pub const LIGHT: FcWeight = FcWeight(300);
+    pub const SEMI_LIGHT: FcWeight = FcWeight(350);
+    pub const BOOK: FcWeight = FcWeight(380);
+    pub const REGULAR: FcWeight = FcWeight(400);
+    pub const MEDIUM: FcWeight = FcWeight(500);
+    pub const SEMI_BOLD: FcWeight = FcWeight(600);
+    pub const BOLD: FcWeight = FcWeight(700);
+    pub const EXTRA_BOLD: FcWeight = FcWeight(800);
+    pub const BLACK: FcWeight = FcWeight(900);
+    pub const EXTRA_BLACK: FcWeight = FcWeight(1000);
+
+    // fontconfig weights (`FC_WEIGHT_*`) and their OpenType weights, see `FcWeightToOpenType`
+    const FONTCONFIG: [(i64, u16); 12] = [
+        (0, 100),
+        (40, 200),
+        (50, 300),
+        (55, 350),
+        (75, 380),
+        (80, 400),
+        (100, 500),
+        (180, 600),
+        (200, 700),
+        (205, 800),
+        (210, 900),
+        (215, 1000),
+    ];
+
+    /// Converts a fontconfig weight (`FC_WEIGHT_REGULAR` = 80, `FC_WEIGHT_BOLD` = 200, ...),
+    /// weights between two constants are interpolated (same as fontconfig)
+    pub fn from_fontconfig(weight: i64) -> Self {
+        let table = &Self::FONTCONFIG;
+        if weight <= table[0].0 {
+            return FcWeight(table[0].1);
+        }
+        for pair in table.windows(2) {
+            let ((fc_from, ot_from), (fc_to, ot_to)) = (pair[0], pair[1]);
+            if weight <= fc_to {
+                let (ot_from, ot_to) = (i64::from(ot_from), i64::from(ot_to));
+                let ot = ot_from + (weight - fc_from) * (ot_to - ot_from) / (fc_to - fc_from);
+                return FcWeight(ot as u16);
+            }
+        }
+        FcWeight(table[table.len() - 1].1)
+    }
+
+    // Weight of the weight name of a Type 1 / X11 bitmap font ("Bold", "Demi", "Roman", ...),
+    // regular for unknown names
+    #[cfg(all(feature = "std", feature = "scan"))]
+    pub(crate) fn from_style_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "demi" => FcWeight::SEMI_BOLD,
+            "roman" | "plain" => FcWeight::REGULAR,
+            _ => FcWeight::from_str(name).unwrap_or(FcWeight::REGULAR),
+        }
+    }
+
+    /// Returns the name of the closest weight constant ("regular", "semibold", ...)
+    pub fn name(&self) -> &'static str {
+        const NAMES: [(FcWeight, &str); 12] = [
+            (FcWeight::THIN, "thin"),
+            (FcWeight::EXTRA_LIGHT, "extralight"),
+            (FcWeight::LIGHT, "light"),
+            (FcWeight::SEMI_LIGHT, "semilight"),
+            (FcWeight::BOOK, "book"),
+            (FcWeight::REGULAR, "regular"),
+            (FcWeight::MEDIUM, "medium"),
+            (FcWeight::SEMI_BOLD, "semibold"),
+            (FcWeight::BOLD, "bold"),
+            (FcWeight::EXTRA_BOLD, "extrabold"),
+            (FcWeight::BLACK, "black"),
+            (FcWeight::EXTRA_BLACK, "extrablack"),
+        ];
+        NAMES
+            .iter()
+            .min_by_key(|(weight, _)| weight.0.abs_diff(self.0))
+            .map_or("regular", |(_, name)| name)
+    }
+
+    /// Returns `true` for weights from `SEMI_BOLD` on, i.e. fonts that are
+    /// shown as bold
+    pub fn is_bold(&self) -> bool {
+        *self >= FcWeight::SEMI_BOLD
+    }
+}
+
+/// `usWeightClass` of the OS/2 table
+impl From<u16> for FcWeight {
+    fn from(weight: u16) -> Self {
+        FcWeight(weight)
+    }
+}
+
+impl From<FcWeight> for u16 {
+    fn from(weight: FcWeight) -> Self {
+        weight.0
+    }
+}
+
+impl fmt::Display for FcWeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Parses a weight name ("semibold", "Extra Bold", "ultra-light", ... - the names of
+/// fontconfig's weight constants) or a number ("600")
+impl FromStr for FcWeight {
+    type Err = FcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        let weight = match name.as_str() {
+            "thin" | "hairline" => FcWeight::THIN,
+            "extralight" | "ultralight" => FcWeight::EXTRA_LIGHT,
+            "light" => FcWeight::LIGHT,
+            "semilight" | "demilight" => FcWeight::SEMI_LIGHT,
+            "book" => FcWeight::BOOK,
+            "regular" | "normal" => FcWeight::REGULAR,
+            "medium" => FcWeight::MEDIUM,
+            "semibold" | "demibold" => FcWeight::SEMI_BOLD,
+            "bold" => FcWeight::BOLD,
+            "extrabold" | "ultrabold" => FcWeight::EXTRA_BOLD,
+            "black" | "heavy" => FcWeight::BLACK,
+            "extrablack" | "ultrablack" => FcWeight::EXTRA_BLACK,
+            _ => match name.parse::<u16>() {
+                Ok(weight) if weight <= 1000 => FcWeight(weight),
+                _ => {
+                    return Err(FcError::InvalidValue {
+                        value: String::from(s),
+                    })
+                }
+            },
+        };
+        Ok(weight)
+    }
+}
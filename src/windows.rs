@@ -0,0 +1,340 @@
+//! Windows font discovery via the registry
+//!
+//! Fonts don't have to live in `C:\Windows\Fonts`: installers can register
+//! fonts from any location under the `Fonts` key, which is what GDI enumerates.
+//! The registry is read through a few raw `advapi32` calls to avoid pulling in
+//! a Windows bindings crate.
+
+use alloc::borrow::ToOwned;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    FcFontPath, FcParseFontFiles, FcPattern, FcRetainLocations, FcScanDirectoriesInner,
+    FcScanOptions,
+};
+
+#[allow(clippy::upper_case_acronyms)]
+type HKEY = isize;
+
+const HKEY_CURRENT_USER: HKEY = 0x8000_0001_u32 as i32 as isize;
+const HKEY_LOCAL_MACHINE: HKEY = 0x8000_0002_u32 as i32 as isize;
+const KEY_READ: u32 = 0x0002_0019;
+const ERROR_SUCCESS: i32 = 0;
+const ERROR_MORE_DATA: i32 = 234;
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+const REG_MULTI_SZ: u32 = 7;
+
+// maximum length of a registry value name, in UTF-16 code units (including the terminator)
+const MAX_VALUE_NAME_LEN: usize = 16_384;
+
+const FONTS_KEY: &str = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Fonts";
+const FONT_LINK_KEY: &str = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\FontLink\\SystemLink";
+const FONT_SUBSTITUTES_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\FontSubstitutes";
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegOpenKeyExW(
+        hkey: HKEY,
+        lpsubkey: *const u16,
+        uloptions: u32,
+        samdesired: u32,
+        phkresult: *mut HKEY,
+    ) -> i32;
+    fn RegEnumValueW(
+        hkey: HKEY,
+        dwindex: u32,
+        lpvaluename: *mut u16,
+        lpcchvaluename: *mut u32,
+        lpreserved: *mut u32,
+        lptype: *mut u32,
+        lpdata: *mut u8,
+        lpcbdata: *mut u32,
+    ) -> i32;
+    fn RegCloseKey(hkey: HKEY) -> i32;
+}
+
+/// Scans the system and per-user font directories, plus the fonts registered in the registry
+pub(crate) fn FcScanWindowsFonts(options: &FcScanOptions) -> Vec<(FcPattern, FcFontPath)> {
+    // `~` isn't actually valid on Windows, but it will be converted by `process_path`
+    let user_fonts_dir = FcUserFontsDir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_else(|| "~\\AppData\\Local\\Microsoft\\Windows\\Fonts\\".to_owned());
+    let mut font_dirs = vec![
+        (None, "C:\\Windows\\Fonts\\".to_owned()),
+        (None, user_fonts_dir),
+    ];
+    FcRetainLocations(&mut font_dirs, options);
+    let mut fonts = FcScanDirectoriesInner(&font_dirs, options);
+
+    // fonts registered from other locations (GDI enumerates these as well)
+    let registry_fonts = FcScanRegistryFonts(&fonts, options);
+    fonts.extend(registry_fonts);
+    fonts
+}
+
+/// Parses the fonts registered in the HKLM / HKCU `Fonts` keys,
+/// skipping files that have already been found by scanning the font directories
+fn FcScanRegistryFonts(
+    already_scanned: &[(FcPattern, FcFontPath)],
+    options: &FcScanOptions,
+) -> Vec<(FcPattern, FcFontPath)> {
+    // relative file names are resolved against the system or per-user font directory
+    let roots = [
+        (HKEY_LOCAL_MACHINE, FcWindowsFontsDir()),
+        (
+            HKEY_CURRENT_USER,
+            FcUserFontsDir().unwrap_or_else(FcWindowsFontsDir),
+        ),
+    ];
+
+    let mut files_to_parse = Vec::new();
+    for (root, fonts_dir) in roots {
+        for (_, values) in FcRegistryValues(root, FONTS_KEY) {
+            for value in values {
+                // per-user fonts are usually registered as "%LOCALAPPDATA%\..." (REG_EXPAND_SZ)
+                let value = FcExpandEnvironmentStrings(&value);
+                let path = Path::new(&value);
+                let path = if path.is_absolute() {
+                    path.to_path_buf()
+                } else {
+                    fonts_dir.join(path)
+                };
+
+                let path_str = path.to_string_lossy();
+                let is_known = already_scanned
+                    .iter()
+                    .filter_map(|(_, p)| p.path())
+                    .any(|p| p.eq_ignore_ascii_case(&path_str))
+                    || files_to_parse
+                        .iter()
+                        .any(|p: &PathBuf| p.to_string_lossy().eq_ignore_ascii_case(&path_str));
+
+                if !is_known && options.accepts_location(&path_str) {
+                    files_to_parse.push(path);
+                }
+            }
+        }
+    }
+
+    FcParseFontFiles(&files_to_parse, options)
+}
+
+/// Reads the `FontSubstitutes` key ("Helv" -> "MS Sans Serif", "MS Shell Dlg" -> "Microsoft Sans Serif", ...),
+/// returns a map of lowercase family name -> substitute family name
+pub(crate) fn FcRegistrySubstitutes() -> BTreeMap<String, String> {
+    // names can have a ",<charset>" suffix ("Arial,161" = Arial with the Greek charset)
+    fn strip_charset(name: &str) -> &str {
+        name.split(',').next().unwrap_or(name).trim()
+    }
+
+    let mut substitutes = BTreeMap::new();
+    for (name, values) in FcRegistryValues(HKEY_LOCAL_MACHINE, FONT_SUBSTITUTES_KEY) {
+        let from = strip_charset(&name);
+        let to = match values.first() {
+            Some(to) => strip_charset(to),
+            None => continue,
+        };
+
+        if from.is_empty() || to.is_empty() || from.eq_ignore_ascii_case(to) {
+            continue;
+        }
+
+        substitutes
+            .entry(from.to_lowercase())
+            .or_insert_with(|| to.to_owned());
+    }
+
+    substitutes
+}
+
+/// Reads the `FontLink\SystemLink` key (i.e. "Segoe UI" -> Tahoma, Meiryo, Malgun Gothic, ...),
+/// returns a map of lowercase family name -> linked fonts, resolved against the scanned fonts
+pub(crate) fn FcRegistryFontLinks(
+    scanned: &[(FcPattern, FcFontPath)],
+) -> BTreeMap<String, Vec<FcFontPath>> {
+    let fonts_dir = FcWindowsFontsDir();
+
+    let mut font_links = BTreeMap::new();
+    for (family, lines) in FcRegistryValues(HKEY_LOCAL_MACHINE, FONT_LINK_KEY) {
+        // every line is "FILE.TTF[,Face name[,scale factors]]"
+        let linked = lines
+            .iter()
+            .filter_map(|line| {
+                let mut parts = line.split(',').map(str::trim);
+                let file = parts.next().filter(|f| !f.is_empty())?;
+                let face = parts.next().filter(|f| !f.is_empty());
+
+                let path = Path::new(file);
+                let path = if path.is_absolute() {
+                    path.to_path_buf()
+                } else {
+                    fonts_dir.join(path)
+                };
+                let path = path.to_string_lossy();
+
+                let mut candidates = scanned
+                    .iter()
+                    .filter(|(_, p)| p.path().is_some_and(|p| p.eq_ignore_ascii_case(&path)));
+
+                let (_, font) = match face {
+                    // pick the right face of a collection (MSGOTHIC.TTC,MS UI Gothic)
+                    Some(face) => candidates.find(|(pattern, _)| {
+                        let is_face = |s: &Option<String>| {
+                            s.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(face))
+                        };
+                        is_face(&pattern.family) || is_face(&pattern.name)
+                    }),
+                    None => candidates.next(),
+                }?;
+
+                Some(font.clone())
+            })
+            .fold(Vec::<FcFontPath>::new(), |mut fonts, font| {
+                if !fonts.contains(&font) {
+                    fonts.push(font);
+                }
+                fonts
+            });
+
+        if !linked.is_empty() {
+            font_links.insert(family.to_lowercase(), linked);
+        }
+    }
+
+    font_links
+}
+
+// `%WINDIR%\Fonts`, where relative file names of the HKLM `Fonts` key are resolved
+fn FcWindowsFontsDir() -> PathBuf {
+    std::env::var("WINDIR")
+        .or_else(|_| std::env::var("SystemRoot"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("C:\\Windows"))
+        .join("Fonts")
+}
+
+// `%LOCALAPPDATA%\Microsoft\Windows\Fonts`, where Windows 10+ installs per-user fonts
+pub(crate) fn FcUserFontsDir() -> Option<PathBuf> {
+    std::env::var("LOCALAPPDATA")
+        .ok()
+        .map(|local_app_data| PathBuf::from(local_app_data).join("Microsoft\\Windows\\Fonts"))
+}
+
+// Replaces `%NAME%` with the value of the environment variable `NAME`,
+// unknown variables are left untouched (same as `ExpandEnvironmentStringsW`)
+fn FcExpandEnvironmentStrings(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('%') {
+        output.push_str(&rest[..start]);
+        let after_start = &rest[start + 1..];
+        match after_start.find('%') {
+            Some(end) => {
+                let name = &after_start[..end];
+                match std::env::var(name) {
+                    Ok(value) if !name.is_empty() => output.push_str(&value),
+                    _ => {
+                        output.push('%');
+                        output.push_str(name);
+                        output.push('%');
+                    }
+                }
+                rest = &after_start[end + 1..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Reads all string values (`REG_SZ`, `REG_EXPAND_SZ`, `REG_MULTI_SZ`) of a registry key,
+/// returns `(value name, strings)` pairs. Returns an empty list if the key doesn't exist.
+pub(crate) fn FcRegistryValues(root: HKEY, subkey: &str) -> Vec<(String, Vec<String>)> {
+    let subkey_w = subkey
+        .encode_utf16()
+        .chain(core::iter::once(0))
+        .collect::<Vec<u16>>();
+
+    let mut hkey: HKEY = 0;
+    let status = unsafe { RegOpenKeyExW(root, subkey_w.as_ptr(), 0, KEY_READ, &mut hkey) };
+    if status != ERROR_SUCCESS {
+        return Vec::new();
+    }
+
+    let mut values = Vec::new();
+    let mut name_buf = vec![0_u16; MAX_VALUE_NAME_LEN];
+    let mut data_buf = vec![0_u8; 4096];
+    let mut index = 0;
+
+    loop {
+        let mut name_len = name_buf.len() as u32;
+        let mut data_len = data_buf.len() as u32;
+        let mut value_type = 0;
+
+        let status = unsafe {
+            RegEnumValueW(
+                hkey,
+                index,
+                name_buf.as_mut_ptr(),
+                &mut name_len,
+                core::ptr::null_mut(),
+                &mut value_type,
+                data_buf.as_mut_ptr(),
+                &mut data_len,
+            )
+        };
+
+        if status == ERROR_MORE_DATA {
+            // `data_len` now contains the required size, retry the same index
+            if data_len as usize <= data_buf.len() {
+                break;
+            }
+            data_buf.resize(data_len as usize, 0);
+            continue;
+        }
+
+        if status != ERROR_SUCCESS {
+            break; // ERROR_NO_MORE_ITEMS or a real error
+        }
+
+        index += 1;
+
+        if !matches!(value_type, REG_SZ | REG_EXPAND_SZ | REG_MULTI_SZ) {
+            continue;
+        }
+
+        let name = String::from_utf16_lossy(&name_buf[..name_len as usize]);
+        let data = data_buf[..data_len as usize]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect::<Vec<u16>>();
+
+        // REG_MULTI_SZ is a sequence of null-terminated strings, REG_SZ is a single one
+        let strings = data
+            .split(|c| *c == 0)
+            .filter(|s| !s.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect::<Vec<_>>();
+
+        if !strings.is_empty() {
+            values.push((name, strings));
+        }
+    }
+
+    unsafe {
+        RegCloseKey(hkey);
+    }
+
+    values
+}
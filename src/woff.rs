@@ -0,0 +1,659 @@
+//! Decoding of WOFF and WOFF2 web font containers, see `FcFontContainer`
+//!
+//! With the `woff` feature, the containers are decompressed to plain SFNT fonts (OpenType
+//! / TrueType, collections for WOFF2 collections) before parsing.
+//!
+//! See: https://www.w3.org/TR/WOFF/ and https://www.w3.org/TR/WOFF2/
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::io::Read;
+
+use crate::utils::{checksum, read_u16, read_u32, table_checksum};
+use crate::FcFontContainer;
+
+// containers that decompress to more than this aren't fonts (but decompression bombs),
+// same limit as for gzip compressed fonts
+const MAX_SIZE: usize = 256 << 20;
+
+// A table of the decoded font
+struct FcSfntTable {
+    tag: u32,
+    checksum: u32,
+    data: Vec<u8>,
+}
+
+// Decompresses a WOFF / WOFF2 file to SFNT data, `None` if it isn't one or is malformed
+pub(crate) fn FcDecodeWoff(font_bytes: &[u8]) -> Option<Vec<u8>> {
+    match FcFontContainer::of(font_bytes) {
+        FcFontContainer::Woff => FcDecodeWoff1(font_bytes),
+        FcFontContainer::Woff2 => FcDecodeWoff2(font_bytes),
+        _ => None,
+    }
+}
+
+fn FcDecodeWoff1(woff: &[u8]) -> Option<Vec<u8>> {
+    const HEADER_SIZE: usize = 44;
+    const ENTRY_SIZE: usize = 20;
+
+    let flavor = read_u32(woff, 4)?;
+    let num_tables = usize::from(read_u16(woff, 12)?);
+
+    let mut tables = Vec::with_capacity(num_tables);
+    let mut total_length = 0usize;
+    for i in 0..num_tables {
+        let entry = HEADER_SIZE + i * ENTRY_SIZE;
+        let offset = read_u32(woff, entry + 4)? as usize;
+        let comp_length = read_u32(woff, entry + 8)? as usize;
+        let orig_length = read_u32(woff, entry + 12)? as usize;
+        total_length = total_length
+            .checked_add(orig_length)
+            .filter(|total| *total <= MAX_SIZE)?;
+        let stored = woff.get(offset..offset.checked_add(comp_length)?)?;
+
+        // tables that don't get smaller are stored uncompressed
+        let data = if comp_length < orig_length {
+            let mut data = Vec::new();
+            flate2::read::ZlibDecoder::new(stored)
+                .take(orig_length as u64)
+                .read_to_end(&mut data)
+                .ok()?;
+            data
+        } else {
+            stored.to_vec()
+        };
+        if data.len() != orig_length {
+            return None;
+        }
+
+        tables.push(FcSfntTable {
+            tag: read_u32(woff, entry)?,
+            checksum: read_u32(woff, entry + 16)?,
+            data,
+        });
+    }
+
+    FcWriteSfnt(&[(flavor, (0..num_tables).collect())], &tables)
+}
+
+fn FcDecodeWoff2(woff2: &[u8]) -> Option<Vec<u8>> {
+    const HEADER_SIZE: usize = 48;
+    const TAG_TTCF: u32 = u32::from_be_bytes(*b"ttcf");
+    const TAG_GLYF: u32 = u32::from_be_bytes(*b"glyf");
+    const TAG_LOCA: u32 = u32::from_be_bytes(*b"loca");
+    const TAG_HMTX: u32 = u32::from_be_bytes(*b"hmtx");
+
+    let flavor = read_u32(woff2, 4)?;
+    let num_tables = usize::from(read_u16(woff2, 12)?);
+    let compressed_size = read_u32(woff2, 20)? as usize;
+
+    // table directory: tag, original length and transformed length of every table
+    let mut r = FcWoff2Reader::new(woff2, HEADER_SIZE);
+    let mut entries = Vec::with_capacity(num_tables);
+    for _ in 0..num_tables {
+        let flags = r.u8()?;
+        let tag = match flags & 0x3f {
+            0x3f => r.u32()?,
+            index => u32::from_be_bytes(**WOFF2_KNOWN_TAGS.get(usize::from(index))?),
+        };
+        let version = flags >> 6;
+        let orig_length = r.base128()?;
+        // glyf / loca are transformed with version 0, the other tables with the others
+        let transformed = match tag {
+            TAG_GLYF | TAG_LOCA => version == 0,
+            _ => version != 0,
+        };
+        let length = match transformed {
+            true => r.base128()?,
+            false => orig_length,
+        };
+        // only glyf / loca / hmtx have a transform
+        if transformed && ![TAG_GLYF, TAG_LOCA, TAG_HMTX].contains(&tag) {
+            return None;
+        }
+        entries.push((tag, orig_length, transformed, length));
+    }
+    let orig_length = entries
+        .iter()
+        .try_fold(0usize, |total, entry| total.checked_add(entry.1))?;
+    if orig_length > MAX_SIZE {
+        return None;
+    }
+
+    // fonts of a collection, as their flavor and the indices of their tables
+    let fonts = if flavor == TAG_TTCF {
+        r.u32()?;
+        let num_fonts = r.u255()?;
+        let mut fonts = Vec::new();
+        for _ in 0..num_fonts {
+            let font_tables = r.u255()?;
+            let font_flavor = r.u32()?;
+            let indices = (0..font_tables)
+                .map(|_| r.u255().filter(|index| *index < num_tables))
+                .collect::<Option<Vec<_>>>()?;
+            fonts.push((font_flavor, indices));
+        }
+        fonts
+    } else {
+        vec![(flavor, (0..num_tables).collect())]
+    };
+
+    // the tables are stored one after the other in a single Brotli stream
+    let compressed = woff2.get(r.pos..r.pos.checked_add(compressed_size)?)?;
+    let total_length = entries
+        .iter()
+        .try_fold(0usize, |total, entry| total.checked_add(entry.3))
+        .filter(|total| *total <= MAX_SIZE)?;
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .take(total_length as u64)
+        .read_to_end(&mut stream)
+        .ok()?;
+    if stream.len() != total_length {
+        return None;
+    }
+
+    let mut datas = Vec::with_capacity(num_tables);
+    let mut offset = 0;
+    for (_, _, _, length) in entries.iter() {
+        datas.push(&stream[offset..offset + length]);
+        offset += length;
+    }
+
+    let mut tables = entries
+        .iter()
+        .zip(datas.iter())
+        .map(|((tag, _, transformed, _), data)| FcSfntTable {
+            tag: *tag,
+            checksum: 0,
+            data: match transformed {
+                true => Vec::new(),
+                false => data.to_vec(),
+            },
+        })
+        .collect::<Vec<_>>();
+
+    // glyf and loca are rebuilt together, hmtx needs the bounding boxes of the glyphs. The
+    // fonts of a collection have their own tables or share them with other fonts, so every
+    // table is rebuilt once, with the other tables of the first font that has it.
+    let mut x_mins = BTreeMap::new();
+    let mut rebuilt_hmtx = BTreeSet::new();
+    for (_, indices) in fonts.iter() {
+        let find = |tag: u32| indices.iter().copied().find(|i| entries[*i].0 == tag);
+        let glyf = find(TAG_GLYF).filter(|glyf| entries[*glyf].2);
+        if let Some(glyf) = glyf.filter(|glyf| !x_mins.contains_key(glyf)) {
+            let loca = find(TAG_LOCA)?;
+            let (glyf_data, loca_data, mins) = FcReconstructGlyf(datas[glyf])?;
+            if loca_data.len() != entries[loca].1 {
+                return None;
+            }
+            tables[glyf].data = glyf_data;
+            tables[loca].data = loca_data;
+            x_mins.insert(glyf, mins);
+        }
+        if let Some(hmtx) = find(TAG_HMTX).filter(|hmtx| entries[*hmtx].2) {
+            if rebuilt_hmtx.insert(hmtx) {
+                let hhea = find(u32::from_be_bytes(*b"hhea"))?;
+                let maxp = find(u32::from_be_bytes(*b"maxp"))?;
+                tables[hmtx].data = FcReconstructHmtx(
+                    datas[hmtx],
+                    usize::from(read_u16(&tables[hhea].data, 34)?),
+                    usize::from(read_u16(&tables[maxp].data, 4)?),
+                    glyf.and_then(|glyf| x_mins.get(&glyf))
+                        .map_or(&[][..], Vec::as_slice),
+                )?;
+            }
+        }
+    }
+
+    for table in tables.iter_mut() {
+        table.checksum = table_checksum(table.tag, &table.data);
+    }
+    FcWriteSfnt(&fonts, &tables)
+}
+
+// Rebuilds the glyf and loca tables from the transformed glyf table, also returns the
+// `xMin` of every glyph (0 for empty glyphs)
+fn FcReconstructGlyf(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>, Vec<i16>)> {
+    const HEADER_SIZE: usize = 36;
+    // component flags, see https://learn.microsoft.com/en-us/typography/opentype/spec/glyf#composite-glyph-description
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+    // simple glyph flags
+    const ON_CURVE_POINT: u8 = 0x01;
+    const X_SHORT_VECTOR: u8 = 0x02;
+    const Y_SHORT_VECTOR: u8 = 0x04;
+    const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+    const OVERLAP_SIMPLE: u8 = 0x40;
+
+    let option_flags = read_u16(data, 2)?;
+    let num_glyphs = usize::from(read_u16(data, 4)?);
+    let index_format = read_u16(data, 6)?;
+
+    // the streams follow the header in the order of their sizes
+    let mut streams = Vec::new();
+    let mut offset = HEADER_SIZE;
+    for i in 0..7 {
+        let size = read_u32(data, 8 + i * 4)? as usize;
+        streams.push(data.get(offset..offset.checked_add(size)?)?);
+        offset += size;
+    }
+    let overlap_bitmap = match option_flags & 1 != 0 {
+        true => Some(data.get(offset..offset + num_glyphs.div_ceil(8))?),
+        false => None,
+    };
+    let mut contours = FcWoff2Reader::new(streams[0], 0);
+    let mut points = FcWoff2Reader::new(streams[1], 0);
+    let mut flags = FcWoff2Reader::new(streams[2], 0);
+    let mut glyphs = FcWoff2Reader::new(streams[3], 0);
+    let mut composites = FcWoff2Reader::new(streams[4], 0);
+    let bbox_bitmap_size = num_glyphs.div_ceil(32) * 4;
+    let bbox_bitmap = streams[5].get(..bbox_bitmap_size)?;
+    let mut bboxes = FcWoff2Reader::new(streams[5], bbox_bitmap_size);
+    let mut instructions = FcWoff2Reader::new(streams[6], 0);
+
+    let has_bit = |bitmap: &[u8], i: usize| bitmap[i / 8] & (0x80 >> (i % 8)) != 0;
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for glyph in 0..num_glyphs {
+        offsets.push(glyf.len());
+        let num_contours = contours.u16()? as i16;
+        let explicit_bbox = match has_bit(bbox_bitmap, glyph) {
+            true => Some([bboxes.u16()?, bboxes.u16()?, bboxes.u16()?, bboxes.u16()?]),
+            false => None,
+        };
+
+        if num_contours == 0 {
+            x_mins.push(0);
+            continue;
+        }
+
+        glyf.extend_from_slice(&num_contours.to_be_bytes());
+        if num_contours < 0 {
+            // composite glyph, the components are copied as they are
+            let bbox = explicit_bbox?;
+            x_mins.push(bbox[0] as i16);
+            for value in bbox.iter() {
+                glyf.extend_from_slice(&value.to_be_bytes());
+            }
+            let start = composites.pos;
+            let mut have_instructions = false;
+            loop {
+                let component_flags = composites.u16()?;
+                let mut size = 2 + if component_flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                    4
+                } else {
+                    2
+                };
+                if component_flags & WE_HAVE_A_SCALE != 0 {
+                    size += 2;
+                } else if component_flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                    size += 4;
+                } else if component_flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                    size += 8;
+                }
+                composites.bytes(size)?;
+                have_instructions |= component_flags & WE_HAVE_INSTRUCTIONS != 0;
+                if component_flags & MORE_COMPONENTS == 0 {
+                    break;
+                }
+            }
+            glyf.extend_from_slice(&streams[4][start..composites.pos]);
+            if have_instructions {
+                let length = glyphs.u255()?;
+                glyf.extend_from_slice(&(length as u16).to_be_bytes());
+                glyf.extend_from_slice(instructions.bytes(length)?);
+            }
+        } else {
+            // simple glyph, the points are stored as deltas in triplet encoding
+            let mut end_points = Vec::with_capacity(num_contours as usize);
+            let mut total = 0usize;
+            for _ in 0..num_contours {
+                total = total.checked_add(points.u255()?)?;
+                end_points.push(u16::try_from(total.checked_sub(1)?).ok()?);
+            }
+
+            let mut coordinates = Vec::with_capacity(total);
+            let (mut x, mut y) = (0i32, 0i32);
+            for _ in 0..total {
+                let flag = flags.u8()?;
+                let (dx, dy) = FcDecodeTriplet(flag & 0x7f, &mut glyphs)?;
+                // coordinates outside the i16 range of glyf can't be written back
+                x = x.checked_add(dx).filter(|x| i16::try_from(*x).is_ok())?;
+                y = y.checked_add(dy).filter(|y| i16::try_from(*y).is_ok())?;
+                coordinates.push((flag & 0x80 == 0, x, y, dx, dy));
+            }
+
+            let bbox = match explicit_bbox {
+                Some(bbox) => bbox.map(|value| value as i16),
+                None => {
+                    let xs = coordinates.iter().map(|point| point.1);
+                    let ys = coordinates.iter().map(|point| point.2);
+                    [
+                        xs.clone().min()? as i16,
+                        ys.clone().min()? as i16,
+                        xs.max()? as i16,
+                        ys.max()? as i16,
+                    ]
+                }
+            };
+            x_mins.push(bbox[0]);
+            for value in bbox.iter() {
+                glyf.extend_from_slice(&value.to_be_bytes());
+            }
+            for end_point in end_points {
+                glyf.extend_from_slice(&end_point.to_be_bytes());
+            }
+            let length = glyphs.u255()?;
+            glyf.extend_from_slice(&(length as u16).to_be_bytes());
+            glyf.extend_from_slice(instructions.bytes(length)?);
+
+            // flags without repeats, then the x and y coordinates as short or long deltas
+            let overlap = overlap_bitmap.is_some_and(|bitmap| has_bit(bitmap, glyph));
+            let (mut xs, mut ys) = (Vec::new(), Vec::new());
+            for (i, (on_curve, _, _, dx, dy)) in coordinates.into_iter().enumerate() {
+                let mut flag = if on_curve { ON_CURVE_POINT } else { 0 };
+                if i == 0 && overlap {
+                    flag |= OVERLAP_SIMPLE;
+                }
+                flag |= FcEncodeDelta(dx, X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE, &mut xs);
+                flag |= FcEncodeDelta(dy, Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE, &mut ys);
+                glyf.push(flag);
+            }
+            glyf.extend_from_slice(&xs);
+            glyf.extend_from_slice(&ys);
+        }
+
+        // 4-byte aligned glyphs, so that both loca formats can address them
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+        if glyf.len() > MAX_SIZE {
+            return None;
+        }
+    }
+    offsets.push(glyf.len());
+
+    let loca = match index_format {
+        0 => offsets
+            .iter()
+            .map(|offset| u16::try_from(offset / 2).ok().map(u16::to_be_bytes))
+            .collect::<Option<Vec<_>>>()?
+            .concat(),
+        _ => offsets
+            .iter()
+            .map(|offset| u32::try_from(*offset).ok().map(u32::to_be_bytes))
+            .collect::<Option<Vec<_>>>()?
+            .concat(),
+    };
+    Some((glyf, loca, x_mins))
+}
+
+// Delta of a point in triplet encoding, see https://www.w3.org/TR/WOFF2/#triplet_decoding
+fn FcDecodeTriplet(flag: u8, glyphs: &mut FcWoff2Reader<'_>) -> Option<(i32, i32)> {
+    fn signed(flag: u8, value: i32) -> i32 {
+        match flag & 1 {
+            0 => -value,
+            _ => value,
+        }
+    }
+
+    let flag_value = i32::from(flag);
+    Some(match flag {
+        0..=9 => {
+            let b0 = i32::from(glyphs.u8()?);
+            (0, signed(flag, ((flag_value & 14) << 7) + b0))
+        }
+        10..=19 => {
+            let b0 = i32::from(glyphs.u8()?);
+            (signed(flag, (((flag_value - 10) & 14) << 7) + b0), 0)
+        }
+        20..=83 => {
+            let b0 = flag_value - 20;
+            let b1 = i32::from(glyphs.u8()?);
+            (
+                signed(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+                signed(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b1 & 0x0f)),
+            )
+        }
+        84..=119 => {
+            let b0 = flag_value - 84;
+            let (b1, b2) = (i32::from(glyphs.u8()?), i32::from(glyphs.u8()?));
+            (
+                signed(flag, 1 + ((b0 / 12) << 8) + b1),
+                signed(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+            )
+        }
+        120..=123 => {
+            let bytes = glyphs.bytes(3)?;
+            let (b1, b2, b3) = (
+                i32::from(bytes[0]),
+                i32::from(bytes[1]),
+                i32::from(bytes[2]),
+            );
+            (
+                signed(flag, (b1 << 4) + (b2 >> 4)),
+                signed(flag >> 1, ((b2 & 0x0f) << 8) + b3),
+            )
+        }
+        _ => {
+            let bytes = glyphs.bytes(4)?;
+            (
+                signed(flag, i32::from(u16::from_be_bytes([bytes[0], bytes[1]]))),
+                signed(
+                    flag >> 1,
+                    i32::from(u16::from_be_bytes([bytes[2], bytes[3]])),
+                ),
+            )
+        }
+    })
+}
+
+// Appends a coordinate delta of a simple glyph, returns its flags
+fn FcEncodeDelta(delta: i32, short: u8, same_or_positive: u8, out: &mut Vec<u8>) -> u8 {
+    if delta == 0 {
+        same_or_positive
+    } else if delta.abs() < 256 {
+        out.push(delta.unsigned_abs() as u8);
+        short | if delta > 0 { same_or_positive } else { 0 }
+    } else {
+        out.extend_from_slice(&(delta as i16).to_be_bytes());
+        0
+    }
+}
+
+// Rebuilds the hmtx table from the transformed one, the left side bearings that were left
+// out are the `xMin` of the glyphs
+fn FcReconstructHmtx(
+    data: &[u8],
+    num_h_metrics: usize,
+    num_glyphs: usize,
+    x_mins: &[i16],
+) -> Option<Vec<u8>> {
+    let flags = *data.first()?;
+    let mut r = FcWoff2Reader::new(data, 1);
+    let advances = (0..num_h_metrics)
+        .map(|_| r.u16())
+        .collect::<Option<Vec<_>>>()?;
+
+    // bearings of the glyphs with an advance, then of the glyphs that share the last one
+    let mut lsbs = Vec::with_capacity(num_glyphs);
+    for glyph in 0..num_glyphs {
+        let omitted = match glyph < num_h_metrics {
+            true => flags & 1 != 0,
+            false => flags & 2 != 0,
+        };
+        lsbs.push(match omitted {
+            true => *x_mins.get(glyph)?,
+            false => r.u16()? as i16,
+        });
+    }
+
+    let mut hmtx = Vec::with_capacity(num_h_metrics * 2 + num_glyphs * 2);
+    for (glyph, lsb) in lsbs.iter().enumerate() {
+        if let Some(advance) = advances.get(glyph) {
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+        }
+        hmtx.extend_from_slice(&lsb.to_be_bytes());
+    }
+    Some(hmtx)
+}
+
+// Writes the tables as a font, or as a collection if there are several fonts (given as
+// their flavor and the indices of their tables), `None` if a font has more tables than
+// its table directory can describe
+fn FcWriteSfnt(fonts: &[(u32, Vec<usize>)], tables: &[FcSfntTable]) -> Option<Vec<u8>> {
+    const TAG_HEAD: u32 = u32::from_be_bytes(*b"head");
+    // searchRange (the largest power of 2 <= numTables, times 16) has to fit into a u16
+    const MAX_TABLES: usize = 4095;
+
+    if fonts.iter().any(|(_, indices)| indices.len() > MAX_TABLES) {
+        return None;
+    }
+
+    let collection_header_size = match fonts.len() {
+        1 => 0,
+        count => 12 + count * 4,
+    };
+    let directory_sizes = fonts
+        .iter()
+        .map(|(_, indices)| 12 + indices.len() * 16)
+        .collect::<Vec<_>>();
+    let data_start = collection_header_size + directory_sizes.iter().sum::<usize>();
+
+    // offsets of the tables, 4-byte aligned
+    let mut table_offsets = Vec::with_capacity(tables.len());
+    let mut offset = data_start;
+    for table in tables {
+        table_offsets.push(offset);
+        offset = (offset + table.data.len()).next_multiple_of(4);
+    }
+
+    let mut out = Vec::with_capacity(offset);
+    if fonts.len() > 1 {
+        out.extend_from_slice(b"ttcf");
+        out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        out.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+        let mut directory = collection_header_size;
+        for size in directory_sizes.iter() {
+            out.extend_from_slice(&(directory as u32).to_be_bytes());
+            directory += size;
+        }
+    }
+    for (flavor, indices) in fonts {
+        let num_tables = indices.len() as u32;
+        let entry_selector = num_tables.max(1).ilog2();
+        let search_range = (1u32 << entry_selector) * 16;
+        let range_shift = (num_tables * 16).saturating_sub(search_range);
+        out.extend_from_slice(&flavor.to_be_bytes());
+        out.extend_from_slice(&(num_tables as u16).to_be_bytes());
+        out.extend_from_slice(&(search_range as u16).to_be_bytes());
+        out.extend_from_slice(&(entry_selector as u16).to_be_bytes());
+        out.extend_from_slice(&(range_shift as u16).to_be_bytes());
+
+        // table records are sorted by tag
+        let mut sorted = indices.clone();
+        sorted.sort_by_key(|index| tables[*index].tag);
+        for index in sorted {
+            let table = &tables[index];
+            out.extend_from_slice(&table.tag.to_be_bytes());
+            out.extend_from_slice(&table.checksum.to_be_bytes());
+            out.extend_from_slice(&(table_offsets[index] as u32).to_be_bytes());
+            out.extend_from_slice(&(table.data.len() as u32).to_be_bytes());
+        }
+    }
+    for table in tables {
+        out.extend_from_slice(&table.data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+
+    // checkSumAdjustment of head, for single fonts (collections share the head tables)
+    if fonts.len() == 1 {
+        if let Some(head) = tables.iter().position(|table| table.tag == TAG_HEAD) {
+            let offset = table_offsets[head] + 8;
+            if tables[head].data.len() >= 12 {
+                out[offset..offset + 4].fill(0);
+                let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+                out[offset..offset + 4].copy_from_slice(&adjustment.to_be_bytes());
+            }
+        }
+    }
+    Some(out)
+}
+
+// Reads the variable-length numbers of WOFF2
+struct FcWoff2Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FcWoff2Reader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        FcWoff2Reader { data, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.bytes(2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.bytes(4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // UIntBase128, 7 bits per byte, at most 5 bytes without leading zeros
+    fn base128(&mut self) -> Option<usize> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let byte = self.u8()?;
+            if i == 0 && byte == 0x80 {
+                return None;
+            }
+            value = value.checked_mul(128)? | u32::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                return Some(value as usize);
+            }
+        }
+        None
+    }
+
+    // 255UInt16
+    fn u255(&mut self) -> Option<usize> {
+        Some(match self.u8()? {
+            253 => usize::from(self.u16()?),
+            254 => usize::from(self.u8()?) + 253 * 2,
+            255 => usize::from(self.u8()?) + 253,
+            value => usize::from(value),
+        })
+    }
+}
+
+// Tags of the table directory of WOFF2, by their index in the flags
+const WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];